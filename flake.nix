@@ -139,6 +139,10 @@
             inherit (inputs) self;
             inherit pkgs lib;
           };
+          packages.nixosTestOidc = import ./nix/tests/oidc.nix {
+            inherit (inputs) self;
+            inherit pkgs lib;
+          };
 
           overlayAttrs.idmail = config.packages.default;
         };
@@ -0,0 +1,22 @@
+-- Numeric ids for aliases and mailboxes, required by API clients which cannot address entities by their email
+-- address (e.g. SimpleLogin). The tables are WITHOUT ROWID, so ids are assigned by triggers after insertion.
+ALTER TABLE aliases ADD COLUMN id INTEGER DEFAULT NULL;
+ALTER TABLE mailboxes ADD COLUMN id INTEGER DEFAULT NULL;
+
+UPDATE aliases SET id = (SELECT COUNT(*) FROM aliases AS a WHERE a.address <= aliases.address);
+UPDATE mailboxes SET id = (SELECT COUNT(*) FROM mailboxes AS m WHERE m.address <= mailboxes.address);
+
+CREATE UNIQUE INDEX IF NOT EXISTS aliases_id ON aliases (id);
+CREATE UNIQUE INDEX IF NOT EXISTS mailboxes_id ON mailboxes (id);
+
+CREATE TRIGGER IF NOT EXISTS aliases_assign_id AFTER INSERT ON aliases
+WHEN NEW.id IS NULL
+BEGIN
+	UPDATE aliases SET id = (SELECT COALESCE(MAX(id), 0) + 1 FROM aliases) WHERE address = NEW.address;
+END;
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_assign_id AFTER INSERT ON mailboxes
+WHEN NEW.id IS NULL
+BEGIN
+	UPDATE mailboxes SET id = (SELECT COALESCE(MAX(id), 0) + 1 FROM mailboxes) WHERE address = NEW.address;
+END;
@@ -0,0 +1,37 @@
+-- API tokens are stored as hashes, so the plaintext token is only known to its owner.
+-- The old mailboxes.api_token column is no longer used. Existing tokens are moved
+-- into this table on startup, because hashing them is not possible in SQL.
+CREATE TABLE IF NOT EXISTS api_tokens (
+	id           INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	-- the user or mailbox this token authenticates as
+	owner        TEXT NOT NULL,
+	name         TEXT NOT NULL,
+	token_hash   TEXT NOT NULL UNIQUE,
+	-- one of 'create', 'read' or 'full'
+	scope        TEXT NOT NULL DEFAULT 'full',
+	expires_at   TIMESTAMP DEFAULT NULL,
+	last_used_at TIMESTAMP DEFAULT NULL,
+	last_used_ip TEXT DEFAULT NULL,
+	provisioned  BOOL NOT NULL DEFAULT FALSE,
+	created_at   TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+	-- FOREIGN KEY (owner) REFERENCES mailboxes (address) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS api_tokens_owner ON api_tokens (owner);
+
+-- Tokens must never outlive their owner, otherwise a re-created account would inherit them
+CREATE TRIGGER IF NOT EXISTS mailboxes_delete_api_tokens AFTER DELETE ON mailboxes
+BEGIN
+	DELETE FROM api_tokens WHERE owner = OLD.address;
+END;
+
+CREATE TRIGGER IF NOT EXISTS users_delete_api_tokens AFTER DELETE ON users
+BEGIN
+	DELETE FROM api_tokens WHERE owner = OLD.username;
+END;
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_rename_api_tokens AFTER UPDATE OF address ON mailboxes
+WHEN NEW.address != OLD.address
+BEGIN
+	UPDATE api_tokens SET owner = NEW.address WHERE owner = OLD.address;
+END;
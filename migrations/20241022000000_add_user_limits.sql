@@ -0,0 +1,5 @@
+-- Per-user overrides of the global limits. NULL means the global default applies,
+-- 0 disables the limit for this user. Mailboxes use the limits of their owner.
+ALTER TABLE users ADD COLUMN max_aliases INTEGER DEFAULT NULL;
+ALTER TABLE users ADD COLUMN max_aliases_per_day INTEGER DEFAULT NULL;
+ALTER TABLE users ADD COLUMN api_requests_per_minute INTEGER DEFAULT NULL;
@@ -0,0 +1,34 @@
+-- Webhooks receive signed notifications about changes to aliases, mailboxes, domains and users
+CREATE TABLE IF NOT EXISTS webhooks (
+	id         INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	url        TEXT NOT NULL,
+	-- comma separated event names or patterns like 'alias.*', empty for all events
+	events     TEXT NOT NULL DEFAULT '',
+	-- key for the HMAC-SHA256 signature of each delivery
+	secret     TEXT NOT NULL,
+	active     BOOL NOT NULL DEFAULT TRUE,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The queue and log of all notifications sent to webhooks
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+	id               INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	webhook_id       INTEGER NOT NULL,
+	event            TEXT NOT NULL,
+	payload          TEXT NOT NULL,
+	-- one of 'pending', 'delivered' or 'failed'
+	status           TEXT NOT NULL DEFAULT 'pending',
+	attempts         INTEGER NOT NULL DEFAULT 0,
+	next_attempt_at  TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	last_status_code INTEGER DEFAULT NULL,
+	last_error       TEXT DEFAULT NULL,
+	created_at       TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+	-- FOREIGN KEY (webhook_id) REFERENCES webhooks (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_pending ON webhook_deliveries (status, next_attempt_at);
+
+CREATE TRIGGER IF NOT EXISTS webhooks_delete_deliveries AFTER DELETE ON webhooks
+BEGIN
+	DELETE FROM webhook_deliveries WHERE webhook_id = OLD.id;
+END;
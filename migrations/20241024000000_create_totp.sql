@@ -0,0 +1,42 @@
+-- TOTP secrets of users and mailboxes that enrolled in two-factor authentication
+CREATE TABLE IF NOT EXISTS totp (
+	-- the user or mailbox this secret belongs to
+	username       TEXT NOT NULL PRIMARY KEY,
+	-- base32 encoded secret
+	secret         TEXT NOT NULL,
+	-- false until the enrolment was confirmed with a valid code
+	enabled        BOOL NOT NULL DEFAULT FALSE,
+	-- the last accepted time step, to prevent reusing a code
+	last_used_step INTEGER NOT NULL DEFAULT 0,
+	created_at     TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+	-- FOREIGN KEY (username) REFERENCES users (username) ON DELETE CASCADE
+);
+
+-- One-time codes to log in without the TOTP device, stored as hashes
+CREATE TABLE IF NOT EXISTS totp_recovery_codes (
+	id         INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	username   TEXT NOT NULL,
+	code_hash  TEXT NOT NULL,
+	used_at    TIMESTAMP DEFAULT NULL
+);
+
+CREATE INDEX IF NOT EXISTS totp_recovery_codes_username ON totp_recovery_codes (username);
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_delete_totp AFTER DELETE ON mailboxes
+BEGIN
+	DELETE FROM totp WHERE username = OLD.address;
+	DELETE FROM totp_recovery_codes WHERE username = OLD.address;
+END;
+
+CREATE TRIGGER IF NOT EXISTS users_delete_totp AFTER DELETE ON users
+BEGIN
+	DELETE FROM totp WHERE username = OLD.username;
+	DELETE FROM totp_recovery_codes WHERE username = OLD.username;
+END;
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_rename_totp AFTER UPDATE OF address ON mailboxes
+WHEN NEW.address != OLD.address
+BEGIN
+	UPDATE totp SET username = NEW.address WHERE username = OLD.address;
+	UPDATE totp_recovery_codes SET username = NEW.address WHERE username = OLD.address;
+END;
@@ -0,0 +1,36 @@
+-- WebAuthn credentials that users and mailboxes can log in with
+CREATE TABLE IF NOT EXISTS passkeys (
+	id            INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	-- the user or mailbox this passkey authenticates as
+	owner         TEXT NOT NULL,
+	name          TEXT NOT NULL,
+	-- base64url encoded credential id, as chosen by the authenticator
+	credential_id TEXT NOT NULL UNIQUE,
+	-- DER encoded SubjectPublicKeyInfo
+	public_key    BLOB NOT NULL,
+	-- COSE algorithm identifier of the public key
+	algorithm     INTEGER NOT NULL,
+	-- the signature counter reported by the authenticator, to detect cloned authenticators
+	sign_count    INTEGER NOT NULL DEFAULT 0,
+	last_used_at  TIMESTAMP DEFAULT NULL,
+	created_at    TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+	-- FOREIGN KEY (owner) REFERENCES mailboxes (address) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS passkeys_owner ON passkeys (owner);
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_delete_passkeys AFTER DELETE ON mailboxes
+BEGIN
+	DELETE FROM passkeys WHERE owner = OLD.address;
+END;
+
+CREATE TRIGGER IF NOT EXISTS users_delete_passkeys AFTER DELETE ON users
+BEGIN
+	DELETE FROM passkeys WHERE owner = OLD.username;
+END;
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_rename_passkeys AFTER UPDATE OF address ON mailboxes
+WHEN NEW.address != OLD.address
+BEGIN
+	UPDATE passkeys SET owner = NEW.address WHERE owner = OLD.address;
+END;
@@ -0,0 +1,4 @@
+-- The subject of the OpenID Connect identity a user is linked to, set on its first single sign-on login
+ALTER TABLE users ADD COLUMN oidc_subject TEXT DEFAULT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_oidc_subject ON users (oidc_subject);
@@ -0,0 +1,13 @@
+-- Failed login attempts per account and per client address, to slow down and lock out brute-force attacks
+CREATE TABLE IF NOT EXISTS login_failures (
+	-- one of 'account' or 'address'
+	kind            TEXT NOT NULL,
+	-- the attempted user name or mailbox address, or the client address
+	key             TEXT NOT NULL,
+	-- failures since the counter was last reset
+	failures        INTEGER NOT NULL DEFAULT 0,
+	-- further attempts are rejected until this time
+	locked_until    TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	last_failure_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (kind, key)
+);
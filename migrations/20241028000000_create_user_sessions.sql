@@ -0,0 +1,34 @@
+-- Logged in sessions of users and mailboxes, which can be listed and revoked
+CREATE TABLE IF NOT EXISTS user_sessions (
+	id           INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	-- random key stored in the session data, which identifies the login
+	token        TEXT NOT NULL UNIQUE,
+	-- the user or mailbox that is logged in
+	username     TEXT NOT NULL,
+	-- whether the session survives idle timeouts
+	remember     BOOL NOT NULL DEFAULT FALSE,
+	-- browser and operating system, derived from the user agent
+	device       TEXT NOT NULL,
+	ip           TEXT DEFAULT NULL,
+	last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	created_at   TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+	-- FOREIGN KEY (username) REFERENCES mailboxes (address) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS user_sessions_username ON user_sessions (username);
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_delete_user_sessions AFTER DELETE ON mailboxes
+BEGIN
+	DELETE FROM user_sessions WHERE username = OLD.address;
+END;
+
+CREATE TRIGGER IF NOT EXISTS users_delete_user_sessions AFTER DELETE ON users
+BEGIN
+	DELETE FROM user_sessions WHERE username = OLD.username;
+END;
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_rename_user_sessions AFTER UPDATE OF address ON mailboxes
+WHEN NEW.address != OLD.address
+BEGIN
+	UPDATE user_sessions SET username = NEW.address WHERE username = OLD.address;
+END;
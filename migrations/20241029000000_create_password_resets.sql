@@ -0,0 +1,37 @@
+-- Single-use links to choose a new password, issued by admins and domain owners.
+-- Entries are kept after they were used or expired, as a record of who reset which password.
+CREATE TABLE IF NOT EXISTS password_resets (
+	id          INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	-- SHA-256 of the token in the link
+	token_hash  TEXT NOT NULL UNIQUE,
+	-- the user or mailbox whose password can be reset
+	username    TEXT NOT NULL,
+	-- the admin or domain owner who issued the link
+	created_by  TEXT NOT NULL,
+	expires_at  TIMESTAMP NOT NULL,
+	used_at     TIMESTAMP DEFAULT NULL,
+	used_ip     TEXT DEFAULT NULL,
+	created_at  TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS password_resets_username ON password_resets (username);
+
+-- Links must not be usable for a different account that is created with the same name later
+CREATE TRIGGER IF NOT EXISTS mailboxes_delete_password_resets AFTER DELETE ON mailboxes
+BEGIN
+	UPDATE password_resets SET expires_at = CURRENT_TIMESTAMP
+	WHERE username = OLD.address AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP;
+END;
+
+CREATE TRIGGER IF NOT EXISTS users_delete_password_resets AFTER DELETE ON users
+BEGIN
+	UPDATE password_resets SET expires_at = CURRENT_TIMESTAMP
+	WHERE username = OLD.username AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP;
+END;
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_rename_password_resets AFTER UPDATE OF address ON mailboxes
+WHEN NEW.address != OLD.address
+BEGIN
+	UPDATE password_resets SET expires_at = CURRENT_TIMESTAMP
+	WHERE username = OLD.address AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP;
+END;
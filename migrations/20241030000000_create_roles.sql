@@ -0,0 +1,32 @@
+-- Roles grant users a set of permissions on the accounts of others, without making them admins
+CREATE TABLE IF NOT EXISTS roles (
+	name        TEXT NOT NULL PRIMARY KEY,
+	description TEXT NOT NULL DEFAULT '',
+	-- comma separated permission names or patterns like 'aliases.*'
+	permissions TEXT NOT NULL DEFAULT '',
+	provisioned BOOL NOT NULL DEFAULT FALSE,
+	created_at  TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO roles (name, description, permissions) VALUES
+	('auditor', 'Read-only access to all users, domains, mailboxes and aliases',
+		'users.read, domains.read, mailboxes.read, aliases.read'),
+	('helpdesk', 'Resets mailbox passwords, toggles aliases and lifts lockouts, but cannot delete anything',
+		'users.read, sessions.revoke, lockouts.lift, domains.read, mailboxes.read, mailboxes.reset_password, aliases.read, aliases.toggle'),
+	('domain-admin', 'Manages all domains, mailboxes and aliases',
+		'domains.*, mailboxes.*, aliases.*');
+
+-- The role of a user, if any. Mailboxes cannot have roles.
+ALTER TABLE users ADD COLUMN role TEXT DEFAULT NULL;
+-- FOREIGN KEY (role) REFERENCES roles (name) ON UPDATE CASCADE ON DELETE SET NULL
+
+CREATE TRIGGER IF NOT EXISTS roles_delete_users AFTER DELETE ON roles
+BEGIN
+	UPDATE users SET role = NULL WHERE role = OLD.name;
+END;
+
+CREATE TRIGGER IF NOT EXISTS roles_rename_users AFTER UPDATE OF name ON roles
+WHEN NEW.name != OLD.name
+BEGIN
+	UPDATE users SET role = NEW.name WHERE role = OLD.name;
+END;
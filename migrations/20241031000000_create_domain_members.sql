@@ -0,0 +1,33 @@
+-- Users who may manage a domain just like its owner, so that a team can share a domain
+CREATE TABLE IF NOT EXISTS domain_members (
+	domain     TEXT NOT NULL,
+	username   TEXT NOT NULL,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (domain, username)
+	-- FOREIGN KEY (domain) REFERENCES domains (domain) ON UPDATE CASCADE ON DELETE CASCADE
+	-- FOREIGN KEY (username) REFERENCES users (username) ON UPDATE CASCADE ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS domain_members_username ON domain_members (username);
+
+CREATE TRIGGER IF NOT EXISTS domains_delete_domain_members AFTER DELETE ON domains
+BEGIN
+	DELETE FROM domain_members WHERE domain = OLD.domain;
+END;
+
+CREATE TRIGGER IF NOT EXISTS domains_rename_domain_members AFTER UPDATE OF domain ON domains
+WHEN NEW.domain != OLD.domain
+BEGIN
+	UPDATE domain_members SET domain = NEW.domain WHERE domain = OLD.domain;
+END;
+
+CREATE TRIGGER IF NOT EXISTS users_delete_domain_members AFTER DELETE ON users
+BEGIN
+	DELETE FROM domain_members WHERE username = OLD.username;
+END;
+
+CREATE TRIGGER IF NOT EXISTS users_rename_domain_members AFTER UPDATE OF username ON users
+WHEN NEW.username != OLD.username
+BEGIN
+	UPDATE domain_members SET username = NEW.username WHERE username = OLD.username;
+END;
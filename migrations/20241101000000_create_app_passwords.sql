@@ -0,0 +1,28 @@
+-- Additional passwords of a mailbox for its mail clients, which can be revoked one by one.
+-- They are only accepted by the mail server, never by the web login.
+CREATE TABLE IF NOT EXISTS app_passwords (
+	id            INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	mailbox       TEXT NOT NULL,
+	name          TEXT NOT NULL,
+	password_hash TEXT NOT NULL,
+	created_at    TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+	-- FOREIGN KEY (mailbox) REFERENCES mailboxes (address) ON UPDATE CASCADE ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS app_passwords_mailbox ON app_passwords (mailbox);
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_delete_app_passwords AFTER DELETE ON mailboxes
+BEGIN
+	DELETE FROM app_passwords WHERE mailbox = OLD.address;
+END;
+
+CREATE TRIGGER IF NOT EXISTS mailboxes_rename_app_passwords AFTER UPDATE OF address ON mailboxes
+WHEN NEW.address != OLD.address
+BEGIN
+	UPDATE app_passwords SET mailbox = NEW.address WHERE mailbox = OLD.address;
+END;
+
+-- All secrets the mail server should accept for a mailbox, one per row
+CREATE VIEW IF NOT EXISTS mailbox_secrets (address, secret) AS
+	SELECT address, password_hash FROM mailboxes
+	UNION ALL SELECT mailbox, password_hash FROM app_passwords;
@@ -0,0 +1,2 @@
+-- A hash of the user agent that created the session, so that sessions can be bound to their client
+ALTER TABLE user_sessions ADD COLUMN fingerprint TEXT DEFAULT NULL;
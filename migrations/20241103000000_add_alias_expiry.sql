@@ -0,0 +1,8 @@
+-- Scheduled activation and expiry of aliases, applied once by a background task which then clears them.
+-- on_expiry is either 'deactivate' or 'delete'.
+ALTER TABLE aliases ADD COLUMN active_from TIMESTAMP DEFAULT NULL;
+ALTER TABLE aliases ADD COLUMN expires_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE aliases ADD COLUMN on_expiry TEXT NOT NULL DEFAULT 'deactivate';
+
+CREATE INDEX IF NOT EXISTS aliases_active_from ON aliases (active_from) WHERE active_from IS NOT NULL;
+CREATE INDEX IF NOT EXISTS aliases_expires_at ON aliases (expires_at) WHERE expires_at IS NOT NULL;
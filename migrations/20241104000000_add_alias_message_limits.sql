@@ -0,0 +1,10 @@
+-- Aliases can be limited to a total number of received messages, e.g. for one-time addresses.
+-- n_recv is counted by the mail server, so exhausted aliases are deactivated right when it is updated.
+ALTER TABLE aliases ADD COLUMN max_recv INTEGER DEFAULT NULL;
+
+-- Also covers activating an exhausted alias or lowering its limit, which deactivates it again
+CREATE TRIGGER IF NOT EXISTS aliases_deactivate_exhausted AFTER UPDATE OF n_recv, max_recv, active ON aliases
+WHEN NEW.active AND NEW.n_recv >= NEW.max_recv
+BEGIN
+	UPDATE aliases SET active = FALSE WHERE address = NEW.address;
+END;
@@ -0,0 +1,25 @@
+-- Ids of aliases and mailboxes must never be reused after a deletion, as API clients cache them.
+-- The last assigned id of each table is kept in a counter, which only ever increases.
+CREATE TABLE IF NOT EXISTS id_counters (
+	name    TEXT NOT NULL PRIMARY KEY,
+	last_id INTEGER NOT NULL
+) WITHOUT ROWID;
+
+INSERT INTO id_counters (name, last_id) SELECT 'aliases', COALESCE(MAX(id), 0) FROM aliases;
+INSERT INTO id_counters (name, last_id) SELECT 'mailboxes', COALESCE(MAX(id), 0) FROM mailboxes;
+
+DROP TRIGGER IF EXISTS aliases_assign_id;
+CREATE TRIGGER IF NOT EXISTS aliases_assign_id AFTER INSERT ON aliases
+WHEN NEW.id IS NULL
+BEGIN
+	UPDATE id_counters SET last_id = last_id + 1 WHERE name = 'aliases';
+	UPDATE aliases SET id = (SELECT last_id FROM id_counters WHERE name = 'aliases') WHERE address = NEW.address;
+END;
+
+DROP TRIGGER IF EXISTS mailboxes_assign_id;
+CREATE TRIGGER IF NOT EXISTS mailboxes_assign_id AFTER INSERT ON mailboxes
+WHEN NEW.id IS NULL
+BEGIN
+	UPDATE id_counters SET last_id = last_id + 1 WHERE name = 'mailboxes';
+	UPDATE mailboxes SET id = (SELECT last_id FROM id_counters WHERE name = 'mailboxes') WHERE address = NEW.address;
+END;
@@ -59,9 +59,333 @@ in
       description = "Port to bind to";
     };
 
+    webauthnOrigin = mkOption {
+      type = types.nullOr types.str;
+      default = null;
+      example = "https://alias.example.com";
+      description = ''
+        The public origin under which idmail is reached, which passkeys are bound to.
+        Passkeys are disabled if unset.
+      '';
+    };
+
+    requireAdminTotp = mkOption {
+      type = types.bool;
+      default = false;
+      description = ''
+        Whether admins must enable two-factor authentication (TOTP) before they can use their admin rights.
+        Until then, they can only manage their own account like a normal user.
+      '';
+    };
+
+    sessionIdleTimeout = mkOption {
+      type = types.nullOr types.ints.positive;
+      default = null;
+      example = 3600;
+      description = ''
+        After how many seconds without activity a session ends, unless "remember me" was checked on login.
+        Defaults to 21600 (6 hours) if null.
+      '';
+    };
+
+    sessionMaxLifetime = mkOption {
+      type = types.nullOr types.ints.positive;
+      default = null;
+      example = 604800;
+      description = "How many seconds a session lasts at most. Defaults to 2592000 (30 days) if null.";
+    };
+
+    sessionBinding = mkOption {
+      type = types.enum [
+        "none"
+        "user-agent"
+        "ip-and-user-agent"
+      ];
+      default = "none";
+      description = ''
+        What a session is bound to besides its cookie. A session used by another user agent (or address)
+        than the one that logged in is ignored. Behind a reverse proxy, only `user-agent` is useful.
+      '';
+    };
+
+    cookie = {
+      name = mkOption {
+        type = types.str;
+        default = "session";
+        example = "__Host-session";
+        description = "Name of the session cookie.";
+      };
+      domain = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        description = "Domain of the session cookie. If null, the cookie is limited to the exact host.";
+      };
+      secure = mkOption {
+        type = types.bool;
+        default = false;
+        description = "Whether the session cookie is only sent over HTTPS. Should be enabled when served over HTTPS.";
+      };
+      sameSite = mkOption {
+        type = types.enum [
+          "strict"
+          "lax"
+          "none"
+        ];
+        default = "lax";
+        description = ''
+          The SameSite attribute of the session cookie. `strict` cannot be combined with OpenID Connect,
+          and `none` requires `secure`.
+        '';
+      };
+    };
+
+    csrfTrustedOrigins = mkOption {
+      type = types.listOf types.str;
+      default = [ ];
+      example = [ "https://mail.example.com" ];
+      description = ''
+        Origins of other sites whose requests may use the session of their users.
+        Cross-site requests from any other origin that could change something are rejected.
+      '';
+    };
+
+    passwordLogin = mkOption {
+      type = types.bool;
+      default = true;
+      description = ''
+        Whether users can log in with their local password. Can only be disabled if single sign-on is configured.
+        Mailboxes can always log in with their password.
+      '';
+    };
+
+    oidc = {
+      issuer = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "https://idm.example.com/oauth2/openid/idmail";
+        description = "The issuer URL of the OpenID Connect provider. Enables single sign-on if set.";
+      };
+      clientId = mkOption {
+        type = types.str;
+        default = "idmail";
+        description = "The client id registered at the OpenID Connect provider.";
+      };
+      clientSecretFile = mkOption {
+        type = types.nullOr types.path;
+        default = null;
+        description = "A file containing the client secret. Leave unset for public clients.";
+      };
+      redirectUrl = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "https://alias.example.com/auth/oidc/callback";
+        description = "The URL the provider redirects back to after login, which must end in /auth/oidc/callback.";
+      };
+      name = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "Kanidm";
+        description = "The provider name shown on the login button. Defaults to 'Single Sign-On'.";
+      };
+      scopes = mkOption {
+        type = types.listOf types.str;
+        default = [
+          "openid"
+          "email"
+          "profile"
+        ];
+        description = "The scopes to request.";
+      };
+      usernameClaim = mkOption {
+        type = types.str;
+        default = "preferred_username";
+        description = "The claim that contains the idmail user name.";
+      };
+      groupsClaim = mkOption {
+        type = types.str;
+        default = "groups";
+        description = "The claim that contains the groups of the user.";
+      };
+      adminGroup = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "idmail-admins";
+        description = ''
+          Members of this group are admins, everyone else is not.
+          If unset, the admin flag is managed in idmail.
+        '';
+      };
+      autoCreate = mkOption {
+        type = types.bool;
+        default = false;
+        description = "Whether to create users on their first login. Otherwise, only existing users can log in.";
+      };
+      linkByName = mkOption {
+        type = types.bool;
+        default = false;
+        description = ''
+          Whether to link an existing user with the same name on its first login, if it has no password, admin flag or role.
+          Otherwise, existing users must be linked with `oidc_subject` when provisioning them.
+        '';
+      };
+    };
+
+    trustedProxies = mkOption {
+      type = types.listOf types.str;
+      default = [ ];
+      example = [
+        "127.0.0.1"
+        "::1"
+      ];
+      description = ''
+        Addresses or CIDR networks of reverse proxies whose `X-Forwarded-For` header is trusted
+        to contain the address of the client, which is used for login lockouts and rate limits.
+      '';
+    };
+
+    proxyAuth = {
+      trustedProxies = mkOption {
+        type = types.listOf types.str;
+        default = [ ];
+        example = [
+          "127.0.0.1/32"
+          "::1/128"
+        ];
+        description = ''
+          Addresses or CIDR networks of reverse proxies that authenticate users, e.g. with forward auth
+          of Authelia or oauth2-proxy. Their requests are logged in as the user or mailbox named in `header`.
+          Enables reverse proxy authentication if not empty.
+        '';
+      };
+      header = mkOption {
+        type = types.str;
+        default = "X-Remote-User";
+        description = "The header in which trusted proxies pass the name of the authenticated user or mailbox.";
+      };
+    };
+
+    passwordPolicy = {
+      minLength = mkOption {
+        type = types.ints.between 1 1024;
+        default = 12;
+        description = "Minimum number of characters of new passwords.";
+      };
+      minStrength = mkOption {
+        type = types.ints.between 0 4;
+        default = 0;
+        description = "Minimum estimated strength of new passwords from 0 (very weak) to 4 (very strong). 0 allows any password.";
+      };
+      disallowUsername = mkOption {
+        type = types.bool;
+        default = true;
+        description = "Whether new passwords must not contain the username or the local part of the mailbox address.";
+      };
+      breachedList = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "/var/lib/pwned-passwords";
+        description = ''
+          Path of the breached passwords of Have I Been Pwned, either a file of SHA-1 hashes sorted by hash
+          or a directory of range files. New passwords in this list are rejected. A string rather than
+          a path, so that the large list is not copied to the store.
+        '';
+      };
+    };
+
+    argon2 = {
+      memory = mkOption {
+        type = types.nullOr types.ints.positive;
+        default = null;
+        description = "Memory cost of new password hashes in KiB. Defaults to 19456 if null.";
+      };
+      iterations = mkOption {
+        type = types.nullOr types.ints.positive;
+        default = null;
+        description = "Number of iterations of new password hashes. Defaults to 2 if null.";
+      };
+      parallelism = mkOption {
+        type = types.nullOr types.ints.positive;
+        default = null;
+        description = ''
+          Degree of parallelism of new password hashes. Defaults to 1 if null.
+          Existing hashes with other parameters are replaced on the next login.
+        '';
+      };
+    };
+
+    limits = {
+      maxAliases = mkOption {
+        type = types.nullOr types.ints.unsigned;
+        default = null;
+        description = "Maximum number of aliases per user or mailbox. Admins are exempt. Unlimited if null or 0.";
+      };
+      maxAliasesPerDay = mkOption {
+        type = types.nullOr types.ints.unsigned;
+        default = null;
+        description = "Maximum number of aliases a user or mailbox may create within 24 hours. Admins are exempt. Unlimited if null or 0.";
+      };
+      apiRequestsPerMinute = mkOption {
+        type = types.nullOr types.ints.unsigned;
+        default = null;
+        description = "Maximum number of API requests per minute and API token. Defaults to 60 if null, unlimited if 0.";
+      };
+      apiRequestsPerMinutePerIp = mkOption {
+        type = types.nullOr types.ints.unsigned;
+        default = null;
+        description = ''
+          Maximum number of API requests per minute and client address. Unlimited if null or 0.
+          Behind a reverse proxy, all requests share the address of the proxy unless it is in `trustedProxies`.
+        '';
+      };
+      loginMaxFailures = mkOption {
+        type = types.nullOr types.ints.unsigned;
+        default = null;
+        description = "Failed logins after which an account is locked. Defaults to 10 if null, disabled if 0.";
+      };
+      loginMaxFailuresPerIp = mkOption {
+        type = types.nullOr types.ints.unsigned;
+        default = null;
+        description = ''
+          Failed logins and invalid API tokens after which a client address is locked. Defaults to 50 if null, disabled if 0.
+          Behind a reverse proxy, all requests share the address of the proxy unless it is in `trustedProxies`.
+        '';
+      };
+      loginLockoutSeconds = mkOption {
+        type = types.nullOr types.ints.positive;
+        default = null;
+        description = "How long a lockout lasts and failed logins are remembered, in seconds. Defaults to 900 if null.";
+      };
+    };
+
     provision = {
       enable = mkEnableOption "provisioning of idmail";
 
+      roles = mkOption {
+        default = { };
+        type = types.attrsOf (
+          types.submodule {
+            options = {
+              description = mkOption {
+                type = types.str;
+                default = "";
+                description = ''A description of the role.'';
+              };
+              permissions = mkOption {
+                type = types.listOf types.str;
+                default = [ ];
+                example = [
+                  "users.read"
+                  "aliases.*"
+                ];
+                description = ''
+                  The granted permissions. Patterns like "aliases.*" select all permissions of an entity.
+                '';
+              };
+            };
+          }
+        );
+      };
+
       users = mkOption {
         default = { };
         type = types.attrsOf (
@@ -70,7 +394,8 @@ in
               password_hash = mkOption {
                 type = types.str;
                 description = ''
-                  Password hash, should be a argon2id hash.
+                  Password hash, preferably an argon2id hash. Legacy crypt and Dovecot {SCHEME} hashes are accepted too.
+                  Plain {PLAIN} passwords must comply with the password policy.
                   Can be generated with: `echo -n "whatever" | argon2 somerandomsalt -id`
                   Also accepts "%{file:/path/to/secret}%" to refer to the contents of a file.
                 '';
@@ -80,11 +405,48 @@ in
                 default = false;
                 description = ''Whether the user should be an admin.'';
               };
+              role = mkOption {
+                type = types.nullOr types.str;
+                default = null;
+                description = ''The role of the user, which must be a built-in or provisioned role.'';
+              };
               active = mkOption {
                 type = types.bool;
                 default = true;
                 description = ''Whether the user should be active.'';
               };
+              max_aliases = mkOption {
+                type = types.nullOr types.ints.unsigned;
+                default = null;
+                description = ''
+                  Maximum number of aliases of this user and each of their mailboxes.
+                  Overrides `limits.maxAliases`, 0 means unlimited.
+                '';
+              };
+              max_aliases_per_day = mkOption {
+                type = types.nullOr types.ints.unsigned;
+                default = null;
+                description = ''
+                  Maximum number of aliases this user and each of their mailboxes may create per day.
+                  Overrides `limits.maxAliasesPerDay`, 0 means unlimited.
+                '';
+              };
+              api_requests_per_minute = mkOption {
+                type = types.nullOr types.ints.unsigned;
+                default = null;
+                description = ''
+                  Maximum number of API requests per minute for each API token of this user and their mailboxes.
+                  Overrides `limits.apiRequestsPerMinute`, 0 means unlimited.
+                '';
+              };
+              oidc_subject = mkOption {
+                type = types.nullOr types.str;
+                default = null;
+                description = ''
+                  The subject (`sub` claim) of the single sign-on identity this user is linked to.
+                  If null, an identity linked on login is kept.
+                '';
+              };
             };
           }
         );
@@ -103,6 +465,11 @@ in
                   Creation and deletion of any domain is always restricted to admins only.
                 '';
               };
+              members = mkOption {
+                type = types.listOf types.str;
+                default = [ ];
+                description = ''Users which may manage this domain just like its owner.'';
+              };
               catch_all = mkOption {
                 type = types.nullOr types.str;
                 default = null;
@@ -135,7 +502,8 @@ in
               password_hash = mkOption {
                 type = types.str;
                 description = ''
-                  Password hash, should be a argon2id hash.
+                  Password hash, preferably an argon2id hash. Legacy crypt and Dovecot {SCHEME} hashes are accepted too.
+                  Plain {PLAIN} passwords must comply with the password policy.
                   Can be generated with: `echo -n "whatever" | argon2 somerandomsalt -id`
                   Also accepts "%{file:/path/to/secret}%" to refer to the contents of a file.
                 '';
@@ -149,7 +517,8 @@ in
                 default = null;
                 description = ''
                   An API token for this mailbox to allow alias creation via the API endpoints.
-                  Optional. Default: None (API access disabled)
+                  It is added with full scope alongside any tokens created in the web interface.
+                  Optional. Default: None (no provisioned token)
                   Minimum length 16. Must be unique!
                   Also accepts "%{file:/path/to/secret}%" to refer to the contents of a file.
                 '';
@@ -194,6 +563,32 @@ in
                 default = true;
                 description = ''Whether the alias should be active.'';
               };
+              active_from = mkOption {
+                type = types.nullOr types.str;
+                default = null;
+                example = "2024-06-01T00:00:00Z";
+                description = ''When the alias should be activated, in RFC 3339 format. It stays inactive until then.'';
+              };
+              expires_at = mkOption {
+                type = types.nullOr types.str;
+                default = null;
+                example = "2024-07-01T00:00:00Z";
+                description = ''When the alias expires, in RFC 3339 format. Never expires if null.'';
+              };
+              on_expiry = mkOption {
+                type = types.enum [
+                  "deactivate"
+                  "delete"
+                ];
+                default = "deactivate";
+                description = ''Whether the alias is deactivated or deleted once it expired.'';
+              };
+              max_recv = mkOption {
+                type = types.nullOr types.ints.positive;
+                default = null;
+                example = 1;
+                description = ''After how many received messages in total the alias is deactivated. Unlimited if null.'';
+              };
             };
           }
         );
@@ -220,10 +615,80 @@ in
 
       environment.LEPTOS_SITE_ADDR = "${cfg.host}:${toString cfg.port}";
       environment.IDMAIL_PROVISION = mkIf cfg.provision.enable provisionToml;
+      environment.IDMAIL_REQUIRE_ADMIN_TOTP = mkIf cfg.requireAdminTotp "true";
+      environment.IDMAIL_WEBAUTHN_ORIGIN = mkIf (cfg.webauthnOrigin != null) cfg.webauthnOrigin;
+      environment.IDMAIL_SESSION_IDLE_TIMEOUT = mkIf (cfg.sessionIdleTimeout != null) (
+        toString cfg.sessionIdleTimeout
+      );
+      environment.IDMAIL_SESSION_MAX_LIFETIME = mkIf (cfg.sessionMaxLifetime != null) (
+        toString cfg.sessionMaxLifetime
+      );
+      environment.IDMAIL_SESSION_BINDING = cfg.sessionBinding;
+      environment.IDMAIL_COOKIE_NAME = cfg.cookie.name;
+      environment.IDMAIL_COOKIE_DOMAIN = mkIf (cfg.cookie.domain != null) cfg.cookie.domain;
+      environment.IDMAIL_COOKIE_SECURE = lib.boolToString cfg.cookie.secure;
+      environment.IDMAIL_COOKIE_SAME_SITE = cfg.cookie.sameSite;
+      environment.IDMAIL_CSRF_TRUSTED_ORIGINS = mkIf (cfg.csrfTrustedOrigins != [ ]) (
+        lib.concatStringsSep "," cfg.csrfTrustedOrigins
+      );
+      environment.IDMAIL_PASSWORD_LOGIN = mkIf (!cfg.passwordLogin) "false";
+      environment.IDMAIL_OIDC_ISSUER = mkIf (cfg.oidc.issuer != null) cfg.oidc.issuer;
+      environment.IDMAIL_OIDC_CLIENT_ID = mkIf (cfg.oidc.issuer != null) cfg.oidc.clientId;
+      environment.IDMAIL_OIDC_CLIENT_SECRET_FILE = mkIf (
+        cfg.oidc.issuer != null && cfg.oidc.clientSecretFile != null
+      ) "%d/oidc-client-secret";
+      environment.IDMAIL_OIDC_REDIRECT_URL = mkIf (cfg.oidc.redirectUrl != null) cfg.oidc.redirectUrl;
+      environment.IDMAIL_OIDC_NAME = mkIf (cfg.oidc.name != null) cfg.oidc.name;
+      environment.IDMAIL_OIDC_SCOPES = mkIf (cfg.oidc.issuer != null) (lib.concatStringsSep " " cfg.oidc.scopes);
+      environment.IDMAIL_OIDC_USERNAME_CLAIM = mkIf (cfg.oidc.issuer != null) cfg.oidc.usernameClaim;
+      environment.IDMAIL_OIDC_GROUPS_CLAIM = mkIf (cfg.oidc.issuer != null) cfg.oidc.groupsClaim;
+      environment.IDMAIL_OIDC_ADMIN_GROUP = mkIf (cfg.oidc.adminGroup != null) cfg.oidc.adminGroup;
+      environment.IDMAIL_OIDC_AUTO_CREATE = mkIf cfg.oidc.autoCreate "true";
+      environment.IDMAIL_OIDC_LINK_BY_NAME = mkIf cfg.oidc.linkByName "true";
+      environment.IDMAIL_TRUSTED_PROXIES = mkIf (cfg.trustedProxies != [ ]) (
+        lib.concatStringsSep "," cfg.trustedProxies
+      );
+      environment.IDMAIL_PROXY_AUTH_TRUSTED_PROXIES = mkIf (cfg.proxyAuth.trustedProxies != [ ]) (
+        lib.concatStringsSep "," cfg.proxyAuth.trustedProxies
+      );
+      environment.IDMAIL_PROXY_AUTH_HEADER = mkIf (cfg.proxyAuth.trustedProxies != [ ]) cfg.proxyAuth.header;
+      environment.IDMAIL_PASSWORD_MIN_LENGTH = toString cfg.passwordPolicy.minLength;
+      environment.IDMAIL_PASSWORD_MIN_STRENGTH = toString cfg.passwordPolicy.minStrength;
+      environment.IDMAIL_PASSWORD_DISALLOW_USERNAME = lib.boolToString cfg.passwordPolicy.disallowUsername;
+      environment.IDMAIL_PASSWORD_BREACHED_LIST = mkIf (
+        cfg.passwordPolicy.breachedList != null
+      ) cfg.passwordPolicy.breachedList;
+      environment.IDMAIL_ARGON2_MEMORY = mkIf (cfg.argon2.memory != null) (toString cfg.argon2.memory);
+      environment.IDMAIL_ARGON2_ITERATIONS = mkIf (cfg.argon2.iterations != null) (toString cfg.argon2.iterations);
+      environment.IDMAIL_ARGON2_PARALLELISM = mkIf (cfg.argon2.parallelism != null) (
+        toString cfg.argon2.parallelism
+      );
+      environment.IDMAIL_MAX_ALIASES = mkIf (cfg.limits.maxAliases != null) (toString cfg.limits.maxAliases);
+      environment.IDMAIL_MAX_ALIASES_PER_DAY = mkIf (cfg.limits.maxAliasesPerDay != null) (
+        toString cfg.limits.maxAliasesPerDay
+      );
+      environment.IDMAIL_API_REQUESTS_PER_MINUTE = mkIf (cfg.limits.apiRequestsPerMinute != null) (
+        toString cfg.limits.apiRequestsPerMinute
+      );
+      environment.IDMAIL_API_REQUESTS_PER_MINUTE_PER_IP = mkIf (cfg.limits.apiRequestsPerMinutePerIp != null) (
+        toString cfg.limits.apiRequestsPerMinutePerIp
+      );
+      environment.IDMAIL_LOGIN_MAX_FAILURES = mkIf (cfg.limits.loginMaxFailures != null) (
+        toString cfg.limits.loginMaxFailures
+      );
+      environment.IDMAIL_LOGIN_MAX_FAILURES_PER_IP = mkIf (cfg.limits.loginMaxFailuresPerIp != null) (
+        toString cfg.limits.loginMaxFailuresPerIp
+      );
+      environment.IDMAIL_LOGIN_LOCKOUT_SECONDS = mkIf (cfg.limits.loginLockoutSeconds != null) (
+        toString cfg.limits.loginLockoutSeconds
+      );
 
       serviceConfig = {
         Restart = "on-failure";
         ExecStart = getExe cfg.package;
+        LoadCredential = mkIf (cfg.oidc.issuer != null && cfg.oidc.clientSecretFile != null) [
+          "oidc-client-secret:${cfg.oidc.clientSecretFile}"
+        ];
         User = cfg.user;
 
         StateDirectory = mkIf (cfg.dataDir == defaultDataDir) "idmail";
@@ -51,6 +51,8 @@ in
     machine.wait_for_unit("idmail.service")
     machine.wait_for_open_port(3000)
     machine.succeed("curl --fail http://localhost:3000/")
+    out = machine.succeed("curl --fail localhost:3000/api/openapi.json | jq -r '.paths | has(\"/api/v1/aliases\")'")
+    expect_output(out, "true\n")
 
     # Test addy.io endpoint
     cmd = [
@@ -65,6 +67,27 @@ in
     out = machine.succeed(' '.join(cmd))
     expect_output(out, "true\n")
 
+    # Test addy.io alias management endpoints
+    cmd = [
+      "curl --fail --globoff",
+      "-H \"Accept: application/json\"",
+      "-H \"Authorization: Bearer ${token}\"",
+      "'localhost:3000/api/v1/aliases?filter[search]=somealias&page[size]=10'",
+      "| jq -c '[.data[].email, .meta.total]'",
+    ]
+    out = machine.succeed(' '.join(cmd))
+    expect_output(out, "[\"somealias@example.com\",1]\n")
+
+    machine.succeed("curl --fail -X DELETE -H \"Authorization: Bearer ${token}\" localhost:3000/api/v1/active-aliases/somealias@example.com")
+    cmd = [
+      "curl --fail",
+      "-H \"Authorization: Bearer ${token}\"",
+      "localhost:3000/api/v1/aliases/somealias@example.com",
+      "| jq '.data.active'",
+    ]
+    out = machine.succeed(' '.join(cmd))
+    expect_output(out, "false\n")
+
     # Test SimpleLogin endpoint
     cmd = [
       "curl --fail -X POST",
@@ -77,5 +100,28 @@ in
     ]
     out = machine.succeed(' '.join(cmd))
     expect_output(out, "true\n")
+
+    # Test SimpleLogin alias management endpoints
+    cmd = [
+      "curl --fail -X POST",
+      "-H \"Authentication: ${token}\"",
+      "localhost:3000/api/aliases/1/toggle",
+      "| jq '.enabled'",
+    ]
+    out = machine.succeed(' '.join(cmd))
+    expect_output(out, "true\n")
+
+    # Test native API
+    cmd = [
+      "curl --fail -X PATCH",
+      "-H \"Content-Type: application/json\"",
+      "-H \"Authorization: Bearer ${token}\"",
+      "--data '{\"comment\":\"Changed comment\"}'",
+      "localhost:3000/api/idmail/v1/aliases/somealias@example.com",
+      "| jq -r '.comment'",
+    ]
+    out = machine.succeed(' '.join(cmd))
+    expect_output(out, "Changed comment\n")
+    machine.fail("curl --fail -H \"Authorization: Bearer ${token}\" localhost:3000/api/idmail/v1/users")
   '';
 }
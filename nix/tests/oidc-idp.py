@@ -0,0 +1,110 @@
+#!/usr/bin/env python3
+"""
+A minimal OpenID Connect provider for tests. Every authorization request is granted
+for the current identity, which can be changed with GET /identity?sub=...&name=...&groups=a,b.
+ID tokens are signed with HS256 using the client secret.
+"""
+
+import base64
+import hashlib
+import hmac
+import json
+import os
+import secrets
+import time
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from urllib.parse import parse_qs, urlencode, urlparse
+
+PORT = int(os.environ.get("IDP_PORT", "9000"))
+ISSUER = os.environ.get("IDP_ISSUER", f"http://localhost:{PORT}")
+CLIENT_ID = os.environ.get("IDP_CLIENT_ID", "idmail")
+CLIENT_SECRET = os.environ.get("IDP_CLIENT_SECRET", "idmail-secret")
+
+identity = {"sub": "alice-subject", "name": "alice", "groups": []}
+codes = {}
+access_tokens = {}
+
+
+def b64(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+def sign(claims):
+    header = b64(json.dumps({"alg": "HS256", "typ": "JWT"}).encode())
+    payload = b64(json.dumps(claims).encode())
+    signature = hmac.new(CLIENT_SECRET.encode(), f"{header}.{payload}".encode(), hashlib.sha256).digest()
+    return f"{header}.{payload}.{b64(signature)}"
+
+
+class Handler(BaseHTTPRequestHandler):
+    def send_json(self, data, status=200):
+        body = json.dumps(data).encode()
+        self.send_response(status)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def do_GET(self):
+        url = urlparse(self.path)
+        query = {k: v[0] for k, v in parse_qs(url.query).items()}
+        if url.path == "/.well-known/openid-configuration":
+            self.send_json({
+                "issuer": ISSUER,
+                "authorization_endpoint": f"{ISSUER}/authorize",
+                "token_endpoint": f"{ISSUER}/token",
+                "userinfo_endpoint": f"{ISSUER}/userinfo",
+                "jwks_uri": f"{ISSUER}/jwks",
+                "response_types_supported": ["code"],
+                "subject_types_supported": ["public"],
+                "id_token_signing_alg_values_supported": ["HS256"],
+                "code_challenge_methods_supported": ["S256"],
+            })
+        elif url.path == "/jwks":
+            self.send_json({"keys": []})
+        elif url.path == "/identity":
+            identity.update(sub=query["sub"], name=query["name"], groups=[x for x in query.get("groups", "").split(",") if x])
+            self.send_json(identity)
+        elif url.path == "/authorize":
+            if query.get("client_id") != CLIENT_ID or query.get("code_challenge_method") != "S256":
+                self.send_json({"error": "invalid_request"}, 400)
+                return
+            code = secrets.token_urlsafe(16)
+            codes[code] = dict(identity, nonce=query["nonce"], challenge=query["code_challenge"], redirect_uri=query["redirect_uri"])
+            self.send_response(302)
+            self.send_header("Location", f"{query['redirect_uri']}?{urlencode({'code': code, 'state': query['state']})}")
+            self.end_headers()
+        elif url.path == "/userinfo":
+            token = self.headers.get("Authorization", "").removeprefix("Bearer ")
+            if token not in access_tokens:
+                self.send_json({"error": "invalid_token"}, 401)
+                return
+            grant = access_tokens[token]
+            self.send_json({"sub": grant["sub"], "preferred_username": grant["name"], "groups": grant["groups"]})
+        else:
+            self.send_json({"error": "not_found"}, 404)
+
+    def do_POST(self):
+        if urlparse(self.path).path != "/token":
+            self.send_json({"error": "not_found"}, 404)
+            return
+        body = self.rfile.read(int(self.headers.get("Content-Length", 0))).decode()
+        form = {k: v[0] for k, v in parse_qs(body).items()}
+        expected_auth = "Basic " + base64.b64encode(f"{CLIENT_ID}:{CLIENT_SECRET}".encode()).decode()
+        grant = codes.pop(form.get("code"), None)
+        if self.headers.get("Authorization") != expected_auth:
+            self.send_json({"error": "invalid_client"}, 401)
+        elif grant is None or form.get("redirect_uri") != grant["redirect_uri"]:
+            self.send_json({"error": "invalid_grant"}, 400)
+        elif b64(hashlib.sha256(form.get("code_verifier", "").encode()).digest()) != grant["challenge"]:
+            self.send_json({"error": "invalid_grant", "error_description": "PKCE verification failed"}, 400)
+        else:
+            access_token = secrets.token_urlsafe(16)
+            access_tokens[access_token] = grant
+            now = int(time.time())
+            # Only the subject is part of the ID token, the other claims come from the userinfo endpoint
+            id_token = sign({"iss": ISSUER, "sub": grant["sub"], "aud": CLIENT_ID, "iat": now, "exp": now + 300, "nonce": grant["nonce"]})
+            self.send_json({"access_token": access_token, "token_type": "Bearer", "expires_in": 300, "id_token": id_token})
+
+
+ThreadingHTTPServer(("127.0.0.1", PORT), Handler).serve_forever()
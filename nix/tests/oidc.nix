@@ -0,0 +1,83 @@
+(import ./lib.nix) {
+  name = "idmail-oidc";
+  nodes.machine =
+    {
+      self,
+      pkgs,
+      ...
+    }:
+    {
+      imports = [ self.nixosModules.default ];
+      environment.systemPackages = [
+        pkgs.jq
+        pkgs.sqlite
+      ];
+
+      systemd.services.oidc-idp = {
+        wantedBy = [ "multi-user.target" ];
+        before = [ "idmail.service" ];
+        environment.IDP_CLIENT_SECRET = "idmail-secret";
+        serviceConfig.ExecStart = "${pkgs.python3}/bin/python3 ${./oidc-idp.py}";
+      };
+      systemd.services.idmail.after = [ "oidc-idp.service" ];
+
+      services.idmail = {
+        enable = true;
+        passwordLogin = false;
+        oidc = {
+          issuer = "http://localhost:9000";
+          clientSecretFile = pkgs.writeText "oidc-client-secret" "idmail-secret";
+          redirectUrl = "http://localhost:3000/auth/oidc/callback";
+          adminGroup = "idmail-admins";
+          autoCreate = true;
+        };
+        provision = {
+          enable = true;
+          users.test.password_hash = "$argon2id$v=19$m=4096,t=3,p=1$YXJnbGluYXJsZ2luMjRvaQ$DXdfVNRSFS1QSvJo7OmXIhAYYtT/D92Ku16DiJwxn8U";
+        };
+      };
+    };
+
+  testScript = ''
+    start_all()
+
+    def expect_output(output, expected):
+      assert output == expected, f"""
+        Expected output: {repr(expected)}
+        Actual output: {repr(output)}
+      """
+
+    def login(jar):
+      # idmail -> identity provider -> idmail callback, which starts the session
+      authorize = machine.succeed(f"curl --fail -s -c {jar} -o /dev/null -w '%{{redirect_url}}' localhost:3000/auth/oidc/login")
+      callback = machine.succeed(f"curl --fail -s -o /dev/null -w '%{{redirect_url}}' '{authorize}'")
+      return machine.succeed(f"curl -s -b {jar} -c {jar} -o /dev/null -w '%{{redirect_url}}' '{callback}'")
+
+    def user(name, column):
+      return machine.succeed(f"echo \"SELECT {column} FROM users WHERE username = '{name}'\" | sqlite3 /var/lib/idmail/idmail.db")
+
+    machine.wait_for_unit("oidc-idp.service")
+    machine.wait_for_unit("idmail.service")
+    machine.wait_for_open_port(9000)
+    machine.wait_for_open_port(3000)
+    machine.succeed("curl --fail -s localhost:3000/login | grep 'Sign in with Single Sign-On'")
+
+    # New users are created on their first login and admin rights follow the group claim
+    machine.succeed("curl --fail -s 'localhost:9000/identity?sub=alice-id&name=alice&groups=idmail-admins'")
+    expect_output(login("/tmp/alice"), "http://localhost:3000/\n")
+    expect_output(user("alice", "admin"), "1\n")
+    machine.succeed("curl --fail -s 'localhost:9000/identity?sub=alice-id&name=alice'")
+    expect_output(login("/tmp/alice"), "http://localhost:3000/\n")
+    expect_output(user("alice", "admin"), "0\n")
+
+    # Existing users are linked to their identity on the first login
+    machine.succeed("curl --fail -s 'localhost:9000/identity?sub=test-id&name=test'")
+    expect_output(login("/tmp/test"), "http://localhost:3000/\n")
+    expect_output(user("test", "oidc_subject"), "test-id\n")
+
+    # A different identity with the same user name is rejected
+    machine.succeed("curl --fail -s 'localhost:9000/identity?sub=mallory-id&name=test'")
+    out = login("/tmp/mallory")
+    assert out.startswith("http://localhost:3000/login?error="), out
+  '';
+}
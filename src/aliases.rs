@@ -1,13 +1,18 @@
+#![allow(clippy::unused_unit)] // The TableRow derive generates unit expressions
 use std::collections::VecDeque;
+use std::fmt;
 use std::ops::Range;
 use std::str::FromStr;
 
 use crate::auth::User;
+use crate::roles::Permission;
 use crate::utils::{DeleteModal, EditModal, Select};
-use crate::utils::{SliderRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+use crate::utils::{
+    OptionalTimediffRenderer, SliderRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer,
+};
 
 use anyhow::bail;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use faker_rand::en_us::internet::Username;
 use leptos::leptos_dom::is_browser;
 use leptos::{ev::MouseEvent, logging::error, *};
@@ -21,9 +26,12 @@ use serde::{Deserialize, Serialize};
 use sqlx::QueryBuilder;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
-#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow, utoipa::ToSchema))]
 #[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
 pub struct Alias {
+    /// A numeric id for API clients that don't identify aliases by their address
+    #[table(skip)]
+    pub id: i64,
     #[table(class = "w-40")]
     pub address: String,
     #[table(class = "w-40")]
@@ -31,6 +39,9 @@ pub struct Alias {
     pub comment: String,
     #[table(class = "w-1", title = "Received")]
     pub n_recv: i64,
+    /// After how many received messages in total the alias is deactivated
+    #[table(class = "w-1", title = "Left", getter = "remaining", none_value = "-", skip_sort)]
+    pub max_recv: Option<i64>,
     #[table(class = "w-1", title = "Sent")]
     pub n_sent: i64,
     #[table(class = "w-1", renderer = "SliderRenderer")]
@@ -39,6 +50,114 @@ pub struct Alias {
     pub owner: String,
     #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
     pub created_at: DateTime<Utc>,
+    #[table(class = "w-1", title = "Expires", renderer = "OptionalTimediffRenderer")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When an inactive alias will be activated
+    #[table(skip)]
+    pub active_from: Option<DateTime<Utc>>,
+    #[table(skip)]
+    pub on_expiry: ExpiryAction,
+}
+
+impl Alias {
+    /// How many messages the alias may still receive before it is deactivated, if it is limited.
+    pub fn remaining(&self) -> Option<i64> {
+        self.max_recv.map(|x| (x - self.n_recv).max(0))
+    }
+}
+
+/// What happens to an alias when it expires
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type, utoipa::ToSchema))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "TEXT", rename_all = "lowercase"))]
+#[serde(rename_all = "lowercase")]
+pub enum ExpiryAction {
+    #[default]
+    Deactivate,
+    Delete,
+}
+
+impl ExpiryAction {
+    pub const ALL: [ExpiryAction; 2] = [ExpiryAction::Deactivate, ExpiryAction::Delete];
+}
+
+impl fmt::Display for ExpiryAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpiryAction::Deactivate => write!(f, "deactivate"),
+            ExpiryAction::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+impl FromStr for ExpiryAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deactivate" => Ok(ExpiryAction::Deactivate),
+            "delete" => Ok(ExpiryAction::Delete),
+            _ => Err(format!("invalid expiry action '{s}'")),
+        }
+    }
+}
+
+/// Why an alias that has received as many messages as it may cannot be activated
+pub const EXHAUSTED_MESSAGE: &str =
+    "This alias has received all messages it may, raise or remove its limit to activate it again";
+
+/// Ensures that a limit of received messages allows at least one message.
+pub(crate) fn validate_max_recv(max_recv: Option<i64>) -> Result<(), String> {
+    match max_recv {
+        Some(x) if x < 1 => Err("The limit of received messages must be at least 1".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Formats a time as the local value of a `datetime-local` input.
+fn to_datetime_input(time: Option<DateTime<Utc>>) -> String {
+    time.map(|x| x.with_timezone(&Local).format("%Y-%m-%dT%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Parses the local value of a `datetime-local` input, which is empty for no time.
+fn from_datetime_input(value: &str) -> Result<Option<DateTime<Utc>>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|e| format!("invalid time '{value}': {e}"))?;
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|x| Some(x.with_timezone(&Utc)))
+        .ok_or_else(|| format!("'{value}' does not exist in the local time zone"))
+}
+
+/// Parses the limit of received messages of the edit modal, which is empty for no limit.
+fn from_max_recv_input(value: &str) -> Result<Option<i64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("invalid limit of received messages '{value}'"))
+}
+
+/// Ensures that an alias does not expire before it is activated.
+pub(crate) fn validate_schedule(
+    active_from: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(), String> {
+    match (active_from, expires_at) {
+        (Some(active_from), Some(expires_at)) if expires_at <= active_from => {
+            Err("The alias must expire after it is activated".to_string())
+        }
+        _ => Ok(()),
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -83,38 +202,403 @@ pub(crate) fn push_and_check_aliases_owner(query: &mut QueryBuilder<'_, sqlx::Sq
     query.push(" ) )");
 }
 
-#[server]
-pub async fn list_aliases(query: AliasQuery) -> Result<Vec<Alias>, ServerFnError> {
-    let user = crate::auth::auth_any().await?;
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{
+        push_and_check_aliases_owner, validate_address, validate_max_recv, validate_schedule, Alias, ExpiryAction,
+        EXHAUSTED_MESSAGE,
+    };
+    use crate::{
+        api::ApiError,
+        auth::User,
+        limits::Limits,
+        roles::Permission,
+        webhooks::{
+            ssr::{notify, notify_system},
+            WebhookEvent,
+        },
+    };
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+    use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+    use std::{ops::Range, time::Duration};
+
+    /// How often scheduled activations and expiries are applied
+    const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// The values of an alias that can be set when creating or updating it
+    pub struct AliasEdit {
+        pub alias: String,
+        pub domain: String,
+        pub target: String,
+        pub comment: String,
+        pub active: bool,
+        pub owner: String,
+        /// When the alias should be activated, it stays inactive until then
+        pub active_from: Option<DateTime<Utc>>,
+        pub expires_at: Option<DateTime<Utc>>,
+        pub on_expiry: ExpiryAction,
+        /// After how many received messages in total the alias is deactivated
+        pub max_recv: Option<i64>,
+    }
 
-    let AliasQuery { sort, range, search } = query;
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        search: &str,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<Alias>, ApiError> {
+        let mut query = QueryBuilder::new("SELECT * FROM aliases WHERE 1=1");
+        if !user.can(Permission::AliasesRead) {
+            push_and_check_aliases_owner(&mut query, user.username.clone());
+        }
+        if !search.is_empty() {
+            query.push(" AND ( address LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') OR comment LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') OR owner LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') )");
+        }
 
-    let mut query = QueryBuilder::new("SELECT * FROM aliases WHERE 1=1");
-    if !user.admin {
-        push_and_check_aliases_owner(&mut query, user.username.clone());
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<Alias>().fetch_all(pool).await?)
+    }
+
+    /// Returns the alias with the given address, if it is visible to the user.
+    pub async fn get(pool: &SqlitePool, user: &User, address: &str) -> Result<Alias, ApiError> {
+        get_with(pool, user, address, Permission::AliasesRead).await
+    }
+
+    /// Returns the alias with the given address, if the user owns it or has the given permission.
+    async fn get_with(
+        pool: &SqlitePool,
+        user: &User,
+        address: &str,
+        permission: Permission,
+    ) -> Result<Alias, ApiError> {
+        let mut query = QueryBuilder::new("SELECT * FROM aliases WHERE address = ");
+        query.push_bind(address);
+        if !user.can(permission) {
+            push_and_check_aliases_owner(&mut query, user.username.clone());
+        }
+
+        query
+            .build_query_as::<Alias>()
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Alias '{address}' does not exist")))
+    }
+
+    pub async fn delete(pool: &SqlitePool, user: &User, address: &str) -> Result<(), ApiError> {
+        let mut query = QueryBuilder::new("DELETE FROM aliases WHERE address = ");
+        query.push_bind(address);
+
+        // Others can only delete their own aliases
+        if !user.can(Permission::AliasesDelete) {
+            push_and_check_aliases_owner(&mut query, user.username.clone());
+        }
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("Alias '{address}' does not exist")));
+        }
+
+        notify(pool, user, WebhookEvent::AliasDeleted, json!({ "address": address })).await;
+        Ok(())
+    }
+
+    /// Fails if the user may not create another alias. Aliases count towards the account
+    /// that owns them, so a user's quota includes the aliases of all their mailboxes.
+    pub async fn ensure_quota(pool: &SqlitePool, user: &User) -> Result<(), ApiError> {
+        if user.admin {
+            return Ok(());
+        }
+
+        let limits = Limits::for_user(pool, user).await?;
+        if let Some(max_aliases) = limits.max_aliases {
+            let mut query = QueryBuilder::new("SELECT COUNT(*) FROM aliases WHERE 1=1");
+            push_and_check_aliases_owner(&mut query, user.username.clone());
+            if query.build_query_scalar::<i64>().fetch_one(pool).await? >= max_aliases as i64 {
+                return Err(ApiError::Forbidden(format!(
+                    "You have reached the limit of {max_aliases} aliases"
+                )));
+            }
+        }
+
+        if let Some(max_aliases_per_day) = limits.max_aliases_per_day {
+            let mut query =
+                QueryBuilder::new("SELECT COUNT(*) FROM aliases WHERE created_at > datetime('now', '-1 day')");
+            push_and_check_aliases_owner(&mut query, user.username.clone());
+            if query.build_query_scalar::<i64>().fetch_one(pool).await? >= max_aliases_per_day as i64 {
+                return Err(ApiError::Forbidden(format!(
+                    "You have reached the limit of {max_aliases_per_day} new aliases per day"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restricts an `INSERT ... SELECT` of a new alias to users within their quota. Unlike [`ensure_quota`],
+    /// this is atomic, so that concurrent requests cannot exceed the quota together.
+    pub async fn push_quota_check(
+        pool: &SqlitePool,
+        query: &mut QueryBuilder<'_, Sqlite>,
+        user: &User,
+    ) -> Result<(), ApiError> {
+        if user.admin {
+            return Ok(());
+        }
+
+        let limits = Limits::for_user(pool, user).await?;
+        if let Some(max_aliases) = limits.max_aliases {
+            query.push(" AND (SELECT COUNT(*) FROM aliases WHERE 1=1");
+            push_and_check_aliases_owner(query, user.username.clone());
+            query.push(") < ");
+            query.push_bind(max_aliases as i64);
+        }
+
+        if let Some(max_aliases_per_day) = limits.max_aliases_per_day {
+            query.push(" AND (SELECT COUNT(*) FROM aliases WHERE created_at > datetime('now', '-1 day')");
+            push_and_check_aliases_owner(query, user.username.clone());
+            query.push(") < ");
+            query.push_bind(max_aliases_per_day as i64);
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_or_update(
+        pool: &SqlitePool,
+        user: &User,
+        old_address: Option<String>,
+        edit: AliasEdit,
+    ) -> Result<Alias, ApiError> {
+        let AliasEdit {
+            alias,
+            domain,
+            target,
+            comment,
+            active,
+            owner,
+            active_from,
+            expires_at,
+            on_expiry,
+            max_recv,
+        } = edit;
+
+        validate_schedule(active_from, expires_at).map_err(ApiError::BadRequest)?;
+        validate_max_recv(max_recv).map_err(ApiError::BadRequest)?;
+        // An alias that is activated later must not be active before
+        let active = active && active_from.is_none_or(|x| x <= Utc::now());
+
+        let can_write = user.can(Permission::AliasesWrite);
+        let target = if target.is_empty() || !can_write {
+            if user.mailbox_owner.is_some() {
+                &user.username
+            } else {
+                if !crate::mailboxes::ssr::allowed_targets(pool, user)
+                    .await?
+                    .contains(&target)
+                {
+                    return Err(ApiError::BadRequest(
+                        "target must be set to a valid email address".to_string(),
+                    ));
+                }
+                &target
+            }
+        } else {
+            &target
+        };
+
+        let owner = if can_write {
+            // Only admins and those allowed to manage all aliases can assign other owners
+            owner.trim()
+        } else if user.mailbox_owner.is_some() {
+            // Mailbox users cannot change the owner
+            &user.username
+        } else {
+            // Normal users must use the target as an owner
+            target
+        };
+
+        // Empty owner -> self owned
+        let owner = if owner.is_empty() { &user.username } else { owner };
+
+        // Check if address is valid
+        let allowed_domains = crate::domains::ssr::allowed(pool, user).await?;
+        let Some((_, manages_domain)) = allowed_domains.iter().find(|x| x.0 == domain) else {
+            return Err(ApiError::BadRequest("domain must be set to a valid domain".to_string()));
+        };
+
+        // Reserved addresses are left to the domain's owner and members, or the mailboxes they own
+        let address = validate_address(&alias, &domain, can_write || *manages_domain)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+        let event = if old_address.is_some() {
+            WebhookEvent::AliasUpdated
+        } else {
+            WebhookEvent::AliasCreated
+        };
+        let mut query = if let Some(old_address) = old_address {
+            // Make sure the alias exists and may be changed by the user
+            let current = get_with(pool, user, &old_address, Permission::AliasesWrite).await?;
+            // Exhausted aliases would be deactivated again right away
+            if active && max_recv.is_some_and(|x| current.n_recv >= x) {
+                return Err(ApiError::BadRequest(EXHAUSTED_MESSAGE.to_string()));
+            }
+
+            let mut query = QueryBuilder::new("UPDATE aliases SET address = ");
+            query.push_bind(&address);
+            query.push(", domain = ");
+            query.push_bind(domain);
+            query.push(", target = ");
+            query.push_bind(target);
+            query.push(", comment = ");
+            query.push_bind(comment);
+            query.push(", active = ");
+            query.push_bind(active);
+            query.push(", owner = ");
+            query.push_bind(owner);
+            query.push(", active_from = datetime(");
+            query.push_bind(active_from);
+            query.push("), expires_at = datetime(");
+            query.push_bind(expires_at);
+            query.push("), on_expiry = ");
+            query.push_bind(on_expiry);
+            query.push(", max_recv = ");
+            query.push_bind(max_recv);
+            query.push(" WHERE address = ");
+            query.push_bind(old_address);
+            if !can_write {
+                push_and_check_aliases_owner(&mut query, user.username.clone());
+            }
+            // make sure that no mailbox exists with that address
+            query.push(" AND NOT EXISTS (SELECT * FROM mailboxes WHERE address = ");
+            query.push_bind(&address);
+            query.push(")");
+
+            query
+        } else {
+            ensure_quota(pool, user).await?;
+
+            let mut query = QueryBuilder::new(
+                "INSERT INTO aliases (address, domain, target, comment, active, owner, active_from, expires_at, on_expiry, max_recv)",
+            );
+            query.push("SELECT ");
+            query.push_bind(&address);
+            query.push(", ");
+            query.push_bind(domain);
+            query.push(", ");
+            query.push_bind(target);
+            query.push(", ");
+            query.push_bind(comment);
+            query.push(", ");
+            query.push_bind(active);
+            query.push(", ");
+            query.push_bind(owner);
+            query.push(", datetime(");
+            query.push_bind(active_from);
+            query.push("), datetime(");
+            query.push_bind(expires_at);
+            query.push("), ");
+            query.push_bind(on_expiry);
+            query.push(", ");
+            query.push_bind(max_recv);
+            // make sure that no mailbox exists with that address
+            query.push(" WHERE NOT EXISTS (SELECT * FROM mailboxes WHERE address = ");
+            query.push_bind(&address);
+            query.push(")");
+            push_quota_check(pool, &mut query, user).await?;
+
+            query
+        };
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            if matches!(event, WebhookEvent::AliasCreated) {
+                // Another alias may have been created in the meantime
+                ensure_quota(pool, user).await?;
+            }
+            return Err(ApiError::Conflict(
+                "This address is already in use by a mailbox!".to_string(),
+            ));
+        }
+
+        let alias = get(pool, user, &address).await?;
+        notify(pool, user, event, &alias).await;
+        Ok(alias)
     }
-    if !search.is_empty() {
-        query.push(" AND ( address LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') OR comment LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') OR owner LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') )");
+
+    /// Activates the aliases whose activation time has come, and deactivates or deletes those that expired.
+    /// Each schedule is applied only once, so that aliases can be changed by hand afterwards.
+    pub async fn apply_schedules(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let activated = sqlx::query_as::<_, Alias>(
+            "UPDATE aliases SET active = TRUE, active_from = NULL \
+            WHERE active_from <= CURRENT_TIMESTAMP RETURNING *",
+        )
+        .fetch_all(pool)
+        .await?;
+        for alias in activated {
+            log::info!("activated alias {}", alias.address);
+            notify_system(pool, WebhookEvent::AliasToggled, &alias).await;
+        }
+
+        let deleted = sqlx::query_scalar::<_, String>(
+            "DELETE FROM aliases WHERE expires_at <= CURRENT_TIMESTAMP AND on_expiry = 'delete' RETURNING address",
+        )
+        .fetch_all(pool)
+        .await?;
+        for address in deleted {
+            log::info!("deleted expired alias {address}");
+            notify_system(pool, WebhookEvent::AliasDeleted, json!({ "address": address })).await;
+        }
+
+        let deactivated = sqlx::query_as::<_, Alias>(
+            "UPDATE aliases SET active = FALSE, active_from = NULL, expires_at = NULL \
+            WHERE expires_at <= CURRENT_TIMESTAMP RETURNING *",
+        )
+        .fetch_all(pool)
+        .await?;
+        for alias in deactivated {
+            log::info!("deactivated expired alias {}", alias.address);
+            notify_system(pool, WebhookEvent::AliasToggled, &alias).await;
+        }
+
+        Ok(())
     }
 
-    if let Some(order) = Alias::sorting_to_sql(&sort) {
-        query.push(" ");
-        query.push(order);
+    /// Starts the background task that applies scheduled activations and expiries.
+    pub fn spawn_schedule_worker(pool: SqlitePool) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = apply_schedules(&pool).await {
+                    log::error!("failed to apply alias schedules: {e}");
+                }
+                tokio::time::sleep(SCHEDULE_INTERVAL).await;
+            }
+        });
     }
+}
+
+#[server]
+pub async fn list_aliases(query: AliasQuery) -> Result<Vec<Alias>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
 
-    query.push(" LIMIT ");
-    query.push_bind(range.len() as i64);
-    query.push(" OFFSET ");
-    query.push_bind(range.start as i64);
+    let AliasQuery { sort, range, search } = query;
 
     let pool = crate::database::ssr::pool()?;
-    Ok(query.build_query_as::<Alias>().fetch_all(&pool).await?)
+    Ok(ssr::list(&pool, &user, &search, Alias::sorting_to_sql(&sort), range).await?)
 }
 
 /// Count all aliases, or just active/inactive ones if specified.
@@ -123,7 +607,7 @@ pub async fn alias_count(active: Option<bool>, since: Option<DateTime<Utc>>) ->
     let user = crate::auth::auth_any().await?;
 
     let mut query = QueryBuilder::new("SELECT COUNT(*) FROM aliases WHERE 1=1");
-    if !user.admin {
+    if !user.can(Permission::AliasesRead) {
         push_and_check_aliases_owner(&mut query, user.username.clone());
     }
     if let Some(active) = active {
@@ -148,7 +632,7 @@ pub async fn count_sent_or_received(sent: bool) -> Result<usize, ServerFnError>
 
     let col_name = if sent { "n_sent" } else { "n_recv" };
     let mut query = QueryBuilder::new(format!("SELECT SUM({col_name}) FROM aliases WHERE 1=1"));
-    if !user.admin {
+    if !user.can(Permission::AliasesRead) {
         push_and_check_aliases_owner(&mut query, user.username.clone());
     }
 
@@ -161,20 +645,11 @@ pub async fn count_sent_or_received(sent: bool) -> Result<usize, ServerFnError>
 #[server]
 pub async fn delete_alias(address: String) -> Result<(), ServerFnError> {
     let user = crate::auth::auth_any().await?;
-
-    let mut query = QueryBuilder::new("DELETE FROM aliases WHERE address = ");
-    query.push_bind(address);
-
-    // Non-admins can only delete their own aliases
-    if !user.admin {
-        push_and_check_aliases_owner(&mut query, user.username.clone());
-    }
-
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
-    Ok(())
+    Ok(ssr::delete(&pool, &user, &address).await?)
 }
 
+#[allow(clippy::too_many_arguments)]
 #[server]
 pub async fn create_or_update_alias(
     old_address: Option<String>,
@@ -184,121 +659,53 @@ pub async fn create_or_update_alias(
     comment: String,
     active: bool,
     owner: String,
+    active_from: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+    on_expiry: ExpiryAction,
+    max_recv: Option<i64>,
 ) -> Result<(), ServerFnError> {
-    use crate::domains::allowed_domains;
-    use crate::mailboxes::allowed_targets;
-
     let user = crate::auth::auth_any().await?;
     let pool = crate::database::ssr::pool()?;
 
-    let target = if target.is_empty() || !user.admin {
-        if user.mailbox_owner.is_some() {
-            &user.username
-        } else {
-            if !allowed_targets().await?.contains(&target) {
-                return Err(ServerFnError::new("target must be set to a valid email address"));
-            }
-            &target
-        }
-    } else {
-        &target
-    };
-
-    let owner = if user.admin {
-        // Only admins can assign other owners
-        owner.trim()
-    } else if user.mailbox_owner.is_some() {
-        // Mailbox users cannot change the owner
-        &user.username
-    } else {
-        // Normal users must use the target as an owner
-        target
-    };
-
-    // Empty owner -> self owned
-    let owner = if owner.is_empty() { &user.username } else { owner };
-
-    // Check if address is valid
-    let allowed_domains = allowed_domains().await?;
-    let Some((_, domain_owner)) = allowed_domains.iter().find(|x| x.0 == domain) else {
-        return Err(ServerFnError::new("domain must be set to a valid domain"));
+    let edit = ssr::AliasEdit {
+        alias,
+        domain,
+        target,
+        comment,
+        active,
+        owner,
+        active_from,
+        expires_at,
+        on_expiry,
+        max_recv,
     };
-
-    let address = validate_address(
-        &alias,
-        &domain,
-        user.admin || *domain_owner == user.username || user.mailbox_owner.is_some_and(|x| x == *domain_owner),
-    )
-    .map_err(ServerFnError::new)?;
-
-    let mut query = if let Some(old_address) = old_address {
-        let mut query = QueryBuilder::new("UPDATE aliases SET address = ");
-        query.push_bind(&address);
-        query.push(", domain = ");
-        query.push_bind(domain);
-        query.push(", target = ");
-        query.push_bind(target);
-        query.push(", comment = ");
-        query.push_bind(comment);
-        query.push(", active = ");
-        query.push_bind(active);
-        query.push(", owner = ");
-        query.push_bind(owner);
-        query.push(" WHERE address = ");
-        query.push_bind(old_address);
-        if !user.admin {
-            push_and_check_aliases_owner(&mut query, user.username.clone());
-        }
-        // make sure that no mailbox exists with that address
-        query.push(" AND NOT EXISTS (SELECT * FROM mailboxes WHERE address = ");
-        query.push_bind(&address);
-        query.push(")");
-
-        query
-    } else {
-        let mut query = QueryBuilder::new("INSERT INTO aliases (address, domain, target, comment, active, owner)");
-        query.push("SELECT ");
-        query.push_bind(&address);
-        query.push(", ");
-        query.push_bind(domain);
-        query.push(", ");
-        query.push_bind(target);
-        query.push(", ");
-        query.push_bind(comment);
-        query.push(", ");
-        query.push_bind(active);
-        query.push(", ");
-        query.push_bind(owner);
-        // make sure that no mailbox exists with that address
-        query.push(" WHERE NOT EXISTS (SELECT * FROM mailboxes WHERE address = ");
-        query.push_bind(&address);
-        query.push(")");
-
-        query
-    };
-
-    if query.build().execute(&pool).await?.rows_affected() == 0 {
-        return Err(ServerFnError::new("This address is already in use by a mailbox!"));
-    }
-
+    ssr::create_or_update(&pool, &user, old_address, edit).await?;
     Ok(())
 }
 
 #[server]
 pub async fn update_alias_active(address: String, active: bool) -> Result<(), ServerFnError> {
     let user = crate::auth::auth_any().await?;
-    let mut query = QueryBuilder::new("UPDATE aliases SET active = ");
+    // Toggling by hand cancels a scheduled activation
+    let mut query = QueryBuilder::new("UPDATE aliases SET active_from = NULL, active = ");
     query.push_bind(active);
     query.push(" WHERE address = ");
-    query.push_bind(address);
+    query.push_bind(&address);
 
-    // Non-admins can only change their own aliases
-    if !user.admin {
+    // Others can only change their own aliases
+    if !user.can(Permission::AliasesToggle) {
         push_and_check_aliases_owner(&mut query, user.username.clone());
     }
 
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
+    if query.build().execute(&pool).await?.rows_affected() > 0 {
+        let alias = ssr::get(&pool, &user, &address).await?;
+        // Exhausted aliases are deactivated again right away
+        if active && !alias.active {
+            return Err(ServerFnError::new(EXHAUSTED_MESSAGE));
+        }
+        crate::webhooks::ssr::notify(&pool, &user, crate::webhooks::WebhookEvent::AliasToggled, &alias).await;
+    }
     Ok(())
 }
 
@@ -339,7 +746,7 @@ impl TableDataProvider<Alias> for AliasTableDataProvider {
 #[component]
 pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
     let mut rows = AliasTableDataProvider::default();
-    let default_sorting = VecDeque::from([(7, ColumnSort::Descending)]);
+    let default_sorting = VecDeque::from([(8, ColumnSort::Descending)]);
     rows.set_sorting(&default_sorting);
     let sorting = create_rw_signal(default_sorting);
 
@@ -390,9 +797,17 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
     let (edit_modal_input_comment, set_edit_modal_input_comment) = create_signal("".to_string());
     let (edit_modal_input_active, set_edit_modal_input_active) = create_signal(true);
     let (edit_modal_input_owner, set_edit_modal_input_owner) = create_signal("".to_string());
-
-    if !user.admin && user.mailbox_owner.is_none() {
-        // Non-admin users that are not mailboxes always need to use the
+    let (edit_modal_input_active_from, set_edit_modal_input_active_from) = create_signal("".to_string());
+    let (edit_modal_input_expires_at, set_edit_modal_input_expires_at) = create_signal("".to_string());
+    let (edit_modal_input_on_expiry, set_edit_modal_input_on_expiry) =
+        create_signal(ExpiryAction::default().to_string());
+    let (edit_modal_input_max_recv, set_edit_modal_input_max_recv) = create_signal("".to_string());
+    let (edit_modal_n_recv, set_edit_modal_n_recv) = create_signal(0);
+    let (on_expiry_choices, _) = create_signal(ExpiryAction::ALL.iter().map(|x| x.to_string()).collect());
+
+    let can_write = user.can(Permission::AliasesWrite);
+    if !can_write && user.mailbox_owner.is_none() {
+        // Other users that are not mailboxes always need to use the
         // target as the owner
         create_effect(move |_| {
             set_edit_modal_input_owner(edit_modal_input_target());
@@ -423,6 +838,11 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
             set_edit_modal_input_comment(edit_alias.comment.clone());
             set_edit_modal_input_active(edit_alias.active);
             set_edit_modal_input_owner(edit_alias.owner.clone());
+            set_edit_modal_input_active_from(to_datetime_input(edit_alias.active_from));
+            set_edit_modal_input_expires_at(to_datetime_input(edit_alias.expires_at));
+            set_edit_modal_input_on_expiry(edit_alias.on_expiry.to_string());
+            set_edit_modal_input_max_recv(edit_alias.max_recv.map(|x| x.to_string()).unwrap_or_default());
+            set_edit_modal_n_recv(edit_alias.n_recv);
         } else {
             // Only set the input domain if the current one is not in the list
             // of allowed domains. This allows users to keep the old value
@@ -442,11 +862,32 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
             }
             set_edit_modal_input_comment("".to_string());
             set_edit_modal_input_active(true);
+            set_edit_modal_input_active_from("".to_string());
+            set_edit_modal_input_expires_at("".to_string());
+            set_edit_modal_input_on_expiry(ExpiryAction::default().to_string());
+            set_edit_modal_input_max_recv("".to_string());
+            set_edit_modal_n_recv(0);
         }
     });
 
     let on_edit = move |(data, on_error): (Option<Alias>, Callback<String>)| {
         spawn_local(async move {
+            let active_from = match from_datetime_input(&edit_modal_input_active_from.get_untracked()) {
+                Ok(active_from) => active_from,
+                Err(e) => return on_error(e),
+            };
+            let expires_at = match from_datetime_input(&edit_modal_input_expires_at.get_untracked()) {
+                Ok(expires_at) => expires_at,
+                Err(e) => return on_error(e),
+            };
+            let on_expiry = match edit_modal_input_on_expiry.get_untracked().parse() {
+                Ok(on_expiry) => on_expiry,
+                Err(e) => return on_error(e),
+            };
+            let max_recv = match from_max_recv_input(&edit_modal_input_max_recv.get_untracked()) {
+                Ok(max_recv) => max_recv,
+                Err(e) => return on_error(e),
+            };
             if let Err(e) = create_or_update_alias(
                 data.map(|x| x.address),
                 edit_modal_input_alias.get_untracked(),
@@ -455,6 +896,10 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
                 edit_modal_input_comment.get_untracked(),
                 edit_modal_input_active.get_untracked(),
                 edit_modal_input_owner.get_untracked(),
+                active_from,
+                expires_at,
+                on_expiry,
+                max_recv,
             )
             .await
             {
@@ -520,7 +965,7 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
     });
     let has_invalid_target = create_memo(move |_| {
         email_address::EmailAddress::from_str(&edit_modal_input_target()).is_err()
-            || (!user.admin && edit_modal_input_target().is_empty())
+            || (!can_write && edit_modal_input_target().is_empty())
     });
 
     let errors = create_memo(move |_| {
@@ -535,6 +980,17 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
         if let Err(e) = email_address::EmailAddress::from_str(&edit_modal_input_target()) {
             errors.push(format!("invalid target address: {}", e));
         }
+        match (
+            from_datetime_input(&edit_modal_input_active_from()),
+            from_datetime_input(&edit_modal_input_expires_at()),
+        ) {
+            (Ok(active_from), Ok(expires_at)) => errors.extend(validate_schedule(active_from, expires_at).err()),
+            (active_from, expires_at) => errors.extend(active_from.err().into_iter().chain(expires_at.err())),
+        }
+        match from_max_recv_input(&edit_modal_input_max_recv()) {
+            Ok(max_recv) => errors.extend(validate_max_recv(max_recv).err()),
+            Err(e) => errors.push(e),
+        }
         errors
     });
 
@@ -667,7 +1123,7 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
                     Target
                 </label>
 
-                {if user.admin || user.mailbox_owner.is_some() {
+                {if can_write || user.mailbox_owner.is_some() {
                     view! {
                         <input
                             class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
@@ -682,7 +1138,7 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
 
                             on:input=move |ev| set_edit_modal_input_target(event_target_value(&ev))
                             prop:value=edit_modal_input_target
-                            disabled=!user.admin
+                            disabled=!can_write
                         />
                     }
                         .into_view()
@@ -727,7 +1183,7 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
                     placeholder=edit_modal_input_owner
                     on:input=move |ev| set_edit_modal_input_owner(event_target_value(&ev))
                     prop:value=edit_modal_input_owner
-                    disabled=!user.admin
+                    disabled=!can_write
                 />
             </div>
             <div class="flex flex-row gap-2 mt-2 items-center">
@@ -745,6 +1201,65 @@ pub fn Aliases(user: User, reload_stats: Callback<()>) -> impl IntoView {
                     Active
                 </label>
             </div>
+            <div class="flex flex-col sm:flex-row gap-2">
+                <div class="flex flex-1 flex-col gap-2">
+                    <label class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70" for="active_from">
+                        Active from
+                    </label>
+                    <input
+                        id="active_from"
+                        class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                        type="datetime-local"
+                        on:input=move |ev| set_edit_modal_input_active_from(event_target_value(&ev))
+                        prop:value=edit_modal_input_active_from
+                    />
+                </div>
+                <div class="flex flex-1 flex-col gap-2">
+                    <label class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70" for="expires_at">
+                        Expires
+                    </label>
+                    <input
+                        id="expires_at"
+                        class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                        type="datetime-local"
+                        on:input=move |ev| set_edit_modal_input_expires_at(event_target_value(&ev))
+                        prop:value=edit_modal_input_expires_at
+                    />
+                </div>
+                <div class="flex flex-col gap-2">
+                    <label class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70" for="on_expiry">
+                        On expiry
+                    </label>
+                    <Select
+                        class="w-full h-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900"
+                        choices=on_expiry_choices
+                        value=edit_modal_input_on_expiry
+                        set_value=set_edit_modal_input_on_expiry
+                    />
+                </div>
+            </div>
+            <p class="text-sm text-gray-500 dark:text-gray-400">
+                "Leave empty for no schedule. An alias stays inactive until it is activated, and is deactivated or deleted when it expires."
+            </p>
+            <div class="flex flex-col gap-2">
+                <label class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70" for="max_recv">
+                    Max. received messages
+                </label>
+                <input
+                    id="max_recv"
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="number"
+                    min="1"
+                    placeholder="Unlimited"
+                    on:input=move |ev| set_edit_modal_input_max_recv(event_target_value(&ev))
+                    prop:value=edit_modal_input_max_recv
+                />
+            </div>
+            <p class="text-sm text-gray-500 dark:text-gray-400">
+                "The alias is deactivated once it has received this many messages in total, "
+                {edit_modal_n_recv}
+                " so far."
+            </p>
         </EditModal>
     }
 }
@@ -1,18 +1,40 @@
-use crate::{aliases::validate_address, auth::User, state::AppState};
+use crate::{
+    aliases::{self, push_and_check_aliases_owner, validate_address, Alias, EXHAUSTED_MESSAGE},
+    api_tokens::{self, ssr::Access},
+    auth::{ssr::AuthSession, User},
+    client_ip::client_ip,
+    domains,
+    limits::Limits,
+    lockouts, mailboxes,
+    roles::Permission,
+    state::AppState,
+    webhooks::{ssr::notify, WebhookEvent},
+};
+use async_trait::async_trait;
 use axum::{
-    extract::{self, rejection::JsonRejection, State},
+    extract::{rejection::JsonRejection, FromRequestParts},
     response::IntoResponse,
     Json,
 };
-use axum_extra::extract::WithRejection;
-use faker_rand::en_us::internet::Username;
-use http::{HeaderMap, StatusCode};
+use faker_rand::{en_us::internet::Username, lorem::Word};
+use http::request::Parts;
+use http::{header::RETRY_AFTER, HeaderMap, HeaderValue, StatusCode};
+use leptos::ServerFnError;
 use rand::seq::SliceRandom;
 use rand::{rngs::OsRng, Rng};
-use serde::Deserialize;
-use serde_json::json;
+use serde::Serialize;
 use sqlx::QueryBuilder;
+use std::{convert::Infallible, net::IpAddr};
 use thiserror::Error;
+use utoipa::ToSchema;
+
+pub mod addy_io;
+pub mod duckduckgo;
+pub mod fastmail;
+pub mod firefox_relay;
+pub mod idmail;
+pub mod openapi;
+pub mod simple_login;
 
 // We derive `thiserror::Error`
 #[derive(Debug, Error)]
@@ -21,82 +43,337 @@ pub enum ApiError {
     // implementation. See `thiserror` docs for more information
     #[error(transparent)]
     JsonExtractorRejection(#[from] JsonRejection),
+    /// Any database error. The details are logged, but never sent to the client.
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
     /// Unauthorized
-    #[error("Unauthorized")]
+    #[error("{0}")]
     Unauthorized(String),
+    /// Forbidden
+    #[error("{0}")]
+    Forbidden(String),
     /// Bad Request
-    #[error("BadRequest")]
+    #[error("{0}")]
     BadRequest(String),
+    /// Not Found
+    #[error("{0}")]
+    NotFound(String),
+    /// Conflict
+    #[error("{0}")]
+    Conflict(String),
+    /// Too Many Requests, with the number of seconds after which the client may retry
+    #[error("{0}")]
+    TooManyRequests(String, u64),
     /// Internal Server Error
-    #[error("ServerError")]
+    #[error("{0}")]
     ServerError(String),
 }
 
+impl From<ServerFnError> for ApiError {
+    fn from(e: ServerFnError) -> Self {
+        match e {
+            ServerFnError::ServerError(message) => ApiError::BadRequest(message),
+            e => ApiError::ServerError(e.to_string()),
+        }
+    }
+}
+
+/// The body of any error response
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// A description of the error
+    error: String,
+    /// The same description, for clients that expect it here
+    #[serde(rename = "statusText")]
+    status_text: String,
+}
+
 // We implement `IntoResponse` so ApiError can be used as a response
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
+        let mut retry_after = None;
         let (status, message) = match self {
             ApiError::JsonExtractorRejection(json_rejection) => (json_rejection.status(), json_rejection.body_text()),
+            ApiError::Database(e) if e.as_database_error().is_some_and(|x| x.is_unique_violation()) => {
+                (StatusCode::CONFLICT, "already exists".to_string())
+            }
+            ApiError::Database(e) => {
+                log::error!("database error in api request: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "database error".to_string())
+            }
             ApiError::Unauthorized(message) => (StatusCode::UNAUTHORIZED, message),
+            ApiError::Forbidden(message) => (StatusCode::FORBIDDEN, message),
             ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Conflict(message) => (StatusCode::CONFLICT, message),
+            ApiError::TooManyRequests(message, seconds) => {
+                retry_after = Some(seconds);
+                (StatusCode::TOO_MANY_REQUESTS, message)
+            }
             ApiError::ServerError(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
         };
 
-        let payload = json!({
-            "error": message,
-            "statusText": message,
-        });
+        let payload = ErrorResponse {
+            status_text: message.clone(),
+            error: message,
+        };
+
+        let mut response = (status, Json(payload)).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
+    }
+}
+
+/// Everything an API request can be authenticated with
+pub struct Credentials {
+    headers: HeaderMap,
+    /// The address of the client, if known
+    ip: Option<IpAddr>,
+    /// The user of the current session, if any
+    session_user: Option<User>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Credentials {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ip = client_ip(&parts.headers, &parts.extensions);
+        let session_user = AuthSession::from_request_parts(parts, state)
+            .await
+            .ok()
+            .and_then(|auth_session| auth_session.current_user);
 
-        (status, Json(payload)).into_response()
+        Ok(Self {
+            headers: parts.headers.clone(),
+            ip,
+            session_user,
+        })
     }
 }
 
-async fn login_with_api_token(app_state: &AppState, headers: &HeaderMap) -> Result<User, ApiError> {
-    let Some(api_token) = headers.get("Authorization").and_then(|x| x.to_str().ok()) else {
+/// Counts the request against the rate limit of the client address, if it is known.
+fn check_ip_rate_limit(app_state: &AppState, credentials: &Credentials) -> Result<(), ApiError> {
+    let Some(ip) = credentials.ip else {
+        return Ok(());
+    };
+    app_state
+        .rate_limiter
+        .check(format!("ip:{ip}"), Limits::global().api_requests_per_minute_per_ip)
+}
+
+async fn login_with_api_token(
+    app_state: &AppState,
+    credentials: &Credentials,
+    access: Access,
+) -> Result<User, ApiError> {
+    check_ip_rate_limit(app_state, credentials)?;
+    check_api_token(app_state, credentials, access).await
+}
+
+async fn check_api_token(app_state: &AppState, credentials: &Credentials, access: Access) -> Result<User, ApiError> {
+    let headers = &credentials.headers;
+    // SimpleLogin clients send the token in a non-standard Authentication header
+    let Some(api_token) = headers
+        .get("Authorization")
+        .or_else(|| headers.get("Authentication"))
+        .and_then(|x| x.to_str().ok())
+    else {
         return Err(ApiError::Unauthorized("Missing API token in request".to_string()));
     };
 
-    let api_token = api_token.strip_prefix("Bearer").unwrap_or(api_token).trim_start();
-    let Some(user) = User::get_by_api_token(api_token, &app_state.pool).await else {
+    // Firefox Relay clients use the "Token" scheme instead of "Bearer"
+    let api_token = api_token
+        .strip_prefix("Bearer")
+        .or_else(|| api_token.strip_prefix("Token "))
+        .unwrap_or(api_token)
+        .trim_start();
+    // Invalid tokens count as failed logins of the client address
+    lockouts::ssr::check(&app_state.pool, None, credentials.ip).await?;
+    let Some((user, token)) = User::get_by_api_token(api_token, &app_state.pool).await else {
+        lockouts::ssr::record_failure(&app_state.pool, None, credentials.ip).await?;
         return Err(ApiError::Unauthorized("Invalid API token".to_string()));
     };
 
-    log::info!("api token used successfully for user '{}'", user.username);
+    let limits = Limits::for_user(&app_state.pool, &user).await?;
+    app_state
+        .rate_limiter
+        .check(format!("token:{}", token.id), limits.api_requests_per_minute)?;
+
+    api_tokens::ssr::mark_used(&app_state.pool, token.id, credentials.ip).await?;
+    if !token.scope.allows(access) {
+        return Err(ApiError::Forbidden(format!(
+            "API token with scope '{}' cannot be used for this request",
+            token.scope
+        )));
+    }
+
+    log::info!(
+        "api token '{}' used successfully for user '{}'",
+        token.name,
+        user.username
+    );
     Ok(user)
 }
 
-async fn allowed_domains(app_state: &AppState, user: &User) -> Result<Vec<String>, String> {
-    let mut query = QueryBuilder::new("SELECT domain FROM domains");
-    query.push(" WHERE active = TRUE AND (public = TRUE");
-    if let Some(mailbox_owner) = &user.mailbox_owner {
-        query.push(" OR owner = ");
-        query.push_bind(mailbox_owner.clone());
+/// Authenticates a request by its API token, or by the session of a logged in user if no token is given.
+async fn authenticate(app_state: &AppState, credentials: &Credentials, access: Access) -> Result<User, ApiError> {
+    check_ip_rate_limit(app_state, credentials)?;
+    if credentials.headers.contains_key("Authorization") {
+        return check_api_token(app_state, credentials, access).await;
+    }
+
+    credentials
+        .session_user
+        .clone()
+        .ok_or_else(|| ApiError::Unauthorized("Missing API token or session in request".to_string()))
+}
+
+/// Returns the domains the user may create new aliases on.
+async fn allowed_domains(app_state: &AppState, user: &User) -> Result<Vec<String>, ApiError> {
+    let domains = domains::ssr::allowed(&app_state.pool, user).await?;
+    Ok(domains.into_iter().map(|(domain, _managed)| domain).collect())
+}
+
+/// Returns the target for a new alias, which must be a mailbox the user may target.
+/// Without an explicit target, the user's first mailbox is used.
+async fn alias_target(app_state: &AppState, user: &User, target: Option<String>) -> Result<String, ApiError> {
+    let allowed_targets = mailboxes::ssr::allowed_targets(&app_state.pool, user).await?;
+    match target {
+        Some(target) if allowed_targets.contains(&target) => Ok(target),
+        Some(target) => Err(ApiError::BadRequest(format!(
+            "Target mailbox '{target}' does not exist or is not allowed to be used"
+        ))),
+        None => allowed_targets
+            .into_iter()
+            .next()
+            .ok_or_else(|| ApiError::BadRequest("no usable target mailbox exists".to_string())),
+    }
+}
+
+/// Returns the alias with the given address, if it is visible to the given user.
+async fn get_alias(app_state: &AppState, user: &User, address: &str) -> Result<Alias, ApiError> {
+    aliases::ssr::get(&app_state.pool, user, address).await
+}
+
+/// Returns the alias with the given numeric id, if it is visible to the given user.
+async fn get_alias_by_id(app_state: &AppState, user: &User, id: i64) -> Result<Alias, ApiError> {
+    let mut query = QueryBuilder::new("SELECT * FROM aliases WHERE id = ");
+    query.push_bind(id);
+    if !user.can(Permission::AliasesRead) {
+        push_and_check_aliases_owner(&mut query, user.username.clone());
     }
-    query.push(")");
 
     query
-        .build_query_scalar::<String>()
-        .fetch_all(&app_state.pool)
-        .await
-        .map_err(|e| e.to_string())
+        .build_query_as::<Alias>()
+        .fetch_optional(&app_state.pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Alias {id} does not exist")))
+}
+
+/// Combines the note given by the client with the hostname the alias is used for.
+fn comment_for(note: Option<String>, hostname: Option<String>) -> String {
+    let note = note.unwrap_or_default();
+    match hostname.filter(|x| !x.is_empty()) {
+        Some(hostname) if note.is_empty() => hostname,
+        Some(hostname) if !note.contains(&hostname) => format!("{note} ({hostname})"),
+        _ => note,
+    }
+}
+
+/// Sets the active state of an alias owned by the user and returns the updated alias.
+async fn update_alias_active(
+    app_state: &AppState,
+    user: &User,
+    address: &str,
+    active: bool,
+) -> Result<Alias, ApiError> {
+    // Toggling by hand cancels a scheduled activation
+    let mut query = QueryBuilder::new("UPDATE aliases SET active_from = NULL, active = ");
+    query.push_bind(active);
+    query.push(" WHERE address = ");
+    query.push_bind(address);
+    if !user.can(Permission::AliasesToggle) {
+        push_and_check_aliases_owner(&mut query, user.username.clone());
+    }
+    if query.build().execute(&app_state.pool).await?.rows_affected() == 0 {
+        return Err(ApiError::NotFound(format!("Alias '{address}' does not exist")));
+    }
+
+    let alias = get_alias(app_state, user, address).await?;
+    // Exhausted aliases are deactivated again right away
+    if active && !alias.active {
+        return Err(ApiError::BadRequest(EXHAUSTED_MESSAGE.to_string()));
+    }
+    notify(&app_state.pool, user, WebhookEvent::AliasToggled, &alias).await;
+    Ok(alias)
+}
+
+/// Sets the comment of an alias owned by the user and returns the updated alias.
+async fn update_alias_comment(
+    app_state: &AppState,
+    user: &User,
+    address: &str,
+    comment: &str,
+) -> Result<Alias, ApiError> {
+    let mut query = QueryBuilder::new("UPDATE aliases SET comment = ");
+    query.push_bind(comment);
+    query.push(" WHERE address = ");
+    query.push_bind(address);
+    if !user.can(Permission::AliasesWrite) {
+        push_and_check_aliases_owner(&mut query, user.username.clone());
+    }
+    if query.build().execute(&app_state.pool).await?.rows_affected() == 0 {
+        return Err(ApiError::NotFound(format!("Alias '{address}' does not exist")));
+    }
+
+    let alias = get_alias(app_state, user, address).await?;
+    notify(&app_state.pool, user, WebhookEvent::AliasUpdated, &alias).await;
+    Ok(alias)
+}
+
+/// Deletes an alias owned by the user.
+async fn delete_alias(app_state: &AppState, user: &User, address: &str) -> Result<(), ApiError> {
+    aliases::ssr::delete(&app_state.pool, user, address).await
 }
 
-async fn create_random_alias(
+/// Creates a new active alias, which expires after the given number of seconds, if any.
+async fn create_alias(
     app_state: &AppState,
     user: &User,
+    localpart: &str,
     domain: Option<String>,
+    target: Option<String>,
     comment: &str,
-) -> Result<(String, String, String), ApiError> {
-    let target = &user.username;
-    let owner = &user.username;
-    let allowed_domains = allowed_domains(app_state, user).await.map_err(ApiError::BadRequest)?;
+    expires_in: Option<u64>,
+) -> Result<Alias, ApiError> {
+    let expires_at = match expires_in {
+        None => None,
+        Some(seconds) => Some(
+            i64::try_from(seconds)
+                .ok()
+                .filter(|x| *x > 0)
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|x| chrono::Utc::now().checked_add_signed(x))
+                .ok_or_else(|| ApiError::BadRequest("expires_in must be a positive number of seconds".to_string()))?,
+        ),
+    };
+    aliases::ssr::ensure_quota(&app_state.pool, user).await?;
+    let target = &alias_target(app_state, user, target).await?;
+    // Like in the web interface, aliases of normal users are owned by their target mailbox
+    let owner = if user.mailbox_owner.is_some() {
+        &user.username
+    } else {
+        target
+    };
+    let allowed_domains = allowed_domains(app_state, user).await?;
 
     let Some(domain) = domain.or_else(|| allowed_domains.choose(&mut OsRng).cloned()) else {
         return Err(ApiError::BadRequest("no usable domains are configured".to_string()));
     };
 
-    let alias = OsRng.gen::<Username>().to_string();
-
     // Check if resulting address is valid
     if !allowed_domains.contains(&domain) {
         return Err(ApiError::BadRequest(format!(
@@ -105,10 +382,11 @@ async fn create_random_alias(
         )));
     };
 
-    let address = validate_address(&alias, &domain, false /* never allow reserved */)
+    let address = validate_address(localpart, &domain, false /* never allow reserved */)
         .map_err(|e| ApiError::BadRequest(e.to_string()))?;
 
-    let mut query = QueryBuilder::new("INSERT INTO aliases (address, domain, target, comment, active, owner)");
+    let mut query =
+        QueryBuilder::new("INSERT INTO aliases (address, domain, target, comment, active, owner, expires_at)");
     query.push("SELECT ");
     query.push_bind(&address);
     query.push(", ");
@@ -121,102 +399,65 @@ async fn create_random_alias(
     query.push_bind(true);
     query.push(", ");
     query.push_bind(owner);
-    // make sure that no mailbox exists with that address
+    query.push(", datetime(");
+    query.push_bind(expires_at);
+    query.push(")");
+    // make sure that no mailbox or alias exists with that address
     query.push(" WHERE NOT EXISTS (SELECT * FROM mailboxes WHERE address = ");
     query.push_bind(&address);
+    query.push(") AND NOT EXISTS (SELECT * FROM aliases WHERE address = ");
+    query.push_bind(&address);
     query.push(")");
+    aliases::ssr::push_quota_check(&app_state.pool, &mut query, user).await?;
 
-    if query
-        .build()
-        .execute(&app_state.pool)
-        .await
-        .map_err(|e| {
-            log::error!("database error while creating alias via api token: {e}");
-            ApiError::ServerError("database error".to_string())
-        })?
-        .rows_affected()
-        == 0
-    {
-        return Err(ApiError::ServerError(
-            "This address is already in use by a mailbox!".to_string(),
-        ));
+    if query.build().execute(&app_state.pool).await?.rows_affected() == 0 {
+        // Another alias may have been created in the meantime
+        aliases::ssr::ensure_quota(&app_state.pool, user).await?;
+        return Err(ApiError::Conflict(format!(
+            "The address '{address}' is already in use!"
+        )));
     }
 
-    Ok((address, alias, domain))
+    let alias = get_alias(app_state, user, &address).await?;
+    notify(&app_state.pool, user, WebhookEvent::AliasCreated, &alias).await;
+    Ok(alias)
 }
 
-#[derive(Deserialize)]
-pub struct SimpleLoginRequest {
-    note: String,
+/// How the local part of a new alias is chosen
+pub enum LocalPart {
+    /// A random username, as suggested by the web interface
+    Username,
+    /// Random lowercase letters and digits
+    RandomCharacters,
+    /// A random UUID
+    Uuid,
+    /// Two random words followed by a number
+    RandomWords,
+    /// A local part chosen by the client
+    Custom(String),
 }
 
-pub async fn create_simple_login(
-    State(app_state): State<AppState>,
-    headers: HeaderMap,
-    WithRejection(extract::Json(body), _): WithRejection<extract::Json<SimpleLoginRequest>, ApiError>,
-) -> Result<impl IntoResponse, ApiError> {
-    let user = login_with_api_token(&app_state, &headers).await?;
-    let (address, _, _) = create_random_alias(&app_state, &user, None, &body.note).await?;
-
-    Ok((
-        StatusCode::CREATED,
-        Json(json!({
-            "alias": address,
-        })),
-    )
-        .into_response())
-}
+impl LocalPart {
+    /// Length of generated random character local parts
+    const RANDOM_CHARACTERS: usize = 8;
 
-#[derive(Deserialize)]
-pub struct AddyIoRequest {
-    domain: String,
-    description: Option<String>,
-}
-
-pub async fn create_addy_io(
-    State(app_state): State<AppState>,
-    headers: HeaderMap,
-    WithRejection(extract::Json(body), _): WithRejection<extract::Json<AddyIoRequest>, ApiError>,
-) -> Result<impl IntoResponse, ApiError> {
-    let user = login_with_api_token(&app_state, &headers).await?;
-    let description = body.description.unwrap_or("".to_string());
-    let (address, _, domain) = create_random_alias(
-        &app_state,
-        &user,
-        (!body.domain.is_empty() && body.domain != "random").then_some(body.domain),
-        &description,
-    )
-    .await?;
-
-    Ok((
-        StatusCode::CREATED,
-        Json(json!({
-            "data": {
-                "id": "00000000-0000-0000-0000-000000000000",
-                "user_id": "00000000-0000-0000-0000-000000000000",
-                "aliasable_id": null,
-                "aliasable_type": null,
-                "local_part": "00000000-0000-0000-0000-000000000000",
-                "extension": null,
-                "domain": domain,
-                "email": address,
-                "active": true,
-                "description": description,
-                "from_name": null,
-                "emails_forwarded": 0,
-                "emails_blocked": 0,
-                "emails_replied": 0,
-                "emails_sent": 0,
-                "recipients": [],
-                "last_forwarded": "2000-01-01 00:00:00",
-                "last_blocked": null,
-                "last_replied": null,
-                "last_sent": null,
-                "created_at": "2000-01-01 00:00:00",
-                "updated_at": "2000-01-01 00:00:00",
-                "deleted_at": null
+    fn generate(self) -> String {
+        match self {
+            LocalPart::Username => OsRng.gen::<Username>().to_string(),
+            LocalPart::RandomCharacters => {
+                const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+                (0..Self::RANDOM_CHARACTERS)
+                    .map(|_| *CHARSET.choose(&mut OsRng).expect("charset is not empty") as char)
+                    .collect()
             }
-        })),
-    )
-        .into_response())
+            LocalPart::Uuid => uuid::Builder::from_random_bytes(OsRng.gen()).into_uuid().to_string(),
+            LocalPart::RandomWords => format!(
+                "{}.{}{}",
+                OsRng.gen::<Word>(),
+                OsRng.gen::<Word>(),
+                OsRng.gen_range(0..1000)
+            ),
+            LocalPart::Custom(local_part) => local_part.trim().to_lowercase(),
+        }
+    }
 }
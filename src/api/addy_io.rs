@@ -0,0 +1,621 @@
+//! Endpoints compatible with the addy.io (AnonAddy) v1 API.
+//!
+//! Aliases are identified by their full address, which is used in place of the UUIDs addy.io hands out.
+
+use super::{
+    allowed_domains, create_alias as create_alias_by_address, delete_alias as delete_alias_by_address, get_alias,
+    login_with_api_token, update_alias_active, update_alias_comment, ApiError, Credentials, ErrorResponse, LocalPart,
+};
+use crate::{
+    aliases::{push_and_check_aliases_owner, Alias},
+    api_tokens::ssr::Access,
+    mailboxes,
+    roles::Permission,
+    state::AppState,
+};
+use axum::{
+    extract::{self, OriginalUri, Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite};
+use utoipa::{IntoParams, ToSchema};
+
+/// The maximum (and default) number of aliases returned per page.
+const MAX_PAGE_SIZE: i64 = 100;
+/// The alias format reported to clients, which is used if a request does not choose one
+const DEFAULT_ALIAS_FORMAT: &str = "random_words";
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// A response of addy.io, which wraps everything in `data`
+#[derive(Serialize, ToSchema)]
+pub struct AddyIoData<T> {
+    data: T,
+}
+
+/// The recipient of an alias, which is its target mailbox
+#[derive(Serialize, ToSchema)]
+pub struct AddyIoRecipient {
+    id: String,
+    user_id: String,
+    email: String,
+    should_encrypt: bool,
+    fingerprint: Option<String>,
+    email_verified_at: String,
+    created_at: String,
+    updated_at: String,
+}
+
+/// An alias in the representation of addy.io
+#[derive(Serialize, ToSchema)]
+pub struct AddyIoAlias {
+    /// The address of the alias, which is used in place of a UUID
+    id: String,
+    user_id: String,
+    aliasable_id: Option<String>,
+    aliasable_type: Option<String>,
+    local_part: String,
+    extension: Option<String>,
+    domain: String,
+    email: String,
+    active: bool,
+    description: Option<String>,
+    from_name: Option<String>,
+    attached_recipients_only: bool,
+    emails_forwarded: i64,
+    emails_blocked: i64,
+    emails_replied: i64,
+    emails_sent: i64,
+    recipients: Vec<AddyIoRecipient>,
+    last_forwarded: Option<String>,
+    last_blocked: Option<String>,
+    last_replied: Option<String>,
+    last_sent: Option<String>,
+    created_at: String,
+    updated_at: String,
+    deleted_at: Option<String>,
+}
+
+impl From<&Alias> for AddyIoAlias {
+    fn from(alias: &Alias) -> Self {
+        let (local_part, domain) = alias.address.split_once('@').unwrap_or((&alias.address, ""));
+        let created_at = format_time(&alias.created_at);
+        Self {
+            id: alias.address.clone(),
+            user_id: alias.owner.clone(),
+            aliasable_id: None,
+            aliasable_type: None,
+            local_part: local_part.to_string(),
+            extension: None,
+            domain: domain.to_string(),
+            email: alias.address.clone(),
+            active: alias.active,
+            description: (!alias.comment.is_empty()).then(|| alias.comment.clone()),
+            from_name: None,
+            attached_recipients_only: false,
+            emails_forwarded: alias.n_recv,
+            emails_blocked: 0,
+            emails_replied: 0,
+            emails_sent: alias.n_sent,
+            recipients: vec![AddyIoRecipient {
+                id: alias.target.clone(),
+                user_id: alias.owner.clone(),
+                email: alias.target.clone(),
+                should_encrypt: false,
+                fingerprint: None,
+                email_verified_at: created_at.clone(),
+                created_at: created_at.clone(),
+                updated_at: created_at.clone(),
+            }],
+            last_forwarded: None,
+            last_blocked: None,
+            last_replied: None,
+            last_sent: None,
+            created_at: created_at.clone(),
+            updated_at: created_at,
+            deleted_at: None,
+        }
+    }
+}
+
+/// A single alias, as returned by most endpoints
+fn alias_response(alias: &Alias) -> Json<AddyIoData<AddyIoAlias>> {
+    Json(AddyIoData { data: alias.into() })
+}
+
+/// The alias formats of addy.io
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AddyIoFormat {
+    RandomCharacters,
+    Uuid,
+    RandomWords,
+    Custom,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddyIoRequest {
+    domain: String,
+    description: Option<String>,
+    /// How the alias is generated. Defaults to random words.
+    format: Option<AddyIoFormat>,
+    /// The local part of the alias, required for the `custom` format
+    local_part: Option<String>,
+    /// The target mailbox of the alias. Defaults to the account's first mailbox.
+    #[serde(default)]
+    recipient_ids: Vec<String>,
+    /// Seconds until the alias expires (an idmail extension). Never expires by default.
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// Create a new alias
+#[utoipa::path(
+    post,
+    path = "/api/v1/aliases",
+    tag = "addy.io",
+    request_body = AddyIoRequest,
+    responses(
+        (status = 201, description = "The created alias", body = AddyIoData<AddyIoAlias>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn create_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<AddyIoRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Create).await?;
+    let description = body.description.unwrap_or("".to_string());
+    // Recipients are identified by their address, and aliases only have a single one
+    let target = match body.recipient_ids.as_slice() {
+        [] => None,
+        [target] => Some(target.clone()),
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Aliases can only have a single recipient".to_string(),
+            ))
+        }
+    };
+    let local_part = match body.format {
+        Some(AddyIoFormat::RandomCharacters) => LocalPart::RandomCharacters,
+        Some(AddyIoFormat::Uuid) => LocalPart::Uuid,
+        // Matches the default format reported to clients
+        None | Some(AddyIoFormat::RandomWords) => LocalPart::RandomWords,
+        Some(AddyIoFormat::Custom) => match body.local_part.filter(|x| !x.trim().is_empty()) {
+            Some(local_part) => LocalPart::Custom(local_part),
+            None => {
+                return Err(ApiError::BadRequest(
+                    "The custom format requires a local_part".to_string(),
+                ))
+            }
+        },
+    };
+    let alias = create_alias_by_address(
+        &app_state,
+        &user,
+        &local_part.generate(),
+        (!body.domain.is_empty() && body.domain != "random").then_some(body.domain),
+        target,
+        &description,
+        body.expires_in,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, alias_response(&alias)).into_response())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListAliasesQuery {
+    #[serde(rename = "filter[search]")]
+    search: Option<String>,
+    #[serde(rename = "filter[active]")]
+    active: Option<bool>,
+    #[serde(rename = "filter[deleted]")]
+    deleted: Option<String>,
+    sort: Option<String>,
+    #[serde(rename = "page[number]")]
+    page_number: Option<i64>,
+    #[serde(rename = "page[size]")]
+    page_size: Option<i64>,
+}
+
+fn push_list_filters(query: &mut QueryBuilder<'_, Sqlite>, username: &str, see_all: bool, params: &ListAliasesQuery) {
+    if !see_all {
+        push_and_check_aliases_owner(query, username.to_string());
+    }
+    if let Some(search) = params.search.as_ref().filter(|x| !x.is_empty()) {
+        query.push(" AND ( address LIKE concat('%', ");
+        query.push_bind(search.clone());
+        query.push(", '%') OR comment LIKE concat('%', ");
+        query.push_bind(search.clone());
+        query.push(", '%') )");
+    }
+    if let Some(active) = params.active {
+        query.push(" AND active = ");
+        query.push_bind(active);
+    }
+    // Deleted aliases are never retained, so asking only for those yields nothing.
+    if params.deleted.as_deref() == Some("only") {
+        query.push(" AND FALSE");
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AddyIoPageLinks {
+    first: String,
+    last: String,
+    prev: Option<String>,
+    next: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AddyIoPageMeta {
+    current_page: i64,
+    /// The number of the first alias on this page, counting from 1
+    from: Option<i64>,
+    last_page: i64,
+    path: String,
+    per_page: i64,
+    /// The number of the last alias on this page
+    to: Option<i64>,
+    total: i64,
+}
+
+/// A page of aliases with links to the other pages
+#[derive(Serialize, ToSchema)]
+pub struct AddyIoAliasPage {
+    data: Vec<AddyIoAlias>,
+    links: AddyIoPageLinks,
+    meta: AddyIoPageMeta,
+}
+
+/// List aliases
+#[utoipa::path(
+    get,
+    path = "/api/v1/aliases",
+    tag = "addy.io",
+    params(ListAliasesQuery),
+    responses(
+        (status = 200, description = "A page of aliases", body = AddyIoAliasPage),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn list_aliases(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<ListAliasesQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Read).await?;
+
+    let (sort_column, descending) = match params.sort.as_deref().unwrap_or("-created_at") {
+        x if x.starts_with('-') => (&x[1..], true),
+        x => (x, false),
+    };
+    let sort_column = match sort_column {
+        "local_part" | "email" => "address",
+        "domain" => "domain",
+        "description" => "comment",
+        "active" => "active",
+        "emails_forwarded" => "n_recv",
+        "emails_sent" => "n_sent",
+        "created_at" | "updated_at" => "created_at",
+        x => return Err(ApiError::BadRequest(format!("Cannot sort by '{x}'"))),
+    };
+
+    let page_size = params.page_size.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page_number = params.page_number.unwrap_or(1).max(1);
+
+    let mut query = QueryBuilder::new("SELECT COUNT(*) FROM aliases WHERE 1=1");
+    push_list_filters(&mut query, &user.username, user.can(Permission::AliasesRead), &params);
+    let total = query.build_query_scalar::<i64>().fetch_one(&app_state.pool).await?;
+
+    let mut query = QueryBuilder::new("SELECT * FROM aliases WHERE 1=1");
+    push_list_filters(&mut query, &user.username, user.can(Permission::AliasesRead), &params);
+    query.push(format!(
+        " ORDER BY {sort_column} {}",
+        if descending { "DESC" } else { "ASC" }
+    ));
+    query.push(" LIMIT ");
+    query.push_bind(page_size);
+    query.push(" OFFSET ");
+    query.push_bind((page_number - 1) * page_size);
+    let aliases = query.build_query_as::<Alias>().fetch_all(&app_state.pool).await?;
+
+    let last_page = ((total + page_size - 1) / page_size).max(1);
+    let first_index = (page_number - 1) * page_size + 1;
+    let path = uri.path();
+    let page_link = |n: i64| format!("{path}?page[number]={n}&page[size]={page_size}");
+
+    Ok(Json(AddyIoAliasPage {
+        data: aliases.iter().map(AddyIoAlias::from).collect(),
+        links: AddyIoPageLinks {
+            first: page_link(1),
+            last: page_link(last_page),
+            prev: (page_number > 1).then(|| page_link(page_number - 1)),
+            next: (page_number < last_page).then(|| page_link(page_number + 1)),
+        },
+        meta: AddyIoPageMeta {
+            current_page: page_number,
+            from: (!aliases.is_empty()).then_some(first_index),
+            last_page,
+            path: path.to_string(),
+            per_page: page_size,
+            to: (!aliases.is_empty()).then_some(first_index + aliases.len() as i64 - 1),
+            total,
+        },
+    })
+    .into_response())
+}
+
+/// Get a specific alias
+#[utoipa::path(
+    get,
+    path = "/api/v1/aliases/{id}",
+    tag = "addy.io",
+    params(("id" = String, Path, description = "The address of the alias")),
+    responses(
+        (status = 200, description = "The alias", body = AddyIoData<AddyIoAlias>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn get_alias_by_id(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Read).await?;
+    let alias = get_alias(&app_state, &user, &id).await?;
+    Ok(alias_response(&alias).into_response())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddyIoUpdateRequest {
+    description: Option<String>,
+}
+
+/// Update the description of an alias
+#[utoipa::path(
+    patch,
+    path = "/api/v1/aliases/{id}",
+    tag = "addy.io",
+    params(("id" = String, Path, description = "The address of the alias")),
+    request_body = AddyIoUpdateRequest,
+    responses(
+        (status = 200, description = "The updated alias", body = AddyIoData<AddyIoAlias>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn update_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<String>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<AddyIoUpdateRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    let alias = match body.description {
+        Some(description) => update_alias_comment(&app_state, &user, &id, &description).await?,
+        None => get_alias(&app_state, &user, &id).await?,
+    };
+    Ok(alias_response(&alias).into_response())
+}
+
+/// Delete an alias
+#[utoipa::path(
+    delete,
+    path = "/api/v1/aliases/{id}",
+    tag = "addy.io",
+    params(("id" = String, Path, description = "The address of the alias")),
+    responses(
+        (status = 204, description = "The alias was deleted"),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn delete_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    delete_alias_by_address(&app_state, &user, &id).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddyIoActivateRequest {
+    id: String,
+}
+
+/// Activate an alias
+#[utoipa::path(
+    post,
+    path = "/api/v1/active-aliases",
+    tag = "addy.io",
+    request_body = AddyIoActivateRequest,
+    responses(
+        (status = 200, description = "The activated alias", body = AddyIoData<AddyIoAlias>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn activate_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<AddyIoActivateRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    let alias = update_alias_active(&app_state, &user, &body.id, true).await?;
+    Ok(alias_response(&alias).into_response())
+}
+
+/// Deactivate an alias
+#[utoipa::path(
+    delete,
+    path = "/api/v1/active-aliases/{id}",
+    tag = "addy.io",
+    params(("id" = String, Path, description = "The address of the alias")),
+    responses(
+        (status = 204, description = "The alias was deactivated"),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn deactivate_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    update_alias_active(&app_state, &user, &id, false).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// The account an API token belongs to, with statistics of its aliases
+#[derive(Serialize, ToSchema)]
+pub struct AddyIoAccountDetails {
+    id: String,
+    username: String,
+    from_name: Option<String>,
+    email_subject: Option<String>,
+    banner_location: String,
+    bandwidth: i64,
+    username_count: i64,
+    username_limit: Option<i64>,
+    default_recipient_id: Option<String>,
+    default_alias_domain: Option<String>,
+    default_alias_format: String,
+    subscription: Option<String>,
+    subscription_ends_at: Option<String>,
+    bandwidth_limit: i64,
+    recipient_count: usize,
+    recipient_limit: Option<i64>,
+    active_domain_count: usize,
+    active_domain_limit: Option<i64>,
+    active_shared_domain_alias_count: i64,
+    active_shared_domain_alias_limit: Option<i64>,
+    total_aliases: i64,
+    total_active_aliases: i64,
+    total_inactive_aliases: i64,
+    total_emails_forwarded: i64,
+    total_emails_blocked: i64,
+    total_emails_replied: i64,
+    total_emails_sent: i64,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// Get account details including alias statistics
+#[utoipa::path(
+    get,
+    path = "/api/v1/account-details",
+    tag = "addy.io",
+    responses(
+        (status = 200, description = "The account details", body = AddyIoData<AddyIoAccountDetails>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn account_details(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Info).await?;
+    let domains = allowed_domains(&app_state, &user).await?;
+    let recipients = mailboxes::ssr::allowed_targets(&app_state.pool, &user).await?;
+
+    let mut query = QueryBuilder::new(
+        "SELECT COUNT(*), COALESCE(SUM(active), 0), COALESCE(SUM(n_recv), 0), COALESCE(SUM(n_sent), 0)",
+    );
+    query.push(" FROM aliases WHERE 1=1");
+    if !user.can(Permission::AliasesRead) {
+        push_and_check_aliases_owner(&mut query, user.username.clone());
+    }
+    let (total, active, received, sent) = query
+        .build_query_as::<(i64, i64, i64, i64)>()
+        .fetch_one(&app_state.pool)
+        .await?;
+
+    Ok(Json(AddyIoData {
+        data: AddyIoAccountDetails {
+            id: user.username.clone(),
+            username: user.username,
+            from_name: None,
+            email_subject: None,
+            banner_location: "off".to_string(),
+            bandwidth: 0,
+            username_count: 1,
+            username_limit: None,
+            default_recipient_id: recipients.first().cloned(),
+            default_alias_domain: None,
+            default_alias_format: DEFAULT_ALIAS_FORMAT.to_string(),
+            subscription: None,
+            subscription_ends_at: None,
+            bandwidth_limit: 0,
+            recipient_count: recipients.len(),
+            recipient_limit: None,
+            active_domain_count: domains.len(),
+            active_domain_limit: None,
+            active_shared_domain_alias_count: active,
+            active_shared_domain_alias_limit: None,
+            total_aliases: total,
+            total_active_aliases: active,
+            total_inactive_aliases: total - active,
+            total_emails_forwarded: received,
+            total_emails_blocked: 0,
+            total_emails_replied: 0,
+            total_emails_sent: sent,
+            created_at: None,
+            updated_at: None,
+        },
+    })
+    .into_response())
+}
+
+/// The domains available for new aliases
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddyIoDomainOptions {
+    data: Vec<String>,
+    default_alias_domain: Option<String>,
+    default_alias_format: String,
+}
+
+/// List the domains available for new aliases
+#[utoipa::path(
+    get,
+    path = "/api/v1/domain-options",
+    tag = "addy.io",
+    responses(
+        (status = 200, description = "The available domains", body = AddyIoDomainOptions),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn domain_options(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Info).await?;
+    let domains = allowed_domains(&app_state, &user).await?;
+
+    Ok(Json(AddyIoDomainOptions {
+        data: domains,
+        default_alias_domain: None,
+        default_alias_format: DEFAULT_ALIAS_FORMAT.to_string(),
+    })
+    .into_response())
+}
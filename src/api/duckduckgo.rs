@@ -0,0 +1,66 @@
+//! The endpoint compatible with DuckDuckGo Email Protection, as used by password managers to create private addresses.
+//!
+//! DuckDuckGo only returns the local part of new addresses, to which clients append their configured domain.
+
+use super::{create_alias, login_with_api_token, ApiError, Credentials, ErrorResponse, LocalPart};
+use crate::{api_tokens::ssr::Access, state::AppState};
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DuckDuckGoQuery {
+    /// The domain of the new address. Not part of the DuckDuckGo API, defaults to a random domain.
+    domain: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DuckDuckGoAddress {
+    /// The local part of the new address
+    address: String,
+}
+
+/// Create a new private address
+#[utoipa::path(
+    post,
+    path = "/api/email/addresses",
+    tag = "DuckDuckGo",
+    params(DuckDuckGoQuery),
+    responses(
+        (status = 201, description = "The local part of the created address", body = DuckDuckGoAddress),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn create_address(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(DuckDuckGoQuery { domain }): Query<DuckDuckGoQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Create).await?;
+    let alias = create_alias(
+        &app_state,
+        &user,
+        &LocalPart::RandomWords.generate(),
+        domain,
+        None,
+        "",
+        None,
+    )
+    .await?;
+    let (local_part, _domain) = alias.address.split_once('@').unwrap_or((&alias.address, ""));
+
+    Ok((
+        StatusCode::CREATED,
+        Json(DuckDuckGoAddress {
+            address: local_part.to_string(),
+        }),
+    )
+        .into_response())
+}
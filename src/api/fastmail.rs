@@ -0,0 +1,488 @@
+//! A JMAP endpoint compatible with Fastmail's masked email extension, as used by password managers.
+//!
+//! Only the `MaskedEmail/get` and `MaskedEmail/set` methods are supported. Masked emails are identified
+//! by the numeric ids of aliases, and the only account is the one the API token belongs to.
+
+use super::{
+    comment_for, create_alias, delete_alias as delete_alias_by_address, get_alias_by_id, login_with_api_token,
+    update_alias_active, update_alias_comment, ApiError, Credentials, ErrorResponse, LocalPart,
+};
+use crate::{
+    aliases::{push_and_check_aliases_owner, Alias},
+    api_tokens::ssr::Access,
+    auth::User,
+    roles::Permission,
+    state::AppState,
+};
+use axum::{
+    extract::{self, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::QueryBuilder;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+const MASKED_EMAIL_CAPABILITY: &str = "https://www.fastmail.com/dev/maskedemail";
+/// Changes are not tracked, so the same state is always reported
+const STATE: &str = "0";
+/// The maximum length of an email prefix, same as Fastmail
+const MAX_PREFIX_LENGTH: usize = 64;
+
+/// An alias in the representation of a masked email
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaskedEmail {
+    /// The id of the alias
+    id: String,
+    email: String,
+    /// Either `enabled` or `disabled`
+    state: String,
+    for_domain: String,
+    description: String,
+    url: Option<String>,
+    email_prefix: String,
+    created_by: String,
+    created_at: String,
+    last_message_at: Option<String>,
+}
+
+impl From<&Alias> for MaskedEmail {
+    fn from(alias: &Alias) -> Self {
+        Self {
+            id: alias.id.to_string(),
+            email: alias.address.clone(),
+            state: if alias.active { "enabled" } else { "disabled" }.to_string(),
+            for_domain: "".to_string(),
+            description: alias.comment.clone(),
+            url: None,
+            email_prefix: "".to_string(),
+            created_by: "idmail".to_string(),
+            created_at: alias.created_at.to_rfc3339(),
+            last_message_at: None,
+        }
+    }
+}
+
+/// The limits of the JMAP core capability
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JmapCoreCapability {
+    max_size_upload: u64,
+    max_concurrent_upload: u64,
+    max_size_request: u64,
+    max_concurrent_requests: u64,
+    max_calls_in_request: u64,
+    max_objects_in_get: u64,
+    max_objects_in_set: u64,
+    collation_algorithms: Vec<String>,
+}
+
+/// A capability without any properties
+#[derive(Serialize, ToSchema)]
+pub struct JmapEmptyCapability {}
+
+/// The capabilities of the server, keyed by their URIs
+#[derive(Serialize, ToSchema)]
+pub struct JmapCapabilities {
+    #[serde(rename = "urn:ietf:params:jmap:core")]
+    core: JmapCoreCapability,
+    #[serde(rename = "https://www.fastmail.com/dev/maskedemail")]
+    masked_email: JmapEmptyCapability,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct JmapAccountCapabilities {
+    #[serde(rename = "https://www.fastmail.com/dev/maskedemail")]
+    masked_email: JmapEmptyCapability,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JmapAccount {
+    name: String,
+    is_personal: bool,
+    is_read_only: bool,
+    account_capabilities: JmapAccountCapabilities,
+}
+
+/// The account used for each capability
+#[derive(Serialize, ToSchema)]
+pub struct JmapPrimaryAccounts {
+    #[serde(rename = "https://www.fastmail.com/dev/maskedemail")]
+    masked_email: String,
+}
+
+/// The JMAP session, whose only account is the one the API token belongs to
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JmapSession {
+    capabilities: JmapCapabilities,
+    /// The accounts by their id, which is the username
+    accounts: HashMap<String, JmapAccount>,
+    primary_accounts: JmapPrimaryAccounts,
+    username: String,
+    api_url: String,
+    download_url: String,
+    upload_url: String,
+    event_source_url: String,
+    state: String,
+}
+
+/// Get the JMAP session
+#[utoipa::path(
+    get,
+    path = "/jmap/session",
+    tag = "Fastmail",
+    responses(
+        (status = 200, description = "The JMAP session", body = JmapSession),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn session(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Info).await?;
+
+    let account = JmapAccount {
+        name: user.username.clone(),
+        is_personal: true,
+        is_read_only: false,
+        account_capabilities: JmapAccountCapabilities {
+            masked_email: JmapEmptyCapability {},
+        },
+    };
+    Ok(Json(JmapSession {
+        capabilities: JmapCapabilities {
+            core: JmapCoreCapability {
+                max_size_upload: 0,
+                max_concurrent_upload: 1,
+                max_size_request: 10_000_000,
+                max_concurrent_requests: 4,
+                max_calls_in_request: 16,
+                max_objects_in_get: 1000,
+                max_objects_in_set: 100,
+                collation_algorithms: Vec::new(),
+            },
+            masked_email: JmapEmptyCapability {},
+        },
+        accounts: HashMap::from([(user.username.clone(), account)]),
+        primary_accounts: JmapPrimaryAccounts {
+            masked_email: user.username.clone(),
+        },
+        username: user.username,
+        api_url: "/jmap/api/".to_string(),
+        download_url: "".to_string(),
+        upload_url: "".to_string(),
+        event_source_url: "".to_string(),
+        state: STATE.to_string(),
+    })
+    .into_response())
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JmapRequest {
+    /// The capabilities used by the method calls
+    #[serde(default)]
+    using: Vec<String>,
+    /// The method calls, each as `[name, arguments, call id]`
+    #[schema(value_type = Vec<Value>)]
+    method_calls: Vec<(String, Value, String)>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JmapResponse {
+    /// The responses to the method calls in the same order, each as `[name, arguments, call id]`
+    #[schema(value_type = Vec<Value>)]
+    method_responses: Vec<(String, Value, String)>,
+    session_state: String,
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum MaskedEmailState {
+    Pending,
+    Enabled,
+    Disabled,
+    Deleted,
+}
+
+#[derive(Deserialize)]
+struct GetArguments {
+    ids: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct SetArguments {
+    create: Option<HashMap<String, MaskedEmailCreate>>,
+    update: Option<HashMap<String, MaskedEmailUpdate>>,
+    destroy: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaskedEmailCreate {
+    state: Option<MaskedEmailState>,
+    description: Option<String>,
+    for_domain: Option<String>,
+    email_prefix: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MaskedEmailUpdate {
+    state: Option<MaskedEmailState>,
+    description: Option<String>,
+}
+
+/// Returns the access a token needs for all of the given method calls.
+fn required_access(method_calls: &[(String, Value, String)]) -> Access {
+    let (mut read, mut create, mut write) = (false, false, false);
+    for (name, arguments, _call_id) in method_calls {
+        let has = |key: &str| arguments.get(key).is_some_and(|x| !x.is_null());
+        match name.as_str() {
+            "MaskedEmail/get" => read = true,
+            "MaskedEmail/set" => {
+                create |= has("create");
+                write |= has("update") || has("destroy");
+            }
+            _ => {}
+        }
+    }
+
+    match (read, create, write) {
+        // Only full tokens may both read and create
+        (_, _, true) | (true, true, _) => Access::Write,
+        (true, false, false) => Access::Read,
+        (false, true, false) => Access::Create,
+        (false, false, false) => Access::Info,
+    }
+}
+
+/// Parses the arguments of a method call, which must refer to the account of the user.
+fn parse_arguments<T: DeserializeOwned>(user: &User, arguments: Value) -> Result<T, Value> {
+    if arguments.get("accountId").and_then(Value::as_str) != Some(&user.username) {
+        return Err(json!({ "type": "accountNotFound" }));
+    }
+    serde_json::from_value(arguments).map_err(|e| json!({ "type": "invalidArguments", "description": e.to_string() }))
+}
+
+/// Converts an error of a single object into a JMAP set error. Internal errors fail the whole request.
+fn set_error(error: ApiError) -> Result<Value, ApiError> {
+    let kind = match &error {
+        ApiError::NotFound(_) => "notFound",
+        ApiError::Forbidden(_) => "forbidden",
+        ApiError::TooManyRequests(..) => "rateLimit",
+        ApiError::BadRequest(_) | ApiError::Conflict(_) => "invalidProperties",
+        _ => return Err(error),
+    };
+    Ok(json!({ "type": kind, "description": error.to_string() }))
+}
+
+async fn get_masked_email(app_state: &AppState, user: &User, id: &str) -> Result<Alias, ApiError> {
+    let id = id
+        .parse::<i64>()
+        .map_err(|_| ApiError::NotFound(format!("Masked email {id} does not exist")))?;
+    get_alias_by_id(app_state, user, id).await
+}
+
+async fn create_masked_email(
+    app_state: &AppState,
+    user: &User,
+    properties: MaskedEmailCreate,
+) -> Result<Alias, ApiError> {
+    let local_part = match properties
+        .email_prefix
+        .map(|x| x.to_lowercase())
+        .filter(|x| !x.is_empty())
+    {
+        Some(prefix)
+            if prefix.len() <= MAX_PREFIX_LENGTH
+                && prefix
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') =>
+        {
+            LocalPart::Custom(format!("{prefix}.{}", LocalPart::RandomCharacters.generate()))
+        }
+        Some(_) => {
+            return Err(ApiError::BadRequest(format!(
+                "emailPrefix must consist of at most {MAX_PREFIX_LENGTH} letters, digits or underscores"
+            )))
+        }
+        None => LocalPart::Username,
+    };
+    if properties.state == Some(MaskedEmailState::Deleted) {
+        return Err(ApiError::BadRequest(
+            "Masked emails cannot be created as deleted".to_string(),
+        ));
+    }
+
+    let alias = create_alias(
+        app_state,
+        user,
+        &local_part.generate(),
+        None,
+        None,
+        &comment_for(properties.description, properties.for_domain),
+        None,
+    )
+    .await?;
+    if properties.state == Some(MaskedEmailState::Disabled) {
+        return update_alias_active(app_state, user, &alias.address, false).await;
+    }
+
+    Ok(alias)
+}
+
+async fn update_masked_email(
+    app_state: &AppState,
+    user: &User,
+    id: &str,
+    properties: MaskedEmailUpdate,
+) -> Result<(), ApiError> {
+    let alias = get_masked_email(app_state, user, id).await?;
+    match properties.state {
+        Some(MaskedEmailState::Deleted) => return delete_alias_by_address(app_state, user, &alias.address).await,
+        Some(state) => {
+            update_alias_active(app_state, user, &alias.address, state != MaskedEmailState::Disabled).await?;
+        }
+        None => {}
+    }
+    if let Some(description) = properties.description {
+        update_alias_comment(app_state, user, &alias.address, &description).await?;
+    }
+
+    Ok(())
+}
+
+async fn masked_email_get(app_state: &AppState, user: &User, arguments: GetArguments) -> Result<Value, ApiError> {
+    let mut list = Vec::new();
+    let mut not_found = Vec::new();
+    match arguments.ids {
+        None => {
+            let mut query = QueryBuilder::new("SELECT * FROM aliases WHERE 1=1");
+            if !user.can(Permission::AliasesRead) {
+                push_and_check_aliases_owner(&mut query, user.username.clone());
+            }
+            query.push(" ORDER BY created_at DESC");
+            let aliases = query.build_query_as::<Alias>().fetch_all(&app_state.pool).await?;
+            list.extend(aliases.iter().map(MaskedEmail::from));
+        }
+        Some(ids) => {
+            for id in ids {
+                match get_masked_email(app_state, user, &id).await {
+                    Ok(alias) => list.push(MaskedEmail::from(&alias)),
+                    Err(ApiError::NotFound(_)) => not_found.push(id),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    Ok(json!({
+        "accountId": user.username,
+        "state": STATE,
+        "list": list,
+        "notFound": not_found,
+    }))
+}
+
+async fn masked_email_set(app_state: &AppState, user: &User, arguments: SetArguments) -> Result<Value, ApiError> {
+    let mut created = Map::new();
+    let mut not_created = Map::new();
+    for (creation_id, properties) in arguments.create.unwrap_or_default() {
+        match create_masked_email(app_state, user, properties).await {
+            Ok(alias) => created.insert(creation_id, json!(MaskedEmail::from(&alias))),
+            Err(e) => not_created.insert(creation_id, set_error(e)?),
+        };
+    }
+
+    let mut updated = Map::new();
+    let mut not_updated = Map::new();
+    for (id, properties) in arguments.update.unwrap_or_default() {
+        match update_masked_email(app_state, user, &id, properties).await {
+            Ok(()) => updated.insert(id, Value::Null),
+            Err(e) => not_updated.insert(id, set_error(e)?),
+        };
+    }
+
+    let mut destroyed = Vec::new();
+    let mut not_destroyed = Map::new();
+    for id in arguments.destroy.unwrap_or_default() {
+        let result = match get_masked_email(app_state, user, &id).await {
+            Ok(alias) => delete_alias_by_address(app_state, user, &alias.address).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => destroyed.push(id),
+            Err(e) => {
+                not_destroyed.insert(id, set_error(e)?);
+            }
+        }
+    }
+
+    Ok(json!({
+        "accountId": user.username,
+        "oldState": STATE,
+        "newState": STATE,
+        "created": created,
+        "notCreated": not_created,
+        "updated": updated,
+        "notUpdated": not_updated,
+        "destroyed": destroyed,
+        "notDestroyed": not_destroyed,
+    }))
+}
+
+/// Execute JMAP method calls
+#[utoipa::path(
+    post,
+    path = "/jmap/api/",
+    tag = "Fastmail",
+    request_body = JmapRequest,
+    responses(
+        (status = 200, description = "The method responses", body = JmapResponse),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = [])),
+)]
+pub async fn api(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<JmapRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, required_access(&body.method_calls)).await?;
+    let masked_email = body.using.iter().any(|x| x == MASKED_EMAIL_CAPABILITY);
+
+    let mut method_responses = Vec::new();
+    for (name, arguments, call_id) in body.method_calls {
+        let result = match name.as_str() {
+            "MaskedEmail/get" if masked_email => match parse_arguments(&user, arguments) {
+                Ok(arguments) => Ok(masked_email_get(&app_state, &user, arguments).await?),
+                Err(e) => Err(e),
+            },
+            "MaskedEmail/set" if masked_email => match parse_arguments(&user, arguments) {
+                Ok(arguments) => Ok(masked_email_set(&app_state, &user, arguments).await?),
+                Err(e) => Err(e),
+            },
+            _ => Err(json!({ "type": "unknownMethod" })),
+        };
+
+        method_responses.push(match result {
+            Ok(arguments) => (name, arguments, call_id),
+            Err(error) => ("error".to_string(), error, call_id),
+        });
+    }
+
+    Ok(Json(JmapResponse {
+        method_responses,
+        session_state: STATE.to_string(),
+    })
+    .into_response())
+}
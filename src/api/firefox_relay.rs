@@ -0,0 +1,234 @@
+//! Endpoints compatible with the Firefox Relay API, as used by password managers to create masks.
+//!
+//! Masks are identified by the numeric ids of aliases. Clients authenticate with `Authorization: Token <token>`.
+
+use super::{
+    comment_for, create_alias, delete_alias as delete_alias_by_address, get_alias_by_id, login_with_api_token,
+    update_alias_active, update_alias_comment, ApiError, Credentials, ErrorResponse, LocalPart,
+};
+use crate::{
+    aliases::{push_and_check_aliases_owner, Alias},
+    api_tokens::ssr::Access,
+    roles::Permission,
+    state::AppState,
+};
+use axum::{
+    extract::{self, Path, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::QueryBuilder;
+use utoipa::ToSchema;
+
+/// An alias in the representation of a Firefox Relay mask
+#[derive(Serialize, ToSchema)]
+pub struct RelayMask {
+    id: i64,
+    mask_type: String,
+    /// The local part of the alias
+    address: String,
+    /// Relay encodes its own domains as numbers, clients use the full address instead
+    domain: i64,
+    full_address: String,
+    enabled: bool,
+    block_list_emails: bool,
+    block_level_one_trackers: bool,
+    description: String,
+    generated_for: String,
+    used_on: String,
+    created_at: String,
+    last_modified_at: String,
+    last_used_at: Option<String>,
+    num_forwarded: i64,
+    num_blocked: i64,
+    num_level_one_trackers_blocked: i64,
+    num_replied: i64,
+    num_spam: i64,
+}
+
+impl From<&Alias> for RelayMask {
+    fn from(alias: &Alias) -> Self {
+        let (local_part, _domain) = alias.address.split_once('@').unwrap_or((&alias.address, ""));
+        let created_at = alias.created_at.to_rfc3339();
+        Self {
+            id: alias.id,
+            mask_type: "random".to_string(),
+            address: local_part.to_string(),
+            domain: 2,
+            full_address: alias.address.clone(),
+            enabled: alias.active,
+            block_list_emails: false,
+            block_level_one_trackers: false,
+            description: alias.comment.clone(),
+            generated_for: "".to_string(),
+            used_on: "".to_string(),
+            created_at: created_at.clone(),
+            last_modified_at: created_at,
+            last_used_at: None,
+            num_forwarded: alias.n_recv,
+            num_blocked: 0,
+            num_level_one_trackers_blocked: 0,
+            num_replied: alias.n_sent,
+            num_spam: 0,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RelayCreateRequest {
+    enabled: Option<bool>,
+    description: Option<String>,
+    /// The website the mask was generated for
+    generated_for: Option<String>,
+    /// Seconds until the alias expires (an idmail extension). Never expires by default.
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// Create a new random mask
+#[utoipa::path(
+    post,
+    path = "/api/v1/relayaddresses/",
+    tag = "Firefox Relay",
+    request_body = RelayCreateRequest,
+    responses(
+        (status = 201, description = "The created mask", body = RelayMask),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("firefox_relay" = [])),
+)]
+pub async fn create_mask(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<RelayCreateRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Create).await?;
+    let mut alias = create_alias(
+        &app_state,
+        &user,
+        &LocalPart::RandomCharacters.generate(),
+        None,
+        None,
+        &comment_for(body.description, body.generated_for),
+        body.expires_in,
+    )
+    .await?;
+    if body.enabled == Some(false) {
+        alias = update_alias_active(&app_state, &user, &alias.address, false).await?;
+    }
+
+    Ok((StatusCode::CREATED, Json(RelayMask::from(&alias))).into_response())
+}
+
+/// List all masks
+#[utoipa::path(
+    get,
+    path = "/api/v1/relayaddresses/",
+    tag = "Firefox Relay",
+    responses(
+        (status = 200, description = "The masks", body = Vec<RelayMask>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("firefox_relay" = [])),
+)]
+pub async fn list_masks(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Read).await?;
+
+    let mut query = QueryBuilder::new("SELECT * FROM aliases WHERE 1=1");
+    if !user.can(Permission::AliasesRead) {
+        push_and_check_aliases_owner(&mut query, user.username.clone());
+    }
+    query.push(" ORDER BY created_at DESC");
+    let aliases = query.build_query_as::<Alias>().fetch_all(&app_state.pool).await?;
+
+    Ok(Json(aliases.iter().map(RelayMask::from).collect::<Vec<_>>()).into_response())
+}
+
+/// Get a specific mask
+#[utoipa::path(
+    get,
+    path = "/api/v1/relayaddresses/{id}/",
+    tag = "Firefox Relay",
+    params(("id" = i64, Path, description = "The id of the mask")),
+    responses(
+        (status = 200, description = "The mask", body = RelayMask),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("firefox_relay" = [])),
+)]
+pub async fn get_mask(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Read).await?;
+    let alias = get_alias_by_id(&app_state, &user, id).await?;
+    Ok(Json(RelayMask::from(&alias)).into_response())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RelayUpdateRequest {
+    enabled: Option<bool>,
+    description: Option<String>,
+}
+
+/// Enable, disable or describe a mask
+#[utoipa::path(
+    patch,
+    path = "/api/v1/relayaddresses/{id}/",
+    tag = "Firefox Relay",
+    params(("id" = i64, Path, description = "The id of the mask")),
+    request_body = RelayUpdateRequest,
+    responses(
+        (status = 200, description = "The updated mask", body = RelayMask),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("firefox_relay" = [])),
+)]
+pub async fn update_mask(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<i64>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<RelayUpdateRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    let mut alias = get_alias_by_id(&app_state, &user, id).await?;
+    if let Some(enabled) = body.enabled {
+        alias = update_alias_active(&app_state, &user, &alias.address, enabled).await?;
+    }
+    if let Some(description) = body.description {
+        alias = update_alias_comment(&app_state, &user, &alias.address, &description).await?;
+    }
+
+    Ok(Json(RelayMask::from(&alias)).into_response())
+}
+
+/// Delete a mask
+#[utoipa::path(
+    delete,
+    path = "/api/v1/relayaddresses/{id}/",
+    tag = "Firefox Relay",
+    params(("id" = i64, Path, description = "The id of the mask")),
+    responses(
+        (status = 204, description = "The mask was deleted"),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("firefox_relay" = [])),
+)]
+pub async fn delete_mask(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    let alias = get_alias_by_id(&app_state, &user, id).await?;
+    delete_alias_by_address(&app_state, &user, &alias.address).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
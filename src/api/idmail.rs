@@ -0,0 +1,792 @@
+//! The native idmail REST API (v1).
+//!
+//! Provides access to users, domains, mailboxes and aliases with the same
+//! permission rules as the web interface. Requests are authenticated either
+//! by an API token or by the session cookie of a logged in user.
+
+use super::{authenticate, ApiError, Credentials, ErrorResponse};
+use crate::{
+    aliases::{self, Alias},
+    api_tokens::ssr::Access,
+    domains::{self, Domain},
+    mailboxes::{self, Mailbox},
+    state::AppState,
+    users::{self, UserLimits},
+};
+use axum::{
+    extract::{self, Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// The default number of entries returned by list endpoints
+const DEFAULT_LIMIT: usize = 100;
+/// The maximum number of entries returned by list endpoints
+const MAX_LIMIT: usize = 1000;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Distinguishes a missing field (`None`) from an explicit `null` (`Some(None)`).
+fn nullable<'de, T: Deserialize<'de>, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error> {
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+fn split_address(address: &str) -> Result<(String, String), ApiError> {
+    address
+        .split_once('@')
+        .map(|(localpart, domain)| (localpart.to_string(), domain.to_string()))
+        .ok_or_else(|| ApiError::BadRequest(format!("'{address}' is not a valid address")))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Only return entries matching this search string
+    #[serde(default)]
+    search: String,
+    /// The maximum number of entries to return
+    limit: Option<usize>,
+    /// The number of entries to skip
+    #[serde(default)]
+    offset: usize,
+}
+
+impl ListQuery {
+    fn range(&self) -> std::ops::Range<usize> {
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        self.offset..self.offset + limit
+    }
+}
+
+/// Most recently created entries are listed first
+const ORDER: &str = "ORDER BY created_at DESC";
+
+// Users
+
+#[derive(Serialize, ToSchema)]
+pub struct UserResponse {
+    username: String,
+    admin: bool,
+    role: Option<String>,
+    active: bool,
+    limits: UserLimits,
+    created_at: DateTime<Utc>,
+}
+
+impl From<users::User> for UserResponse {
+    fn from(user: users::User) -> Self {
+        Self {
+            username: user.username,
+            admin: user.admin,
+            role: user.role,
+            active: user.active,
+            limits: user.limits,
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateUserRequest {
+    username: String,
+    password: String,
+    #[serde(default)]
+    admin: bool,
+    /// The name of a role to assign
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default = "default_true")]
+    active: bool,
+    #[serde(default)]
+    limits: UserLimits,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateUserRequest {
+    password: Option<String>,
+    admin: Option<bool>,
+    /// The name of a role to assign, or an empty string to remove the role
+    role: Option<String>,
+    active: Option<bool>,
+    /// Replaces all limits of the user
+    limits: Option<UserLimits>,
+}
+
+/// List users
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/users",
+    tag = "idmail",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "The users", body = Vec<UserResponse>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn list_users(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(params): Query<ListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let users = users::ssr::list(
+        &app_state.pool,
+        &user,
+        &params.search,
+        Some(ORDER.to_string()),
+        params.range(),
+    )
+    .await?;
+    Ok(Json(users.into_iter().map(UserResponse::from).collect::<Vec<_>>()).into_response())
+}
+
+/// Get a specific user
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/users/{username}",
+    tag = "idmail",
+    params(("username" = String, Path, description = "The username of the user")),
+    responses(
+        (status = 200, description = "The user", body = UserResponse),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn get_user(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let user = users::ssr::get(&app_state.pool, &user, &username).await?;
+    Ok(Json(UserResponse::from(user)).into_response())
+}
+
+/// Create a new user
+#[utoipa::path(
+    post,
+    path = "/api/idmail/v1/users",
+    tag = "idmail",
+    request_body = CreateUserRequest,
+    responses(
+        (status = 201, description = "The created user", body = UserResponse),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn create_user(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<CreateUserRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    let edit = users::ssr::UserEdit {
+        username: body.username,
+        password: body.password,
+        admin: body.admin,
+        role: body.role.filter(|x| !x.is_empty()),
+        active: body.active,
+        limits: body.limits,
+    };
+    let user = users::ssr::create_or_update(&app_state.pool, &user, None, edit).await?;
+    Ok((StatusCode::CREATED, Json(UserResponse::from(user))).into_response())
+}
+
+/// Update the given fields of a user
+#[utoipa::path(
+    patch,
+    path = "/api/idmail/v1/users/{username}",
+    tag = "idmail",
+    params(("username" = String, Path, description = "The username of the user")),
+    request_body = UpdateUserRequest,
+    responses(
+        (status = 200, description = "The updated user", body = UserResponse),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn update_user(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(username): Path<String>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<UpdateUserRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    let current = users::ssr::get(&app_state.pool, &user, &username).await?;
+    let edit = users::ssr::UserEdit {
+        username: current.username.clone(),
+        password: body.password.unwrap_or_default(),
+        admin: body.admin.unwrap_or(current.admin),
+        role: body
+            .role
+            .map_or(current.role.clone(), |x| Some(x).filter(|x| !x.is_empty())),
+        active: body.active.unwrap_or(current.active),
+        limits: body.limits.unwrap_or(current.limits.clone()),
+    };
+    let user = users::ssr::create_or_update(&app_state.pool, &user, Some(current.username), edit).await?;
+    Ok(Json(UserResponse::from(user)).into_response())
+}
+
+/// Delete a user
+#[utoipa::path(
+    delete,
+    path = "/api/idmail/v1/users/{username}",
+    tag = "idmail",
+    params(("username" = String, Path, description = "The username of the user")),
+    responses(
+        (status = 204, description = "The user was deleted"),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn delete_user(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    users::ssr::delete(&app_state.pool, &user, &username).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Domains
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateDomainRequest {
+    domain: String,
+    #[serde(default)]
+    catch_all: String,
+    #[serde(default)]
+    public: bool,
+    #[serde(default = "default_true")]
+    active: bool,
+    #[serde(default)]
+    owner: String,
+    #[serde(default)]
+    members: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateDomainRequest {
+    domain: Option<String>,
+    catch_all: Option<String>,
+    public: Option<bool>,
+    active: Option<bool>,
+    owner: Option<String>,
+    members: Option<Vec<String>>,
+}
+
+/// List domains
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/domains",
+    tag = "idmail",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "The domains", body = Vec<Domain>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn list_domains(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(params): Query<ListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let domains = domains::ssr::list(
+        &app_state.pool,
+        &user,
+        &params.search,
+        Some(ORDER.to_string()),
+        params.range(),
+    )
+    .await?;
+    Ok(Json(domains).into_response())
+}
+
+/// Get a specific domain
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/domains/{domain}",
+    tag = "idmail",
+    params(("domain" = String, Path, description = "The domain of the domain")),
+    responses(
+        (status = 200, description = "The domain", body = Domain),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn get_domain(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(domain): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let domain = domains::ssr::get(&app_state.pool, &user, &domain).await?;
+    Ok(Json(domain).into_response())
+}
+
+/// Create a new domain
+#[utoipa::path(
+    post,
+    path = "/api/idmail/v1/domains",
+    tag = "idmail",
+    request_body = CreateDomainRequest,
+    responses(
+        (status = 201, description = "The created domain", body = Domain),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn create_domain(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<CreateDomainRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    let edit = domains::ssr::DomainEdit {
+        domain: body.domain,
+        catch_all: body.catch_all,
+        public: body.public,
+        active: body.active,
+        owner: body.owner,
+        members: body.members,
+    };
+    let domain = domains::ssr::create_or_update(&app_state.pool, &user, None, edit).await?;
+    Ok((StatusCode::CREATED, Json(domain)).into_response())
+}
+
+/// Update the given fields of a domain
+#[utoipa::path(
+    patch,
+    path = "/api/idmail/v1/domains/{domain}",
+    tag = "idmail",
+    params(("domain" = String, Path, description = "The domain of the domain")),
+    request_body = UpdateDomainRequest,
+    responses(
+        (status = 200, description = "The updated domain", body = Domain),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn update_domain(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(domain): Path<String>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<UpdateDomainRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    let current = domains::ssr::get(&app_state.pool, &user, &domain).await?;
+    let edit = domains::ssr::DomainEdit {
+        domain: body.domain.unwrap_or(current.domain.clone()),
+        catch_all: body.catch_all.or(current.catch_all).unwrap_or_default(),
+        public: body.public.unwrap_or(current.public),
+        active: body.active.unwrap_or(current.active),
+        owner: body.owner.unwrap_or(current.owner),
+        members: body.members.unwrap_or_else(|| domains::split_members(&current.members)),
+    };
+    let domain = domains::ssr::create_or_update(&app_state.pool, &user, Some(current.domain), edit).await?;
+    Ok(Json(domain).into_response())
+}
+
+/// Delete a domain
+#[utoipa::path(
+    delete,
+    path = "/api/idmail/v1/domains/{domain}",
+    tag = "idmail",
+    params(("domain" = String, Path, description = "The domain of the domain")),
+    responses(
+        (status = 204, description = "The domain was deleted"),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn delete_domain(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(domain): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    domains::ssr::delete(&app_state.pool, &user, &domain).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Mailboxes
+
+#[derive(Serialize, ToSchema)]
+pub struct MailboxResponse {
+    address: String,
+    active: bool,
+    owner: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<Mailbox> for MailboxResponse {
+    fn from(mailbox: Mailbox) -> Self {
+        Self {
+            address: mailbox.address,
+            active: mailbox.active,
+            owner: mailbox.owner,
+            created_at: mailbox.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateMailboxRequest {
+    address: String,
+    password: String,
+    #[serde(default = "default_true")]
+    active: bool,
+    #[serde(default)]
+    owner: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateMailboxRequest {
+    address: Option<String>,
+    password: Option<String>,
+    active: Option<bool>,
+    owner: Option<String>,
+}
+
+/// List mailboxes
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/mailboxes",
+    tag = "idmail",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "The mailboxes", body = Vec<MailboxResponse>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn list_mailboxes(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(params): Query<ListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let mailboxes = mailboxes::ssr::list(
+        &app_state.pool,
+        &user,
+        &params.search,
+        Some(ORDER.to_string()),
+        params.range(),
+    )
+    .await?;
+    Ok(Json(mailboxes.into_iter().map(MailboxResponse::from).collect::<Vec<_>>()).into_response())
+}
+
+/// Get a specific mailbox
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/mailboxes/{address}",
+    tag = "idmail",
+    params(("address" = String, Path, description = "The address of the mailbox")),
+    responses(
+        (status = 200, description = "The mailbox", body = MailboxResponse),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn get_mailbox(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(address): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let mailbox = mailboxes::ssr::get(&app_state.pool, &user, &address).await?;
+    Ok(Json(MailboxResponse::from(mailbox)).into_response())
+}
+
+/// Create a new mailbox
+#[utoipa::path(
+    post,
+    path = "/api/idmail/v1/mailboxes",
+    tag = "idmail",
+    request_body = CreateMailboxRequest,
+    responses(
+        (status = 201, description = "The created mailbox", body = MailboxResponse),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn create_mailbox(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<CreateMailboxRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    let (localpart, domain) = split_address(&body.address)?;
+    let edit = mailboxes::ssr::MailboxEdit {
+        localpart,
+        domain,
+        password: body.password,
+        active: body.active,
+        owner: body.owner,
+    };
+    let mailbox = mailboxes::ssr::create_or_update(&app_state.pool, &user, None, edit).await?;
+    Ok((StatusCode::CREATED, Json(MailboxResponse::from(mailbox))).into_response())
+}
+
+/// Update the given fields of a mailbox
+#[utoipa::path(
+    patch,
+    path = "/api/idmail/v1/mailboxes/{address}",
+    tag = "idmail",
+    params(("address" = String, Path, description = "The address of the mailbox")),
+    request_body = UpdateMailboxRequest,
+    responses(
+        (status = 200, description = "The updated mailbox", body = MailboxResponse),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn update_mailbox(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(address): Path<String>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<UpdateMailboxRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    let current = mailboxes::ssr::get(&app_state.pool, &user, &address).await?;
+    let (localpart, domain) = split_address(body.address.as_ref().unwrap_or(&current.address))?;
+    let edit = mailboxes::ssr::MailboxEdit {
+        localpart,
+        domain,
+        password: body.password.unwrap_or_default(),
+        active: body.active.unwrap_or(current.active),
+        owner: body.owner.unwrap_or(current.owner),
+    };
+    let mailbox = mailboxes::ssr::create_or_update(&app_state.pool, &user, Some(current.address), edit).await?;
+    Ok(Json(MailboxResponse::from(mailbox)).into_response())
+}
+
+/// Delete a mailbox
+#[utoipa::path(
+    delete,
+    path = "/api/idmail/v1/mailboxes/{address}",
+    tag = "idmail",
+    params(("address" = String, Path, description = "The address of the mailbox")),
+    responses(
+        (status = 204, description = "The mailbox was deleted"),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn delete_mailbox(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(address): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    mailboxes::ssr::delete(&app_state.pool, &user, &address).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Aliases
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateAliasRequest {
+    address: String,
+    #[serde(default)]
+    target: String,
+    #[serde(default)]
+    comment: String,
+    #[serde(default = "default_true")]
+    active: bool,
+    #[serde(default)]
+    owner: String,
+    /// When the alias should be activated, it stays inactive until then
+    #[serde(default)]
+    active_from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+    /// What happens to the alias when it expires
+    #[serde(default)]
+    on_expiry: aliases::ExpiryAction,
+    /// After how many received messages in total the alias is deactivated
+    #[serde(default)]
+    max_recv: Option<i64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateAliasRequest {
+    address: Option<String>,
+    target: Option<String>,
+    comment: Option<String>,
+    active: Option<bool>,
+    owner: Option<String>,
+    /// When the alias should be activated, or null to cancel the activation
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    active_from: Option<Option<DateTime<Utc>>>,
+    /// When the alias expires, or null to never expire
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    expires_at: Option<Option<DateTime<Utc>>>,
+    on_expiry: Option<aliases::ExpiryAction>,
+    /// After how many received messages in total the alias is deactivated, or null for no limit
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<i64>)]
+    max_recv: Option<Option<i64>>,
+}
+
+/// List aliases
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/aliases",
+    tag = "idmail",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "The aliases", body = Vec<Alias>),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn list_aliases(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(params): Query<ListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let aliases = aliases::ssr::list(
+        &app_state.pool,
+        &user,
+        &params.search,
+        Some(ORDER.to_string()),
+        params.range(),
+    )
+    .await?;
+    Ok(Json(aliases).into_response())
+}
+
+/// Get a specific alias
+#[utoipa::path(
+    get,
+    path = "/api/idmail/v1/aliases/{address}",
+    tag = "idmail",
+    params(("address" = String, Path, description = "The address of the alias")),
+    responses(
+        (status = 200, description = "The alias", body = Alias),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn get_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(address): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Read).await?;
+    let alias = aliases::ssr::get(&app_state.pool, &user, &address).await?;
+    Ok(Json(alias).into_response())
+}
+
+/// Create a new alias
+#[utoipa::path(
+    post,
+    path = "/api/idmail/v1/aliases",
+    tag = "idmail",
+    request_body = CreateAliasRequest,
+    responses(
+        (status = 201, description = "The created alias", body = Alias),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn create_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<CreateAliasRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Create).await?;
+    let (alias, domain) = split_address(&body.address)?;
+    let edit = aliases::ssr::AliasEdit {
+        alias,
+        domain,
+        target: body.target,
+        comment: body.comment,
+        active: body.active,
+        owner: body.owner,
+        active_from: body.active_from,
+        expires_at: body.expires_at,
+        on_expiry: body.on_expiry,
+        max_recv: body.max_recv,
+    };
+    let alias = aliases::ssr::create_or_update(&app_state.pool, &user, None, edit).await?;
+    Ok((StatusCode::CREATED, Json(alias)).into_response())
+}
+
+/// Update the given fields of a alias
+#[utoipa::path(
+    patch,
+    path = "/api/idmail/v1/aliases/{address}",
+    tag = "idmail",
+    params(("address" = String, Path, description = "The address of the alias")),
+    request_body = UpdateAliasRequest,
+    responses(
+        (status = 200, description = "The updated alias", body = Alias),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn update_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(address): Path<String>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<UpdateAliasRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    let current = aliases::ssr::get(&app_state.pool, &user, &address).await?;
+    let (alias, domain) = split_address(body.address.as_ref().unwrap_or(&current.address))?;
+    let edit = aliases::ssr::AliasEdit {
+        alias,
+        domain,
+        target: body.target.unwrap_or(current.target),
+        comment: body.comment.unwrap_or(current.comment),
+        active: body.active.unwrap_or(current.active),
+        owner: body.owner.unwrap_or(current.owner),
+        active_from: body.active_from.unwrap_or(current.active_from),
+        expires_at: body.expires_at.unwrap_or(current.expires_at),
+        on_expiry: body.on_expiry.unwrap_or(current.on_expiry),
+        max_recv: body.max_recv.unwrap_or(current.max_recv),
+    };
+    let alias = aliases::ssr::create_or_update(&app_state.pool, &user, Some(current.address), edit).await?;
+    Ok(Json(alias).into_response())
+}
+
+/// Delete a alias
+#[utoipa::path(
+    delete,
+    path = "/api/idmail/v1/aliases/{address}",
+    tag = "idmail",
+    params(("address" = String, Path, description = "The address of the alias")),
+    responses(
+        (status = 204, description = "The alias was deleted"),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("api_token" = []), ("session" = [])),
+)]
+pub async fn delete_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(address): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = authenticate(&app_state, &credentials, Access::Write).await?;
+    aliases::ssr::delete(&app_state.pool, &user, &address).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
@@ -0,0 +1,187 @@
+//! The OpenAPI specification of all HTTP API endpoints, generated from the handlers and their types.
+//!
+//! The endpoints are added to the router from the same list, at the path and methods of their
+//! `#[utoipa::path]` attribute, so the specification always describes what is served.
+
+use super::{addy_io, duckduckgo, fastmail, firefox_relay, idmail, simple_login, ErrorResponse};
+use crate::state::AppState;
+use axum::{
+    handler::Handler,
+    routing::{get, on, MethodFilter},
+    Json, Router,
+};
+use std::sync::Arc;
+use utoipa::{
+    __dev::{SchemaReferences, Tags},
+    openapi::{
+        path::HttpMethod,
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    },
+    Modify, OpenApi, Path,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "idmail", description = "API endpoints provided by idmail"),
+    components(schemas(ErrorResponse)),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "addy.io", description = "Endpoints compatible with the addy.io (AnonAddy) API"),
+        (name = "SimpleLogin", description = "Endpoints compatible with the SimpleLogin API"),
+        (name = "Firefox Relay", description = "Endpoints compatible with the Firefox Relay API"),
+        (name = "DuckDuckGo", description = "Endpoints compatible with DuckDuckGo Email Protection"),
+        (name = "Fastmail", description = "Endpoints compatible with Fastmail's masked email JMAP extension"),
+        (name = "idmail", description = "The native idmail API"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        // SimpleLogin clients send the plain token in a non-standard header
+        components.add_security_scheme(
+            "simple_login",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("Authentication"))),
+        );
+        // Firefox Relay clients send the token as "Token <token>"
+        components.add_security_scheme(
+            "firefox_relay",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "Authorization",
+                "Token <api token>",
+            ))),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(crate::sessions::ssr::cookie_name()))),
+        );
+    }
+}
+
+/// Adds a handler to the router and its documentation to the specification.
+fn route<P, H, T>(router: Router<AppState>, openapi: &mut utoipa::openapi::OpenApi, handler: H) -> Router<AppState>
+where
+    P: Path + SchemaReferences + Tags<'static>,
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    let path = P::path();
+    let methods = P::methods();
+    let filter = methods
+        .iter()
+        .map(|method| match method {
+            HttpMethod::Get => MethodFilter::GET,
+            HttpMethod::Post => MethodFilter::POST,
+            HttpMethod::Put => MethodFilter::PUT,
+            HttpMethod::Delete => MethodFilter::DELETE,
+            HttpMethod::Options => MethodFilter::OPTIONS,
+            HttpMethod::Head => MethodFilter::HEAD,
+            HttpMethod::Patch => MethodFilter::PATCH,
+            HttpMethod::Trace => MethodFilter::TRACE,
+        })
+        .reduce(MethodFilter::or)
+        .expect("every path has a method");
+    // Path parameters are written as `{id}` in OpenAPI, but as `:id` in axum
+    let axum_path = path
+        .split('/')
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|x| x.strip_suffix('}')) {
+                Some(parameter) => format!(":{parameter}"),
+                None => segment.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let mut schemas = Vec::new();
+    P::schemas(&mut schemas);
+    openapi
+        .components
+        .get_or_insert_with(Default::default)
+        .schemas
+        .extend(schemas);
+    let mut operation = P::operation();
+    operation
+        .tags
+        .get_or_insert_with(Vec::new)
+        .extend(P::tags().into_iter().map(String::from));
+    openapi.paths.add_path_operation(path, methods, operation);
+
+    router.route(&axum_path, on(filter, handler))
+}
+
+/// Adds each handler with the path struct generated by `#[utoipa::path]` for it.
+macro_rules! routes {
+    ($router:ident, $openapi:ident; $($module:ident::$handler:ident),* $(,)?) => {
+        $(
+            let $router = paste::paste! {
+                route::<$module::[<__path_ $handler>], _, _>($router, &mut $openapi, $module::$handler)
+            };
+        )*
+    };
+}
+
+/// The router of all API endpoints, including the specification at `/api/openapi.json`.
+pub fn router() -> Router<AppState> {
+    let mut openapi = ApiDoc::openapi();
+    let router = Router::new();
+    routes!(router, openapi;
+        addy_io::create_alias,
+        addy_io::list_aliases,
+        addy_io::get_alias_by_id,
+        addy_io::update_alias,
+        addy_io::delete_alias,
+        addy_io::activate_alias,
+        addy_io::deactivate_alias,
+        addy_io::account_details,
+        addy_io::domain_options,
+        simple_login::create_alias_random,
+        simple_login::create_alias_custom,
+        simple_login::alias_options,
+        simple_login::user_info,
+        simple_login::list_mailboxes,
+        simple_login::list_aliases,
+        simple_login::get_alias_by_id,
+        simple_login::update_alias,
+        simple_login::toggle_alias,
+        simple_login::delete_alias,
+        firefox_relay::create_mask,
+        firefox_relay::list_masks,
+        firefox_relay::get_mask,
+        firefox_relay::update_mask,
+        firefox_relay::delete_mask,
+        duckduckgo::create_address,
+        fastmail::session,
+        fastmail::api,
+        idmail::list_users,
+        idmail::get_user,
+        idmail::create_user,
+        idmail::update_user,
+        idmail::delete_user,
+        idmail::list_domains,
+        idmail::get_domain,
+        idmail::create_domain,
+        idmail::update_domain,
+        idmail::delete_domain,
+        idmail::list_mailboxes,
+        idmail::get_mailbox,
+        idmail::create_mailbox,
+        idmail::update_mailbox,
+        idmail::delete_mailbox,
+        idmail::list_aliases,
+        idmail::get_alias,
+        idmail::create_alias,
+        idmail::update_alias,
+        idmail::delete_alias,
+    );
+
+    let openapi = Arc::new(openapi);
+    router.route("/api/openapi.json", get(move || async move { Json(openapi) }))
+}
@@ -0,0 +1,701 @@
+//! Endpoints compatible with the SimpleLogin API, as used by its browser extension and CLI.
+//!
+//! Aliases and mailboxes are identified by their numeric ids.
+
+use super::{
+    allowed_domains, comment_for, create_alias, delete_alias as delete_alias_by_address, login_with_api_token,
+    update_alias_active, update_alias_comment, ApiError, Credentials, ErrorResponse, LocalPart,
+};
+use crate::{
+    aliases::{push_and_check_aliases_owner, Alias},
+    api_tokens::ssr::Access,
+    auth::User,
+    mailboxes,
+    roles::Permission,
+    state::AppState,
+};
+use axum::{
+    extract::{self, Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::QueryBuilder;
+use utoipa::{IntoParams, ToSchema};
+
+/// Number of aliases returned per page, same as SimpleLogin
+const PAGE_SIZE: i64 = 20;
+/// Signed suffixes expire after this many seconds
+const SIGNED_SUFFIX_VALIDITY: i64 = 600;
+
+#[derive(sqlx::FromRow)]
+struct AliasWithMailbox {
+    #[sqlx(flatten)]
+    alias: Alias,
+    /// The id of the target mailbox, if the target is a mailbox
+    mailbox_id: Option<i64>,
+}
+
+/// The target mailbox of an alias
+#[derive(Clone, Serialize, ToSchema)]
+pub struct SimpleLoginAliasMailbox {
+    /// The id of the mailbox, or 0 if the target is not a mailbox
+    id: i64,
+    email: String,
+}
+
+/// An alias in the representation of SimpleLogin
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginAlias {
+    id: i64,
+    email: String,
+    name: Option<String>,
+    enabled: bool,
+    creation_date: String,
+    creation_timestamp: i64,
+    note: Option<String>,
+    nb_block: i64,
+    nb_forward: i64,
+    nb_reply: i64,
+    latest_activity: Option<String>,
+    pinned: bool,
+    disable_pgp: bool,
+    support_pgp: bool,
+    mailbox: SimpleLoginAliasMailbox,
+    mailboxes: Vec<SimpleLoginAliasMailbox>,
+}
+
+impl From<&AliasWithMailbox> for SimpleLoginAlias {
+    fn from(AliasWithMailbox { alias, mailbox_id }: &AliasWithMailbox) -> Self {
+        let mailbox = SimpleLoginAliasMailbox {
+            id: mailbox_id.unwrap_or(0),
+            email: alias.target.clone(),
+        };
+        Self {
+            id: alias.id,
+            email: alias.address.clone(),
+            name: None,
+            enabled: alias.active,
+            creation_date: alias.created_at.format("%Y-%m-%d %H:%M:%S%:z").to_string(),
+            creation_timestamp: alias.created_at.timestamp(),
+            note: (!alias.comment.is_empty()).then(|| alias.comment.clone()),
+            nb_block: 0,
+            nb_forward: alias.n_recv,
+            nb_reply: alias.n_sent,
+            latest_activity: None,
+            pinned: false,
+            disable_pgp: false,
+            support_pgp: false,
+            mailbox: mailbox.clone(),
+            mailboxes: vec![mailbox],
+        }
+    }
+}
+
+/// A newly created random alias
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginRandomAlias {
+    #[serde(flatten)]
+    alias: SimpleLoginAlias,
+    /// The address of the alias, as older clients only look at this field
+    #[serde(rename = "alias")]
+    address: String,
+}
+
+fn select_aliases(user: &User) -> QueryBuilder<'static, sqlx::Sqlite> {
+    let mut query = QueryBuilder::new(
+        "SELECT *, (SELECT id FROM mailboxes WHERE address = aliases.target) AS mailbox_id FROM aliases WHERE 1=1",
+    );
+    if !user.can(Permission::AliasesRead) {
+        push_and_check_aliases_owner(&mut query, user.username.clone());
+    }
+    query
+}
+
+async fn get_alias(app_state: &AppState, user: &User, id: i64) -> Result<AliasWithMailbox, ApiError> {
+    let mut query = select_aliases(user);
+    query.push(" AND id = ");
+    query.push_bind(id);
+
+    query
+        .build_query_as::<AliasWithMailbox>()
+        .fetch_optional(&app_state.pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Alias {id} does not exist")))
+}
+
+async fn get_alias_by_address(app_state: &AppState, user: &User, address: &str) -> Result<AliasWithMailbox, ApiError> {
+    let mut query = select_aliases(user);
+    query.push(" AND address = ");
+    query.push_bind(address.to_string());
+
+    query
+        .build_query_as::<AliasWithMailbox>()
+        .fetch_optional(&app_state.pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Alias '{address}' does not exist")))
+}
+
+/// Returns the address of the mailbox with the given id, if the user may use it as an alias target.
+async fn get_mailbox_address(app_state: &AppState, user: &User, id: i64) -> Result<String, ApiError> {
+    let address = sqlx::query_scalar::<_, String>("SELECT address FROM mailboxes WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&app_state.pool)
+        .await?;
+
+    let allowed_targets = mailboxes::ssr::allowed_targets(&app_state.pool, user).await?;
+    address
+        .filter(|x| allowed_targets.contains(x))
+        .ok_or_else(|| ApiError::BadRequest(format!("Mailbox {id} does not exist")))
+}
+
+fn suffix_mac(key: &[u8], payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take a key of any size");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// Signs a suffix so that clients can later use it to create a custom alias.
+fn sign_suffix(key: &[u8], suffix: &str) -> String {
+    let payload = format!("{suffix}.{}", Utc::now().timestamp());
+    let signature = hex::encode(suffix_mac(key, &payload).finalize().into_bytes());
+    format!("{payload}.{signature}")
+}
+
+/// Verifies a signed suffix and returns the contained suffix.
+fn verify_suffix(key: &[u8], signed_suffix: &str) -> Result<String, ApiError> {
+    let invalid = || ApiError::BadRequest("Invalid or expired signed suffix".to_string());
+
+    let (payload, signature) = signed_suffix.rsplit_once('.').ok_or_else(invalid)?;
+    let signature = hex::decode(signature).map_err(|_| invalid())?;
+    suffix_mac(key, payload)
+        .verify_slice(&signature)
+        .map_err(|_| invalid())?;
+
+    let (suffix, timestamp) = payload.rsplit_once('.').ok_or_else(invalid)?;
+    let timestamp = timestamp.parse::<i64>().map_err(|_| invalid())?;
+    if Utc::now().timestamp() - timestamp > SIGNED_SUFFIX_VALIDITY {
+        return Err(invalid());
+    }
+
+    Ok(suffix.to_string())
+}
+
+/// Derives a prefix suggestion from a hostname, e.g. "github" for "www.github.com".
+fn prefix_suggestion(hostname: &str) -> String {
+    let hostname = hostname.strip_prefix("www.").unwrap_or(hostname);
+    let name = hostname.rsplit_once('.').map_or(hostname, |x| x.0);
+    let name = name.rsplit('.').next().unwrap_or(name);
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HostnameQuery {
+    hostname: Option<String>,
+}
+
+/// The alias generation modes of SimpleLogin
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SimpleLoginMode {
+    Uuid,
+    Word,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RandomAliasQuery {
+    hostname: Option<String>,
+    /// How the alias is generated. Defaults to a random username.
+    #[param(inline)]
+    mode: Option<SimpleLoginMode>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SimpleLoginRequest {
+    #[serde(default)]
+    note: Option<String>,
+    /// Seconds until the alias expires (an idmail extension). Never expires by default.
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// Create a new random alias
+#[utoipa::path(
+    post,
+    path = "/api/alias/random/new",
+    tag = "SimpleLogin",
+    params(RandomAliasQuery),
+    request_body = SimpleLoginRequest,
+    responses(
+        (status = 201, description = "The created alias", body = SimpleLoginRandomAlias),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn create_alias_random(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(RandomAliasQuery { hostname, mode }): Query<RandomAliasQuery>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<SimpleLoginRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Create).await?;
+    let local_part = match mode {
+        None => LocalPart::Username,
+        Some(SimpleLoginMode::Uuid) => LocalPart::Uuid,
+        Some(SimpleLoginMode::Word) => LocalPart::RandomWords,
+    };
+    let alias = create_alias(
+        &app_state,
+        &user,
+        &local_part.generate(),
+        None,
+        None,
+        &comment_for(body.note, hostname),
+        body.expires_in,
+    )
+    .await?;
+    let alias = get_alias_by_address(&app_state, &user, &alias.address).await?;
+
+    let response = SimpleLoginRandomAlias {
+        alias: (&alias).into(),
+        address: alias.alias.address,
+    };
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SimpleLoginCustomRequest {
+    alias_prefix: String,
+    signed_suffix: String,
+    #[serde(default)]
+    note: Option<String>,
+    /// The target mailbox of the alias. Defaults to the first (default) mailbox.
+    #[serde(default)]
+    mailbox_ids: Vec<i64>,
+    /// The target mailbox, as sent by older clients
+    #[serde(default)]
+    mailbox_id: Option<i64>,
+    /// Seconds until the alias expires (an idmail extension). Never expires by default.
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// Create a new alias from a prefix and a signed suffix
+#[utoipa::path(
+    post,
+    path = "/api/v3/alias/custom/new",
+    tag = "SimpleLogin",
+    params(HostnameQuery),
+    request_body = SimpleLoginCustomRequest,
+    responses(
+        (status = 201, description = "The created alias", body = SimpleLoginAlias),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn create_alias_custom(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(HostnameQuery { hostname }): Query<HostnameQuery>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<SimpleLoginCustomRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Create).await?;
+    let suffix = verify_suffix(&app_state.signing_key, &body.signed_suffix)?;
+    let Some(domain) = suffix.strip_prefix('@') else {
+        return Err(ApiError::BadRequest(format!("Invalid suffix '{suffix}'")));
+    };
+
+    let mailbox_ids = body
+        .mailbox_ids
+        .iter()
+        .chain(body.mailbox_id.iter())
+        .collect::<Vec<_>>();
+    let target = match mailbox_ids.as_slice() {
+        [] => None,
+        [id] => Some(get_mailbox_address(&app_state, &user, **id).await?),
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Aliases can only have a single mailbox".to_string(),
+            ))
+        }
+    };
+
+    let alias = create_alias(
+        &app_state,
+        &user,
+        &LocalPart::Custom(body.alias_prefix).generate(),
+        Some(domain.to_string()),
+        target,
+        &comment_for(body.note, hostname),
+        body.expires_in,
+    )
+    .await?;
+    let alias = get_alias_by_address(&app_state, &user, &alias.address).await?;
+
+    Ok((StatusCode::CREATED, Json(SimpleLoginAlias::from(&alias))).into_response())
+}
+
+/// A suffix for custom aliases, i.e. one of the available domains
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginSuffix {
+    /// The suffix with a signature, which is valid for a limited time
+    signed_suffix: String,
+    suffix: String,
+    is_custom: bool,
+    is_premium: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginAliasOptions {
+    can_create: bool,
+    prefix_suggestion: String,
+    suffixes: Vec<SimpleLoginSuffix>,
+}
+
+/// Get the available suffixes for custom aliases
+#[utoipa::path(
+    get,
+    path = "/api/v5/alias/options",
+    tag = "SimpleLogin",
+    params(HostnameQuery),
+    responses(
+        (status = 200, description = "The alias options", body = SimpleLoginAliasOptions),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn alias_options(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(HostnameQuery { hostname }): Query<HostnameQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Info).await?;
+    let domains = allowed_domains(&app_state, &user).await?;
+
+    let suffixes = domains
+        .iter()
+        .map(|domain| {
+            let suffix = format!("@{domain}");
+            SimpleLoginSuffix {
+                signed_suffix: sign_suffix(&app_state.signing_key, &suffix),
+                suffix,
+                is_custom: true,
+                is_premium: false,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(SimpleLoginAliasOptions {
+        can_create: !suffixes.is_empty(),
+        prefix_suggestion: hostname.as_deref().map(prefix_suggestion).unwrap_or_default(),
+        suffixes,
+    })
+    .into_response())
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginUserInfo {
+    name: String,
+    email: String,
+    is_premium: bool,
+    in_trial: bool,
+    profile_picture_url: Option<String>,
+    max_alias_free_plan: Option<i64>,
+}
+
+/// Get information about the authenticated user
+#[utoipa::path(
+    get,
+    path = "/api/user_info",
+    tag = "SimpleLogin",
+    responses(
+        (status = 200, description = "The user information", body = SimpleLoginUserInfo),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn user_info(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Info).await?;
+
+    Ok(Json(SimpleLoginUserInfo {
+        name: user.username.clone(),
+        email: user.username,
+        is_premium: true,
+        in_trial: false,
+        profile_picture_url: None,
+        max_alias_free_plan: None,
+    })
+    .into_response())
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginMailbox {
+    id: i64,
+    email: String,
+    /// Whether new aliases use this mailbox by default, which is the first one
+    default: bool,
+    creation_timestamp: i64,
+    nb_alias: i64,
+    verified: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginMailboxes {
+    mailboxes: Vec<SimpleLoginMailbox>,
+}
+
+/// List mailboxes
+#[utoipa::path(
+    get,
+    path = "/api/v2/mailboxes",
+    tag = "SimpleLogin",
+    responses(
+        (status = 200, description = "The mailboxes", body = SimpleLoginMailboxes),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn list_mailboxes(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Info).await?;
+
+    let mut query = QueryBuilder::new(
+        "SELECT id, address, created_at, (SELECT COUNT(*) FROM aliases WHERE target = mailboxes.address) \
+        FROM mailboxes",
+    );
+    if user.mailbox_owner.is_some() {
+        query.push(" WHERE address = ");
+    } else {
+        query.push(" WHERE owner = ");
+    }
+    query.push_bind(&user.username);
+    query.push(" ORDER BY id");
+
+    let mailboxes = query
+        .build_query_as::<(i64, String, DateTime<Utc>, i64)>()
+        .fetch_all(&app_state.pool)
+        .await?;
+
+    Ok(Json(SimpleLoginMailboxes {
+        mailboxes: mailboxes
+            .into_iter()
+            .enumerate()
+            .map(|(i, (id, address, created_at, n_aliases))| SimpleLoginMailbox {
+                id,
+                email: address,
+                default: i == 0,
+                creation_timestamp: created_at.timestamp(),
+                nb_alias: n_aliases,
+                verified: true,
+            })
+            .collect(),
+    })
+    .into_response())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListAliasesQuery {
+    page_id: i64,
+    pinned: Option<String>,
+    disabled: Option<String>,
+    enabled: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SimpleLoginSearchRequest {
+    query: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginAliases {
+    aliases: Vec<SimpleLoginAlias>,
+}
+
+/// List aliases
+#[utoipa::path(
+    method(get, post),
+    path = "/api/v2/aliases",
+    tag = "SimpleLogin",
+    params(ListAliasesQuery),
+    request_body = Option<SimpleLoginSearchRequest>,
+    responses(
+        (status = 200, description = "A page of aliases", body = SimpleLoginAliases),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn list_aliases(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Query(params): Query<ListAliasesQuery>,
+    body: Option<Json<SimpleLoginSearchRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Read).await?;
+
+    let mut query = select_aliases(&user);
+    if let Some(search) = body.and_then(|x| x.0.query).filter(|x| !x.is_empty()) {
+        query.push(" AND ( address LIKE concat('%', ");
+        query.push_bind(search.clone());
+        query.push(", '%') OR comment LIKE concat('%', ");
+        query.push_bind(search);
+        query.push(", '%') )");
+    }
+    if params.enabled.is_some() {
+        query.push(" AND active = TRUE");
+    }
+    if params.disabled.is_some() {
+        query.push(" AND active = FALSE");
+    }
+    // Aliases cannot be pinned, so asking only for those yields nothing.
+    if params.pinned.is_some() {
+        query.push(" AND FALSE");
+    }
+    query.push(" ORDER BY created_at DESC LIMIT ");
+    query.push_bind(PAGE_SIZE);
+    query.push(" OFFSET ");
+    query.push_bind(params.page_id.max(0) * PAGE_SIZE);
+
+    let aliases = query
+        .build_query_as::<AliasWithMailbox>()
+        .fetch_all(&app_state.pool)
+        .await?;
+
+    Ok(Json(SimpleLoginAliases {
+        aliases: aliases.iter().map(SimpleLoginAlias::from).collect(),
+    })
+    .into_response())
+}
+
+/// Get a specific alias
+#[utoipa::path(
+    get,
+    path = "/api/aliases/{id}",
+    tag = "SimpleLogin",
+    params(("id" = i64, Path, description = "The id of the alias")),
+    responses(
+        (status = 200, description = "The alias", body = SimpleLoginAlias),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn get_alias_by_id(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Read).await?;
+    let alias = get_alias(&app_state, &user, id).await?;
+    Ok(Json(SimpleLoginAlias::from(&alias)).into_response())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SimpleLoginUpdateRequest {
+    note: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginOk {
+    ok: bool,
+}
+
+/// Update the note of an alias
+#[utoipa::path(
+    patch,
+    path = "/api/aliases/{id}",
+    tag = "SimpleLogin",
+    params(("id" = i64, Path, description = "The id of the alias")),
+    request_body = SimpleLoginUpdateRequest,
+    responses(
+        (status = 200, description = "The alias was updated", body = SimpleLoginOk),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn update_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<i64>,
+    WithRejection(extract::Json(body), _): WithRejection<extract::Json<SimpleLoginUpdateRequest>, ApiError>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    let alias = get_alias(&app_state, &user, id).await?.alias;
+    if let Some(note) = body.note {
+        update_alias_comment(&app_state, &user, &alias.address, &note).await?;
+    }
+
+    Ok(Json(SimpleLoginOk { ok: true }).into_response())
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginToggled {
+    enabled: bool,
+}
+
+/// Toggle whether an alias is enabled
+#[utoipa::path(
+    post,
+    path = "/api/aliases/{id}/toggle",
+    tag = "SimpleLogin",
+    params(("id" = i64, Path, description = "The id of the alias")),
+    responses(
+        (status = 200, description = "The new state of the alias", body = SimpleLoginToggled),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn toggle_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    let alias = get_alias(&app_state, &user, id).await?.alias;
+    let alias = update_alias_active(&app_state, &user, &alias.address, !alias.active).await?;
+
+    Ok(Json(SimpleLoginToggled { enabled: alias.active }).into_response())
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimpleLoginDeleted {
+    deleted: bool,
+}
+
+/// Delete an alias
+#[utoipa::path(
+    delete,
+    path = "/api/aliases/{id}",
+    tag = "SimpleLogin",
+    params(("id" = i64, Path, description = "The id of the alias")),
+    responses(
+        (status = 200, description = "The alias was deleted", body = SimpleLoginDeleted),
+        (status = "4XX", description = "The request failed", body = ErrorResponse),
+    ),
+    security(("simple_login" = [])),
+)]
+pub async fn delete_alias(
+    State(app_state): State<AppState>,
+    credentials: Credentials,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = login_with_api_token(&app_state, &credentials, Access::Write).await?;
+    let alias = get_alias(&app_state, &user, id).await?.alias;
+    delete_alias_by_address(&app_state, &user, &alias.address).await?;
+
+    Ok(Json(SimpleLoginDeleted { deleted: true }).into_response())
+}
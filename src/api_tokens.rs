@@ -0,0 +1,635 @@
+#![allow(clippy::unused_unit)] // The TableRow derive generates unit expressions
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::utils::{DeleteModal, EditModal, Modal, Select};
+use crate::utils::{OptionalTimediffRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::html::Dialog;
+use leptos::{ev::MouseEvent, logging::error, *};
+use leptos_icons::Icon;
+use leptos_struct_table::*;
+use leptos_use::use_timeout_fn;
+use serde::{Deserialize, Serialize};
+
+/// Determines which API endpoints a token may be used for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "TEXT", rename_all = "lowercase"))]
+#[serde(rename_all = "lowercase")]
+pub enum ApiTokenScope {
+    /// Only create new aliases
+    Create,
+    /// Only read existing entities
+    Read,
+    /// Unrestricted access
+    Full,
+}
+
+impl ApiTokenScope {
+    pub const ALL: [ApiTokenScope; 3] = [ApiTokenScope::Full, ApiTokenScope::Read, ApiTokenScope::Create];
+}
+
+impl fmt::Display for ApiTokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiTokenScope::Create => write!(f, "create"),
+            ApiTokenScope::Read => write!(f, "read"),
+            ApiTokenScope::Full => write!(f, "full"),
+        }
+    }
+}
+
+impl FromStr for ApiTokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(ApiTokenScope::Create),
+            "read" => Ok(ApiTokenScope::Read),
+            "full" => Ok(ApiTokenScope::Full),
+            _ => Err(format!("invalid token scope '{s}'")),
+        }
+    }
+}
+
+impl CellValue for ApiTokenScope {
+    type RenderOptions = ();
+
+    fn render_value(self, _options: &Self::RenderOptions) -> impl IntoView {
+        self.to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct ApiToken {
+    #[table(skip)]
+    pub id: i64,
+    pub name: String,
+    #[table(class = "w-1")]
+    pub scope: ApiTokenScope,
+    #[table(class = "w-1", title = "Expires", renderer = "OptionalTimediffRenderer")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[table(class = "w-1", title = "Last used", renderer = "OptionalTimediffRenderer")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[table(class = "w-1", title = "Last IP", none_value = "-")]
+    pub last_used_ip: Option<String>,
+    #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// The choices for the lifetime of new tokens, in days
+const EXPIRY_CHOICES: [(&str, Option<u32>); 4] = [
+    ("Never", None),
+    ("30 days", Some(30)),
+    ("90 days", Some(90)),
+    ("365 days", Some(365)),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiTokenQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{ApiToken, ApiTokenScope, EXPIRY_CHOICES};
+    use crate::{api::ApiError, auth::User};
+    use sha2::{Digest, Sha256};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::{net::IpAddr, ops::Range};
+
+    /// The kind of access an API endpoint requires
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Access {
+        /// Information required to create aliases, like the usable domains
+        Info,
+        /// Creating new aliases
+        Create,
+        /// Reading existing entities
+        Read,
+        /// Modifying or deleting entities
+        Write,
+    }
+
+    impl ApiTokenScope {
+        pub fn allows(self, access: Access) -> bool {
+            match self {
+                ApiTokenScope::Full => true,
+                ApiTokenScope::Read => matches!(access, Access::Info | Access::Read),
+                ApiTokenScope::Create => matches!(access, Access::Info | Access::Create),
+            }
+        }
+    }
+
+    /// Hashes a token for storage. Tokens are generated with enough entropy
+    /// that a fast, unsalted hash is sufficient and allows direct lookups.
+    pub fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<ApiToken>, ApiError> {
+        let mut query = QueryBuilder::new(
+            "SELECT id, name, scope, expires_at, last_used_at, last_used_ip, created_at FROM api_tokens",
+        );
+        query.push(" WHERE owner = ");
+        query.push_bind(&user.username);
+
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<ApiToken>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM api_tokens WHERE owner = ?")
+            .bind(&user.username)
+            .fetch_one(pool)
+            .await?;
+        Ok(count as usize)
+    }
+
+    /// Creates a new token for the user and returns it. The plaintext token is not stored.
+    pub async fn create(
+        pool: &SqlitePool,
+        user: &User,
+        name: &str,
+        scope: ApiTokenScope,
+        expires_in_days: Option<u32>,
+    ) -> Result<String, ApiError> {
+        let name = name.trim();
+        if name.is_empty() || name.len() > 64 {
+            return Err(ApiError::BadRequest(
+                "Token name must be between 1 and 64 characters".to_string(),
+            ));
+        }
+        if !EXPIRY_CHOICES.iter().any(|(_, days)| *days == expires_in_days) {
+            return Err(ApiError::BadRequest("Invalid token expiry".to_string()));
+        }
+
+        let mut buf = [0u8; 24];
+        getrandom::getrandom(&mut buf).map_err(|e| ApiError::ServerError(e.to_string()))?;
+        let token = hex::encode(buf);
+
+        let mut query = QueryBuilder::new("INSERT INTO api_tokens (owner, name, token_hash, scope, expires_at)");
+        query.push(" VALUES (");
+        query.push_bind(&user.username);
+        query.push(", ");
+        query.push_bind(name);
+        query.push(", ");
+        query.push_bind(hash_token(&token));
+        query.push(", ");
+        query.push_bind(scope);
+        match expires_in_days {
+            Some(days) => {
+                query.push(", datetime('now', ");
+                query.push_bind(format!("+{days} days"));
+                query.push("))");
+            }
+            None => {
+                query.push(", NULL)");
+            }
+        }
+        query.build().execute(pool).await?;
+
+        Ok(token)
+    }
+
+    pub async fn delete(pool: &SqlitePool, user: &User, id: i64) -> Result<(), ApiError> {
+        let mut query = QueryBuilder::new("DELETE FROM api_tokens WHERE id = ");
+        query.push_bind(id);
+        query.push(" AND owner = ");
+        query.push_bind(&user.username);
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("API token {id} does not exist")));
+        }
+
+        Ok(())
+    }
+
+    #[derive(sqlx::FromRow)]
+    struct OwnedApiToken {
+        owner: String,
+        #[sqlx(flatten)]
+        token: ApiToken,
+    }
+
+    /// Returns the owner and the details of a valid, unexpired token.
+    pub async fn find(pool: &SqlitePool, token: &str) -> Option<(String, ApiToken)> {
+        sqlx::query_as::<_, OwnedApiToken>(
+            "SELECT owner, id, name, scope, expires_at, last_used_at, last_used_ip, created_at \
+            FROM api_tokens WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
+        )
+        .bind(hash_token(token))
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .map(|x| (x.owner, x.token))
+    }
+
+    /// Records the time and address of the latest use of a token
+    pub async fn mark_used(pool: &SqlitePool, id: i64, ip: Option<IpAddr>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = CURRENT_TIMESTAMP, last_used_ip = ? WHERE id = ?")
+            .bind(ip.map(|x| x.to_string()))
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Moves the plaintext tokens of the former `mailboxes.api_token` column into the
+    /// api_tokens table. Hashing is not possible in SQL, so this has to run on startup.
+    pub async fn migrate_legacy_tokens(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let legacy_tokens = sqlx::query_as::<_, (String, String)>(
+            "SELECT address, api_token FROM mailboxes WHERE api_token IS NOT NULL",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        if legacy_tokens.is_empty() {
+            return Ok(());
+        }
+
+        log::info!("Migrating {} legacy API tokens", legacy_tokens.len());
+        let mut tx = pool.begin().await?;
+        for (address, token) in legacy_tokens {
+            sqlx::query(
+                "INSERT INTO api_tokens (owner, name, token_hash, scope) VALUES (?, 'API token', ?, 'full') \
+                ON CONFLICT (token_hash) DO NOTHING",
+            )
+            .bind(&address)
+            .bind(hash_token(&token))
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query("UPDATE mailboxes SET api_token = NULL")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+}
+
+#[server]
+pub async fn list_api_tokens(query: ApiTokenQuery) -> Result<Vec<ApiToken>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let ApiTokenQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, ApiToken::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn api_token_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn create_api_token(
+    name: String,
+    scope: ApiTokenScope,
+    expires_in_days: Option<u32>,
+) -> Result<String, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::create(&pool, &user, &name, scope, expires_in_days).await?)
+}
+
+#[server]
+pub async fn delete_api_token(id: i64) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::delete(&pool, &user, id).await?)
+}
+
+#[derive(Default)]
+pub struct ApiTokenTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<ApiToken> for ApiTokenTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<ApiToken>, Range<usize>), String> {
+        list_api_tokens(ApiTokenQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        api_token_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+#[component]
+pub fn ApiTokens() -> impl IntoView {
+    let mut rows = ApiTokenTableDataProvider::default();
+    let default_sorting = VecDeque::from([(5, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload = create_trigger();
+    let reload_controller = ReloadController::default();
+    create_effect(move |_| {
+        reload.track();
+        reload_controller.reload();
+    });
+
+    let (count, set_count) = create_signal(0);
+
+    let delete_modal_token = create_rw_signal(None);
+    let delete_modal_token_id = create_rw_signal(None);
+    let edit_modal_token = create_rw_signal(None);
+
+    let (scope_choices, _) = create_signal(ApiTokenScope::ALL.iter().map(|x| x.to_string()).collect());
+    let (expiry_choices, _) = create_signal(EXPIRY_CHOICES.iter().map(|x| x.0.to_string()).collect());
+    let (edit_modal_input_name, set_edit_modal_input_name) = create_signal("".to_string());
+    let (edit_modal_input_scope, set_edit_modal_input_scope) = create_signal(ApiTokenScope::Full.to_string());
+    let (edit_modal_input_expiry, set_edit_modal_input_expiry) = create_signal(EXPIRY_CHOICES[0].0.to_string());
+    let edit_modal_open = move || {
+        edit_modal_token.set(Some(None::<ApiToken>));
+        set_edit_modal_input_name("".to_string());
+        set_edit_modal_input_scope(ApiTokenScope::Full.to_string());
+        set_edit_modal_input_expiry(EXPIRY_CHOICES[0].0.to_string());
+    };
+
+    let api_token_modal = create_node_ref::<Dialog>();
+    let api_token_modal_open = create_rw_signal(false);
+    let api_token_modal_token = create_rw_signal("".to_string());
+    let api_token_modal_copied_timer = use_timeout_fn(|_: ()| (), 3000.0);
+    create_effect(move |_| {
+        // Clear API token when dialog closes in any way
+        if !api_token_modal_open() {
+            api_token_modal_token.set("".to_string());
+            (api_token_modal_copied_timer.stop)();
+        }
+    });
+
+    let on_edit = move |(_data, on_error): (Option<ApiToken>, Callback<String>)| {
+        spawn_local(async move {
+            let scope = match edit_modal_input_scope.get_untracked().parse() {
+                Ok(scope) => scope,
+                Err(e) => return on_error(e),
+            };
+            let expires_in_days = EXPIRY_CHOICES
+                .iter()
+                .find(|x| x.0 == edit_modal_input_expiry.get_untracked())
+                .and_then(|x| x.1);
+
+            match create_api_token(edit_modal_input_name.get_untracked(), scope, expires_in_days).await {
+                Err(e) => on_error(e.to_string()),
+                Ok(api_token) => {
+                    reload.notify();
+                    edit_modal_token.set(None);
+                    api_token_modal_token.set(api_token);
+                    api_token_modal_open.set(true);
+                }
+            }
+        });
+    };
+
+    #[allow(unused_variables, non_snake_case)]
+    let api_token_row_renderer =
+        move |class: Signal<String>,
+              row: ApiToken,
+              index: usize,
+              selected: Signal<bool>,
+              on_select: EventHandler<MouseEvent>,
+              on_change: EventHandler<ChangeEvent<ApiToken>>| {
+            let delete_name = row.name.clone();
+            let delete_id = row.id;
+            view! {
+                <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                    {row.render_row(index, on_change)}
+                    <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                        <div class="inline-flex items-center rounded-md">
+                            <button
+                                class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                                on:click=move |_| {
+                                    delete_modal_token_id.set(Some(delete_id));
+                                    delete_modal_token.set(Some(delete_name.clone()));
+                                }
+                            >
+
+                                <Icon icon=icondata::FiTrash2 class="w-5 h-5"/>
+                            </button>
+                        </div>
+                    </td>
+                </tr>
+            }
+        };
+
+    let errors = create_memo(move |_| {
+        let mut errors = Vec::new();
+        let name = edit_modal_input_name();
+        if name.trim().is_empty() || name.trim().len() > 64 {
+            errors.push("Name must be between 1 and 64 characters".to_string());
+        }
+        errors
+    });
+
+    view! {
+        <div class="flex items-center justify-between space-y-2 mt-8 mb-4">
+            <h3 class="text-2xl font-bold">API Tokens</h3>
+        </div>
+        <div class="space-y-4">
+            <div class="flex flex-wrap items-center justify-between">
+                <button
+                    type="button"
+                    class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-blue-600 dark:bg-blue-700 hover:bg-blue-500 dark:hover:bg-blue-600 focus:ring-blue-300 dark:focus:ring-blue-900"
+                    on:click=move |_| edit_modal_open()
+                >
+                    <Icon icon=icondata::FiPlus class="w-6 h-6 me-2"/>
+                    New
+                </button>
+                <div class="flex flex-1"></div>
+                <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                    {count} " tokens"
+                </div>
+            </div>
+
+            <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                <div class="overflow-auto grow min-h-0">
+                    <table class="table-auto text-left w-full">
+                        <TableContent
+                            rows
+                            sorting=sorting
+                            sorting_mode=SortingMode::SingleColumn
+                            row_renderer=api_token_row_renderer
+                            reload_controller=reload_controller
+                            loading_row_display_limit=0
+                            on_row_count=set_count
+                        />
+                    </table>
+                </div>
+            </div>
+        </div>
+
+        <DeleteModal
+            data=delete_modal_token
+            text="Are you sure you want to revoke this API token? Applications using it will lose access immediately."
+                .into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Some(id) = delete_modal_token_id.get_untracked() {
+                        if let Err(e) = delete_api_token(id).await {
+                            error!("Failed to delete API token: {}", e);
+                        } else {
+                            reload.notify();
+                        }
+                    }
+                    delete_modal_token.set(None);
+                    delete_modal_token_id.set(None);
+                });
+            }
+        />
+
+        <EditModal
+            data=edit_modal_token
+            what="API Token".to_string()
+            get_title=move |x| { &x.name }
+            on_confirm=on_edit
+            errors
+        >
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="name"
+                >
+                    Name
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="text"
+                    placeholder="Password manager"
+                    maxlength="64"
+                    on:input=move |ev| set_edit_modal_input_name(event_target_value(&ev))
+                    prop:value=edit_modal_input_name
+                />
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="scope"
+                >
+                    Scope
+                </label>
+                <Select
+                    class="w-full h-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900"
+                    choices=scope_choices
+                    value=edit_modal_input_scope
+                    set_value=set_edit_modal_input_scope
+                />
+                <p class="text-sm text-gray-500 dark:text-gray-400">
+                    "A create token can only create new aliases, a read token can only list them."
+                </p>
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="expiry"
+                >
+                    Expires after
+                </label>
+                <Select
+                    class="w-full h-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900"
+                    choices=expiry_choices
+                    value=edit_modal_input_expiry
+                    set_value=set_edit_modal_input_expiry
+                />
+            </div>
+        </EditModal>
+
+        <Modal open=api_token_modal_open dialog_el=api_token_modal>
+            <div class="relative p-4 transform overflow-hidden rounded-lg bg-white dark:bg-black text-left transition-all sm:w-full sm:max-w-xl">
+                <h3 class="text-2xl tracking-tight mt-2 mb-2 font-semibold text-gray-900 dark:text-gray-200">
+                    "API Token"
+                </h3>
+                <div class="pb-3 space-y-3">
+                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                        "Your new API Token is displayed below. Make sure to save it now, as it will not be displayed again."
+                    </p>
+                    <div class="w-full relative">
+                        <input
+                            type="text"
+                            class="col-span-6 bg-gray-50 dark:bg-gray-900 dark:bg-black border border-gray-300 text-gray-500 dark:text-gray-400 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full px-2.5 py-4"
+                            value=move || api_token_modal_token
+                            disabled
+                            readonly
+                        />
+                        <button
+                            class="absolute end-2.5 top-1/2 -translate-y-1/2 text-gray-900 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg py-2 px-2.5 inline-flex items-center justify-center bg-white dark:bg-black border-gray-200 border"
+                            on:click=move |_ev| {
+                                (api_token_modal_copied_timer.start)(());
+                                let clipboard = window().navigator().clipboard();
+                                let _ = clipboard.write_text(&api_token_modal_token.get());
+                            }
+                        >
+
+                            <span
+                                class="inline-flex items-center"
+                                class=("hidden", api_token_modal_copied_timer.is_pending)
+                            >
+                                <Icon icon=icondata::RiFileCopy2DocumentFill class="w-3 h-3 me-1.5"/>
+                                <span class="text-xs font-semibold">Copy</span>
+                            </span>
+                            <span
+                                class="hidden items-center"
+                                class=("!inline-flex", api_token_modal_copied_timer.is_pending)
+                            >
+                                <Icon
+                                    icon=icondata::BiCheckRegular
+                                    class="w-3 h-3 me-1.5 text-blue-700 dark:text-blue-300"
+                                />
+                                <span class="text-xs font-semibold text-blue-700 dark:text-blue-300">Copied</span>
+                            </span>
+                        </button>
+                    </div>
+                </div>
+                <div class="flex flex-col gap-3 sm:flex-row-reverse">
+                    <button
+                        type="button"
+                        class="inline-flex w-full min-w-20 justify-center rounded-lg transition-all bg-white dark:bg-black px-3 py-2 font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                        on:click=move |_ev| {
+                            api_token_modal_open.set(false);
+                        }
+                    >
+
+                        Dismiss
+                    </button>
+                </div>
+            </div>
+        </Modal>
+    }
+}
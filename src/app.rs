@@ -1,10 +1,16 @@
 use crate::{
     aliases::{alias_count, count_sent_or_received, Aliases},
+    app_passwords::AppPasswords,
     auth::{get_user, Login, LoginView, Logout},
     domains::Domains,
+    lockouts::LoginLockouts,
     mailboxes::Mailboxes,
+    password_resets::{PasswordResets, ResetPassword},
+    roles::{Permission, Roles},
+    sessions::AllSessions,
     users::{AccountSettings, Users},
     utils::ColorModeToggle,
+    webhooks::Webhooks,
 };
 use chrono::{Months, Utc};
 use leptos::{html::Div, *};
@@ -22,6 +28,7 @@ pub enum Tab {
     Mailboxes,
     Domains,
     Users,
+    Webhooks,
     AccountSettings,
 }
 
@@ -76,6 +83,16 @@ pub fn App() -> impl IntoView {
                         }
                     />
 
+                    <Route
+                        path="/reset-password"
+                        view=move || {
+                            view! {
+                                <Title text="Reset password"/>
+                                <ResetPassword color_mode=color_mode.mode set_color_mode=color_mode.set_mode/>
+                            }
+                        }
+                    />
+
                     <Route
                         path="/aliases"
                         view=move || {
@@ -140,6 +157,22 @@ pub fn App() -> impl IntoView {
                         }
                     />
 
+                    <Route
+                        path="/webhooks"
+                        view=move || {
+                            view! {
+                                <Title text="Webhooks"/>
+                                <Tab
+                                    login
+                                    logout
+                                    color_mode=color_mode.mode
+                                    set_color_mode=color_mode.set_mode
+                                    tab=Tab::Webhooks
+                                />
+                            }
+                        }
+                    />
+
                     <Route
                         path="/account"
                         view=move || {
@@ -164,7 +197,7 @@ pub fn App() -> impl IntoView {
 
 #[component]
 pub fn Tab(
-    login: Action<Login, Result<(), ServerFnError>>,
+    login: Action<Login, Result<bool, ServerFnError>>,
     logout: Action<Logout, Result<(), ServerFnError>>,
     color_mode: Signal<ColorMode>,
     set_color_mode: WriteSignal<ColorMode>,
@@ -221,6 +254,7 @@ pub fn Tab(
                     .map(|user| match user {
                         Ok(Some(user)) => {
                             let is_mailbox = user.mailbox_owner.is_some();
+                            let can_see_users = user.can(Permission::UsersRead);
                             view! {
                                 <div class="flex flex-col sm:flex-row items-center py-6 px-4 md:px-12">
                                     <div class="flex-1 flex flex-col sm:flex-row items-center w-full sm:w-auto">
@@ -240,11 +274,16 @@ pub fn Tab(
                                                     "Domains"
                                                 </A>
                                             </Show>
-                                            <Show when=move || user.admin>
+                                            <Show when=move || can_see_users>
                                                 <A href="/users" class=class_for(Tab::Users)>
                                                     "Users"
                                                 </A>
                                             </Show>
+                                            <Show when=move || user.admin>
+                                                <A href="/webhooks" class=class_for(Tab::Webhooks)>
+                                                    "Webhooks"
+                                                </A>
+                                            </Show>
                                         </div>
                                     </div>
                                     <div class="flex flex-row items-center w-full sm:w-auto relative">
@@ -285,11 +324,13 @@ pub fn Tab(
                                             <div class="px-4 py-3 text-sm text-gray-900 dark:text-gray-200">
                                                 <div class="font-medium">
                                                     {if user.admin {
-                                                        "Admin"
+                                                        "Admin".to_string()
                                                     } else if is_mailbox {
-                                                        "Mailbox"
+                                                        "Mailbox".to_string()
+                                                    } else if let Some(role) = &user.role {
+                                                        format!("User ({role})")
                                                     } else {
-                                                        "User"
+                                                        "User".to_string()
                                                     }}
 
                                                 </div>
@@ -319,6 +360,18 @@ pub fn Tab(
                                     </div>
                                 </div>
                                 <div class="overflow-hidden px-4 md:px-12">
+                                    <Show when=move || user.admin_requires_totp>
+                                        <div class="rounded-lg p-4 mb-4 flex bg-yellow-100 dark:bg-yellow-900 text-yellow-800 dark:text-yellow-200">
+                                            <Icon icon=icondata::AiWarningFilled class="w-5 h-5 me-3 flex-none"/>
+                                            <p>
+                                                "Admin access and role permissions require two-factor authentication. Enable it in your "
+                                                <A href="/account" class="font-semibold underline">
+                                                    "account settings"
+                                                </A>
+                                                " to manage this server."
+                                            </p>
+                                        </div>
+                                    </Show>
                                     <Show when=move || tab == Tab::Aliases>
                                         <div class="grid gap-4 lg:grid-cols-3">
                                             <div class="rounded-xl border-[1.5px] border-gray-200 dark:border-zinc-800">
@@ -410,10 +463,28 @@ pub fn Tab(
                                     {match tab {
                                         Tab::Aliases => view! { <Aliases user=user.clone() reload_stats/> }.into_view(),
                                         Tab::Mailboxes => {
-                                            view! { <Mailboxes user=user.clone() reload_stats/> }.into_view()
+                                            view! {
+                                                <Mailboxes user=user.clone() reload_stats/>
+                                                <AppPasswords/>
+                                                // Those who can see all users see all reset links on the users tab
+                                                <Show when=move || !can_see_users>
+                                                    <PasswordResets/>
+                                                </Show>
+                                            }
+                                                .into_view()
                                         }
                                         Tab::Domains => view! { <Domains user=user.clone()/> }.into_view(),
-                                        Tab::Users => view! { <Users/> }.into_view(),
+                                        Tab::Users => {
+                                            view! {
+                                                <Users user=user.clone()/>
+                                                <Roles/>
+                                                <PasswordResets/>
+                                                <AllSessions user=user.clone()/>
+                                                <LoginLockouts user=user.clone()/>
+                                            }
+                                                .into_view()
+                                        }
+                                        Tab::Webhooks => view! { <Webhooks/> }.into_view(),
                                         Tab::AccountSettings => {
                                             view! { <AccountSettings user=user.clone()/> }.into_view()
                                         }
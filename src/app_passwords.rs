@@ -0,0 +1,484 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::utils::{DeleteModal, EditModal, Modal, Select};
+use crate::utils::{THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::html::Dialog;
+use leptos::leptos_dom::is_browser;
+use leptos::{ev::MouseEvent, logging::error, *};
+use leptos_icons::Icon;
+use leptos_struct_table::*;
+use leptos_use::use_timeout_fn;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct AppPassword {
+    #[table(skip)]
+    pub id: i64,
+    #[table(class = "w-1")]
+    pub mailbox: String,
+    pub name: String,
+    #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AppPasswordQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::AppPassword;
+    use crate::{api::ApiError, auth::User};
+    use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+    use std::ops::Range;
+
+    /// Restricts a query to the app passwords of the mailbox account itself, or of the mailboxes the user owns.
+    fn push_visible_to(query: &mut QueryBuilder<'_, Sqlite>, user: &User) {
+        query.push(" WHERE (mailbox = ");
+        query.push_bind(user.username.clone());
+        query.push(" OR mailbox IN (SELECT address FROM mailboxes WHERE owner = ");
+        query.push_bind(user.username.clone());
+        query.push("))");
+    }
+
+    /// Generates a password of five groups of four lowercase letters, which is easy to type on any device.
+    fn generate() -> Result<String, ApiError> {
+        let mut letters = Vec::new();
+        while letters.len() < 20 {
+            let mut buf = [0u8; 32];
+            getrandom::getrandom(&mut buf).map_err(|e| ApiError::ServerError(e.to_string()))?;
+            // Reject values which would make some letters more likely than others
+            letters.extend(buf.into_iter().filter(|x| *x < 234).map(|x| (b'a' + x % 26) as char));
+        }
+        Ok(letters[..20]
+            .chunks(4)
+            .map(|x| x.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("-"))
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<AppPassword>, ApiError> {
+        let mut query = QueryBuilder::new("SELECT id, mailbox, name, created_at FROM app_passwords");
+        push_visible_to(&mut query, user);
+
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<AppPassword>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM app_passwords");
+        push_visible_to(&mut query, user);
+        Ok(query.build_query_scalar::<i64>().fetch_one(pool).await? as usize)
+    }
+
+    /// Creates a new app password for a mailbox of the user and returns it. Only its hash is stored.
+    pub async fn create(pool: &SqlitePool, user: &User, mailbox: &str, name: &str) -> Result<String, ApiError> {
+        let name = name.trim();
+        if name.is_empty() || name.len() > 64 {
+            return Err(ApiError::BadRequest(
+                "App password name must be between 1 and 64 characters".to_string(),
+            ));
+        }
+
+        if !crate::mailboxes::ssr::allowed_targets(pool, user)
+            .await?
+            .iter()
+            .any(|x| x == mailbox)
+        {
+            return Err(ApiError::NotFound(format!("Mailbox {mailbox} does not exist")));
+        }
+
+        let password = generate()?;
+        let password_hash = crate::passwords::hash(&password).map_err(|e| ApiError::ServerError(e.to_string()))?;
+
+        sqlx::query("INSERT INTO app_passwords (mailbox, name, password_hash) VALUES (?, ?, ?)")
+            .bind(mailbox)
+            .bind(name)
+            .bind(password_hash)
+            .execute(pool)
+            .await?;
+
+        Ok(password)
+    }
+
+    pub async fn delete(pool: &SqlitePool, user: &User, id: i64) -> Result<(), ApiError> {
+        let mut query = QueryBuilder::new("DELETE FROM app_passwords");
+        push_visible_to(&mut query, user);
+        query.push(" AND id = ");
+        query.push_bind(id);
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("App password {id} does not exist")));
+        }
+
+        Ok(())
+    }
+}
+
+#[server]
+pub async fn list_app_passwords(query: AppPasswordQuery) -> Result<Vec<AppPassword>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let AppPasswordQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, AppPassword::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn app_password_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn create_app_password(mailbox: String, name: String) -> Result<String, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::create(&pool, &user, &mailbox, &name).await?)
+}
+
+#[server]
+pub async fn delete_app_password(id: i64) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::delete(&pool, &user, id).await?)
+}
+
+#[derive(Default)]
+pub struct AppPasswordTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<AppPassword> for AppPasswordTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<AppPassword>, Range<usize>), String> {
+        list_app_passwords(AppPasswordQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        app_password_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+/// Lists the app passwords of the mailboxes of the user, or of the mailbox account itself.
+/// App passwords are used by mail clients and are not accepted by the web login.
+#[component]
+pub fn AppPasswords() -> impl IntoView {
+    let mut rows = AppPasswordTableDataProvider::default();
+    let default_sorting = VecDeque::from([(2, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload = create_trigger();
+    let reload_controller = ReloadController::default();
+    create_effect(move |_| {
+        reload.track();
+        reload_controller.reload();
+    });
+
+    let (count, set_count) = create_signal(0);
+
+    let (mailboxes, set_mailboxes) = create_signal(vec![]);
+    let (edit_modal_input_mailbox, set_edit_modal_input_mailbox) = create_signal("".to_string());
+    let refresh_mailboxes = move || {
+        spawn_local(async move {
+            use crate::mailboxes::allowed_targets;
+            match allowed_targets().await {
+                Err(e) => error!("Failed to load mailboxes: {}", e),
+                Ok(targets) => {
+                    if !targets.contains(&edit_modal_input_mailbox.get_untracked()) {
+                        set_edit_modal_input_mailbox(targets.first().cloned().unwrap_or_default());
+                    }
+                    set_mailboxes(targets);
+                }
+            }
+        });
+    };
+
+    if is_browser() {
+        refresh_mailboxes();
+    }
+
+    let delete_modal_password = create_rw_signal(None);
+    let delete_modal_password_id = create_rw_signal(None);
+    let edit_modal_password = create_rw_signal(None);
+
+    let (edit_modal_input_name, set_edit_modal_input_name) = create_signal("".to_string());
+    let edit_modal_open = move || {
+        // Mailboxes may have been created since the page was loaded
+        refresh_mailboxes();
+        edit_modal_password.set(Some(None::<AppPassword>));
+        set_edit_modal_input_name("".to_string());
+    };
+
+    let app_password_modal = create_node_ref::<Dialog>();
+    let app_password_modal_open = create_rw_signal(false);
+    let app_password_modal_password = create_rw_signal("".to_string());
+    let app_password_modal_copied_timer = use_timeout_fn(|_: ()| (), 3000.0);
+    create_effect(move |_| {
+        // Clear the password when dialog closes in any way
+        if !app_password_modal_open() {
+            app_password_modal_password.set("".to_string());
+            (app_password_modal_copied_timer.stop)();
+        }
+    });
+
+    let on_edit = move |(_data, on_error): (Option<AppPassword>, Callback<String>)| {
+        spawn_local(async move {
+            match create_app_password(
+                edit_modal_input_mailbox.get_untracked(),
+                edit_modal_input_name.get_untracked(),
+            )
+            .await
+            {
+                Err(e) => on_error(e.to_string()),
+                Ok(password) => {
+                    reload.notify();
+                    edit_modal_password.set(None);
+                    app_password_modal_password.set(password);
+                    app_password_modal_open.set(true);
+                }
+            }
+        });
+    };
+
+    #[allow(unused_variables, non_snake_case)]
+    let app_password_row_renderer =
+        move |class: Signal<String>,
+              row: AppPassword,
+              index: usize,
+              selected: Signal<bool>,
+              on_select: EventHandler<MouseEvent>,
+              on_change: EventHandler<ChangeEvent<AppPassword>>| {
+            let delete_name = format!("{} ({})", row.name, row.mailbox);
+            let delete_id = row.id;
+            view! {
+                <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                    {row.render_row(index, on_change)}
+                    <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                        <div class="inline-flex items-center rounded-md">
+                            <button
+                                class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                                on:click=move |_| {
+                                    delete_modal_password_id.set(Some(delete_id));
+                                    delete_modal_password.set(Some(delete_name.clone()));
+                                }
+                            >
+
+                                <Icon icon=icondata::FiTrash2 class="w-5 h-5"/>
+                            </button>
+                        </div>
+                    </td>
+                </tr>
+            }
+        };
+
+    let errors = create_memo(move |_| {
+        let mut errors = Vec::new();
+        if edit_modal_input_mailbox().is_empty() {
+            errors.push("Select a mailbox".to_string());
+        }
+        let name = edit_modal_input_name();
+        if name.trim().is_empty() || name.trim().len() > 64 {
+            errors.push("Name must be between 1 and 64 characters".to_string());
+        }
+        errors
+    });
+
+    view! {
+        <div class="flex items-center justify-between space-y-2 mt-8 mb-4">
+            <h3 class="text-2xl font-bold">App Passwords</h3>
+        </div>
+        <p class="text-sm text-gray-500 dark:text-gray-400 mb-4">
+            "Use a separate password for each mail client, so that a lost device can be locked out without changing the mailbox password. App passwords only work for IMAP and SMTP, not for this website."
+        </p>
+        <div class="space-y-4">
+            <div class="flex flex-wrap items-center justify-between">
+                <button
+                    type="button"
+                    class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-blue-600 dark:bg-blue-700 hover:bg-blue-500 dark:hover:bg-blue-600 focus:ring-blue-300 dark:focus:ring-blue-900"
+                    on:click=move |_| edit_modal_open()
+                >
+                    <Icon icon=icondata::FiPlus class="w-6 h-6 me-2"/>
+                    New
+                </button>
+                <div class="flex flex-1"></div>
+                <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                    {count} " app passwords"
+                </div>
+            </div>
+
+            <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                <div class="overflow-auto grow min-h-0">
+                    <table class="table-auto text-left w-full">
+                        <TableContent
+                            rows
+                            sorting=sorting
+                            sorting_mode=SortingMode::SingleColumn
+                            row_renderer=app_password_row_renderer
+                            reload_controller=reload_controller
+                            loading_row_display_limit=0
+                            on_row_count=set_count
+                        />
+                    </table>
+                </div>
+            </div>
+        </div>
+
+        <DeleteModal
+            data=delete_modal_password
+            text="Are you sure you want to revoke this app password? Mail clients using it will no longer be able to log in."
+                .into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Some(id) = delete_modal_password_id.get_untracked() {
+                        if let Err(e) = delete_app_password(id).await {
+                            error!("Failed to delete app password: {}", e);
+                        } else {
+                            reload.notify();
+                        }
+                    }
+                    delete_modal_password.set(None);
+                    delete_modal_password_id.set(None);
+                });
+            }
+        />
+
+        <EditModal
+            data=edit_modal_password
+            what="App Password".to_string()
+            get_title=move |x| { &x.name }
+            on_confirm=on_edit
+            errors
+        >
+            <Show when=move || { mailboxes.with(|x| x.len() > 1) }>
+                <div class="flex flex-col gap-2">
+                    <label
+                        class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                        for="mailbox"
+                    >
+                        Mailbox
+                    </label>
+                    <Select
+                        class="w-full h-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900"
+                        choices=mailboxes
+                        value=edit_modal_input_mailbox
+                        set_value=set_edit_modal_input_mailbox
+                    />
+                </div>
+            </Show>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="name"
+                >
+                    Name
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="text"
+                    placeholder="Phone"
+                    maxlength="64"
+                    on:input=move |ev| set_edit_modal_input_name(event_target_value(&ev))
+                    prop:value=edit_modal_input_name
+                />
+            </div>
+        </EditModal>
+
+        <Modal open=app_password_modal_open dialog_el=app_password_modal>
+            <div class="relative p-4 transform overflow-hidden rounded-lg bg-white dark:bg-black text-left transition-all sm:w-full sm:max-w-xl">
+                <h3 class="text-2xl tracking-tight mt-2 mb-2 font-semibold text-gray-900 dark:text-gray-200">
+                    "App Password"
+                </h3>
+                <div class="pb-3 space-y-3">
+                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                        "Enter this password in your mail client instead of the mailbox password. Make sure to save it now, as it will not be displayed again."
+                    </p>
+                    <div class="w-full relative">
+                        <input
+                            type="text"
+                            class="col-span-6 bg-gray-50 dark:bg-gray-900 dark:bg-black border border-gray-300 text-gray-500 dark:text-gray-400 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full px-2.5 py-4"
+                            value=move || app_password_modal_password
+                            disabled
+                            readonly
+                        />
+                        <button
+                            class="absolute end-2.5 top-1/2 -translate-y-1/2 text-gray-900 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg py-2 px-2.5 inline-flex items-center justify-center bg-white dark:bg-black border-gray-200 border"
+                            on:click=move |_ev| {
+                                (app_password_modal_copied_timer.start)(());
+                                let clipboard = window().navigator().clipboard();
+                                let _ = clipboard.write_text(&app_password_modal_password.get());
+                            }
+                        >
+
+                            <span
+                                class="inline-flex items-center"
+                                class=("hidden", app_password_modal_copied_timer.is_pending)
+                            >
+                                <Icon icon=icondata::RiFileCopy2DocumentFill class="w-3 h-3 me-1.5"/>
+                                <span class="text-xs font-semibold">Copy</span>
+                            </span>
+                            <span
+                                class="hidden items-center"
+                                class=("!inline-flex", app_password_modal_copied_timer.is_pending)
+                            >
+                                <Icon
+                                    icon=icondata::BiCheckRegular
+                                    class="w-3 h-3 me-1.5 text-blue-700 dark:text-blue-300"
+                                />
+                                <span class="text-xs font-semibold text-blue-700 dark:text-blue-300">Copied</span>
+                            </span>
+                        </button>
+                    </div>
+                </div>
+                <div class="flex flex-col gap-3 sm:flex-row-reverse">
+                    <button
+                        type="button"
+                        class="inline-flex w-full min-w-20 justify-center rounded-lg transition-all bg-white dark:bg-black px-3 py-2 font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                        on:click=move |_ev| {
+                            app_password_modal_open.set(false);
+                        }
+                    >
+
+                        Dismiss
+                    </button>
+                </div>
+            </div>
+        </Modal>
+    }
+}
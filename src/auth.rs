@@ -1,9 +1,12 @@
 use leptos::*;
 use leptos_icons::Icon;
-use leptos_router::{ActionForm, Redirect};
+use leptos_router::{use_query_map, ActionForm, Redirect};
 use leptos_use::ColorMode;
 use serde::{Deserialize, Serialize};
 
+use crate::oidc::get_oidc_name;
+use crate::passkeys::{get_passkeys_enabled, sign_passkey_login, LoginWithPasskey};
+use crate::roles::Permission;
 use crate::utils::ColorModeToggle;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,13 +20,30 @@ pub struct User {
     pub mailbox_owner: Option<String>,
     /// Whether the user is an admin
     pub admin: bool,
+    /// The role of the user, if any
+    pub role: Option<String>,
     /// Whether the user is active
     pub active: bool,
+    /// Whether the user is an admin that must enable two-factor authentication before using its admin rights
+    #[cfg_attr(feature = "ssr", sqlx(default))]
+    pub admin_requires_totp: bool,
+    /// The permissions granted by the role of the user
+    #[cfg_attr(feature = "ssr", sqlx(skip))]
+    pub permissions: Vec<Permission>,
+}
+
+impl User {
+    /// Whether the user may act on the accounts of others as described by the permission.
+    /// Admins have all permissions.
+    pub fn can(&self, permission: Permission) -> bool {
+        self.admin || self.permissions.contains(&permission)
+    }
 }
 
 #[cfg(feature = "ssr")]
 pub mod ssr {
     pub use super::User;
+    use crate::{api::ApiError, api_tokens::ApiToken, roles::Permission, sessions::ssr::Client};
     use anyhow::{anyhow, Context};
     pub use axum_session_auth::{Authentication, HasPermission};
     pub use axum_session_sqlx::SessionSqlitePool;
@@ -35,33 +55,52 @@ pub mod ssr {
     impl User {
         pub async fn get(username: &str, pool: &SqlitePool) -> Option<Self> {
             let user = sqlx::query_as::<_, User>(
-                "SELECT username, password_hash, NULL AS mailbox_owner, admin, active \
+                "SELECT username, password_hash, NULL AS mailbox_owner, admin, role, active \
                 FROM users WHERE username = $1 \
-                UNION SELECT address AS username, password_hash, owner AS mailbox_owner, FALSE AS admin, active \
-                FROM mailboxes WHERE address = $1",
+                UNION SELECT address AS username, password_hash, owner AS mailbox_owner, FALSE AS admin, \
+                NULL AS role, active FROM mailboxes WHERE address = $1",
             )
             .bind(username)
             .fetch_one(pool)
             .await
             .ok()?;
 
-            Some(user)
+            Some(user.with_permissions(pool).await?.with_totp_requirement(pool).await)
+        }
+
+        /// Resolves the permissions granted by the role of the user.
+        async fn with_permissions(mut self, pool: &SqlitePool) -> Option<Self> {
+            if let Some(role) = &self.role {
+                self.permissions = crate::roles::ssr::permissions(pool, role).await.ok()?;
+            }
+            Some(self)
+        }
+
+        /// Withholds the admin rights and role permissions of users without two-factor authentication,
+        /// if that is required for admins.
+        async fn with_totp_requirement(mut self, pool: &SqlitePool) -> Self {
+            if (self.admin || !self.permissions.is_empty())
+                && crate::totp::ssr::required_for_admins()
+                && !crate::totp::ssr::is_enabled(pool, &self.username)
+                    .await
+                    .unwrap_or(false)
+            {
+                self.admin = false;
+                self.permissions.clear();
+                self.admin_requires_totp = true;
+            }
+            self
         }
 
-        pub async fn get_by_api_token(api_token: &str, pool: &SqlitePool) -> Option<Self> {
+        /// Returns the user and the details of the given API token, if it is valid.
+        pub async fn get_by_api_token(api_token: &str, pool: &SqlitePool) -> Option<(Self, ApiToken)> {
             if api_token.len() < 16 {
                 // Disregard insecure API tokens directly
                 return None;
             }
 
-            let user = sqlx::query_as::<_, User>(
-                "SELECT address AS username, password_hash, owner AS mailbox_owner, FALSE AS admin, active \
-                FROM mailboxes WHERE api_token = $1",
-            )
-            .bind(api_token)
-            .fetch_one(pool)
-            .await
-            .ok()?;
+            let (owner, token) = crate::api_tokens::ssr::find(pool, api_token).await?;
+            let user = User::get(&owner, pool).await?;
 
             if !user.active {
                 log::warn!(
@@ -71,15 +110,43 @@ pub mod ssr {
                 return None;
             }
 
-            Some(user)
+            Some((user, token))
+        }
+
+        /// Ensure that the user is an admin
+        pub fn ensure_admin(&self) -> Result<(), ApiError> {
+            if !self.admin {
+                return Err(ApiError::Forbidden("Unauthorized".to_string()));
+            }
+            Ok(())
+        }
+
+        /// Ensure that the user has the given permission
+        pub fn ensure_permission(&self, permission: Permission) -> Result<(), ApiError> {
+            if !self.can(permission) {
+                return Err(ApiError::Forbidden("Unauthorized".to_string()));
+            }
+            Ok(())
+        }
+
+        /// Ensure that the user is not a mailbox
+        pub fn ensure_not_mailbox(&self) -> Result<(), ApiError> {
+            if self.mailbox_owner.is_some() {
+                return Err(ApiError::Forbidden("Unauthorized".to_string()));
+            }
+            Ok(())
         }
     }
 
+    /// The user id of a session is the token of its entry in `user_sessions`, so that sessions can be revoked.
     #[async_trait]
     impl Authentication<User, String, SqlitePool> for User {
-        async fn load_user(username: String, pool: Option<&SqlitePool>) -> Result<User, anyhow::Error> {
+        async fn load_user(token: String, pool: Option<&SqlitePool>) -> Result<User, anyhow::Error> {
             let pool = pool.context("Missing sql pool")?;
 
+            let username = crate::sessions::ssr::validate(pool, &token)
+                .await?
+                .ok_or_else(|| anyhow!("Session expired"))?;
             User::get(&username, pool)
                 .await
                 .ok_or_else(|| anyhow!("Cannot get user"))
@@ -98,10 +165,74 @@ pub mod ssr {
         }
     }
 
+    /// Returns the user whose login passed the password check and waits for the second factor, if any.
+    pub fn pending_login(auth: &AuthSession) -> Option<String> {
+        auth.session
+            .get::<super::PendingLogin>(super::PENDING_LOGIN_KEY)
+            .filter(|x| x.expires_at >= chrono::Utc::now().timestamp())
+            .map(|x| x.username)
+    }
+
+    /// Replaces the password hash of a user or mailbox by one with the current scheme and parameters.
+    /// Provisioned accounts are skipped, since provisioning would restore their configured hash.
+    pub async fn rehash_password(pool: &SqlitePool, user: &User, password: &str) {
+        let password_hash = match crate::passwords::hash(password) {
+            Ok(x) => x,
+            Err(e) => {
+                log::error!("failed to rehash password of user '{}': {e}", user.username);
+                return;
+            }
+        };
+
+        let query = if user.mailbox_owner.is_some() {
+            "UPDATE mailboxes SET password_hash = ? WHERE address = ? AND password_hash = ? AND NOT provisioned"
+        } else {
+            "UPDATE users SET password_hash = ? WHERE username = ? AND password_hash = ? AND NOT provisioned"
+        };
+        match sqlx::query(query)
+            .bind(password_hash)
+            .bind(&user.username)
+            .bind(&user.password_hash)
+            .execute(pool)
+            .await
+        {
+            Ok(x) if x.rows_affected() > 0 => log::info!("upgraded password hash of user '{}'", user.username),
+            Ok(_) => (),
+            Err(e) => log::error!("failed to rehash password of user '{}': {e}", user.username),
+        }
+    }
+
+    /// Logs in the user, discarding any pending login.
+    pub async fn start_session(
+        auth: &AuthSession,
+        pool: &SqlitePool,
+        username: String,
+        remember: bool,
+        client: Client,
+    ) -> Result<(), ApiError> {
+        let token = crate::sessions::ssr::create(pool, &username, remember, client).await?;
+        auth.session.remove(super::PENDING_LOGIN_KEY);
+        auth.login_user(token);
+        auth.remember_user(remember);
+        Ok(())
+    }
+
+    /// Logs in the user from a server function and redirects to the main page.
+    pub async fn complete_login(
+        auth: &AuthSession,
+        pool: &SqlitePool,
+        username: String,
+        remember: bool,
+    ) -> Result<(), ApiError> {
+        start_session(auth, pool, username, remember, Client::current()).await?;
+        leptos_axum::redirect("/");
+        Ok(())
+    }
+
     #[async_trait]
     impl HasPermission<SqlitePool> for User {
-        async fn has(&self, _perm: &str, _pool: &Option<&SqlitePool>) -> bool {
-            false
+        async fn has(&self, perm: &str, _pool: &Option<&SqlitePool>) -> bool {
+            Permission::from_name(perm).is_some_and(|x| self.can(x))
         }
     }
 }
@@ -142,19 +273,20 @@ pub async fn auth_any() -> Result<User, ServerFnError> {
 
 #[server]
 pub async fn authenticate_user(username: String, password: String) -> Result<User, ServerFnError> {
-    use argon2::{
-        password_hash::{PasswordHash, PasswordVerifier},
-        Argon2,
-    };
-
     // A generic error message to not leak information to the clients
     let generic_err = || ServerFnError::new("Wrong password or invalid user.");
 
     let pool = crate::database::ssr::pool()?;
-    let user = User::get(&username, &pool).await.ok_or_else(generic_err)?;
+    let ip = crate::lockouts::ssr::client_ip();
+    crate::lockouts::ssr::check(&pool, Some(&username), ip).await?;
 
-    let verify_result = PasswordHash::new(&user.password_hash)
-        .and_then(|hash| Argon2::default().verify_password(password.as_bytes(), &hash));
+    let Some(user) = User::get(&username, &pool).await else {
+        log::warn!("failed authentication of unknown user '{username}'");
+        crate::lockouts::ssr::record_failure(&pool, Some(&username), ip).await?;
+        return Err(generic_err());
+    };
+
+    let verify_result = crate::passwords::verify(&password, &user.password_hash);
     if verify_result.is_ok() {
         if !user.active {
             log::warn!("denying successful login attempt because user '{username}' is inactive");
@@ -162,42 +294,173 @@ pub async fn authenticate_user(username: String, password: String) -> Result<Use
         }
 
         log::info!("login successful for user '{username}'");
+        if crate::passwords::needs_rehash(&user.password_hash) {
+            ssr::rehash_password(&pool, &user, &password).await;
+        }
         Ok(user)
     } else {
         log::warn!(
             "failed authentication of user '{username}': {}",
             verify_result.unwrap_err()
         );
+        crate::lockouts::ssr::record_failure(&pool, Some(&username), ip).await?;
         Err(generic_err())
     }
 }
 
+/// A login that passed the password check and waits for the second factor
+#[cfg(feature = "ssr")]
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    username: String,
+    expires_at: i64,
+    failed_attempts: u32,
+    /// Whether the session should survive idle timeouts once the login is complete
+    #[serde(default)]
+    remember: bool,
+}
+
+#[cfg(feature = "ssr")]
+const PENDING_LOGIN_KEY: &str = "pending_login";
+/// How long the second factor may take to enter, in seconds
+#[cfg(feature = "ssr")]
+const PENDING_LOGIN_VALIDITY: i64 = 300;
+/// The number of wrong codes after which the password must be entered again
+#[cfg(feature = "ssr")]
+const PENDING_LOGIN_MAX_ATTEMPTS: u32 = 5;
+
+/// Logs in the user, or returns true if a second factor is required to complete the login.
 #[server]
-pub async fn login(username: String, password: String) -> Result<(), ServerFnError> {
+pub async fn login(
+    username: String,
+    password: String,
+    #[server(default)] remember: bool,
+) -> Result<bool, ServerFnError> {
     let user = authenticate_user(username.clone(), password.clone()).await?;
+    if user.mailbox_owner.is_none() && !crate::oidc::ssr::password_login_allowed() {
+        return Err(ServerFnError::new(
+            "Password login is disabled, please use single sign-on.",
+        ));
+    }
+
     let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
 
-    auth.login_user(user.username);
-    auth.remember_user(false);
-    leptos_axum::redirect("/");
+    if crate::totp::ssr::is_enabled(&pool, &user.username).await? {
+        auth.session.set(
+            PENDING_LOGIN_KEY,
+            PendingLogin {
+                username: user.username,
+                expires_at: chrono::Utc::now().timestamp() + PENDING_LOGIN_VALIDITY,
+                failed_attempts: 0,
+                remember,
+            },
+        );
+        return Ok(true);
+    }
+
+    // The failures are only forgotten once the second factor was entered as well
+    crate::lockouts::ssr::reset(&pool, &user.username).await?;
+    ssr::complete_login(&auth, &pool, user.username, remember).await?;
+    Ok(false)
+}
+
+/// Completes a login with a TOTP or recovery code.
+#[server]
+pub async fn login_second_factor(code: String) -> Result<(), ServerFnError> {
+    let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
+    let expired_err = || ServerFnError::new("Your login has expired, please log in again.");
+
+    let Some(mut pending) = auth.session.get::<PendingLogin>(PENDING_LOGIN_KEY) else {
+        return Err(expired_err());
+    };
+    if pending.expires_at < chrono::Utc::now().timestamp() {
+        auth.session.remove(PENDING_LOGIN_KEY);
+        return Err(expired_err());
+    }
+
+    if !crate::totp::ssr::verify(&pool, &pending.username, &code).await? {
+        log::warn!("failed second factor of user '{}'", pending.username);
+        crate::lockouts::ssr::record_failure(&pool, Some(&pending.username), crate::lockouts::ssr::client_ip()).await?;
+        pending.failed_attempts += 1;
+        if pending.failed_attempts >= PENDING_LOGIN_MAX_ATTEMPTS {
+            auth.session.remove(PENDING_LOGIN_KEY);
+            return Err(ServerFnError::new("Too many invalid codes, please log in again."));
+        }
+        auth.session.set(PENDING_LOGIN_KEY, pending);
+        return Err(ServerFnError::new("Invalid code."));
+    }
+
+    auth.session.remove(PENDING_LOGIN_KEY);
+    // The account may have been deactivated in the meantime
+    if !User::get(&pending.username, &pool).await.is_some_and(|x| x.active) {
+        return Err(expired_err());
+    }
+
+    log::info!("login successful for user '{}'", pending.username);
+    crate::lockouts::ssr::reset(&pool, &pending.username).await?;
+    ssr::complete_login(&auth, &pool, pending.username, pending.remember).await?;
     Ok(())
 }
 
 #[server]
 pub async fn logout() -> Result<(), ServerFnError> {
     let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
+    crate::sessions::ssr::end(&pool, &auth.id).await?;
     auth.logout_user();
     leptos_axum::redirect("/");
     Ok(())
 }
 
+pub(crate) fn login_errors(errors: RwSignal<Errors>) -> impl IntoView {
+    view! {
+        <div class="rounded-lg p-4 flex bg-red-100 dark:bg-red-800">
+            <div>
+                <Icon icon=icondata::BiXCircleSolid class="w-5 h-5 text-red-400 dark:text-red-300"/>
+            </div>
+            <div class="ml-3 text-red-700 dark:text-red-300">
+                <p>
+                    {move || errors.get().into_iter().map(|(_, e)| view! { {e.to_string()} }).collect_view()}
+                </p>
+            </div>
+        </div>
+    }
+}
+
 #[component]
 pub fn Login(
-    action: Action<Login, Result<(), ServerFnError>>,
+    action: Action<Login, Result<bool, ServerFnError>>,
     color_mode: Signal<ColorMode>,
     set_color_mode: WriteSignal<ColorMode>,
 ) -> impl IntoView {
-    let action_value = Signal::derive(move || action.value().get().unwrap_or(Ok(())));
+    let action_value = Signal::derive(move || action.value().get().unwrap_or(Ok(false)).map(|_| ()));
+    let second_factor_required = Signal::derive(move || matches!(action.value().get(), Some(Ok(true))));
+    let second_factor = create_server_action::<LoginSecondFactor>();
+    let second_factor_value = Signal::derive(move || second_factor.value().get().unwrap_or(Ok(())));
+    let oidc_name = create_resource(|| (), |_| get_oidc_name());
+    let passkeys_enabled = create_resource(|| (), |_| get_passkeys_enabled());
+    // Failed single sign-on logins redirect back to the login page with an error
+    let query = use_query_map();
+    let oidc_error = Signal::derive(move || match query.with(|x| x.get("error").cloned()) {
+        Some(e) => Err(ServerFnError::new(e)),
+        None => Ok(()),
+    });
+    let (remember, set_remember) = create_signal(false);
+    let passkey_login = create_server_action::<LoginWithPasskey>();
+    let passkey_login_value = Signal::derive(move || passkey_login.value().get().unwrap_or(Ok(())));
+    let sign_in_with_passkey = move |second_factor: bool| {
+        spawn_local(async move {
+            match sign_passkey_login(second_factor).await {
+                Ok(credential) => passkey_login.dispatch(LoginWithPasskey {
+                    credential,
+                    remember: remember.get_untracked(),
+                }),
+                Err(e) => passkey_login.value().set(Some(Err(e))),
+            }
+        });
+    };
 
     view! {
         <div class="relative flex min-h-screen flex-col">
@@ -211,86 +474,196 @@ pub fn Login(
                         <img class="w-16 h-16 me-2" src="/logo.svg"/>
                         <h2 class="text-4xl leading-none font-bold inline-block">idmail</h2>
                     </div>
-                    <ActionForm
-                        action
-                        class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-card-foreground max-w-sm"
+                    <Show
+                        when=move || !second_factor_required()
+                        fallback=move || {
+                            view! {
+                                <ActionForm
+                                    action=second_factor
+                                    class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-card-foreground max-w-sm"
+                                >
+                                    <div class="flex flex-col space-y-1.5 p-6">
+                                        <h2 class="font-semibold tracking-tight text-2xl mb-2">
+                                            Two-Factor Authentication
+                                        </h2>
+                                        <p class="text-sm text-gray-500 dark:text-gray-400">
+                                            "Enter the code from your authenticator app or one of your recovery codes"
+                                        </p>
+                                    </div>
+                                    <div class="p-6 pt-0">
+                                        <div class="grid gap-4">
+                                            <div class="grid gap-2">
+                                                <label
+                                                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                                                    for="code"
+                                                >
+                                                    Code
+                                                </label>
+                                                <input
+                                                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                                                    type="text"
+                                                    name="code"
+                                                    autocomplete="one-time-code"
+                                                    placeholder="123456"
+                                                    required="required"
+                                                    autofocus
+                                                />
+                                            </div>
+                                            <ErrorBoundary fallback=login_errors>{second_factor_value}</ErrorBoundary>
+                                            <ErrorBoundary fallback=login_errors>{passkey_login_value}</ErrorBoundary>
+                                            <div class="flex flex-col-reverse gap-3 mt-3 sm:flex-row sm:justify-end">
+                                                <button
+                                                    type="button"
+                                                    class="inline-flex w-full justify-center items-center rounded-lg transition-all p-2.5 bg-white dark:bg-black font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                                                    on:click=move |_| {
+                                                        second_factor.value().set(None);
+                                                        passkey_login.value().set(None);
+                                                        action.value().set(None);
+                                                    }
+                                                >
+                                                    Back
+                                                </button>
+                                                <Transition fallback=|| ()>
+                                                    <Show when=move || {
+                                                        passkeys_enabled.get().and_then(Result::ok).unwrap_or(false)
+                                                    }>
+                                                        <button
+                                                            type="button"
+                                                            class="inline-flex w-full justify-center items-center rounded-lg transition-all p-2.5 bg-white dark:bg-black font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                                                            on:click=move |_| sign_in_with_passkey(true)
+                                                        >
+                                                            <Icon icon=icondata::FiKey class="w-5 h-5 me-2"/>
+                                                            "Use a passkey"
+                                                        </button>
+                                                    </Show>
+                                                </Transition>
+                                                <button
+                                                    type="submit"
+                                                    tabindex="0"
+                                                    class="inline-flex w-full justify-center items-center rounded-lg transition-all p-2.5 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 font-semibold text-white focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900 sm:w-auto"
+                                                >
+                                                    Verify
+                                                </button>
+                                            </div>
+                                        </div>
+                                    </div>
+                                </ActionForm>
+                            }
+                        }
                     >
-                        <div class="flex flex-col space-y-1.5 p-6">
-                            <h2 class="font-semibold tracking-tight text-2xl mb-2">Login</h2>
-                            <p class="text-sm text-gray-500 dark:text-gray-400">
-                                "Enter your mailbox address and password below to login"
-                            </p>
-                        </div>
-                        <div class="p-6 pt-0">
-                            <div class="grid gap-4">
-                                <div class="grid gap-2">
-                                    <label
-                                        class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
-                                        for="username"
-                                    >
-                                        Email
-                                    </label>
-                                    <input
-                                        class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
-                                        type="text"
-                                        name="username"
-                                        placeholder="username@example.com"
-                                        required="required"
-                                    />
-                                </div>
-                                <div class="grid gap-2">
-                                    <div class="flex items-center">
+
+                        <ActionForm
+                            action
+                            class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-card-foreground max-w-sm"
+                        >
+                            <div class="flex flex-col space-y-1.5 p-6">
+                                <h2 class="font-semibold tracking-tight text-2xl mb-2">Login</h2>
+                                <p class="text-sm text-gray-500 dark:text-gray-400">
+                                    "Enter your mailbox address and password below to login"
+                                </p>
+                            </div>
+                            <div class="p-6 pt-0">
+                                <div class="grid gap-4">
+                                    <div class="grid gap-2">
                                         <label
                                             class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
-                                            for="password"
+                                            for="username"
                                         >
-                                            Password
+                                            Email
                                         </label>
+                                        <input
+                                            class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                                            type="text"
+                                            name="username"
+                                            placeholder="username@example.com"
+                                            required="required"
+                                        />
                                     </div>
-                                    <input
-                                        class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
-                                        type="password"
-                                        name="password"
-                                        required="required"
-                                    />
-                                </div>
-                                <ErrorBoundary fallback=|errors| {
-                                    view! {
-                                        <div class="rounded-lg p-4 flex bg-red-100 dark:bg-red-800">
-                                            <div>
-                                                <Icon
-                                                    icon=icondata::BiXCircleSolid
-                                                    class="w-5 h-5 text-red-400 dark:text-red-300"
-                                                />
-                                            </div>
-                                            <div class="ml-3 text-red-700 dark:text-red-300">
-                                                <p>
-                                                    {move || {
-                                                        errors
-                                                            .get()
-                                                            .into_iter()
-                                                            .map(|(_, e)| view! { {e.to_string()} })
-                                                            .collect_view()
-                                                    }}
-
-                                                </p>
-                                            </div>
+                                    <div class="grid gap-2">
+                                        <div class="flex items-center">
+                                            <label
+                                                class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                                                for="password"
+                                            >
+                                                Password
+                                            </label>
                                         </div>
-                                    }
-                                }>
-
-                                    {action_value}
-                                </ErrorBoundary>
-                                <button
-                                    type="submit"
-                                    tabindex="0"
-                                    class="inline-flex w-full justify-center mt-3 items-center rounded-lg transition-all p-2.5 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 font-semibold text-white focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900 sm:w-auto"
-                                >
-                                    Login
-                                </button>
+                                        <input
+                                            class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                                            type="password"
+                                            name="password"
+                                            required="required"
+                                        />
+                                    </div>
+                                    <div class="flex flex-row gap-2 items-center">
+                                        <input
+                                            id="remember"
+                                            class="w-4 h-4 bg-transparent dark:bg-transparent text-blue-600 border-[1.5px] border-gray-200 dark:border-zinc-800 rounded checked:bg-blue-600 dark:checked:bg-blue-600 dark:bg-blue-600 focus:ring-ring focus:ring-4 transition-all"
+                                            type="checkbox"
+                                            name="remember"
+                                            value="true"
+                                            on:change=move |ev| set_remember(event_target_checked(&ev))
+                                            prop:checked=remember
+                                        />
+                                        <label
+                                            class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                                            for="remember"
+                                        >
+                                            "Remember me"
+                                        </label>
+                                    </div>
+                                    <ErrorBoundary fallback=login_errors>{action_value}</ErrorBoundary>
+                                    <ErrorBoundary fallback=login_errors>{passkey_login_value}</ErrorBoundary>
+                                    <ErrorBoundary fallback=login_errors>{oidc_error}</ErrorBoundary>
+                                    <button
+                                        type="submit"
+                                        tabindex="0"
+                                        class="inline-flex w-full justify-center mt-3 items-center rounded-lg transition-all p-2.5 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 font-semibold text-white focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900 sm:w-auto"
+                                    >
+                                        Login
+                                    </button>
+                                    <Transition fallback=|| ()>
+                                        <Show when=move || {
+                                            passkeys_enabled.get().and_then(Result::ok).unwrap_or(false)
+                                        }>
+                                            <button
+                                                type="button"
+                                                class="inline-flex w-full justify-center items-center rounded-lg transition-all p-2.5 bg-white dark:bg-black font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                                                on:click=move |_| {
+                                                    action.value().set(None);
+                                                    sign_in_with_passkey(false);
+                                                }
+                                            >
+                                                <Icon icon=icondata::FiKey class="w-5 h-5 me-2"/>
+                                                "Sign in with passkey"
+                                            </button>
+                                        </Show>
+                                    </Transition>
+                                    <Transition fallback=|| ()>
+                                        {move || {
+                                            oidc_name
+                                                .get()
+                                                .and_then(Result::ok)
+                                                .flatten()
+                                                .map(|name| {
+                                                    view! {
+                                                        <a
+                                                            href="/auth/oidc/login"
+                                                            rel="external"
+                                                            class="inline-flex w-full justify-center items-center rounded-lg transition-all p-2.5 bg-white dark:bg-black font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                                                        >
+                                                            <Icon icon=icondata::FiLogIn class="w-5 h-5 me-2"/>
+                                                            {format!("Sign in with {name}")}
+                                                        </a>
+                                                    }
+                                                })
+                                        }}
+
+                                    </Transition>
+                                </div>
                             </div>
-                        </div>
-                    </ActionForm>
+                        </ActionForm>
+                    </Show>
                 </div>
             </div>
         </div>
@@ -299,7 +672,7 @@ pub fn Login(
 
 #[component]
 pub fn LoginView(
-    login: Action<Login, Result<(), ServerFnError>>,
+    login: Action<Login, Result<bool, ServerFnError>>,
     logout: Action<Logout, Result<(), ServerFnError>>,
     color_mode: Signal<ColorMode>,
     set_color_mode: WriteSignal<ColorMode>,
@@ -0,0 +1,59 @@
+use crate::proxy_auth::parse_networks;
+use anyhow::Result;
+use axum::extract::ConnectInfo;
+use http::{Extensions, HeaderMap};
+use ipnet::IpNet;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+};
+
+static TRUSTED_PROXIES: OnceLock<Vec<IpNet>> = OnceLock::new();
+
+/// Reads the reverse proxies whose forwarded client addresses are trusted. Must be called once on startup.
+pub fn init() -> Result<()> {
+    let trusted_proxies = match std::env::var("IDMAIL_TRUSTED_PROXIES") {
+        Ok(value) => parse_networks("IDMAIL_TRUSTED_PROXIES", &value)?,
+        Err(_) => Vec::new(),
+    };
+
+    if trusted_proxies.is_empty() {
+        log::info!("Not trusting any reverse proxy for client addresses");
+    } else {
+        log::info!("Trusting client addresses forwarded by {trusted_proxies:?}");
+    }
+    let _ = TRUSTED_PROXIES.set(trusted_proxies);
+    Ok(())
+}
+
+fn is_trusted(ip: &IpAddr) -> bool {
+    TRUSTED_PROXIES.get().is_some_and(|x| x.iter().any(|x| x.contains(ip)))
+}
+
+/// Returns the address of the client of a request. If the connection comes from a trusted reverse proxy,
+/// this is the last address in `X-Forwarded-For` that was not added by a trusted proxy itself,
+/// since anything before it can be made up by the client.
+pub fn client_ip(headers: &HeaderMap, extensions: &Extensions) -> Option<IpAddr> {
+    let peer = extensions.get::<ConnectInfo<SocketAddr>>()?.0.ip();
+    if !is_trusted(&peer) {
+        return Some(peer);
+    }
+
+    let mut client = peer;
+    let forwarded = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .collect::<Vec<_>>();
+    for hop in forwarded.into_iter().rev() {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(&ip) {
+            break;
+        }
+    }
+    Some(client)
+}
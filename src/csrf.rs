@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{header, Method, StatusCode};
+use std::sync::OnceLock;
+
+static TRUSTED_ORIGINS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Reads the additional trusted origins from the environment. Must be called once on startup.
+pub fn init() -> Result<()> {
+    let trusted_origins = std::env::var("IDMAIL_CSRF_TRUSTED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|x| x.trim().trim_end_matches('/').to_ascii_lowercase())
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            // An origin is a scheme and a host with an optional port, but no path
+            x.strip_prefix("https://")
+                .or_else(|| x.strip_prefix("http://"))
+                .filter(|host| !host.is_empty() && !host.contains('/'))
+                .map(|_| x.clone())
+                .context(format!("Invalid origin in IDMAIL_CSRF_TRUSTED_ORIGINS: '{x}'"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    log::info!("Trusting cross-origin requests from {trusted_origins:?}");
+    let _ = TRUSTED_ORIGINS.set(trusted_origins);
+    Ok(())
+}
+
+/// Whether a request that may change something was sent by a page of this site, or by a client
+/// that is not a browser. Browsers mark requests with `Sec-Fetch-Site`, and older ones at least
+/// send the `Origin`, which must then match the requested host.
+fn is_same_origin(request: &Request) -> bool {
+    let headers = request.headers();
+    let origin = headers
+        .get(header::ORIGIN)
+        .and_then(|x| x.to_str().ok())
+        .map(|x| x.trim_end_matches('/').to_ascii_lowercase());
+    if origin
+        .as_ref()
+        .is_some_and(|x| TRUSTED_ORIGINS.get().is_some_and(|trusted| trusted.contains(x)))
+    {
+        return true;
+    }
+
+    if let Some(site) = headers.get("sec-fetch-site").and_then(|x| x.to_str().ok()) {
+        // "none" is a navigation of the user, like a bookmark
+        return matches!(site, "same-origin" | "none");
+    }
+
+    let Some(origin) = origin else {
+        // Not a browser, so there is no session that could be abused
+        return true;
+    };
+    let host = headers
+        .get(header::HOST)
+        .and_then(|x| x.to_str().ok())
+        .map(str::to_ascii_lowercase);
+    let origin_host = origin.split_once("://").map(|(_, host)| host);
+    host.is_some() && origin_host == host.as_deref()
+}
+
+/// Rejects cross-site requests which could act on behalf of the logged in user, which are all requests
+/// except for reads. Requests with an API token are not affected, as browsers never add one on their own.
+pub async fn middleware(request: Request, next: Next) -> Response {
+    let is_safe = matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let has_api_token = ["Authorization", "Authentication"]
+        .into_iter()
+        .any(|x| request.headers().contains_key(x));
+
+    if !is_safe && !has_api_token && !is_same_origin(&request) {
+        log::warn!(
+            "rejecting cross-site {} request to {} from origin {:?}",
+            request.method(),
+            request.uri().path(),
+            request.headers().get(header::ORIGIN)
+        );
+        return (StatusCode::FORBIDDEN, "Cross-site request rejected").into_response();
+    }
+
+    next.run(request).await
+}
@@ -2,6 +2,7 @@ use std::collections::VecDeque;
 use std::ops::Range;
 
 use crate::auth::User;
+use crate::roles::Permission;
 use crate::utils::{DeleteModal, EditModal};
 use crate::utils::{SliderRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
 
@@ -15,7 +16,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::QueryBuilder;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
-#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow, utoipa::ToSchema))]
 #[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
 pub struct Domain {
     #[table(class = "w-40")]
@@ -27,6 +28,8 @@ pub struct Domain {
     pub active: bool,
     #[table(class = "w-1")]
     pub owner: String,
+    /// Users who may manage the domain like its owner, comma separated
+    pub members: String,
     #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
     pub created_at: DateTime<Utc>,
 }
@@ -39,56 +42,315 @@ pub struct DomainQuery {
     search: String,
 }
 
-#[server]
-pub async fn allowed_domains() -> Result<Vec<(String, String)>, ServerFnError> {
-    let user = crate::auth::auth_any().await?;
+/// Splits a comma separated list of domain members.
+pub fn split_members(members: &str) -> Vec<String> {
+    members
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(str::to_string)
+        .collect()
+}
 
-    let mut query = QueryBuilder::new("SELECT domain, owner FROM domains");
-    query.push(" WHERE active = TRUE AND (public = TRUE OR owner = ");
-    query.push_bind(&user.username);
-    if let Some(mailbox_owner) = user.mailbox_owner {
-        query.push(" OR owner = ");
-        query.push_bind(mailbox_owner.clone());
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::Domain;
+    use crate::{
+        api::ApiError,
+        auth::User,
+        roles::Permission,
+        webhooks::{ssr::notify, WebhookEvent},
+    };
+    use serde_json::json;
+    use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+    use std::ops::Range;
+
+    /// Selects all columns of a domain, including its members
+    const SELECT_DOMAINS: &str = "SELECT domain, catch_all, public, active, owner, \
+        COALESCE((SELECT group_concat(username, ', ') FROM domain_members \
+        WHERE domain_members.domain = domains.domain), '') AS members, created_at FROM domains";
+
+    /// The values of a domain that can be set when creating or updating it
+    pub struct DomainEdit {
+        pub domain: String,
+        pub catch_all: String,
+        pub public: bool,
+        pub active: bool,
+        pub owner: String,
+        /// The users who may manage the domain besides the owner.
+        /// Only changed by the owner and those allowed to manage all domains.
+        pub members: Vec<String>,
     }
-    query.push(")");
 
-    let pool = crate::database::ssr::pool()?;
-    Ok(query.build_query_as::<(String, String)>().fetch_all(&pool).await?)
-}
+    /// The names under which the user manages domains. Mailboxes act on behalf of their owner.
+    fn manager_names(user: &User) -> Vec<String> {
+        std::iter::once(user.username.clone())
+            .chain(user.mailbox_owner.clone())
+            .collect()
+    }
 
-#[server]
-pub async fn list_domains(query: DomainQuery) -> Result<Vec<Domain>, ServerFnError> {
-    let user = crate::auth::auth_user().await?;
+    /// Pushes a condition selecting the domains that one of the given users owns or is a member of.
+    fn push_managed_by(query: &mut QueryBuilder<'_, Sqlite>, names: &[String]) {
+        query.push("(owner IN (");
+        let mut separated = query.separated(", ");
+        for name in names {
+            separated.push_bind(name.clone());
+        }
+        query.push(") OR domain IN (SELECT domain FROM domain_members WHERE username IN (");
+        let mut separated = query.separated(", ");
+        for name in names {
+            separated.push_bind(name.clone());
+        }
+        query.push(")))");
+    }
 
-    let DomainQuery { sort, range, search } = query;
+    /// Whether the given user owns the domain or is one of its members.
+    pub async fn is_managed_by(pool: &SqlitePool, domain: &str, username: &str) -> Result<bool, ApiError> {
+        let mut query = QueryBuilder::new("SELECT EXISTS (SELECT * FROM domains WHERE domain = ");
+        query.push_bind(domain);
+        query.push(" AND ");
+        push_managed_by(&mut query, &[username.to_string()]);
+        query.push(")");
+        Ok(query.build_query_scalar::<bool>().fetch_one(pool).await?)
+    }
 
-    let mut query = QueryBuilder::new("SELECT * FROM domains WHERE 1=1");
-    if !user.admin {
-        query.push(" AND owner = ");
-        query.push_bind(&user.username);
+    /// Returns all domains that the user may use for new addresses,
+    /// and whether the user manages them, which allows using reserved addresses.
+    pub async fn allowed(pool: &SqlitePool, user: &User) -> Result<Vec<(String, bool)>, ApiError> {
+        let names = manager_names(user);
+        let mut query = QueryBuilder::new("SELECT domain, ");
+        push_managed_by(&mut query, &names);
+        query.push(" FROM domains WHERE active = TRUE AND (public = TRUE OR ");
+        push_managed_by(&mut query, &names);
+        query.push(")");
+
+        Ok(query.build_query_as::<(String, bool)>().fetch_all(pool).await?)
     }
-    if !search.is_empty() {
-        query.push(" AND ( domain LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') OR catch_all LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') OR owner LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') )");
+
+    /// Fails if one of the given members is not an existing user.
+    async fn ensure_members_exist(pool: &SqlitePool, members: &[String]) -> Result<(), ApiError> {
+        for member in members.iter().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT * FROM users WHERE username = ?)")
+                .bind(member)
+                .fetch_one(pool)
+                .await?;
+            if !exists {
+                return Err(ApiError::BadRequest(format!("User '{member}' does not exist")));
+            }
+        }
+        Ok(())
     }
 
-    if let Some(order) = Domain::sorting_to_sql(&sort) {
-        query.push(" ");
-        query.push(order);
+    /// Replaces the members of a domain. The owner is never a member.
+    async fn set_members(pool: &SqlitePool, domain: &str, owner: &str, members: &[String]) -> Result<(), ApiError> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM domain_members WHERE domain = ?")
+            .bind(domain)
+            .execute(&mut *tx)
+            .await?;
+
+        for member in members
+            .iter()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty() && *x != owner)
+        {
+            sqlx::query("INSERT OR IGNORE INTO domain_members (domain, username) VALUES (?, ?)")
+                .bind(domain)
+                .bind(member)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        search: &str,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<Domain>, ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mut query = QueryBuilder::new(SELECT_DOMAINS);
+        query.push(" WHERE 1=1");
+        if !user.can(Permission::DomainsRead) {
+            query.push(" AND ");
+            push_managed_by(&mut query, &manager_names(user));
+        }
+        if !search.is_empty() {
+            query.push(" AND ( domain LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') OR catch_all LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') OR owner LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') )");
+        }
+
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<Domain>().fetch_all(pool).await?)
+    }
+
+    /// Returns the given domain, if it is visible to the user.
+    pub async fn get(pool: &SqlitePool, user: &User, domain: &str) -> Result<Domain, ApiError> {
+        get_with(pool, user, domain, Permission::DomainsRead).await
+    }
+
+    /// Returns the given domain, if the user owns it, is a member or has the given permission.
+    async fn get_with(
+        pool: &SqlitePool,
+        user: &User,
+        domain: &str,
+        permission: Permission,
+    ) -> Result<Domain, ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mut query = QueryBuilder::new(SELECT_DOMAINS);
+        query.push(" WHERE domain = ");
+        query.push_bind(domain);
+        if !user.can(permission) {
+            query.push(" AND ");
+            push_managed_by(&mut query, &manager_names(user));
+        }
+
+        query
+            .build_query_as::<Domain>()
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Domain '{domain}' does not exist")))
+    }
+
+    pub async fn delete(pool: &SqlitePool, user: &User, domain: &str) -> Result<(), ApiError> {
+        // Deleting only as admin or with the permission to delete domains!
+        user.ensure_permission(Permission::DomainsDelete)?;
+
+        let mut query = QueryBuilder::new("DELETE FROM domains WHERE domain = ");
+        query.push_bind(domain);
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("Domain '{domain}' does not exist")));
+        }
+
+        notify(pool, user, WebhookEvent::DomainDeleted, json!({ "domain": domain })).await;
+        Ok(())
+    }
+
+    pub async fn create_or_update(
+        pool: &SqlitePool,
+        user: &User,
+        old_domain: Option<String>,
+        edit: DomainEdit,
+    ) -> Result<Domain, ApiError> {
+        if old_domain.is_some() {
+            // Editing is allowed for some users
+            user.ensure_not_mailbox()?;
+        } else {
+            // Creation only as admin or with the permission to manage all domains.
+            user.ensure_permission(Permission::DomainsWrite)?;
+        }
+        let can_write = user.can(Permission::DomainsWrite);
+
+        let DomainEdit {
+            domain,
+            catch_all,
+            public,
+            active,
+            owner,
+            members,
+        } = edit;
+
+        // Only admins and those allowed to manage all domains can assign other owners
+        let owner = if can_write { owner.trim() } else { &user.username };
+        // Empty owner -> self owned
+        let owner = if owner.is_empty() { &user.username } else { owner };
+        // Only they may create public domains
+        let public = public && can_write;
+        if domain.is_empty() {
+            return Err(ApiError::BadRequest("domain cannot be empty".to_string()));
+        }
+        ensure_members_exist(pool, &members).await?;
+
+        if let Some(old_domain) = old_domain {
+            // Make sure the domain exists and may be changed by the user
+            let current = get_with(pool, user, &old_domain, Permission::DomainsWrite).await?;
+
+            let mut query = QueryBuilder::new("UPDATE domains SET catch_all = ");
+            query.push_bind(catch_all);
+            if can_write {
+                // Only they can edit the domain itself and its owner
+                query.push(", domain = ");
+                query.push_bind(&domain);
+                query.push(", owner = ");
+                query.push_bind(owner);
+            }
+            query.push(", public = ");
+            query.push_bind(public);
+            query.push(", active = ");
+            query.push_bind(active);
+            query.push(" WHERE domain = ");
+            query.push_bind(&old_domain);
+            if !can_write {
+                query.push(" AND ");
+                push_managed_by(&mut query, &manager_names(user));
+            }
+
+            query.build().execute(pool).await?;
+
+            let domain_name = if can_write { &domain } else { &old_domain };
+            // Members can manage the domain, but only the owner can choose them
+            if can_write || current.owner == user.username {
+                let owner = if can_write { owner } else { &current.owner };
+                set_members(pool, domain_name, owner, &members).await?;
+            }
+
+            let domain = get(pool, user, domain_name).await?;
+            notify(pool, user, WebhookEvent::DomainUpdated, &domain).await;
+            Ok(domain)
+        } else {
+            sqlx::query("INSERT INTO domains (domain, catch_all, public, active, owner) VALUES (?, ?, ?, ?, ?)")
+                .bind(&domain)
+                .bind(catch_all)
+                .bind(public)
+                .bind(active)
+                .bind(owner)
+                .execute(pool)
+                .await?;
+            set_members(pool, &domain, owner, &members).await?;
+
+            let domain = get(pool, user, &domain).await?;
+            notify(pool, user, WebhookEvent::DomainCreated, &domain).await;
+            Ok(domain)
+        }
     }
+}
+
+#[server]
+pub async fn allowed_domains() -> Result<Vec<(String, bool)>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::allowed(&pool, &user).await?)
+}
 
-    query.push(" LIMIT ");
-    query.push_bind(range.len() as i64);
-    query.push(" OFFSET ");
-    query.push_bind(range.start as i64);
+#[server]
+pub async fn list_domains(query: DomainQuery) -> Result<Vec<Domain>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+
+    let DomainQuery { sort, range, search } = query;
 
     let pool = crate::database::ssr::pool()?;
-    Ok(query.build_query_as::<Domain>().fetch_all(&pool).await?)
+    Ok(ssr::list(&pool, &user, &search, Domain::sorting_to_sql(&sort), range).await?)
 }
 
 #[server]
@@ -96,9 +358,12 @@ pub async fn domain_count() -> Result<usize, ServerFnError> {
     let user = crate::auth::auth_user().await?;
 
     let mut query = QueryBuilder::new("SELECT COUNT(*) FROM domains");
-    if !user.admin {
+    if !user.can(Permission::DomainsRead) {
         query.push(" WHERE owner = ");
         query.push_bind(&user.username);
+        query.push(" OR domain IN (SELECT domain FROM domain_members WHERE username = ");
+        query.push_bind(&user.username);
+        query.push(")");
     }
 
     let pool = crate::database::ssr::pool()?;
@@ -109,21 +374,9 @@ pub async fn domain_count() -> Result<usize, ServerFnError> {
 
 #[server]
 pub async fn delete_domain(domain: String) -> Result<(), ServerFnError> {
-    // Creating/Deleting only as admin!
-    let user = crate::auth::auth_admin().await?;
-
-    let mut query = QueryBuilder::new("DELETE FROM domains WHERE domain = ");
-    query.push_bind(domain);
-
-    // (Hypothetical) Non-admins can only delete their own domains
-    if !user.admin {
-        query.push(" AND owner = ");
-        query.push_bind(&user.username);
-    }
-
+    let user = crate::auth::auth_any().await?;
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
-    Ok(())
+    Ok(ssr::delete(&pool, &user, &domain).await?)
 }
 
 #[server]
@@ -134,60 +387,20 @@ pub async fn create_or_update_domain(
     public: bool,
     active: bool,
     owner: String,
+    members: String,
 ) -> Result<(), ServerFnError> {
-    let user = if old_domain.is_some() {
-        // Editing is allowed for some users
-        crate::auth::auth_user().await?
-    } else {
-        // Creation only as admin.
-        crate::auth::auth_admin().await?
-    };
+    let user = crate::auth::auth_any().await?;
     let pool = crate::database::ssr::pool()?;
 
-    // Only admins can assign other owners
-    let owner = if user.admin { owner.trim() } else { &user.username };
-    // Empty owner -> self owned
-    let owner = if owner.is_empty() { &user.username } else { owner };
-    // Only admins may create public domains
-    let public = public && user.admin;
-    if domain.is_empty() {
-        return Err(ServerFnError::new("domain cannot be empty"));
-    }
-
-    if let Some(old_domain) = old_domain {
-        let mut query = QueryBuilder::new("UPDATE domains SET catch_all = ");
-        query.push_bind(catch_all);
-        if user.admin {
-            // Only admins can edit the domain itself
-            query.push(", domain = ");
-            query.push_bind(domain);
-        }
-        query.push(", public = ");
-        query.push_bind(public);
-        query.push(", active = ");
-        query.push_bind(active);
-        query.push(", owner = ");
-        query.push_bind(owner);
-        query.push(" WHERE domain = ");
-        query.push_bind(old_domain);
-        if !user.admin {
-            query.push(" AND owner = ");
-            query.push_bind(&user.username);
-        }
-
-        query.build().execute(&pool).await.map(|_| ())?;
-    } else {
-        sqlx::query("INSERT INTO domains (domain, catch_all, public, active, owner) VALUES (?, ?, ?, ?, ?)")
-            .bind(domain)
-            .bind(catch_all)
-            .bind(public)
-            .bind(active)
-            .bind(owner)
-            .execute(&pool)
-            .await
-            .map(|_| ())?;
-    }
-
+    let edit = ssr::DomainEdit {
+        domain,
+        catch_all,
+        public,
+        active,
+        owner,
+        members: split_members(&members),
+    };
+    ssr::create_or_update(&pool, &user, old_domain, edit).await?;
     Ok(())
 }
 
@@ -195,24 +408,31 @@ pub async fn create_or_update_domain(
 pub async fn update_domain_public_and_active(domain: String, public: bool, active: bool) -> Result<(), ServerFnError> {
     let user = crate::auth::auth_user().await?;
 
-    // Only admins may create public domains
-    let public = public && user.admin;
+    // Only admins and those allowed to manage all domains may create public domains
+    let can_write = user.can(Permission::DomainsWrite);
+    let public = public && can_write;
 
     let mut query = QueryBuilder::new("UPDATE domains SET public = ");
     query.push_bind(public);
     query.push(", active = ");
     query.push_bind(active);
     query.push(" WHERE domain = ");
-    query.push_bind(domain);
+    query.push_bind(&domain);
 
-    // Non-admins can only change their own domains
-    if !user.admin {
-        query.push(" AND owner = ");
+    // Others can only change the domains they own or are a member of
+    if !can_write {
+        query.push(" AND (owner = ");
         query.push_bind(&user.username);
+        query.push(" OR domain IN (SELECT domain FROM domain_members WHERE username = ");
+        query.push_bind(&user.username);
+        query.push("))");
     }
 
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
+    if query.build().execute(&pool).await?.rows_affected() > 0 {
+        let domain = ssr::get(&pool, &user, &domain).await?;
+        crate::webhooks::ssr::notify(&pool, &user, crate::webhooks::WebhookEvent::DomainUpdated, &domain).await;
+    }
     Ok(())
 }
 
@@ -252,8 +472,10 @@ impl TableDataProvider<Domain> for DomainTableDataProvider {
 
 #[component]
 pub fn Domains(user: User) -> impl IntoView {
+    let can_write = user.can(Permission::DomainsWrite);
+    let can_delete = user.can(Permission::DomainsDelete);
     let mut rows = DomainTableDataProvider::default();
-    let default_sorting = VecDeque::from([(5, ColumnSort::Descending)]);
+    let default_sorting = VecDeque::from([(6, ColumnSort::Descending)]);
     rows.set_sorting(&default_sorting);
     let sorting = create_rw_signal(default_sorting);
 
@@ -269,6 +491,9 @@ pub fn Domains(user: User) -> impl IntoView {
     let (edit_modal_input_public, set_edit_modal_input_public) = create_signal(true);
     let (edit_modal_input_active, set_edit_modal_input_active) = create_signal(true);
     let (edit_modal_input_owner, set_edit_modal_input_owner) = create_signal("".to_string());
+    let (edit_modal_input_members, set_edit_modal_input_members) = create_signal("".to_string());
+    let (edit_modal_can_edit_members, set_edit_modal_can_edit_members) = create_signal(false);
+    let username = user.username.clone();
     let edit_modal_open_with = Callback::new(move |edit_domain: Option<Domain>| {
         edit_modal_domain.set(Some(edit_domain.clone()));
 
@@ -278,12 +503,17 @@ pub fn Domains(user: User) -> impl IntoView {
             set_edit_modal_input_public(edit_domain.public);
             set_edit_modal_input_active(edit_domain.active);
             set_edit_modal_input_owner(edit_domain.owner.clone());
+            set_edit_modal_input_members(edit_domain.members.clone());
+            // Members may manage the domain, but only the owner chooses them
+            set_edit_modal_can_edit_members(can_write || edit_domain.owner == username);
         } else {
             set_edit_modal_input_domain("".to_string());
             set_edit_modal_input_catchall("".to_string());
-            set_edit_modal_input_public(user.admin);
+            set_edit_modal_input_public(can_write);
             set_edit_modal_input_active(true);
             set_edit_modal_input_owner("".to_string());
+            set_edit_modal_input_members("".to_string());
+            set_edit_modal_can_edit_members(can_write);
         }
     });
 
@@ -298,6 +528,7 @@ pub fn Domains(user: User) -> impl IntoView {
                 edit_modal_input_public.get_untracked(),
                 edit_modal_input_active.get_untracked(),
                 edit_modal_input_owner.get_untracked(),
+                edit_modal_input_members.get_untracked(),
             )
             .await
             {
@@ -353,7 +584,7 @@ pub fn Domains(user: User) -> impl IntoView {
                                 delete_modal_domain.set(Some(delete_domain.clone()));
                             }
 
-                            disabled=move || !user.admin
+                            disabled=move || !can_delete
                         >
 
                             <Icon icon=icondata::FiTrash2 class="w-5 h-5"/>
@@ -449,7 +680,7 @@ pub fn Domains(user: User) -> impl IntoView {
                     placeholder="example.com"
                     on:input=move |ev| set_edit_modal_input_domain(event_target_value(&ev))
                     prop:value=edit_modal_input_domain
-                    disabled=move || !user.admin
+                    disabled=move || !can_write
                 />
             </div>
             <div class="flex flex-col gap-2">
@@ -480,10 +711,26 @@ pub fn Domains(user: User) -> impl IntoView {
                     placeholder=move || user.username.clone()
                     on:input=move |ev| set_edit_modal_input_owner(event_target_value(&ev))
                     prop:value=edit_modal_input_owner
-                    disabled=move || !user.admin
+                    disabled=move || !can_write
+                />
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="members"
+                >
+                    Members
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="text"
+                    placeholder="alice, bob"
+                    on:input=move |ev| set_edit_modal_input_members(event_target_value(&ev))
+                    prop:value=edit_modal_input_members
+                    disabled=move || !edit_modal_can_edit_members()
                 />
             </div>
-            <Show when=move || user.admin>
+            <Show when=move || can_write>
                 <div class="flex flex-row gap-2 mt-2 items-center">
                     <input
                         id="public"
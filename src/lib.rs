@@ -1,20 +1,41 @@
 pub mod aliases;
 #[cfg(feature = "ssr")]
 pub mod api;
+pub mod api_tokens;
 pub mod app;
+pub mod app_passwords;
 pub mod auth;
+#[cfg(feature = "ssr")]
+pub mod client_ip;
+#[cfg(feature = "ssr")]
+pub mod csrf;
 pub mod database;
 pub mod domains;
 pub mod error_template;
 #[cfg(feature = "ssr")]
 pub mod fileserv;
+#[cfg(feature = "ssr")]
+pub mod limits;
+pub mod lockouts;
 pub mod mailboxes;
+pub mod oidc;
+pub mod passkeys;
+pub mod password_policy;
+pub mod password_resets;
+#[cfg(feature = "ssr")]
+pub mod passwords;
 #[cfg(feature = "ssr")]
 pub mod provision;
 #[cfg(feature = "ssr")]
+pub mod proxy_auth;
+pub mod roles;
+pub mod sessions;
+#[cfg(feature = "ssr")]
 pub mod state;
+pub mod totp;
 pub mod users;
 pub mod utils;
+pub mod webhooks;
 
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
@@ -0,0 +1,139 @@
+use crate::{api::ApiError, auth::User, users::UserLimits};
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+static LIMITS: OnceLock<Limits> = OnceLock::new();
+
+/// The global limits, which apply to every user that doesn't override them.
+/// A value of `None` means unlimited.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Maximum number of aliases an account may own
+    pub max_aliases: Option<u32>,
+    /// Maximum number of aliases an account may create within 24 hours
+    pub max_aliases_per_day: Option<u32>,
+    /// Maximum number of API requests per minute and token
+    pub api_requests_per_minute: Option<u32>,
+    /// Maximum number of API requests per minute and client address
+    pub api_requests_per_minute_per_ip: Option<u32>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_aliases: None,
+            max_aliases_per_day: None,
+            api_requests_per_minute: Some(60),
+            api_requests_per_minute_per_ip: None,
+        }
+    }
+}
+
+/// Reads a limit from the given environment variable, where 0 means unlimited.
+pub(crate) fn limit_from_env(name: &str, default: Option<u32>) -> Result<Option<u32>> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(default);
+    };
+    let value = value
+        .trim()
+        .parse::<u32>()
+        .context(format!("Invalid value for {name}: '{value}'"))?;
+    Ok((value > 0).then_some(value))
+}
+
+impl Limits {
+    /// Reads the global limits from the environment. Must be called once on startup.
+    pub fn init() -> Result<()> {
+        let defaults = Self::default();
+        let limits = Self {
+            max_aliases: limit_from_env("IDMAIL_MAX_ALIASES", defaults.max_aliases)?,
+            max_aliases_per_day: limit_from_env("IDMAIL_MAX_ALIASES_PER_DAY", defaults.max_aliases_per_day)?,
+            api_requests_per_minute: limit_from_env(
+                "IDMAIL_API_REQUESTS_PER_MINUTE",
+                defaults.api_requests_per_minute,
+            )?,
+            api_requests_per_minute_per_ip: limit_from_env(
+                "IDMAIL_API_REQUESTS_PER_MINUTE_PER_IP",
+                defaults.api_requests_per_minute_per_ip,
+            )?,
+        };
+
+        log::info!("Using limits: {limits:?}");
+        let _ = LIMITS.set(limits);
+        Ok(())
+    }
+
+    pub fn global() -> &'static Limits {
+        LIMITS.get_or_init(Limits::default)
+    }
+
+    /// Returns the limits that apply to the given user, taking the overrides
+    /// of the user (or of the owner of a mailbox) into account.
+    pub async fn for_user(pool: &SqlitePool, user: &User) -> Result<Limits, ApiError> {
+        let username = user.mailbox_owner.as_ref().unwrap_or(&user.username);
+        let overrides = sqlx::query_as::<_, UserLimits>(
+            "SELECT max_aliases, max_aliases_per_day, api_requests_per_minute FROM users WHERE username = ?",
+        )
+        .bind(username)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or_default();
+
+        // An override of 0 disables the limit
+        let apply = |value: Option<u32>, default: Option<u32>| value.map(|x| (x > 0).then_some(x)).unwrap_or(default);
+        let global = Self::global();
+        Ok(Limits {
+            max_aliases: apply(overrides.max_aliases, global.max_aliases),
+            max_aliases_per_day: apply(overrides.max_aliases_per_day, global.max_aliases_per_day),
+            api_requests_per_minute: apply(overrides.api_requests_per_minute, global.api_requests_per_minute),
+            api_requests_per_minute_per_ip: global.api_requests_per_minute_per_ip,
+        })
+    }
+}
+
+/// Counts requests per key in fixed windows of one minute.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    const WINDOW: Duration = Duration::from_secs(60);
+    /// Expired windows are removed once this many keys are tracked
+    const CLEANUP_THRESHOLD: usize = 10_000;
+
+    /// Counts a request for the given key and fails if it exceeds the given limit.
+    pub fn check(&self, key: String, limit: Option<u32>) -> Result<(), ApiError> {
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= Self::CLEANUP_THRESHOLD {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < Self::WINDOW);
+        }
+
+        let (start, count) = windows.entry(key).or_insert((now, 0));
+        if now.duration_since(*start) >= Self::WINDOW {
+            *start = now;
+            *count = 0;
+        }
+
+        if *count >= limit {
+            let retry_after = Self::WINDOW.saturating_sub(now.duration_since(*start));
+            return Err(ApiError::TooManyRequests(
+                format!("Rate limit of {limit} requests per minute exceeded"),
+                (retry_after.as_secs_f64().ceil() as u64).max(1),
+            ));
+        }
+
+        *count += 1;
+        Ok(())
+    }
+}
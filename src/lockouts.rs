@@ -0,0 +1,504 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+
+use crate::auth::User;
+use crate::roles::Permission;
+use crate::utils::{THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::{ev::MouseEvent, logging::error, *};
+use leptos_icons::Icon;
+use leptos_struct_table::*;
+use serde::{Deserialize, Serialize};
+
+/// What failed login attempts are counted for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "TEXT", rename_all = "lowercase"))]
+#[serde(rename_all = "lowercase")]
+pub enum LockoutKind {
+    /// A user name or mailbox address, whether it exists or not
+    Account,
+    /// The address of a client
+    Address,
+}
+
+impl fmt::Display for LockoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockoutKind::Account => write!(f, "account"),
+            LockoutKind::Address => write!(f, "address"),
+        }
+    }
+}
+
+impl CellValue for LockoutKind {
+    type RenderOptions = ();
+
+    fn render_value(self, _options: &Self::RenderOptions) -> impl IntoView {
+        self.to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct LoginLockout {
+    #[table(class = "w-1")]
+    pub kind: LockoutKind,
+    #[table(title = "Account or address")]
+    pub key: String,
+    #[table(class = "w-1")]
+    pub failures: i64,
+    #[table(class = "w-1", title = "Blocked until", renderer = "TimediffRenderer")]
+    pub locked_until: DateTime<Utc>,
+    #[table(class = "w-1", title = "Last failure", renderer = "TimediffRenderer")]
+    pub last_failure_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoginLockoutQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{LockoutKind, LoginLockout};
+    use crate::{api::ApiError, auth::User, limits::limit_from_env, roles::Permission};
+    use anyhow::{Context, Result};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::{net::IpAddr, ops::Range, sync::OnceLock};
+
+    /// The number of failures that are tolerated before each further failure delays the next attempt
+    const FREE_FAILURES: u32 = 3;
+
+    static CONFIG: OnceLock<LockoutConfig> = OnceLock::new();
+
+    #[derive(Debug)]
+    struct LockoutConfig {
+        /// Failures per account after which it is locked, `None` disables the counter
+        max_failures: Option<u32>,
+        /// Failures per client address after which it is locked, `None` disables the counter
+        max_failures_per_ip: Option<u32>,
+        /// How long a lockout lasts, which is also how long failures are remembered
+        duration: u32,
+    }
+
+    impl Default for LockoutConfig {
+        fn default() -> Self {
+            Self {
+                max_failures: Some(10),
+                max_failures_per_ip: Some(50),
+                duration: 900,
+            }
+        }
+    }
+
+    impl LockoutConfig {
+        fn max_failures(&self, kind: LockoutKind) -> Option<u32> {
+            match kind {
+                LockoutKind::Account => self.max_failures,
+                LockoutKind::Address => self.max_failures_per_ip,
+            }
+        }
+
+        /// Returns for how many seconds further attempts are rejected after the given number of failures.
+        fn delay(&self, kind: LockoutKind, failures: u32) -> u32 {
+            match self.max_failures(kind) {
+                Some(max) if failures >= max => self.duration,
+                Some(_) if failures > FREE_FAILURES => {
+                    2u32.saturating_pow(failures - FREE_FAILURES - 1).min(self.duration)
+                }
+                _ => 0,
+            }
+        }
+    }
+
+    /// Reads the lockout settings from the environment. Must be called once on startup.
+    pub fn init() -> Result<()> {
+        let defaults = LockoutConfig::default();
+        let duration = match std::env::var("IDMAIL_LOGIN_LOCKOUT_SECONDS") {
+            Ok(value) => value
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|x| *x > 0)
+                .context(format!("Invalid value for IDMAIL_LOGIN_LOCKOUT_SECONDS: '{value}'"))?,
+            Err(_) => defaults.duration,
+        };
+        let config = LockoutConfig {
+            max_failures: limit_from_env("IDMAIL_LOGIN_MAX_FAILURES", defaults.max_failures)?,
+            max_failures_per_ip: limit_from_env("IDMAIL_LOGIN_MAX_FAILURES_PER_IP", defaults.max_failures_per_ip)?,
+            duration,
+        };
+
+        log::info!("Using login lockout: {config:?}");
+        let _ = CONFIG.set(config);
+        Ok(())
+    }
+
+    fn config() -> &'static LockoutConfig {
+        CONFIG.get_or_init(LockoutConfig::default)
+    }
+
+    /// Returns the address of the client of the current server function request.
+    pub fn client_ip() -> Option<IpAddr> {
+        let parts = leptos::use_context::<http::request::Parts>()?;
+        crate::client_ip::client_ip(&parts.headers, &parts.extensions)
+    }
+
+    /// The counters that apply to an attempt for the given account from the given address
+    fn keys(account: Option<&str>, ip: Option<IpAddr>) -> Vec<(LockoutKind, String)> {
+        let config = config();
+        let mut keys = Vec::new();
+        if let (Some(account), Some(_)) = (account, config.max_failures) {
+            keys.push((LockoutKind::Account, account.to_string()));
+        }
+        if let (Some(ip), Some(_)) = (ip, config.max_failures_per_ip) {
+            keys.push((LockoutKind::Address, ip.to_string()));
+        }
+        keys
+    }
+
+    /// Fails if the account or the client address is currently locked.
+    pub async fn check(pool: &SqlitePool, account: Option<&str>, ip: Option<IpAddr>) -> Result<(), ApiError> {
+        for (kind, key) in keys(account, ip) {
+            let remaining = sqlx::query_scalar::<_, i64>(
+                "SELECT unixepoch(locked_until) - unixepoch('now') FROM login_failures \
+                WHERE kind = ? AND key = ? AND locked_until > CURRENT_TIMESTAMP",
+            )
+            .bind(kind)
+            .bind(&key)
+            .fetch_optional(pool)
+            .await?;
+
+            if let Some(remaining) = remaining {
+                log::warn!("rejecting login attempt because {kind} '{key}' is locked for {remaining} seconds");
+                let seconds = remaining.max(1) as u64;
+                let wait = match seconds {
+                    1 => "1 second".to_string(),
+                    2..=90 => format!("{seconds} seconds"),
+                    _ => format!("{} minutes", seconds.div_ceil(60)),
+                };
+                return Err(ApiError::TooManyRequests(
+                    format!("Too many failed login attempts, please try again in {wait}."),
+                    seconds,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts a failed attempt for the account and the client address and delays further attempts accordingly.
+    pub async fn record_failure(pool: &SqlitePool, account: Option<&str>, ip: Option<IpAddr>) -> Result<(), ApiError> {
+        let config = config();
+        let window = format!("-{} seconds", config.duration);
+        sqlx::query("DELETE FROM login_failures WHERE last_failure_at < datetime('now', ?)")
+            .bind(&window)
+            .execute(pool)
+            .await?;
+
+        for (kind, key) in keys(account, ip) {
+            let failures = sqlx::query_scalar::<_, u32>(
+                "INSERT INTO login_failures (kind, key, failures) VALUES (?, ?, 1) \
+                ON CONFLICT (kind, key) DO UPDATE SET failures = failures + 1, last_failure_at = CURRENT_TIMESTAMP \
+                RETURNING failures",
+            )
+            .bind(kind)
+            .bind(&key)
+            .fetch_one(pool)
+            .await?;
+
+            let delay = config.delay(kind, failures);
+            if delay > 0 {
+                if config.max_failures(kind).is_some_and(|max| failures >= max) {
+                    log::warn!("locking {kind} '{key}' for {delay} seconds after {failures} failed login attempts");
+                }
+                sqlx::query("UPDATE login_failures SET locked_until = datetime('now', ?) WHERE kind = ? AND key = ?")
+                    .bind(format!("+{delay} seconds"))
+                    .bind(kind)
+                    .bind(&key)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the failed attempts of an account after a successful login.
+    pub async fn reset(pool: &SqlitePool, account: &str) -> Result<(), ApiError> {
+        sqlx::query("DELETE FROM login_failures WHERE kind = ? AND key = ?")
+            .bind(LockoutKind::Account)
+            .bind(account)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<LoginLockout>, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+
+        let mut query = QueryBuilder::new(
+            "SELECT kind, key, failures, locked_until, last_failure_at FROM login_failures \
+            WHERE last_failure_at >= datetime('now', ",
+        );
+        query.push_bind(format!("-{} seconds", config().duration));
+        query.push(")");
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<LoginLockout>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM login_failures WHERE last_failure_at >= datetime('now', ?)",
+        )
+        .bind(format!("-{} seconds", config().duration))
+        .fetch_one(pool)
+        .await?;
+        Ok(count as usize)
+    }
+
+    /// Lifts a lockout and forgets the failed attempts of the account or client address.
+    pub async fn clear(pool: &SqlitePool, user: &User, kind: LockoutKind, key: &str) -> Result<(), ApiError> {
+        user.ensure_permission(Permission::LockoutsLift)?;
+
+        if sqlx::query("DELETE FROM login_failures WHERE kind = ? AND key = ?")
+            .bind(kind)
+            .bind(key)
+            .execute(pool)
+            .await?
+            .rows_affected()
+            == 0
+        {
+            return Err(ApiError::NotFound(format!(
+                "No failed logins recorded for {kind} '{key}'"
+            )));
+        }
+
+        log::info!("{kind} '{key}' was unlocked by '{}'", user.username);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use sqlx::sqlite::SqlitePoolOptions;
+
+        async fn pool() -> SqlitePool {
+            let pool = SqlitePoolOptions::new()
+                .max_connections(1)
+                .connect("sqlite::memory:")
+                .await
+                .unwrap();
+            sqlx::migrate!().run(&pool).await.unwrap();
+            pool
+        }
+
+        /// Returns for how many seconds the client address is locked, or 0.
+        async fn locked_for(pool: &SqlitePool, ip: IpAddr) -> u64 {
+            match check(pool, None, Some(ip)).await {
+                Ok(()) => 0,
+                Err(ApiError::TooManyRequests(_, seconds)) => seconds,
+                Err(e) => panic!("unexpected error: {e:?}"),
+            }
+        }
+
+        // Invalid API tokens are recorded without an account, so only the client address counts
+        #[tokio::test]
+        async fn locks_out_addresses_trying_invalid_tokens() {
+            let pool = pool().await;
+            let ip = "203.0.113.7".parse().unwrap();
+            let other = "203.0.113.8".parse().unwrap();
+
+            for _ in 0..FREE_FAILURES {
+                record_failure(&pool, None, Some(ip)).await.unwrap();
+                assert_eq!(locked_for(&pool, ip).await, 0);
+            }
+            record_failure(&pool, None, Some(ip)).await.unwrap();
+            assert!(locked_for(&pool, ip).await > 0);
+            assert_eq!(locked_for(&pool, other).await, 0);
+
+            for _ in FREE_FAILURES + 1..config().max_failures_per_ip.unwrap() {
+                record_failure(&pool, None, Some(ip)).await.unwrap();
+            }
+            assert!(locked_for(&pool, ip).await > u64::from(config().duration) - 5);
+            assert_eq!(locked_for(&pool, other).await, 0);
+        }
+
+        #[tokio::test]
+        async fn locks_out_accounts_from_any_address() {
+            let pool = pool().await;
+            let ips = (1..=10)
+                .map(|x| format!("203.0.113.{x}").parse().unwrap())
+                .collect::<Vec<IpAddr>>();
+            for ip in &ips {
+                record_failure(&pool, Some("admin"), Some(*ip)).await.unwrap();
+            }
+
+            let other = Some("198.51.100.1".parse().unwrap());
+            assert!(check(&pool, Some("admin"), other).await.is_err());
+            assert!(check(&pool, Some("someone"), other).await.is_ok());
+            reset(&pool, "admin").await.unwrap();
+            assert!(check(&pool, Some("admin"), other).await.is_ok());
+        }
+    }
+}
+
+#[server]
+pub async fn list_login_lockouts(query: LoginLockoutQuery) -> Result<Vec<LoginLockout>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let LoginLockoutQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, LoginLockout::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn login_lockout_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn clear_login_lockout(kind: LockoutKind, key: String) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::clear(&pool, &user, kind, &key).await?)
+}
+
+#[derive(Default)]
+pub struct LoginLockoutTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<LoginLockout> for LoginLockoutTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<LoginLockout>, Range<usize>), String> {
+        list_login_lockouts(LoginLockoutQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        login_lockout_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+#[component]
+pub fn LoginLockouts(user: User) -> impl IntoView {
+    let can_lift = user.can(Permission::LockoutsLift);
+    let mut rows = LoginLockoutTableDataProvider::default();
+    let default_sorting = VecDeque::from([(4, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload_controller = ReloadController::default();
+    let (count, set_count) = create_signal(0);
+
+    #[allow(unused_variables, non_snake_case)]
+    let lockout_row_renderer =
+        move |class: Signal<String>,
+              row: LoginLockout,
+              index: usize,
+              selected: Signal<bool>,
+              on_select: EventHandler<MouseEvent>,
+              on_change: EventHandler<ChangeEvent<LoginLockout>>| {
+            let kind = row.kind;
+            let key = row.key.clone();
+            view! {
+                <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                    {row.render_row(index, on_change)}
+                    <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-blue-600 dark:hover:bg-blue-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            title="Unlock"
+                            on:click=move |_| {
+                                let key = key.clone();
+                                spawn_local(async move {
+                                    if let Err(e) = clear_login_lockout(kind, key).await {
+                                        error!("Failed to clear login lockout: {}", e);
+                                    }
+                                    reload_controller.reload();
+                                });
+                            }
+
+                            disabled=move || !can_lift
+                        >
+                            <Icon icon=icondata::FiUnlock class="w-5 h-5"/>
+                        </button>
+                    </td>
+                </tr>
+            }
+        };
+
+    view! {
+        <div class="h-full flex-1 flex-col mt-12">
+            <div class="flex items-center justify-between space-y-2 mb-4">
+                <h3 class="text-2xl font-bold">Failed logins</h3>
+            </div>
+            <div class="space-y-4">
+                <div class="flex flex-wrap items-center justify-between">
+                    <button
+                        type="button"
+                        class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-gray-800 dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-white dark:bg-black border-[1.5px] border-gray-200 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900"
+                        on:click=move |_| reload_controller.reload()
+                    >
+                        <Icon icon=icondata::FiRefreshCw class="w-5 h-5 me-2"/>
+                        Refresh
+                    </button>
+                    <div class="flex flex-1"></div>
+                    <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                        {count} " accounts or addresses"
+                    </div>
+                </div>
+
+                <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                    <div class="overflow-auto grow min-h-0">
+                        <table class="table-auto text-left w-full">
+                            <TableContent
+                                rows
+                                sorting=sorting
+                                sorting_mode=SortingMode::SingleColumn
+                                row_renderer=lockout_row_renderer
+                                reload_controller=reload_controller
+                                loading_row_display_limit=0
+                                on_row_count=set_count
+                            />
+                        </table>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
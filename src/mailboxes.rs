@@ -2,11 +2,13 @@ use std::collections::VecDeque;
 use std::ops::Range;
 
 use crate::aliases::validate_address;
-use crate::users::is_valid_pw;
+use crate::password_policy::{use_password_policy, PasswordStrength};
+use crate::password_resets::PasswordResetModal;
 use crate::utils::{DeleteModal, EditModal, Select};
 use crate::utils::{SliderRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
 
 use crate::auth::User;
+use crate::roles::Permission;
 use chrono::{DateTime, Utc};
 use leptos::leptos_dom::is_browser;
 use leptos::{ev::MouseEvent, logging::error, *};
@@ -40,54 +42,265 @@ pub struct MailboxQuery {
     search: String,
 }
 
-#[server]
-pub async fn allowed_targets() -> Result<Vec<String>, ServerFnError> {
-    let user = crate::auth::auth_any().await?;
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{validate_address, Mailbox};
+    use crate::{
+        api::ApiError,
+        auth::User,
+        roles::Permission,
+        users::mk_password_hash,
+        webhooks::{ssr::notify, WebhookEvent},
+    };
+    use serde_json::{json, Value};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::ops::Range;
+
+    /// The values of a mailbox that can be set when creating or updating it
+    pub struct MailboxEdit {
+        pub localpart: String,
+        pub domain: String,
+        /// The new password, or empty to keep the current one
+        pub password: String,
+        pub active: bool,
+        pub owner: String,
+    }
 
-    // Mailbox users can only target themselves
-    if user.mailbox_owner.is_some() {
-        return Ok(vec![user.username]);
+    /// The data sent to webhooks for mailbox events, which must not include the password hash.
+    pub fn webhook_data(mailbox: &Mailbox) -> Value {
+        json!({
+            "address": mailbox.address,
+            "active": mailbox.active,
+            "owner": mailbox.owner,
+            "created_at": mailbox.created_at,
+        })
     }
 
-    let mut query = QueryBuilder::new("SELECT address FROM mailboxes");
-    query.push(" WHERE owner = ");
-    query.push_bind(&user.username);
+    /// Returns all addresses that the user may use as an alias target, oldest mailbox first.
+    pub async fn allowed_targets(pool: &SqlitePool, user: &User) -> Result<Vec<String>, ApiError> {
+        // Mailbox users can only target themselves
+        if user.mailbox_owner.is_some() {
+            return Ok(vec![user.username.clone()]);
+        }
 
-    let pool = crate::database::ssr::pool()?;
-    Ok(query.build_query_scalar::<String>().fetch_all(&pool).await?)
-}
+        let mut query = QueryBuilder::new("SELECT address FROM mailboxes");
+        query.push(" WHERE owner = ");
+        query.push_bind(&user.username);
+        query.push(" ORDER BY id");
 
-#[server]
-pub async fn list_mailboxes(query: MailboxQuery) -> Result<Vec<Mailbox>, ServerFnError> {
-    let user = crate::auth::auth_user().await?;
+        Ok(query.build_query_scalar::<String>().fetch_all(pool).await?)
+    }
 
-    let MailboxQuery { sort, range, search } = query;
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        search: &str,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<Mailbox>, ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mut query = QueryBuilder::new("SELECT * FROM mailboxes WHERE 1=1");
+        if !user.can(Permission::MailboxesRead) {
+            query.push(" AND owner = ");
+            query.push_bind(&user.username);
+        }
+        if !search.is_empty() {
+            query.push(" AND ( address LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') OR owner LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%') )");
+        }
 
-    let mut query = QueryBuilder::new("SELECT * FROM mailboxes WHERE 1=1");
-    if !user.admin {
-        query.push(" AND owner = ");
-        query.push_bind(&user.username);
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        let mut mailboxes = query.build_query_as::<Mailbox>().fetch_all(pool).await?;
+        // Users with a role must not see the password hashes of mailboxes they don't own
+        if !user.admin {
+            mailboxes
+                .iter_mut()
+                .filter(|x| x.owner != user.username && x.address != user.username)
+                .for_each(|x| x.password_hash.clear());
+        }
+        Ok(mailboxes)
     }
-    if !search.is_empty() {
-        query.push(" AND ( address LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') OR owner LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%') )");
+
+    /// Returns the given mailbox, if it is visible to the user.
+    pub async fn get(pool: &SqlitePool, user: &User, address: &str) -> Result<Mailbox, ApiError> {
+        get_with(pool, user, address, Permission::MailboxesRead).await
     }
 
-    if let Some(order) = Mailbox::sorting_to_sql(&sort) {
-        query.push(" ");
-        query.push(order);
+    /// Returns the given mailbox, if the user owns it or has the given permission.
+    async fn get_with(
+        pool: &SqlitePool,
+        user: &User,
+        address: &str,
+        permission: Permission,
+    ) -> Result<Mailbox, ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mut query = QueryBuilder::new("SELECT * FROM mailboxes WHERE address = ");
+        query.push_bind(address);
+        if !user.can(permission) {
+            query.push(" AND owner = ");
+            query.push_bind(&user.username);
+        }
+
+        query
+            .build_query_as::<Mailbox>()
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Mailbox '{address}' does not exist")))
+    }
+
+    pub async fn delete(pool: &SqlitePool, user: &User, address: &str) -> Result<(), ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mut query = QueryBuilder::new("DELETE FROM mailboxes WHERE address = ");
+        query.push_bind(address);
+
+        // Others can only delete their own mailboxes
+        if !user.can(Permission::MailboxesDelete) {
+            query.push(" AND owner = ");
+            query.push_bind(&user.username);
+        }
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("Mailbox '{address}' does not exist")));
+        }
+
+        notify(pool, user, WebhookEvent::MailboxDeleted, json!({ "address": address })).await;
+        Ok(())
     }
 
-    query.push(" LIMIT ");
-    query.push_bind(range.len() as i64);
-    query.push(" OFFSET ");
-    query.push_bind(range.start as i64);
+    pub async fn create_or_update(
+        pool: &SqlitePool,
+        user: &User,
+        old_address: Option<String>,
+        edit: MailboxEdit,
+    ) -> Result<Mailbox, ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let MailboxEdit {
+            localpart,
+            domain,
+            password,
+            active,
+            owner,
+        } = edit;
+
+        // Only admins and those allowed to manage all mailboxes can assign other owners
+        let can_write = user.can(Permission::MailboxesWrite);
+        let owner = if can_write { owner.trim() } else { &user.username };
+        // Empty owner -> self owned
+        let owner = if owner.is_empty() { &user.username } else { owner };
+
+        // Check if address is valid
+        let allowed_domains = crate::domains::ssr::allowed(pool, user).await?;
+        let Some((_, manages_domain)) = allowed_domains.iter().find(|x| x.0 == domain) else {
+            return Err(ApiError::BadRequest("domain must be set to a valid domain".to_string()));
+        };
+
+        // Reserved addresses are left to the domain's owner and members
+        let address = validate_address(&localpart, &domain, can_write || *manages_domain)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+        let password_changed = old_address.is_some() && !password.is_empty();
+        let event = if old_address.is_some() {
+            WebhookEvent::MailboxUpdated
+        } else {
+            WebhookEvent::MailboxCreated
+        };
+        let mut query = if let Some(old_address) = old_address {
+            // Make sure the mailbox exists and may be changed by the user
+            get_with(pool, user, &old_address, Permission::MailboxesWrite).await?;
+
+            let mut query = QueryBuilder::new("UPDATE mailboxes SET address = ");
+            query.push_bind(&address);
+            query.push(", domain = ");
+            query.push_bind(domain);
+            if !password.is_empty() {
+                let password_hash = mk_password_hash(&password, &address)?;
+                query.push(", password_hash = ");
+                query.push_bind(password_hash);
+            }
+            query.push(", active = ");
+            query.push_bind(active);
+            query.push(", owner = ");
+            query.push_bind(owner);
+            query.push(" WHERE address = ");
+            query.push_bind(old_address);
+            if !can_write {
+                query.push(" AND owner = ");
+                query.push_bind(&user.username);
+            }
+            // make sure that no alias exists with that address
+            query.push(" AND NOT EXISTS (SELECT * FROM aliases WHERE address = ");
+            query.push_bind(&address);
+            query.push(")");
+
+            query
+        } else {
+            let password_hash = mk_password_hash(&password, &address)?;
+            let mut query = QueryBuilder::new("INSERT INTO mailboxes (address, domain, password_hash, active, owner)");
+            query.push("SELECT ");
+            query.push_bind(&address);
+            query.push(", ");
+            query.push_bind(domain);
+            query.push(", ");
+            query.push_bind(password_hash);
+            query.push(", ");
+            query.push_bind(active);
+            query.push(", ");
+            query.push_bind(owner);
+            // make sure that no alias exists with that address
+            query.push(" WHERE NOT EXISTS (SELECT * FROM aliases WHERE address = ");
+            query.push_bind(&address);
+            query.push(")");
+
+            query
+        };
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::Conflict(
+                "This address is already in use by an alias!".to_string(),
+            ));
+        }
+
+        if password_changed {
+            crate::sessions::ssr::end_all(pool, &address, None).await?;
+        }
+
+        let mailbox = get(pool, user, &address).await?;
+        notify(pool, user, event, webhook_data(&mailbox)).await;
+        Ok(mailbox)
+    }
+}
+
+#[server]
+pub async fn allowed_targets() -> Result<Vec<String>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::allowed_targets(&pool, &user).await?)
+}
+
+#[server]
+pub async fn list_mailboxes(query: MailboxQuery) -> Result<Vec<Mailbox>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+
+    let MailboxQuery { sort, range, search } = query;
 
     let pool = crate::database::ssr::pool()?;
-    Ok(query.build_query_as::<Mailbox>().fetch_all(&pool).await?)
+    Ok(ssr::list(&pool, &user, &search, Mailbox::sorting_to_sql(&sort), range).await?)
 }
 
 #[server]
@@ -95,7 +308,7 @@ pub async fn mailbox_count() -> Result<usize, ServerFnError> {
     let user = crate::auth::auth_user().await?;
 
     let mut query = QueryBuilder::new("SELECT COUNT(*) FROM mailboxes");
-    if !user.admin {
+    if !user.can(Permission::MailboxesRead) {
         query.push(" WHERE owner = ");
         query.push_bind(&user.username);
     }
@@ -108,20 +321,9 @@ pub async fn mailbox_count() -> Result<usize, ServerFnError> {
 
 #[server]
 pub async fn delete_mailbox(address: String) -> Result<(), ServerFnError> {
-    let user = crate::auth::auth_user().await?;
-
-    let mut query = QueryBuilder::new("DELETE FROM mailboxes WHERE address = ");
-    query.push_bind(address);
-
-    // Non-admins can only delete their own mailboxes
-    if !user.admin {
-        query.push(" AND owner = ");
-        query.push_bind(&user.username);
-    }
-
+    let user = crate::auth::auth_any().await?;
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
-    Ok(())
+    Ok(ssr::delete(&pool, &user, &address).await?)
 }
 
 #[server]
@@ -133,77 +335,17 @@ pub async fn create_or_update_mailbox(
     active: bool,
     owner: String,
 ) -> Result<(), ServerFnError> {
-    use crate::domains::allowed_domains;
-    use crate::users::mk_password_hash;
-
-    let user = crate::auth::auth_user().await?;
+    let user = crate::auth::auth_any().await?;
     let pool = crate::database::ssr::pool()?;
 
-    // Only admins can assign other owners
-    let owner = if user.admin { owner.trim() } else { &user.username };
-    // Empty owner -> self owned
-    let owner = if owner.is_empty() { &user.username } else { owner };
-
-    // Check if address is valid
-    let allowed_domains = allowed_domains().await?;
-    let Some((_, domain_owner)) = allowed_domains.iter().find(|x| x.0 == domain) else {
-        return Err(ServerFnError::new("domain must be set to a valid domain"));
+    let edit = ssr::MailboxEdit {
+        localpart,
+        domain,
+        password,
+        active,
+        owner,
     };
-
-    let address = validate_address(&localpart, &domain, user.admin || *domain_owner == user.username)
-        .map_err(ServerFnError::new)?;
-
-    let mut query = if let Some(old_address) = old_address {
-        let mut query = QueryBuilder::new("UPDATE mailboxes SET address = ");
-        query.push_bind(&address);
-        query.push(", domain = ");
-        query.push_bind(domain);
-        if !password.is_empty() {
-            let password_hash = mk_password_hash(&password)?;
-            query.push(", password_hash = ");
-            query.push_bind(password_hash);
-        }
-        query.push(", active = ");
-        query.push_bind(active);
-        query.push(", owner = ");
-        query.push_bind(owner);
-        query.push(" WHERE address = ");
-        query.push_bind(old_address);
-        if !user.admin {
-            query.push(" AND owner = ");
-            query.push_bind(&user.username);
-        }
-        // make sure that no alias exists with that address
-        query.push(" AND NOT EXISTS (SELECT * FROM aliases WHERE address = ");
-        query.push_bind(&address);
-        query.push(")");
-
-        query
-    } else {
-        let password_hash = mk_password_hash(&password)?;
-        let mut query = QueryBuilder::new("INSERT INTO mailboxes (address, domain, password_hash, active, owner)");
-        query.push("SELECT ");
-        query.push_bind(&address);
-        query.push(", ");
-        query.push_bind(domain);
-        query.push(", ");
-        query.push_bind(password_hash);
-        query.push(", ");
-        query.push_bind(active);
-        query.push(", ");
-        query.push_bind(owner);
-        // make sure that no alias exists with that address
-        query.push(" WHERE NOT EXISTS (SELECT * FROM aliases WHERE address = ");
-        query.push_bind(&address);
-        query.push(")");
-
-        query
-    };
-
-    if query.build().execute(&pool).await?.rows_affected() == 0 {
-        return Err(ServerFnError::new("This address is already in use by an alias!"));
-    }
-
+    ssr::create_or_update(&pool, &user, old_address, edit).await?;
     Ok(())
 }
 
@@ -213,16 +355,20 @@ pub async fn update_mailbox_active(address: String, active: bool) -> Result<(),
     let mut query = QueryBuilder::new("UPDATE mailboxes SET active = ");
     query.push_bind(active);
     query.push(" WHERE address = ");
-    query.push_bind(address);
+    query.push_bind(&address);
 
-    // Non-admins can only change their own domains
-    if !user.admin {
+    // Others can only change their own mailboxes
+    if !user.can(Permission::MailboxesWrite) {
         query.push(" AND owner = ");
         query.push_bind(&user.username);
     }
 
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
+    if query.build().execute(&pool).await?.rows_affected() > 0 {
+        let mailbox = ssr::get(&pool, &user, &address).await?;
+        let data = ssr::webhook_data(&mailbox);
+        crate::webhooks::ssr::notify(&pool, &user, crate::webhooks::WebhookEvent::MailboxUpdated, data).await;
+    }
     Ok(())
 }
 
@@ -294,6 +440,7 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
     }
 
     let delete_modal_mailbox = create_rw_signal(None);
+    let reset_modal_mailbox = create_rw_signal(None);
     let edit_modal_mailbox = create_rw_signal(None);
 
     let (edit_modal_input_localpart, set_edit_modal_input_localpart) = create_signal("".to_string());
@@ -373,6 +520,7 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
                                      on_select: EventHandler<MouseEvent>,
                                      on_change: EventHandler<ChangeEvent<Mailbox>>| {
         let delete_address = row.address.clone();
+        let reset_address = row.address.clone();
         let edit_mailbox = row.clone();
         view! {
             <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
@@ -385,6 +533,13 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
                         >
                             <Icon icon=icondata::FiEdit class="w-5 h-5"/>
                         </button>
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-blue-600 dark:hover:bg-blue-500 transition-all border-l-0 border-[1.5px] border-gray-200 dark:border-zinc-800 font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            title="Create password reset link"
+                            on:click=move |_| reset_modal_mailbox.set(Some(reset_address.clone()))
+                        >
+                            <Icon icon=icondata::FiLink class="w-5 h-5"/>
+                        </button>
                         <button
                             class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-l-0 border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-r-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
                             on:click=move |_| {
@@ -401,14 +556,18 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
     };
 
     let has_password_mismatch = move || edit_modal_input_password() != edit_modal_input_password_repeat();
-    let has_invalid_password = create_memo(move |_| {
+    let password_policy = use_password_policy();
+    let password_problems = create_memo(move |_| {
         // Either we edit an existing mailbox (in which case an empty password means no change)
-        // or the password is of correct length.
+        // or the password complies with the password policy.
         let is_new = matches!(edit_modal_mailbox.get(), Some(None));
-        let is_valid_pw = is_valid_pw(&edit_modal_input_password());
-        let valid = is_valid_pw || (!is_new && edit_modal_input_password().is_empty());
-        !valid
+        if !is_new && edit_modal_input_password().is_empty() {
+            return Vec::new();
+        }
+        let address = format!("{}@{}", edit_modal_input_localpart(), edit_modal_input_domain());
+        password_policy().problems(&edit_modal_input_password(), &address)
     });
+    let has_invalid_password = move || !password_problems().is_empty();
     let has_invalid_address = create_memo(move |_| {
         validate_address(
             &edit_modal_input_localpart(),
@@ -429,9 +588,7 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
         if has_password_mismatch() {
             errors.push("Passwords don't match".to_string());
         }
-        if has_invalid_password() {
-            errors.push("Password must be between 12 and 512 characters".to_string());
-        }
+        errors.extend(password_problems());
         errors
     });
 
@@ -485,6 +642,8 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
             </div>
         </div>
 
+        <PasswordResetModal username=reset_modal_mailbox/>
+
         <DeleteModal
             data=delete_modal_mailbox
             text="Are you sure you want to delete this mailbox? This action cannot be undone.".into_view()
@@ -567,6 +726,7 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
                     on:input=move |ev| set_edit_modal_input_password(event_target_value(&ev))
                     prop:value=edit_modal_input_password
                 />
+                <PasswordStrength password=edit_modal_input_password/>
             </div>
             <div class="flex flex-col gap-2">
                 <label
@@ -599,7 +759,7 @@ pub fn Mailboxes(user: User, reload_stats: Callback<()>) -> impl IntoView {
                     placeholder=user.username.clone()
                     on:input=move |ev| set_edit_modal_input_owner(event_target_value(&ev))
                     prop:value=edit_modal_input_owner
-                    disabled=!user.admin
+                    disabled=!user.can(Permission::MailboxesWrite)
                 />
             </div>
             <div class="flex flex-row gap-2 mt-2 items-center">
@@ -1,26 +1,37 @@
-use anyhow::{anyhow, Result};
+use anyhow::{Context, Result};
 use axum::{
     body::Body as AxumBody,
     extract::{Path, State},
     http::Request,
+    middleware,
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::get,
     Router,
 };
 use axum_session::{SessionConfig, SessionLayer, SessionStore};
 use axum_session_auth::{AuthConfig, AuthSessionLayer};
 use axum_session_sqlx::SessionSqlitePool;
 use idmail::{
+    aliases::ssr::spawn_schedule_worker,
+    api::{fastmail, openapi},
+    api_tokens::ssr::migrate_legacy_tokens,
     app::App,
     auth::{ssr::AuthSession, User},
+    client_ip, csrf,
     fileserv::file_and_error_handler,
+    limits::Limits,
+    lockouts, oidc, passkeys, password_policy, passwords,
     provision::provision,
+    proxy_auth, sessions,
     state::AppState,
+    totp,
+    webhooks::ssr::spawn_delivery_worker,
 };
 use leptos::{get_configuration, provide_context};
 use leptos_axum::{generate_route_list, handle_server_fns_with_context, LeptosRoutes};
 use log::{info, warn};
 use sqlx::{sqlite::SqliteConnectOptions, QueryBuilder, SqlitePool};
+use std::net::SocketAddr;
 
 async fn server_fn_handler(
     State(app_state): State<AppState>,
@@ -66,17 +77,34 @@ async fn connect(filename: impl AsRef<std::path::Path>) -> Result<sqlx::Pool<sql
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt().without_time().init();
+    Limits::init()?;
+    lockouts::ssr::init()?;
+    passwords::init()?;
+    password_policy::ssr::init()?;
+    totp::ssr::init()?;
+    passkeys::ssr::init()?;
+    oidc::ssr::init()?;
+    proxy_auth::init()?;
+    client_ip::init()?;
+    csrf::init()?;
+    sessions::ssr::init()?;
 
     let pool = connect("idmail.db").await?;
 
     // Auth section
-    let session_config = SessionConfig::default().with_table_name("axum_sessions");
+    // Sessions are only valid while they exist in `user_sessions`, which enforces the timeouts
+    let session_config = sessions::ssr::with_cookie_config(SessionConfig::default())
+        .with_table_name("axum_sessions")
+        .with_lifetime(sessions::ssr::idle_timeout())
+        .with_max_lifetime(sessions::ssr::max_lifetime())
+        .with_max_age(Some(sessions::ssr::max_lifetime()));
     // Disable user caching
     let auth_config = AuthConfig::<String>::default().set_cache(false);
     let session_store =
         SessionStore::<SessionSqlitePool>::new(Some(SessionSqlitePool::from(pool.clone())), session_config).await?;
 
     sqlx::migrate!().run(&pool).await?;
+    migrate_legacy_tokens(&pool).await?;
 
     // Provisioning
     provision(&pool).await?;
@@ -94,8 +122,8 @@ async fn main() -> Result<()> {
         getrandom::getrandom(&mut buf)?;
         let password = hex::encode(buf);
 
-        let password_hash = idmail::users::mk_password_hash(&password)
-            .map_err(|e| anyhow!("failed to hash password for admin user: {e}"))?;
+        // The random password is not checked against the password policy, which could otherwise prevent the recovery
+        let password_hash = passwords::hash(&password).context("failed to hash password for admin user")?;
         sqlx::query("INSERT INTO users (username, password_hash, admin) VALUES ('admin', ?, TRUE)")
             .bind(password_hash)
             .execute(&pool)
@@ -105,36 +133,56 @@ async fn main() -> Result<()> {
         warn!("created admin user with password '{password}'");
     }
 
+    spawn_delivery_worker(pool.clone())?;
+    spawn_schedule_worker(pool.clone());
+
     // Setting this to None means we'll be using cargo-leptos and its env vars
     let conf = get_configuration(None).await?;
     let leptos_options = conf.leptos_options;
     let addr = leptos_options.site_addr;
     let routes = generate_route_list(App);
 
+    let mut signing_key = [0u8; 32];
+    getrandom::getrandom(&mut signing_key)?;
+
     let app_state = AppState {
         leptos_options,
         pool: pool.clone(),
         routes: routes.clone(),
+        signing_key,
+        rate_limiter: Default::default(),
     };
 
     // build our application with a route
     let app = Router::new()
         .route("/api/*fn_name", get(server_fn_handler).post(server_fn_handler))
-        .route("/api/alias/random/new", post(idmail::api::create_simple_login))
-        .route("/api/v1/aliases", post(idmail::api::create_addy_io))
+        .route("/auth/oidc/login", get(oidc::ssr::login))
+        .route("/auth/oidc/callback", get(oidc::ssr::callback))
+        .merge(openapi::router())
+        // The well-known location of the documented JMAP session
+        .route("/.well-known/jmap", get(fastmail::session))
         .leptos_routes_with_handler(routes, get(leptos_routes_handler))
         .fallback(file_and_error_handler)
+        // Must run after the auth session was loaded, so it is added before the session layers
+        .layer(middleware::from_fn_with_state(pool.clone(), proxy_auth::middleware))
+        .layer(middleware::from_fn_with_state(
+            pool.clone(),
+            sessions::ssr::binding_middleware,
+        ))
         .layer(
             AuthSessionLayer::<User, String, SessionSqlitePool, SqlitePool>::new(Some(pool.clone()))
                 .with_config(auth_config),
         )
         .layer(SessionLayer::new(session_store))
+        // Runs first, so that cross-site requests never touch a session
+        .layer(middleware::from_fn(csrf::middleware))
         .with_state(app_state);
 
     // run our app with hyper
     // `axum::Server` is a re-export of `hyper::Server`
     info!("listening on http://{addr}");
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app.into_make_service()).await?;
+    // The client address is recorded when API tokens are used
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
     Ok(())
 }
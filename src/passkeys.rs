@@ -0,0 +1,1157 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::utils::{DeleteModal, EditModal};
+use crate::utils::{OptionalTimediffRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::{ev::MouseEvent, logging::error, *};
+use leptos_icons::Icon;
+use leptos_struct_table::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct Passkey {
+    #[table(skip)]
+    pub id: i64,
+    pub name: String,
+    #[table(class = "w-1", title = "Last used", renderer = "OptionalTimediffRenderer")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PasskeyQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+/// A new credential as created by the browser. Binary fields are base64url encoded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasskeyRegistration {
+    /// The credential id
+    pub id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    /// The DER encoded public key, missing if the browser doesn't know the algorithm
+    pub public_key: Option<String>,
+    /// The COSE algorithm identifier of the public key
+    pub algorithm: i64,
+}
+
+/// A login challenge signed by a passkey in the browser. Binary fields are base64url encoded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasskeyAssertion {
+    /// The credential id
+    pub id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{Passkey, PasskeyAssertion, PasskeyRegistration};
+    use crate::{api::ApiError, auth::User};
+    use anyhow::{Context, Result};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use p256::{ecdsa::signature::Verifier, pkcs8::DecodePublicKey};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::{ops::Range, sync::OnceLock};
+
+    static RELYING_PARTY: OnceLock<Option<RelyingParty>> = OnceLock::new();
+
+    /// Session key of the challenge for adding a passkey
+    pub const REGISTRATION_CHALLENGE_KEY: &str = "passkey_registration";
+    /// Session key of the challenge for logging in with a passkey
+    pub const LOGIN_CHALLENGE_KEY: &str = "passkey_login";
+    /// How long the browser may take to answer a challenge, in seconds
+    const CHALLENGE_VALIDITY: i64 = 300;
+    const MAX_PASSKEYS: i64 = 32;
+    const RP_NAME: &str = "idmail";
+
+    /// The supported COSE algorithms, in order of preference
+    const ES256: i64 = -7;
+    const EDDSA: i64 = -8;
+    const RS256: i64 = -257;
+
+    const FLAG_USER_PRESENT: u8 = 0x01;
+    const FLAG_USER_VERIFIED: u8 = 0x04;
+    const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+
+    /// Reads the origin that passkeys are bound to from the environment. Passkeys are disabled without it,
+    /// since the origin of a request is chosen by the client. Must be called once on startup.
+    pub fn init() -> Result<()> {
+        let rp = match std::env::var("IDMAIL_WEBAUTHN_ORIGIN") {
+            Ok(value) => {
+                let origin = value.trim().trim_end_matches('/').to_string();
+                let id = relying_party_id(&origin)
+                    .context(format!("Invalid value for IDMAIL_WEBAUTHN_ORIGIN: '{value}'"))?;
+                Some(RelyingParty { id, origin })
+            }
+            Err(_) => None,
+        };
+
+        match &rp {
+            Some(rp) => log::info!("Passkey origin: {}", rp.origin),
+            None => log::info!("Passkeys are disabled, set IDMAIL_WEBAUTHN_ORIGIN to enable them"),
+        }
+        let _ = RELYING_PARTY.set(rp);
+        Ok(())
+    }
+
+    /// Whether passkeys can be used, which requires a configured origin.
+    pub fn enabled() -> bool {
+        RELYING_PARTY.get().is_some_and(Option::is_some)
+    }
+
+    /// Returns the host of an origin like `https://example.com`, which identifies the relying party.
+    fn relying_party_id(origin: &str) -> Option<String> {
+        let uri = origin.parse::<http::Uri>().ok()?;
+        if !matches!(uri.scheme_str(), Some("https" | "http")) {
+            return None;
+        }
+        uri.host().map(|x| x.to_lowercase())
+    }
+
+    /// The site that passkeys are bound to
+    pub struct RelyingParty {
+        id: String,
+        origin: String,
+    }
+
+    /// Returns the configured relying party, or fails if passkeys are disabled.
+    pub fn relying_party() -> Result<&'static RelyingParty, ApiError> {
+        RELYING_PARTY
+            .get()
+            .and_then(Option::as_ref)
+            .ok_or_else(|| ApiError::BadRequest("Passkeys are not enabled on this server".to_string()))
+    }
+
+    /// A challenge that was sent to the browser and can be answered once
+    #[derive(Serialize, Deserialize)]
+    pub struct Challenge {
+        challenge: String,
+        /// The account that must answer the challenge, if it is already known
+        username: Option<String>,
+        expires_at: i64,
+    }
+
+    impl Challenge {
+        pub fn new(username: Option<String>) -> Result<Self, ApiError> {
+            let mut buf = [0u8; 32];
+            getrandom::getrandom(&mut buf).map_err(|e| ApiError::ServerError(e.to_string()))?;
+            Ok(Self {
+                challenge: URL_SAFE_NO_PAD.encode(buf),
+                username,
+                expires_at: chrono::Utc::now().timestamp() + CHALLENGE_VALIDITY,
+            })
+        }
+    }
+
+    /// The parts of the client data that the browser signs along with the authenticator data
+    #[derive(Deserialize)]
+    struct ClientData {
+        #[serde(rename = "type")]
+        kind: String,
+        challenge: String,
+        origin: String,
+        #[serde(default, rename = "crossOrigin")]
+        cross_origin: bool,
+    }
+
+    /// Ensures that the client data answers the challenge for this site.
+    fn verify_client_data(
+        client_data: &[u8],
+        kind: &str,
+        rp: &RelyingParty,
+        challenge: &Challenge,
+    ) -> Result<(), ApiError> {
+        let client_data = serde_json::from_slice::<ClientData>(client_data)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid client data: {e}")))?;
+
+        if challenge.expires_at < chrono::Utc::now().timestamp() {
+            return Err(ApiError::BadRequest(
+                "The passkey request has expired, please try again".to_string(),
+            ));
+        }
+        if client_data.kind != kind || client_data.challenge != challenge.challenge {
+            return Err(ApiError::BadRequest("Invalid challenge".to_string()));
+        }
+        if client_data.origin != rp.origin || client_data.cross_origin {
+            return Err(ApiError::BadRequest(format!("Invalid origin '{}'", client_data.origin)));
+        }
+
+        Ok(())
+    }
+
+    /// The authenticator data as defined by the WebAuthn specification
+    struct AuthenticatorData<'a> {
+        rp_id_hash: &'a [u8],
+        flags: u8,
+        sign_count: u32,
+        /// The id of a newly created credential
+        credential_id: Option<&'a [u8]>,
+    }
+
+    impl<'a> AuthenticatorData<'a> {
+        fn parse(data: &'a [u8]) -> Result<Self, ApiError> {
+            let invalid = || ApiError::BadRequest("Invalid authenticator data".to_string());
+            if data.len() < 37 {
+                return Err(invalid());
+            }
+
+            let flags = data[32];
+            let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+            let credential_id = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+                // The credential id follows the AAGUID and its own length
+                let len = data.get(53..55).ok_or_else(invalid)?;
+                let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+                Some(data.get(55..55 + len).ok_or_else(invalid)?)
+            } else {
+                None
+            };
+
+            Ok(Self {
+                rp_id_hash: &data[..32],
+                flags,
+                sign_count,
+                credential_id,
+            })
+        }
+
+        /// Ensures that the data was created for this site and the user interacted with the authenticator.
+        fn verify(&self, rp: &RelyingParty, require_user_verification: bool) -> Result<(), ApiError> {
+            if self.rp_id_hash != Sha256::digest(rp.id.as_bytes()).as_slice() {
+                return Err(ApiError::BadRequest(
+                    "The passkey belongs to a different site".to_string(),
+                ));
+            }
+            if self.flags & FLAG_USER_PRESENT == 0 {
+                return Err(ApiError::BadRequest("User presence was not confirmed".to_string()));
+            }
+            if require_user_verification && self.flags & FLAG_USER_VERIFIED == 0 {
+                return Err(ApiError::BadRequest(
+                    "The passkey did not verify the user, e.g. by PIN or biometrics".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    enum PublicKey {
+        Es256(p256::ecdsa::VerifyingKey),
+        EdDsa(ed25519_dalek::VerifyingKey),
+        Rs256(rsa::pkcs1v15::VerifyingKey<Sha256>),
+    }
+
+    impl PublicKey {
+        fn from_der(algorithm: i64, der: &[u8]) -> Option<Self> {
+            match algorithm {
+                ES256 => p256::ecdsa::VerifyingKey::from_public_key_der(der)
+                    .ok()
+                    .map(Self::Es256),
+                EDDSA => ed25519_dalek::VerifyingKey::from_public_key_der(der)
+                    .ok()
+                    .map(Self::EdDsa),
+                RS256 => rsa::RsaPublicKey::from_public_key_der(der)
+                    .ok()
+                    .map(|x| Self::Rs256(rsa::pkcs1v15::VerifyingKey::new(x))),
+                _ => None,
+            }
+        }
+
+        fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+            match self {
+                Self::Es256(key) => p256::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|signature| key.verify(message, &signature).is_ok()),
+                Self::EdDsa(key) => ed25519_dalek::Signature::from_slice(signature)
+                    .is_ok_and(|signature| key.verify(message, &signature).is_ok()),
+                Self::Rs256(key) => rsa::pkcs1v15::Signature::try_from(signature)
+                    .is_ok_and(|signature| key.verify(message, &signature).is_ok()),
+            }
+        }
+    }
+
+    fn decode(value: &str) -> Result<Vec<u8>, ApiError> {
+        URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|_| ApiError::BadRequest("Invalid base64url encoding".to_string()))
+    }
+
+    async fn credential_ids(pool: &SqlitePool, username: &str) -> Result<Vec<String>, ApiError> {
+        Ok(
+            sqlx::query_scalar::<_, String>("SELECT credential_id FROM passkeys WHERE owner = ?")
+                .bind(username)
+                .fetch_all(pool)
+                .await?,
+        )
+    }
+
+    /// Returns the options for `navigator.credentials.create()` to add a passkey to the account.
+    pub async fn registration_options(
+        pool: &SqlitePool,
+        user: &User,
+        rp: &RelyingParty,
+        challenge: &Challenge,
+    ) -> Result<String, ApiError> {
+        let existing = credential_ids(pool, &user.username).await?;
+        if existing.len() as i64 >= MAX_PASSKEYS {
+            return Err(ApiError::Conflict(format!(
+                "An account can have at most {MAX_PASSKEYS} passkeys"
+            )));
+        }
+
+        Ok(json!({
+            "challenge": challenge.challenge,
+            "rp": { "id": rp.id, "name": RP_NAME },
+            "user": {
+                // An opaque handle, so the authenticator replaces an older passkey of the same account
+                "id": URL_SAFE_NO_PAD.encode(Sha256::digest(user.username.as_bytes())),
+                "name": user.username,
+                "displayName": user.username,
+            },
+            "pubKeyCredParams": ([ES256, EDDSA, RS256].map(|alg| json!({ "type": "public-key", "alg": alg }))),
+            "excludeCredentials": existing.iter().map(|id| json!({ "type": "public-key", "id": id })).collect::<Vec<_>>(),
+            "authenticatorSelection": { "residentKey": "preferred", "userVerification": "preferred" },
+            "attestation": "none",
+            "timeout": CHALLENGE_VALIDITY * 1000,
+        })
+        .to_string())
+    }
+
+    /// Verifies a new credential created for the challenge and adds it to the account.
+    pub async fn register(
+        pool: &SqlitePool,
+        user: &User,
+        rp: &RelyingParty,
+        challenge: Challenge,
+        name: &str,
+        credential: &PasskeyRegistration,
+    ) -> Result<(), ApiError> {
+        let name = name.trim();
+        if name.is_empty() || name.len() > 64 {
+            return Err(ApiError::BadRequest(
+                "Passkey name must be between 1 and 64 characters".to_string(),
+            ));
+        }
+        if challenge.username.as_deref() != Some(&user.username) {
+            return Err(ApiError::BadRequest("Invalid challenge".to_string()));
+        }
+
+        verify_client_data(
+            &decode(&credential.client_data_json)?,
+            "webauthn.create",
+            rp,
+            &challenge,
+        )?;
+        let authenticator_data = decode(&credential.authenticator_data)?;
+        let authenticator_data = AuthenticatorData::parse(&authenticator_data)?;
+        authenticator_data.verify(rp, false)?;
+
+        let credential_id = authenticator_data
+            .credential_id
+            .ok_or_else(|| ApiError::BadRequest("Missing credential".to_string()))?;
+        if URL_SAFE_NO_PAD.encode(credential_id) != credential.id {
+            return Err(ApiError::BadRequest("Mismatching credential id".to_string()));
+        }
+
+        let unsupported = || ApiError::BadRequest("The passkey uses an unsupported algorithm".to_string());
+        let public_key = decode(credential.public_key.as_deref().ok_or_else(unsupported)?)?;
+        PublicKey::from_der(credential.algorithm, &public_key).ok_or_else(unsupported)?;
+
+        sqlx::query(
+            "INSERT INTO passkeys (owner, name, credential_id, public_key, algorithm, sign_count) \
+            VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&user.username)
+        .bind(name)
+        .bind(&credential.id)
+        .bind(public_key)
+        .bind(credential.algorithm)
+        .bind(authenticator_data.sign_count as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                ApiError::Conflict("This passkey is already registered".to_string())
+            }
+            e => e.into(),
+        })?;
+        log::info!("added passkey '{name}' for '{}'", user.username);
+
+        Ok(())
+    }
+
+    /// Returns the options for `navigator.credentials.get()` to log in with a passkey.
+    /// Without a known account, any discoverable passkey can be used but it must verify the user.
+    pub async fn authentication_options(
+        pool: &SqlitePool,
+        rp: &RelyingParty,
+        challenge: &Challenge,
+    ) -> Result<String, ApiError> {
+        let allowed = match &challenge.username {
+            Some(username) => {
+                let ids = credential_ids(pool, username).await?;
+                if ids.is_empty() {
+                    return Err(ApiError::NotFound(
+                        "No passkeys are registered for this account".to_string(),
+                    ));
+                }
+                ids
+            }
+            None => Vec::new(),
+        };
+
+        Ok(json!({
+            "challenge": challenge.challenge,
+            "rpId": rp.id,
+            "allowCredentials": allowed.iter().map(|id| json!({ "type": "public-key", "id": id })).collect::<Vec<_>>(),
+            "userVerification": if challenge.username.is_some() { "preferred" } else { "required" },
+            "timeout": CHALLENGE_VALIDITY * 1000,
+        })
+        .to_string())
+    }
+
+    #[derive(sqlx::FromRow)]
+    struct StoredPasskey {
+        id: i64,
+        owner: String,
+        public_key: Vec<u8>,
+        algorithm: i64,
+    }
+
+    /// Verifies the answer to a login challenge and returns the account it belongs to.
+    pub async fn authenticate(
+        pool: &SqlitePool,
+        rp: &RelyingParty,
+        challenge: Challenge,
+        assertion: &PasskeyAssertion,
+    ) -> Result<String, ApiError> {
+        let invalid = || ApiError::Unauthorized("Invalid passkey".to_string());
+        let passkey = sqlx::query_as::<_, StoredPasskey>(
+            "SELECT id, owner, public_key, algorithm FROM passkeys WHERE credential_id = ?",
+        )
+        .bind(&assertion.id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(invalid)?;
+        if challenge.username.as_ref().is_some_and(|x| *x != passkey.owner) {
+            return Err(invalid());
+        }
+
+        let client_data = decode(&assertion.client_data_json)?;
+        verify_client_data(&client_data, "webauthn.get", rp, &challenge)?;
+        let raw_authenticator_data = decode(&assertion.authenticator_data)?;
+        let authenticator_data = AuthenticatorData::parse(&raw_authenticator_data)?;
+        authenticator_data.verify(rp, challenge.username.is_none())?;
+
+        let public_key = PublicKey::from_der(passkey.algorithm, &passkey.public_key)
+            .ok_or_else(|| ApiError::ServerError(format!("invalid public key of passkey {}", passkey.id)))?;
+        let mut message = raw_authenticator_data.clone();
+        message.extend_from_slice(&Sha256::digest(&client_data));
+        if !public_key.verify(&message, &decode(&assertion.signature)?) {
+            log::warn!("invalid passkey signature for user '{}'", passkey.owner);
+            return Err(invalid());
+        }
+
+        // Authenticators without a signature counter always report zero,
+        // otherwise it must increase or the authenticator may have been cloned.
+        let sign_count = authenticator_data.sign_count as i64;
+        let updated = sqlx::query(
+            "UPDATE passkeys SET sign_count = ?1, last_used_at = CURRENT_TIMESTAMP \
+            WHERE id = ?2 AND (sign_count < ?1 OR (sign_count = 0 AND ?1 = 0))",
+        )
+        .bind(sign_count)
+        .bind(passkey.id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            log::warn!(
+                "passkey {} of user '{}' reported a stale signature counter, it may have been cloned",
+                passkey.id,
+                passkey.owner
+            );
+            return Err(invalid());
+        }
+
+        Ok(passkey.owner)
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<Passkey>, ApiError> {
+        let mut query = QueryBuilder::new("SELECT id, name, last_used_at, created_at FROM passkeys");
+        query.push(" WHERE owner = ");
+        query.push_bind(&user.username);
+
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<Passkey>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM passkeys WHERE owner = ?")
+            .bind(&user.username)
+            .fetch_one(pool)
+            .await?;
+        Ok(count as usize)
+    }
+
+    pub async fn delete(pool: &SqlitePool, user: &User, id: i64) -> Result<(), ApiError> {
+        let mut query = QueryBuilder::new("DELETE FROM passkeys WHERE id = ");
+        query.push_bind(id);
+        query.push(" AND owner = ");
+        query.push_bind(&user.username);
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("Passkey {id} does not exist")));
+        }
+        log::info!("removed passkey {id} of '{}'", user.username);
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use sqlx::sqlite::SqlitePoolOptions;
+
+        // Assertions of the challenge 0x00..0x1f on https://idmail.example.com with a signature counter of 7,
+        // signed with fixed keys by an independent WebAuthn implementation
+        const CHALLENGE: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8";
+        const CLIENT_DATA: &str = "eyJ0eXBlIjoid2ViYXV0aG4uZ2V0IiwiY2hhbGxlbmdlIjoiQUFFQ0F3UUZCZ2NJQ1FvTERBME9EeEFSRWhNVUZSWVhHQmthR3h3ZEhoOCIsIm9yaWdpbiI6Imh0dHBzOi8vaWRtYWlsLmV4YW1wbGUuY29tIiwiY3Jvc3NPcmlnaW4iOmZhbHNlfQ";
+        const AUTHENTICATOR_DATA: &str = "CXE7wR5rOdQMiWFc2TuL4JQ_m7b0ezmL1m_8fnhn4BgFAAAABw";
+        const ES256_KEY: &str = "MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEn62Erq4Iu-9_AQAU2CzvagneKwz4cbXODE8dE6WaWTQHy0V2nxBw4sJHD-Wxv-YxM8CwzcZOpL83kajsKgf9Tw";
+        const ES256_SIGNATURE: &str =
+            "MEUCIQDTiBDIuCelhLHz39K4gobiTKfH7UTXAfOBDd-qRqyt6gIgED7ig9hQzWl_gQ4fKtcLB2Yd-3Eqj0M1ARnbNMPIq2Y";
+        const EDDSA_KEY: &str = "MCowBQYDK2VwAyEA6kpsY-KcUgq-9VB7Ey7F-ZVHdq6-vnuSQh7qaRRG0iw";
+        const EDDSA_SIGNATURE: &str =
+            "7t5QggOQvFHCOBtrOQ4AiuxkXB2IN3F_JfMrrBaUB78zUX9urCx1SClrloMY2YbeyWptxOaU3gbbhyQLW9fxDA";
+        const RS256_KEY: &str = "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAm23pf_Hk8Q74Nj6KcodCZdzBV_Bfq-nF7fddCB-_Op8Acc6DyAZY6L6UIeL6PIfYg1uuXdF8IqxyTzHSDVWF9-UI7gNiUQPte_ZkDPxJELbxkKE7lmbIa1Sb_KWv3vSg2Vc3hfW3CR8otRovYHR5iYpNyb0vsfS50HKPGt9onsbQjAGFhqKhdDUsElxNhwZVh5iib01TmkuK5stuTWUES8BOU-e0NNr5HRNYahWu2cjvafiVI36Spt30BrjJYB-iK_HFzxEp9vxMm9ZPWqcD7dzZr_jothVD-52oJ-wiifdFuTcPoxjxUX6LIjnTEoZuZDb72X5bJfXUazD8-pBXRwIDAQAB";
+        const RS256_SIGNATURE: &str = "jYMG5nYEY4GsVcEr8SQyAHdWSkqdp3QXhHjQhcMychfvAcCoE-JlXSY7jGVYwzUcxdjEsF_qGfDon7Vn5zWXtdZXfaiBNJz50Oe9NCxvnXmpFnnIfsv-2GOf3LE7rbeMAizN-n29NLo-MfC6is46Red2ombJb_sGa4WUSIUxSX25uVsL1a_vooSiHRTRfMM6zDIOGcpGzYMgvFQZyGlpEhtcvBzIp_GdAjHZzNIBbFXI4PeuQiJGJL99ZoAO8rPN3_b8pr4MZj44z7pAjGdACuM7RdmT_g2awWo16Pn4Z_DbkDUJ17gaPfvA-zCH4FSU52v__KdXGzEndlCJkfFGuQ";
+        /// Authenticator data of a registration with the credential id 0x64..0x73
+        const REGISTRATION_AUTHENTICATOR_DATA: &str = "CXE7wR5rOdQMiWFc2TuL4JQ_m7b0ezmL1m_8fnhn4BhFAAAAAAAAAAAAAAAAAAAAAAAAAAAAEGRlZmdoaWprbG1ub3BxcnOkAQEDJyAGIVggAA";
+        const CREDENTIAL_ID: &str = "ZGVmZ2hpamtsbW5vcHFycw";
+
+        const OWNER: &str = "me@example.com";
+
+        fn rp() -> RelyingParty {
+            RelyingParty {
+                id: "idmail.example.com".to_string(),
+                origin: "https://idmail.example.com".to_string(),
+            }
+        }
+
+        fn challenge() -> Challenge {
+            Challenge {
+                challenge: CHALLENGE.to_string(),
+                username: None,
+                expires_at: chrono::Utc::now().timestamp() + CHALLENGE_VALIDITY,
+            }
+        }
+
+        fn assertion(signature: &str) -> PasskeyAssertion {
+            PasskeyAssertion {
+                id: CREDENTIAL_ID.to_string(),
+                client_data_json: CLIENT_DATA.to_string(),
+                authenticator_data: AUTHENTICATOR_DATA.to_string(),
+                signature: signature.to_string(),
+            }
+        }
+
+        /// Flips a bit in the last byte of a signature.
+        fn tamper(signature: &str) -> String {
+            let mut signature = decode(signature).unwrap();
+            *signature.last_mut().unwrap() ^= 1;
+            URL_SAFE_NO_PAD.encode(signature)
+        }
+
+        async fn pool_with_passkey(algorithm: i64, public_key: &str, sign_count: i64) -> SqlitePool {
+            let pool = SqlitePoolOptions::new()
+                .max_connections(1)
+                .connect("sqlite::memory:")
+                .await
+                .unwrap();
+            sqlx::migrate!().run(&pool).await.unwrap();
+            sqlx::query(
+                "INSERT INTO passkeys (owner, name, credential_id, public_key, algorithm, sign_count) \
+                VALUES (?, 'Test', ?, ?, ?, ?)",
+            )
+            .bind(OWNER)
+            .bind(CREDENTIAL_ID)
+            .bind(decode(public_key).unwrap())
+            .bind(algorithm)
+            .bind(sign_count)
+            .execute(&pool)
+            .await
+            .unwrap();
+            pool
+        }
+
+        const VECTORS: [(i64, &str, &str); 3] = [
+            (ES256, ES256_KEY, ES256_SIGNATURE),
+            (EDDSA, EDDSA_KEY, EDDSA_SIGNATURE),
+            (RS256, RS256_KEY, RS256_SIGNATURE),
+        ];
+
+        #[test]
+        fn parses_authenticator_data() {
+            let data = decode(AUTHENTICATOR_DATA).unwrap();
+            let data = AuthenticatorData::parse(&data).unwrap();
+            assert_eq!(data.rp_id_hash, Sha256::digest(b"idmail.example.com").as_slice());
+            assert_eq!(data.flags, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+            assert_eq!(data.sign_count, 7);
+            assert!(data.credential_id.is_none());
+            assert!(data.verify(&rp(), true).is_ok());
+        }
+
+        #[test]
+        fn parses_attested_credential() {
+            let data = decode(REGISTRATION_AUTHENTICATOR_DATA).unwrap();
+            let parsed = AuthenticatorData::parse(&data).unwrap();
+            assert_eq!(parsed.sign_count, 0);
+            assert_eq!(parsed.credential_id, Some(decode(CREDENTIAL_ID).unwrap().as_slice()));
+
+            // The credential id must fit into the data
+            assert!(AuthenticatorData::parse(&data[..60]).is_err());
+            assert!(AuthenticatorData::parse(&data[..54]).is_err());
+            assert!(AuthenticatorData::parse(&data[..36]).is_err());
+        }
+
+        #[test]
+        fn rejects_wrong_rp_id_hash() {
+            let data = decode(AUTHENTICATOR_DATA).unwrap();
+            let data = AuthenticatorData::parse(&data).unwrap();
+            let other = RelyingParty {
+                id: "example.com".to_string(),
+                origin: "https://example.com".to_string(),
+            };
+            assert!(data.verify(&other, false).is_err());
+        }
+
+        #[test]
+        fn verifies_signatures() {
+            let mut message = decode(AUTHENTICATOR_DATA).unwrap();
+            message.extend_from_slice(&Sha256::digest(decode(CLIENT_DATA).unwrap()));
+            for (algorithm, key, signature) in VECTORS {
+                let key = PublicKey::from_der(algorithm, &decode(key).unwrap()).unwrap();
+                assert!(
+                    key.verify(&message, &decode(signature).unwrap()),
+                    "algorithm {algorithm}"
+                );
+                assert!(
+                    !key.verify(&message, &decode(&tamper(signature)).unwrap()),
+                    "algorithm {algorithm}"
+                );
+                assert!(
+                    !key.verify(&message[1..], &decode(signature).unwrap()),
+                    "algorithm {algorithm}"
+                );
+            }
+        }
+
+        #[test]
+        fn rejects_keys_of_other_algorithms() {
+            assert!(PublicKey::from_der(ES256, &decode(EDDSA_KEY).unwrap()).is_none());
+            assert!(PublicKey::from_der(RS256, &decode(ES256_KEY).unwrap()).is_none());
+            assert!(PublicKey::from_der(-35, &decode(ES256_KEY).unwrap()).is_none());
+        }
+
+        #[tokio::test]
+        async fn authenticates() {
+            for (algorithm, key, signature) in VECTORS {
+                let pool = pool_with_passkey(algorithm, key, 3).await;
+                let owner = authenticate(&pool, &rp(), challenge(), &assertion(signature)).await;
+                assert_eq!(owner.ok().as_deref(), Some(OWNER), "algorithm {algorithm}");
+            }
+        }
+
+        #[tokio::test]
+        async fn rejects_tampered_signature() {
+            for (algorithm, key, signature) in VECTORS {
+                let pool = pool_with_passkey(algorithm, key, 0).await;
+                let result = authenticate(&pool, &rp(), challenge(), &assertion(&tamper(signature))).await;
+                assert!(result.is_err(), "algorithm {algorithm}");
+            }
+        }
+
+        #[tokio::test]
+        async fn rejects_wrong_site() {
+            let pool = pool_with_passkey(ES256, ES256_KEY, 0).await;
+            // The browser signed a different origin, e.g. of a phishing site
+            let phishing = RelyingParty {
+                id: "idmail.example.com".to_string(),
+                origin: "https://idmail.example.org".to_string(),
+            };
+            assert!(authenticate(&pool, &phishing, challenge(), &assertion(ES256_SIGNATURE))
+                .await
+                .is_err());
+            // The authenticator signed for a different relying party id
+            let other = RelyingParty {
+                id: "example.com".to_string(),
+                origin: "https://idmail.example.com".to_string(),
+            };
+            assert!(authenticate(&pool, &other, challenge(), &assertion(ES256_SIGNATURE))
+                .await
+                .is_err());
+        }
+
+        #[tokio::test]
+        async fn rejects_other_challenge() {
+            let pool = pool_with_passkey(ES256, ES256_KEY, 0).await;
+            let mut other = challenge();
+            other.challenge = URL_SAFE_NO_PAD.encode([0u8; 32]);
+            assert!(authenticate(&pool, &rp(), other, &assertion(ES256_SIGNATURE))
+                .await
+                .is_err());
+
+            let mut expired = challenge();
+            expired.expires_at = chrono::Utc::now().timestamp() - 1;
+            assert!(authenticate(&pool, &rp(), expired, &assertion(ES256_SIGNATURE))
+                .await
+                .is_err());
+        }
+
+        #[tokio::test]
+        async fn rejects_counter_regression() {
+            // The assertion reports 7, so a stored counter of 7 or more means it was replayed or cloned
+            for stored in [7, 8] {
+                let pool = pool_with_passkey(ES256, ES256_KEY, stored).await;
+                assert!(authenticate(&pool, &rp(), challenge(), &assertion(ES256_SIGNATURE))
+                    .await
+                    .is_err());
+            }
+
+            let pool = pool_with_passkey(ES256, ES256_KEY, 6).await;
+            assert!(authenticate(&pool, &rp(), challenge(), &assertion(ES256_SIGNATURE))
+                .await
+                .is_ok());
+            assert!(authenticate(&pool, &rp(), challenge(), &assertion(ES256_SIGNATURE))
+                .await
+                .is_err());
+            let stored = sqlx::query_scalar::<_, i64>("SELECT sign_count FROM passkeys")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(stored, 7);
+        }
+    }
+}
+
+/// Calls the WebAuthn API of the browser, which exchanges binary data as `ArrayBuffer`s.
+#[cfg(feature = "hydrate")]
+mod browser {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen(inline_js = r#"
+const decode = (value) => Uint8Array.from(atob(value.replace(/-/g, "+").replace(/_/g, "/")), (x) => x.charCodeAt(0));
+const encode = (buffer) =>
+  btoa(String.fromCharCode(...new Uint8Array(buffer))).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+const fail = (e) => {
+  throw e && e.message ? e.message : String(e);
+};
+const ensureSupported = () => {
+  if (!window.PublicKeyCredential) {
+    throw "Passkeys are not supported by this browser or require a secure (https) connection";
+  }
+};
+
+export async function create_passkey(options) {
+  ensureSupported();
+  const publicKey = JSON.parse(options);
+  publicKey.challenge = decode(publicKey.challenge);
+  publicKey.user.id = decode(publicKey.user.id);
+  publicKey.excludeCredentials = publicKey.excludeCredentials.map((x) => ({ ...x, id: decode(x.id) }));
+  const credential = await navigator.credentials.create({ publicKey }).catch(fail);
+  const publicKeyDer = credential.response.getPublicKey();
+  return JSON.stringify({
+    id: credential.id,
+    client_data_json: encode(credential.response.clientDataJSON),
+    authenticator_data: encode(credential.response.getAuthenticatorData()),
+    public_key: publicKeyDer ? encode(publicKeyDer) : null,
+    algorithm: credential.response.getPublicKeyAlgorithm(),
+  });
+}
+
+export async function get_passkey(options) {
+  ensureSupported();
+  const publicKey = JSON.parse(options);
+  publicKey.challenge = decode(publicKey.challenge);
+  publicKey.allowCredentials = publicKey.allowCredentials.map((x) => ({ ...x, id: decode(x.id) }));
+  const credential = await navigator.credentials.get({ publicKey }).catch(fail);
+  return JSON.stringify({
+    id: credential.id,
+    client_data_json: encode(credential.response.clientDataJSON),
+    authenticator_data: encode(credential.response.authenticatorData),
+    signature: encode(credential.response.signature),
+  });
+}
+"#)]
+    extern "C" {
+        #[wasm_bindgen(catch)]
+        async fn create_passkey(options: &str) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        async fn get_passkey(options: &str) -> Result<JsValue, JsValue>;
+    }
+
+    fn to_string(value: JsValue) -> String {
+        value.as_string().unwrap_or_else(|| format!("{value:?}"))
+    }
+
+    pub async fn create(options: &str) -> Result<String, String> {
+        create_passkey(options).await.map(to_string).map_err(to_string)
+    }
+
+    pub async fn get(options: &str) -> Result<String, String> {
+        get_passkey(options).await.map(to_string).map_err(to_string)
+    }
+}
+
+#[cfg(not(feature = "hydrate"))]
+mod browser {
+    pub async fn create(_options: &str) -> Result<String, String> {
+        Err("Passkeys can only be used in the browser".to_string())
+    }
+
+    pub async fn get(_options: &str) -> Result<String, String> {
+        Err("Passkeys can only be used in the browser".to_string())
+    }
+}
+
+/// Returns whether passkeys are enabled, which requires the public origin of the server to be configured.
+#[server]
+pub async fn get_passkeys_enabled() -> Result<bool, ServerFnError> {
+    Ok(ssr::enabled())
+}
+
+#[server]
+pub async fn list_passkeys(query: PasskeyQuery) -> Result<Vec<Passkey>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let PasskeyQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, Passkey::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn passkey_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn delete_passkey(id: i64) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::delete(&pool, &user, id).await?)
+}
+
+/// Returns the options to create a new passkey in the browser.
+#[server]
+pub async fn start_passkey_registration() -> Result<String, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
+
+    let rp = ssr::relying_party()?;
+    let challenge = ssr::Challenge::new(Some(user.username.clone()))?;
+    let options = ssr::registration_options(&pool, &user, rp, &challenge).await?;
+    auth.session.set(ssr::REGISTRATION_CHALLENGE_KEY, challenge);
+    Ok(options)
+}
+
+#[server]
+pub async fn finish_passkey_registration(name: String, credential: PasskeyRegistration) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
+
+    let challenge = auth
+        .session
+        .get::<ssr::Challenge>(ssr::REGISTRATION_CHALLENGE_KEY)
+        .ok_or_else(|| ServerFnError::new("No passkey registration in progress"))?;
+    auth.session.remove(ssr::REGISTRATION_CHALLENGE_KEY);
+
+    let rp = ssr::relying_party()?;
+    Ok(ssr::register(&pool, &user, rp, challenge, &name, &credential).await?)
+}
+
+/// Returns the options to sign a login challenge in the browser. As a second factor,
+/// only the passkeys of the account whose password was just entered are allowed.
+#[server]
+pub async fn start_passkey_login(second_factor: bool) -> Result<String, ServerFnError> {
+    let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
+
+    let username = if second_factor {
+        let username = crate::auth::ssr::pending_login(&auth)
+            .ok_or_else(|| ServerFnError::new("Your login has expired, please log in again."))?;
+        Some(username)
+    } else {
+        None
+    };
+
+    let rp = ssr::relying_party()?;
+    let challenge = ssr::Challenge::new(username)?;
+    let options = ssr::authentication_options(&pool, rp, &challenge).await?;
+    auth.session.set(ssr::LOGIN_CHALLENGE_KEY, challenge);
+    Ok(options)
+}
+
+/// Logs in with a passkey, either instead of a password or as the second factor after it.
+#[server]
+pub async fn login_with_passkey(
+    credential: PasskeyAssertion,
+    #[server(default)] remember: bool,
+) -> Result<(), ServerFnError> {
+    let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
+
+    let challenge = auth
+        .session
+        .get::<ssr::Challenge>(ssr::LOGIN_CHALLENGE_KEY)
+        .ok_or_else(|| ServerFnError::new("Your login has expired, please try again."))?;
+    auth.session.remove(ssr::LOGIN_CHALLENGE_KEY);
+
+    let rp = ssr::relying_party()?;
+    let username = ssr::authenticate(&pool, rp, challenge, &credential).await?;
+    if !crate::auth::User::get(&username, &pool).await.is_some_and(|x| x.active) {
+        log::warn!("denying successful passkey login because user '{username}' is inactive");
+        return Err(ServerFnError::new("Invalid passkey"));
+    }
+
+    log::info!("passkey login successful for user '{username}'");
+    crate::auth::ssr::complete_login(&auth, &pool, username, remember).await?;
+    Ok(())
+}
+
+/// Creates a new passkey in the browser and adds it to the current account.
+async fn register_passkey(name: String) -> Result<(), String> {
+    let options = start_passkey_registration().await.map_err(|e| e.to_string())?;
+    let credential = browser::create(&options).await?;
+    let credential = serde_json::from_str::<PasskeyRegistration>(&credential).map_err(|e| e.to_string())?;
+    finish_passkey_registration(name, credential)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Signs a login challenge with a passkey in the browser.
+pub async fn sign_passkey_login(second_factor: bool) -> Result<PasskeyAssertion, ServerFnError> {
+    let options = start_passkey_login(second_factor).await?;
+    let assertion = browser::get(&options).await.map_err(ServerFnError::new)?;
+    serde_json::from_str::<PasskeyAssertion>(&assertion).map_err(ServerFnError::new)
+}
+
+#[derive(Default)]
+pub struct PasskeyTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<Passkey> for PasskeyTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<Passkey>, Range<usize>), String> {
+        list_passkeys(PasskeyQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        passkey_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+#[component]
+pub fn Passkeys() -> impl IntoView {
+    let mut rows = PasskeyTableDataProvider::default();
+    let default_sorting = VecDeque::from([(2, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload = create_trigger();
+    let reload_controller = ReloadController::default();
+    create_effect(move |_| {
+        reload.track();
+        reload_controller.reload();
+    });
+
+    let (count, set_count) = create_signal(0);
+
+    let delete_modal_passkey = create_rw_signal(None);
+    let delete_modal_passkey_id = create_rw_signal(None);
+    let edit_modal_passkey = create_rw_signal(None);
+
+    let (edit_modal_input_name, set_edit_modal_input_name) = create_signal("".to_string());
+    let edit_modal_open = move || {
+        edit_modal_passkey.set(Some(None::<Passkey>));
+        set_edit_modal_input_name("".to_string());
+    };
+
+    let on_edit = move |(_data, on_error): (Option<Passkey>, Callback<String>)| {
+        spawn_local(async move {
+            match register_passkey(edit_modal_input_name.get_untracked()).await {
+                Err(e) => on_error(e),
+                Ok(()) => {
+                    reload.notify();
+                    edit_modal_passkey.set(None);
+                }
+            }
+        });
+    };
+
+    #[allow(unused_variables, non_snake_case)]
+    let passkey_row_renderer = move |class: Signal<String>,
+                                     row: Passkey,
+                                     index: usize,
+                                     selected: Signal<bool>,
+                                     on_select: EventHandler<MouseEvent>,
+                                     on_change: EventHandler<ChangeEvent<Passkey>>| {
+        let delete_name = row.name.clone();
+        let delete_id = row.id;
+        view! {
+            <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                {row.render_row(index, on_change)}
+                <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                    <div class="inline-flex items-center rounded-md">
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            on:click=move |_| {
+                                delete_modal_passkey_id.set(Some(delete_id));
+                                delete_modal_passkey.set(Some(delete_name.clone()));
+                            }
+                        >
+
+                            <Icon icon=icondata::FiTrash2 class="w-5 h-5"/>
+                        </button>
+                    </div>
+                </td>
+            </tr>
+        }
+    };
+
+    let errors = create_memo(move |_| {
+        let mut errors = Vec::new();
+        let name = edit_modal_input_name();
+        if name.trim().is_empty() || name.trim().len() > 64 {
+            errors.push("Name must be between 1 and 64 characters".to_string());
+        }
+        errors
+    });
+
+    view! {
+        <div class="flex items-center justify-between space-y-2 mt-8 mb-4">
+            <h3 class="text-2xl font-bold">Passkeys</h3>
+        </div>
+        <div class="space-y-4">
+            <p class="text-sm text-gray-500 dark:text-gray-400">
+                "Passkeys let you log in without your password, using your device's screen lock or a security key. They can also replace the code from your authenticator app."
+            </p>
+            <div class="flex flex-wrap items-center justify-between">
+                <button
+                    type="button"
+                    class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-blue-600 dark:bg-blue-700 hover:bg-blue-500 dark:hover:bg-blue-600 focus:ring-blue-300 dark:focus:ring-blue-900"
+                    on:click=move |_| edit_modal_open()
+                >
+                    <Icon icon=icondata::FiPlus class="w-6 h-6 me-2"/>
+                    New
+                </button>
+                <div class="flex flex-1"></div>
+                <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                    {count} " passkeys"
+                </div>
+            </div>
+
+            <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                <div class="overflow-auto grow min-h-0">
+                    <table class="table-auto text-left w-full">
+                        <TableContent
+                            rows
+                            sorting=sorting
+                            sorting_mode=SortingMode::SingleColumn
+                            row_renderer=passkey_row_renderer
+                            reload_controller=reload_controller
+                            loading_row_display_limit=0
+                            on_row_count=set_count
+                        />
+                    </table>
+                </div>
+            </div>
+        </div>
+
+        <DeleteModal
+            data=delete_modal_passkey
+            text="Are you sure you want to remove this passkey? It can no longer be used to log in.".into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Some(id) = delete_modal_passkey_id.get_untracked() {
+                        if let Err(e) = delete_passkey(id).await {
+                            error!("Failed to delete passkey: {}", e);
+                        } else {
+                            reload.notify();
+                        }
+                    }
+                    delete_modal_passkey.set(None);
+                    delete_modal_passkey_id.set(None);
+                });
+            }
+        />
+
+        <EditModal
+            data=edit_modal_passkey
+            what="Passkey".to_string()
+            get_title=move |x| { &x.name }
+            on_confirm=on_edit
+            errors
+        >
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="name"
+                >
+                    Name
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="text"
+                    placeholder="Laptop"
+                    maxlength="64"
+                    on:input=move |ev| set_edit_modal_input_name(event_target_value(&ev))
+                    prop:value=edit_modal_input_name
+                />
+                <p class="text-sm text-gray-500 dark:text-gray-400">
+                    "Your browser will ask you to create the passkey after you confirm."
+                </p>
+            </div>
+        </EditModal>
+    }
+}
@@ -0,0 +1,354 @@
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+/// Passwords are never longer than this, to bound the cost of hashing them
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+/// Common words and keyboard patterns, which hardly make a password stronger
+const COMMON_WORDS: [&str; 24] = [
+    "password", "passwort", "qwerty", "qwertz", "azerty", "asdf", "yxcv", "zxcv", "letmein", "welcome", "admin",
+    "login", "iloveyou", "dragon", "monkey", "secret", "master", "sunshine", "princess", "football", "baseball",
+    "shadow", "trustno1", "abc123",
+];
+
+/// Names of the strength scores returned by [`strength`]
+pub const STRENGTH_NAMES: [&str; 5] = ["Very weak", "Weak", "Fair", "Strong", "Very strong"];
+
+/// The requirements for new passwords
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    /// The minimum number of characters
+    pub min_length: usize,
+    /// The minimum score of [`strength`], 0 allows any password
+    pub min_strength: u8,
+    /// Whether passwords must not contain the name of the account
+    pub disallow_username: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            min_strength: 0,
+            disallow_username: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Returns everything that is wrong with a new password of the given user or mailbox.
+    /// The breached password list is only checked on the server.
+    pub fn problems(&self, password: &str, username: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            problems.push(format!("Password must be at least {} characters", self.min_length));
+        }
+        if length > MAX_PASSWORD_LENGTH {
+            problems.push(format!("Password must be at most {MAX_PASSWORD_LENGTH} characters"));
+        }
+        if self.disallow_username && contains_username(password, username) {
+            problems.push("Password must not contain the username".to_string());
+        }
+        if strength(password) < self.min_strength {
+            problems.push(format!(
+                "Password is too weak, it must be at least {}",
+                STRENGTH_NAMES[self.min_strength.min(4) as usize].to_lowercase()
+            ));
+        }
+        problems
+    }
+}
+
+/// Whether the password contains the name of the account, or the local part of its address.
+fn contains_username(password: &str, username: &str) -> bool {
+    let password = password.to_lowercase();
+    let username = username.trim().to_lowercase();
+    let localpart = username.split('@').next().unwrap_or_default().to_string();
+    [username, localpart]
+        .iter()
+        .any(|x| x.chars().count() >= 3 && password.contains(x.as_str()))
+}
+
+/// Estimates the strength of a password from 0 (very weak) to 4 (very strong). Each character counts
+/// with the size of the character classes used in the password, except for repetitions, sequences like
+/// `abc` or `321` and common words, which count very little.
+pub fn strength(password: &str) -> u8 {
+    let chars = password.chars().collect::<Vec<_>>();
+    // Lowercased per character, as lowercasing the whole string can change its length
+    let lower = chars.iter().map(char::to_ascii_lowercase).collect::<Vec<_>>();
+
+    let mut pool = 0;
+    if chars.iter().any(char::is_ascii_lowercase) {
+        pool += 26;
+    }
+    if chars.iter().any(char::is_ascii_uppercase) {
+        pool += 26;
+    }
+    if chars.iter().any(char::is_ascii_digit) {
+        pool += 10;
+    }
+    if chars.iter().any(|x| x.is_ascii_punctuation() || *x == ' ') {
+        pool += 33;
+    }
+    if chars.iter().any(|x| !x.is_ascii()) {
+        pool += 100;
+    }
+    let bits_per_char = f64::from(pool.max(1)).log2();
+
+    let mut bits = 0.0;
+    let mut i = 0;
+    while i < chars.len() {
+        // A common word counts like a single, rather predictable choice
+        if let Some(word) = COMMON_WORDS.iter().find(|word| {
+            let word = word.chars().collect::<Vec<_>>();
+            lower[i..].starts_with(&word)
+        }) {
+            bits += 10.0;
+            i += word.chars().count();
+            continue;
+        }
+
+        let predictable =
+            (i >= 1 && (chars[i] as i64 - chars[i - 1] as i64).abs() <= 1) || (i >= 2 && chars[i] == chars[i - 2]);
+        bits += if predictable { 1.0 } else { bits_per_char };
+        i += 1;
+    }
+
+    match bits {
+        x if x < 30.0 => 0,
+        x if x < 45.0 => 1,
+        x if x < 60.0 => 2,
+        x if x < 80.0 => 3,
+        _ => 4,
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::PasswordPolicy;
+    use anyhow::{bail, ensure, Context, Result};
+    use sha1::{Digest, Sha1};
+    use std::{
+        cmp::Ordering,
+        fs::File,
+        io::{BufRead, BufReader, Seek, SeekFrom},
+        path::{Path, PathBuf},
+        sync::OnceLock,
+    };
+
+    static CONFIG: OnceLock<(PasswordPolicy, Option<PathBuf>)> = OnceLock::new();
+
+    fn env(name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Reads the password policy from the environment. Must be called once on startup.
+    pub fn init() -> Result<()> {
+        let defaults = PasswordPolicy::default();
+        let policy = PasswordPolicy {
+            min_length: match env("IDMAIL_PASSWORD_MIN_LENGTH") {
+                Some(x) => x
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|x| (1..=super::MAX_PASSWORD_LENGTH).contains(x))
+                    .context(format!("Invalid value for IDMAIL_PASSWORD_MIN_LENGTH: '{x}'"))?,
+                None => defaults.min_length,
+            },
+            min_strength: match env("IDMAIL_PASSWORD_MIN_STRENGTH") {
+                Some(x) => x
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|x| *x <= 4)
+                    .context(format!("Invalid value for IDMAIL_PASSWORD_MIN_STRENGTH: '{x}'"))?,
+                None => defaults.min_strength,
+            },
+            disallow_username: match env("IDMAIL_PASSWORD_DISALLOW_USERNAME") {
+                Some(x) => x
+                    .parse::<bool>()
+                    .context(format!("Invalid value for IDMAIL_PASSWORD_DISALLOW_USERNAME: '{x}'"))?,
+                None => defaults.disallow_username,
+            },
+        };
+
+        let breached_list = env("IDMAIL_PASSWORD_BREACHED_LIST").map(PathBuf::from);
+        if let Some(path) = &breached_list {
+            ensure!(
+                path.exists(),
+                "IDMAIL_PASSWORD_BREACHED_LIST '{}' does not exist",
+                path.display()
+            );
+        }
+
+        log::info!("Using password policy: {policy:?}, breached passwords: {breached_list:?}");
+        let _ = CONFIG.set((policy, breached_list));
+        Ok(())
+    }
+
+    pub fn policy() -> PasswordPolicy {
+        CONFIG.get().map(|x| x.0.clone()).unwrap_or_default()
+    }
+
+    /// Ensures that a new password of the given user or mailbox complies with the policy.
+    pub fn check(password: &str, username: &str) -> Result<(), String> {
+        if let Some(problem) = policy().problems(password, username).into_iter().next() {
+            return Err(problem);
+        }
+
+        if let Some(path) = CONFIG.get().and_then(|x| x.1.as_ref()) {
+            match is_breached(path, password) {
+                Ok(false) => (),
+                Ok(true) => return Err("Password appears in a list of breached passwords".to_string()),
+                // Better reject a password than letting a broken list go unnoticed
+                Err(e) => {
+                    log::error!("failed to check the breached password list: {e:#}");
+                    return Err("Password could not be checked, please try again later".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares the hash at the beginning of a line of a breached password list to the given one.
+    fn compare_line(line: &str, hash: &str) -> Ordering {
+        let line_hash = line.split(':').next().unwrap_or_default().trim();
+        line_hash.to_ascii_uppercase().as_str().cmp(hash)
+    }
+
+    /// Whether the password is in a breached password list as published by Have I Been Pwned. This is either
+    /// a single file of `HASH:COUNT` lines with SHA-1 hashes sorted in ascending order, or a directory of
+    /// range files named by the first five characters of the hash which contain `SUFFIX:COUNT` lines.
+    fn is_breached(path: &Path, password: &str) -> Result<bool> {
+        let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+
+        if path.is_dir() {
+            let (prefix, suffix) = hash.split_at(5);
+            let Some(range_file) = [path.join(prefix), path.join(format!("{prefix}.txt"))]
+                .into_iter()
+                .find(|x| x.exists())
+            else {
+                return Ok(false);
+            };
+            let content =
+                std::fs::read_to_string(&range_file).context(format!("failed to read '{}'", range_file.display()))?;
+            return Ok(content.lines().any(|x| compare_line(x, suffix) == Ordering::Equal));
+        }
+
+        // Binary search over the byte offsets of the file, looking at the first line starting at or after each offset
+        let mut reader = BufReader::new(File::open(path).context(format!("failed to open '{}'", path.display()))?);
+        let (mut low, mut high) = (0, reader.get_ref().metadata()?.len());
+        let mut line = String::new();
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let mut position = middle;
+            if middle > 0 {
+                // Skip the rest of the line that contains the byte before the middle
+                reader.seek(SeekFrom::Start(middle - 1))?;
+                let mut skipped = Vec::new();
+                position = middle - 1 + reader.read_until(b'\n', &mut skipped)? as u64;
+            } else {
+                reader.seek(SeekFrom::Start(0))?;
+            }
+
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                high = middle;
+                continue;
+            }
+            match compare_line(&line, &hash) {
+                Ordering::Less => low = position + read as u64,
+                Ordering::Equal => return Ok(true),
+                Ordering::Greater => high = middle,
+            }
+        }
+
+        if low > reader.get_ref().metadata()?.len() {
+            bail!("unexpected position in '{}'", path.display());
+        }
+        Ok(false)
+    }
+}
+
+/// Returns the requirements for new passwords. Does not require a login.
+#[server]
+pub async fn get_password_policy() -> Result<PasswordPolicy, ServerFnError> {
+    Ok(ssr::policy())
+}
+
+/// Loads the password policy, using the defaults until it is available.
+pub fn use_password_policy() -> Signal<PasswordPolicy> {
+    let policy = create_resource(|| (), |_| get_password_policy());
+    Signal::derive(move || policy.get().and_then(Result::ok).unwrap_or_default())
+}
+
+/// Shows the estimated strength of a password while it is typed
+#[component]
+pub fn PasswordStrength(#[prop(into)] password: Signal<String>) -> impl IntoView {
+    let score = move || strength(&password());
+    let color = move || match score() {
+        0 | 1 => "bg-red-500",
+        2 => "bg-yellow-500",
+        _ => "bg-green-500",
+    };
+
+    view! {
+        <Show when=move || !password().is_empty()>
+            <div class="flex flex-row items-center gap-2">
+                <div class="flex flex-1 flex-row gap-1">
+                    {(0..4)
+                        .map(|i| {
+                            view! {
+                                <div class=move || {
+                                    if score() > i {
+                                        format!("h-1.5 flex-1 rounded-full {}", color())
+                                    } else {
+                                        "h-1.5 flex-1 rounded-full bg-gray-200 dark:bg-zinc-800".to_string()
+                                    }
+                                }></div>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                <span class="text-xs font-medium text-gray-500 dark:text-gray-400 w-20 text-right">
+                    {move || STRENGTH_NAMES[score() as usize]}
+                </span>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_predictable_passwords_weak() {
+        for password in [
+            "password",
+            "PassWord123",
+            "aaaaaaaaaaaaaaaa",
+            "abcdefghijklmnop",
+            "qwertyqwerty",
+        ] {
+            assert_eq!(strength(password), 0, "{password}");
+        }
+    }
+
+    #[test]
+    fn rates_random_passwords_strong() {
+        assert_eq!(strength("correct horse battery staple"), 4);
+        assert_eq!(strength("xK9#mQ2$vL7@pR4!"), 4);
+        assert!(strength("Tr0ub4dor&3") >= 2);
+    }
+
+    #[test]
+    fn finds_common_words_after_characters_with_longer_lowercase() {
+        // 'İ' lowercases to two characters, which must not shift the position of the following ones
+        assert_eq!(strength("İİİİİİİİpassword"), 0);
+        assert_eq!(strength("İpasswordİ"), strength("İdragonİ"));
+    }
+}
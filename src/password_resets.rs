@@ -0,0 +1,732 @@
+#![allow(clippy::unused_unit)] // The TableRow derive generates unit expressions
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::auth::login_errors;
+use crate::password_policy::{use_password_policy, PasswordStrength};
+use crate::utils::{ColorModeToggle, DeleteModal, Modal, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::html::Dialog;
+use leptos::{ev::MouseEvent, logging::error, *};
+use leptos_icons::Icon;
+use leptos_router::use_query_map;
+use leptos_struct_table::*;
+use leptos_use::{use_timeout_fn, ColorMode, UseTimeoutFnReturn};
+use serde::{Deserialize, Serialize};
+
+/// How long a reset link can be used
+const RESET_LINK_VALIDITY_HOURS: u32 = 24;
+
+/// An issued password reset link
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct PasswordReset {
+    #[table(skip)]
+    pub id: i64,
+    #[table(title = "Account")]
+    pub username: String,
+    #[table(title = "Issued by")]
+    pub created_by: String,
+    /// One of "pending", "used" or "expired"
+    #[table(class = "w-1")]
+    pub status: String,
+    #[table(class = "w-1", title = "Expires", renderer = "TimediffRenderer")]
+    pub expires_at: DateTime<Utc>,
+    #[table(class = "w-1", title = "Used from", none_value = "-")]
+    pub used_ip: Option<String>,
+    #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PasswordResetQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{PasswordReset, RESET_LINK_VALIDITY_HOURS};
+    use crate::{api::ApiError, api_tokens::ssr::hash_token, auth::User, roles::Permission, users::mk_password_hash};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::{net::IpAddr, ops::Range};
+
+    const INVALID_LINK: &str = "This link is invalid or has expired";
+
+    /// Ensures that the user may issue a reset link for the given user or mailbox.
+    /// Admins may reset any password, those allowed to reset mailbox passwords those of all mailboxes,
+    /// and others only those of mailboxes they own or that are on domains they own or are a member of.
+    async fn ensure_can_reset(pool: &SqlitePool, user: &User, username: &str) -> Result<(), ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mailbox = sqlx::query_as::<_, (String, String)>("SELECT owner, domain FROM mailboxes WHERE address = ?")
+            .bind(username)
+            .fetch_optional(pool)
+            .await?;
+        if let Some((owner, domain)) = mailbox {
+            if user.can(Permission::MailboxesResetPassword)
+                || owner == user.username
+                || crate::domains::ssr::is_managed_by(pool, &domain, &user.username).await?
+            {
+                return Ok(());
+            }
+            return Err(ApiError::NotFound(format!("Mailbox '{username}' does not exist")));
+        }
+
+        user.ensure_admin()?;
+        let oidc_subject = sqlx::query_scalar::<_, Option<String>>("SELECT oidc_subject FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("User '{username}' does not exist")))?;
+        if oidc_subject.is_some() || !crate::oidc::ssr::password_login_allowed() {
+            return Err(ApiError::BadRequest(format!(
+                "User '{username}' signs in through single sign-on and has no password"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Issues a reset link for the given user or mailbox and returns its token. The plaintext token is not stored.
+    pub async fn create(pool: &SqlitePool, user: &User, username: &str) -> Result<String, ApiError> {
+        ensure_can_reset(pool, user, username).await?;
+
+        let mut buf = [0u8; 32];
+        getrandom::getrandom(&mut buf).map_err(|e| ApiError::ServerError(e.to_string()))?;
+        let token = hex::encode(buf);
+
+        sqlx::query(
+            "INSERT INTO password_resets (token_hash, username, created_by, expires_at) \
+            VALUES (?, ?, ?, datetime('now', ?))",
+        )
+        .bind(hash_token(&token))
+        .bind(username)
+        .bind(&user.username)
+        .bind(format!("+{RESET_LINK_VALIDITY_HOURS} hours"))
+        .execute(pool)
+        .await?;
+
+        log::info!("password reset link for '{username}' was issued by '{}'", user.username);
+        Ok(token)
+    }
+
+    /// The statement selecting the reset links visible to the user, which are all for those who
+    /// can see all users and the ones they issued for others.
+    fn select_visible<'a>(select: &str, user: &'a User) -> QueryBuilder<'a, sqlx::Sqlite> {
+        let mut query = QueryBuilder::new(select);
+        query.push(" FROM password_resets");
+        if !user.can(Permission::UsersRead) {
+            query.push(" WHERE created_by = ");
+            query.push_bind(&user.username);
+        }
+        query
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<PasswordReset>, ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mut query = select_visible(
+            "SELECT id, username, created_by, CASE WHEN used_at IS NOT NULL THEN 'used' \
+            WHEN expires_at <= CURRENT_TIMESTAMP THEN 'expired' ELSE 'pending' END AS status, \
+            expires_at, used_ip, created_at",
+            user,
+        );
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<PasswordReset>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        user.ensure_not_mailbox()?;
+        let count = select_visible("SELECT COUNT(*)", user)
+            .build_query_scalar::<i64>()
+            .fetch_one(pool)
+            .await?;
+        Ok(count as usize)
+    }
+
+    /// Makes an unused reset link unusable. The entry is kept as a record that it was issued.
+    /// Admins may revoke any link, those allowed to reset mailbox passwords all links for mailboxes,
+    /// and others only the links they issued.
+    pub async fn revoke(pool: &SqlitePool, user: &User, id: i64) -> Result<(), ApiError> {
+        user.ensure_not_mailbox()?;
+
+        let mut query = QueryBuilder::new("UPDATE password_resets SET expires_at = CURRENT_TIMESTAMP WHERE id = ");
+        query.push_bind(id);
+        query.push(" AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP");
+        if !user.admin {
+            query.push(" AND (created_by = ");
+            query.push_bind(&user.username);
+            if user.can(Permission::MailboxesResetPassword) {
+                query.push(" OR username IN (SELECT address FROM mailboxes)");
+            }
+            query.push(")");
+        }
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!(
+                "Reset link {id} does not exist or can no longer be used"
+            )));
+        }
+
+        log::info!("password reset link {id} was revoked by '{}'", user.username);
+        Ok(())
+    }
+
+    /// Returns the id and the account of a usable reset link. Unknown tokens count as failed logins of the client.
+    async fn find(pool: &SqlitePool, token: &str, ip: Option<IpAddr>) -> Result<(i64, String), ApiError> {
+        crate::lockouts::ssr::check(pool, None, ip).await?;
+
+        let reset = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, username FROM password_resets \
+            WHERE token_hash = ? AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP",
+        )
+        .bind(hash_token(token))
+        .fetch_optional(pool)
+        .await?;
+
+        match reset {
+            Some(reset) => Ok(reset),
+            None => {
+                crate::lockouts::ssr::record_failure(pool, None, ip).await?;
+                Err(ApiError::BadRequest(INVALID_LINK.to_string()))
+            }
+        }
+    }
+
+    /// Returns the user or mailbox whose password a reset link is for.
+    pub async fn account(pool: &SqlitePool, token: &str, ip: Option<IpAddr>) -> Result<String, ApiError> {
+        find(pool, token, ip).await.map(|(_, username)| username)
+    }
+
+    /// Sets a new password with a reset link, which can not be used again afterwards.
+    /// All sessions of the account end and its failed logins are forgotten.
+    pub async fn redeem(pool: &SqlitePool, token: &str, password: &str, ip: Option<IpAddr>) -> Result<(), ApiError> {
+        let (id, username) = find(pool, token, ip).await?;
+        let password_hash = mk_password_hash(password, &username)?;
+
+        let mut tx = pool.begin().await?;
+        let marked = sqlx::query(
+            "UPDATE password_resets SET used_at = CURRENT_TIMESTAMP, used_ip = ? WHERE id = ? AND used_at IS NULL",
+        )
+        .bind(ip.map(|x| x.to_string()))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if marked == 0 {
+            return Err(ApiError::BadRequest(INVALID_LINK.to_string()));
+        }
+
+        // Other links for the account are no longer needed
+        sqlx::query(
+            "UPDATE password_resets SET expires_at = CURRENT_TIMESTAMP \
+            WHERE username = ? AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP",
+        )
+        .bind(&username)
+        .execute(&mut *tx)
+        .await?;
+
+        let updated = sqlx::query("UPDATE mailboxes SET password_hash = ? WHERE address = ?")
+            .bind(&password_hash)
+            .bind(&username)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+            + sqlx::query("UPDATE users SET password_hash = ? WHERE username = ?")
+                .bind(&password_hash)
+                .bind(&username)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        if updated == 0 {
+            return Err(ApiError::BadRequest(INVALID_LINK.to_string()));
+        }
+        tx.commit().await?;
+
+        crate::sessions::ssr::end_all(pool, &username, None).await?;
+        crate::lockouts::ssr::reset(pool, &username).await?;
+        log::info!("password of '{username}' was reset with link {id}");
+        Ok(())
+    }
+}
+
+#[server]
+pub async fn create_password_reset(username: String) -> Result<String, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::create(&pool, &user, &username).await?)
+}
+
+#[server]
+pub async fn list_password_resets(query: PasswordResetQuery) -> Result<Vec<PasswordReset>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let PasswordResetQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, PasswordReset::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn password_reset_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn revoke_password_reset(id: i64) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::revoke(&pool, &user, id).await?)
+}
+
+/// Returns the account a reset link is for. Does not require a login.
+#[server]
+pub async fn password_reset_account(token: String) -> Result<String, ServerFnError> {
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::account(&pool, &token, crate::lockouts::ssr::client_ip()).await?)
+}
+
+/// Sets a new password with a reset link. Does not require a login.
+#[server]
+pub async fn reset_password(token: String, password: String) -> Result<(), ServerFnError> {
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::redeem(&pool, &token, &password, crate::lockouts::ssr::client_ip()).await?)
+}
+
+#[derive(Default)]
+pub struct PasswordResetTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<PasswordReset> for PasswordResetTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<PasswordReset>, Range<usize>), String> {
+        list_password_resets(PasswordResetQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        password_reset_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+/// The reset links issued by the current user, or by anyone for those who can see all users
+#[component]
+pub fn PasswordResets() -> impl IntoView {
+    let mut rows = PasswordResetTableDataProvider::default();
+    let default_sorting = VecDeque::from([(5, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload_controller = ReloadController::default();
+    let (count, set_count) = create_signal(0);
+
+    let revoke_modal_reset = create_rw_signal(None);
+    let revoke_modal_reset_id = create_rw_signal(None);
+
+    #[allow(unused_variables, non_snake_case)]
+    let reset_row_renderer = move |class: Signal<String>,
+                                   row: PasswordReset,
+                                   index: usize,
+                                   selected: Signal<bool>,
+                                   on_select: EventHandler<MouseEvent>,
+                                   on_change: EventHandler<ChangeEvent<PasswordReset>>| {
+        let pending = row.status == "pending";
+        let revoke_name = format!("reset link for {}", row.username);
+        let revoke_id = row.id;
+        view! {
+            <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                {row.render_row(index, on_change)}
+                <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                    <Show when=move || pending>
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            on:click={
+                                let revoke_name = revoke_name.clone();
+                                move |_| {
+                                    revoke_modal_reset_id.set(Some(revoke_id));
+                                    revoke_modal_reset.set(Some(revoke_name.clone()));
+                                }
+                            }
+                        >
+                            <Icon icon=icondata::FiTrash2 class="w-5 h-5"/>
+                        </button>
+                    </Show>
+                </td>
+            </tr>
+        }
+    };
+
+    view! {
+        <div class="flex items-center justify-between space-y-2 mt-12 mb-4">
+            <h2 class="text-4xl font-bold">Password resets</h2>
+        </div>
+        <div class="space-y-4">
+            <p class="text-sm text-gray-500 dark:text-gray-400">
+                "Reset links let the account holder choose a new password. Each link can be used once within "
+                {RESET_LINK_VALIDITY_HOURS} " hours."
+            </p>
+            <div class="flex flex-wrap items-center justify-between">
+                <div class="flex flex-1"></div>
+                <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                    {count} " links"
+                </div>
+            </div>
+
+            <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                <div class="overflow-auto grow min-h-0">
+                    <table class="table-auto text-left w-full">
+                        <TableContent
+                            rows
+                            sorting=sorting
+                            sorting_mode=SortingMode::SingleColumn
+                            row_renderer=reset_row_renderer
+                            reload_controller=reload_controller
+                            loading_row_display_limit=0
+                            on_row_count=set_count
+                        />
+                    </table>
+                </div>
+            </div>
+        </div>
+
+        <DeleteModal
+            data=revoke_modal_reset
+            text="Are you sure you want to revoke this reset link? It can no longer be used afterwards.".into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Some(id) = revoke_modal_reset_id.get_untracked() {
+                        if let Err(e) = revoke_password_reset(id).await {
+                            error!("Failed to revoke reset link: {}", e);
+                        } else {
+                            reload_controller.reload();
+                        }
+                    }
+                    revoke_modal_reset.set(None);
+                    revoke_modal_reset_id.set(None);
+                });
+            }
+        />
+    }
+}
+
+/// Issues a reset link for the user or mailbox in `username` after confirmation, and displays it.
+#[component]
+pub fn PasswordResetModal(#[prop(into)] username: RwSignal<Option<String>>) -> impl IntoView {
+    let modal_elem = create_node_ref::<Dialog>();
+    let open = Signal::derive(move || username.get().is_some());
+    let link = create_rw_signal(None::<Result<String, String>>);
+    let (waiting, set_waiting) = create_signal(false);
+    let UseTimeoutFnReturn {
+        start: start_copied_timer,
+        stop: stop_copied_timer,
+        is_pending: copied,
+        ..
+    } = use_timeout_fn(|_: ()| (), 3000.0);
+    create_effect(move |_| {
+        // Forget the link when the dialog closes in any way
+        if !open() {
+            link.set(None);
+            set_waiting(false);
+            stop_copied_timer();
+        }
+    });
+
+    let create = move |_| {
+        let Some(username) = username.get_untracked() else {
+            return;
+        };
+        set_waiting(true);
+        spawn_local(async move {
+            let result = create_password_reset(username).await.map_err(|e| e.to_string());
+            let origin = window().location().origin().unwrap_or_default();
+            link.set(Some(
+                result.map(|token| format!("{origin}/reset-password?token={token}")),
+            ));
+            set_waiting(false);
+        });
+    };
+
+    view! {
+        <Modal open dialog_el=modal_elem>
+            <div class="relative p-4 transform overflow-hidden rounded-lg bg-white dark:bg-black text-left transition-all sm:w-full sm:max-w-xl">
+                <h3 class="text-2xl tracking-tight mt-2 mb-2 font-semibold text-gray-900 dark:text-gray-200">
+                    "Reset password of " {move || username.get().unwrap_or_default()}
+                </h3>
+                <div class="pb-3 space-y-3">
+                    {move || match link.get() {
+                        None => {
+                            view! {
+                                <p class="text-sm text-gray-500 dark:text-gray-400">
+                                    "Create a link that lets the account holder choose a new password. It can be used once within "
+                                    {RESET_LINK_VALIDITY_HOURS}
+                                    " hours, and logs out all sessions of the account when used."
+                                </p>
+                            }
+                                .into_view()
+                        }
+                        Some(Err(e)) => {
+                            view! {
+                                <div class="rounded-lg p-4 flex bg-red-100 dark:bg-red-800">
+                                    <div>
+                                        <Icon icon=icondata::BiXCircleSolid class="w-5 h-5 text-red-400 dark:text-red-300"/>
+                                    </div>
+                                    <div class="ml-3 text-red-700 dark:text-red-300">
+                                        <p>{e}</p>
+                                    </div>
+                                </div>
+                            }
+                                .into_view()
+                        }
+                        Some(Ok(link)) => {
+                            view! {
+                                <p class="text-sm text-gray-500 dark:text-gray-400">
+                                    "Send this link to the account holder. Make sure to copy it now, as it will not be displayed again."
+                                </p>
+                                <div class="w-full relative">
+                                    <input
+                                        type="text"
+                                        class="col-span-6 bg-gray-50 dark:bg-gray-900 dark:bg-black border border-gray-300 text-gray-500 dark:text-gray-400 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full px-2.5 py-4 pe-24"
+                                        value=link.clone()
+                                        disabled
+                                        readonly
+                                    />
+                                    <button
+                                        class="absolute end-2.5 top-1/2 -translate-y-1/2 text-gray-900 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg py-2 px-2.5 inline-flex items-center justify-center bg-white dark:bg-black border-gray-200 border"
+                                        on:click={
+                                            let start_copied_timer = start_copied_timer.clone();
+                                            let link = link.clone();
+                                            move |_ev| {
+                                            start_copied_timer(());
+                                            let clipboard = window().navigator().clipboard();
+                                            let _ = clipboard.write_text(&link);
+                                            }
+                                        }
+                                    >
+
+                                        <span class="inline-flex items-center" class=("hidden", copied)>
+                                            <Icon icon=icondata::RiFileCopy2DocumentFill class="w-3 h-3 me-1.5"/>
+                                            <span class="text-xs font-semibold">Copy</span>
+                                        </span>
+                                        <span class="hidden items-center" class=("!inline-flex", copied)>
+                                            <Icon
+                                                icon=icondata::BiCheckRegular
+                                                class="w-3 h-3 me-1.5 text-blue-700 dark:text-blue-300"
+                                            />
+                                            <span class="text-xs font-semibold text-blue-700 dark:text-blue-300">
+                                                Copied
+                                            </span>
+                                        </span>
+                                    </button>
+                                </div>
+                            }
+                                .into_view()
+                        }
+                    }}
+
+                </div>
+                <div class="flex flex-col gap-3 sm:flex-row-reverse">
+                    <Show when=move || link.get().is_none()>
+                        <button
+                            type="button"
+                            class="inline-flex w-full min-w-20 justify-center rounded-lg transition-all px-3 py-2 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 font-semibold text-white focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900 sm:w-auto disabled:cursor-not-allowed disabled:opacity-50"
+                            disabled=waiting
+                            on:click=create
+                        >
+                            "Create link"
+                        </button>
+                    </Show>
+                    <button
+                        type="button"
+                        class="inline-flex w-full min-w-20 justify-center rounded-lg transition-all bg-white dark:bg-black px-3 py-2 font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                        on:click=move |_ev| username.set(None)
+                    >
+                        {move || if link.get().is_none() { "Cancel" } else { "Dismiss" }}
+                    </button>
+                </div>
+            </div>
+        </Modal>
+    }
+}
+
+/// The page opened from a reset link, on which the account holder chooses a new password
+#[component]
+pub fn ResetPassword(color_mode: Signal<ColorMode>, set_color_mode: WriteSignal<ColorMode>) -> impl IntoView {
+    let query = use_query_map();
+    let token = move || query.with(|x| x.get("token").cloned().unwrap_or_default());
+    let account = create_resource(token, password_reset_account);
+
+    let (password, set_password) = create_signal("".to_string());
+    let (password_repeat, set_password_repeat) = create_signal("".to_string());
+    let reset =
+        create_action(move |(token, password): &(String, String)| reset_password(token.clone(), password.clone()));
+    let reset_value = Signal::derive(move || reset.value().get().unwrap_or(Ok(())));
+    let done = Signal::derive(move || matches!(reset.value().get(), Some(Ok(()))));
+
+    let has_password_mismatch = move || password() != password_repeat();
+    let password_policy = use_password_policy();
+    let password_problem = move || {
+        let account = account.get().and_then(Result::ok).unwrap_or_default();
+        password_policy().problems(&password(), &account).into_iter().next()
+    };
+    let input_errors = Signal::derive(move || {
+        if let Some(problem) = password_problem() {
+            Err(ServerFnError::new(problem))
+        } else if has_password_mismatch() {
+            Err(ServerFnError::new("Passwords don't match"))
+        } else {
+            Ok(())
+        }
+    });
+
+    view! {
+        <div class="relative flex min-h-screen flex-col">
+            <div class="absolute top-4 right-4">
+                <ColorModeToggle color_mode set_color_mode/>
+            </div>
+            <div class="w-full h-screen flex items-center justify-center px-4">
+                <div class="flex flex-col mx-auto">
+                    <div class="mx-auto mb-4 flex flex-row items-center">
+                        <img class="w-16 h-16 me-2" src="/logo.svg"/>
+                        <h2 class="text-4xl leading-none font-bold inline-block">idmail</h2>
+                    </div>
+                    <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-card-foreground max-w-sm">
+                        <div class="flex flex-col space-y-1.5 p-6">
+                            <h2 class="font-semibold tracking-tight text-2xl mb-2">Reset password</h2>
+                            <Transition fallback=|| ()>
+                                {move || {
+                                    account
+                                        .get()
+                                        .map(|account| match account {
+                                            Ok(account) if !done() => {
+                                                view! {
+                                                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                                                        "Choose a new password for " {account}
+                                                    </p>
+                                                }
+                                                    .into_view()
+                                            }
+                                            Ok(_) => {
+                                                view! {
+                                                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                                                        "Your password has been changed. You can now log in with it."
+                                                    </p>
+                                                }
+                                                    .into_view()
+                                            }
+                                            Err(e) => {
+                                                view! {
+                                                    <ErrorBoundary fallback=login_errors>
+                                                        {Err::<(), _>(e)}
+                                                    </ErrorBoundary>
+                                                }
+                                                    .into_view()
+                                            }
+                                        })
+                                }}
+
+                            </Transition>
+                        </div>
+                        <div class="p-6 pt-0">
+                            <Show
+                                when=move || matches!(account.get(), Some(Ok(_))) && !done()
+                                fallback=|| {
+                                    view! {
+                                        <a
+                                            href="/login"
+                                            class="inline-flex w-full justify-center items-center rounded-lg transition-all p-2.5 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 font-semibold text-white focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900"
+                                        >
+                                            "Go to login"
+                                        </a>
+                                    }
+                                }
+                            >
+                                <div class="grid gap-4">
+                                    <div class="grid gap-2">
+                                        <label
+                                            class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                                            for="password"
+                                        >
+                                            "New password"
+                                        </label>
+                                        <input
+                                            class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                                            type="password"
+                                            id="password"
+                                            autocomplete="new-password"
+                                            maxlength="1024"
+                                            required="required"
+                                            on:input=move |ev| set_password(event_target_value(&ev))
+                                            prop:value=password
+                                        />
+                                        <PasswordStrength password/>
+                                    </div>
+                                    <div class="grid gap-2">
+                                        <label
+                                            class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                                            for="password_2"
+                                        >
+                                            "Repeat password"
+                                        </label>
+                                        <input
+                                            class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                                            type="password"
+                                            id="password_2"
+                                            autocomplete="new-password"
+                                            maxlength="1024"
+                                            required="required"
+                                            on:input=move |ev| set_password_repeat(event_target_value(&ev))
+                                            prop:value=password_repeat
+                                        />
+                                    </div>
+                                    <Show when=move || !password().is_empty()>
+                                        <ErrorBoundary fallback=login_errors>{input_errors}</ErrorBoundary>
+                                    </Show>
+                                    <ErrorBoundary fallback=login_errors>{reset_value}</ErrorBoundary>
+                                    <button
+                                        type="button"
+                                        class="inline-flex w-full justify-center mt-3 items-center rounded-lg transition-all p-2.5 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 font-semibold text-white focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900 disabled:cursor-not-allowed disabled:opacity-50"
+                                        disabled=move || input_errors().is_err() || reset.pending().get()
+                                        on:click=move |_| reset.dispatch((token(), password.get_untracked()))
+                                    >
+                                        "Set password"
+                                    </button>
+                                </div>
+                            </Show>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
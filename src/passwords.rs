@@ -0,0 +1,336 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::sync::OnceLock;
+
+static PARAMS: OnceLock<Params> = OnceLock::new();
+
+/// Reads a positive number from the given environment variable.
+fn param_from_env(name: &str, default: u32) -> Result<u32> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(default);
+    };
+    value
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|x| *x > 0)
+        .context(format!("Invalid value for {name}: '{value}'"))
+}
+
+/// Reads the argon2 parameters for new password hashes from the environment. Must be called once on startup.
+pub fn init() -> Result<()> {
+    let params = Params::new(
+        param_from_env("IDMAIL_ARGON2_MEMORY", Params::DEFAULT_M_COST)?,
+        param_from_env("IDMAIL_ARGON2_ITERATIONS", Params::DEFAULT_T_COST)?,
+        param_from_env("IDMAIL_ARGON2_PARALLELISM", Params::DEFAULT_P_COST)?,
+        None,
+    )
+    .map_err(|e| anyhow!("Invalid argon2 parameters: {e}"))?;
+
+    log::info!(
+        "Hashing new passwords with argon2id (m={}, t={}, p={})",
+        params.m_cost(),
+        params.t_cost(),
+        params.p_cost()
+    );
+    let _ = PARAMS.set(params);
+    Ok(())
+}
+
+fn argon2() -> Argon2<'static> {
+    let params = PARAMS.get_or_init(Params::default).clone();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+/// Hashes a password with argon2id and the configured parameters.
+pub fn hash(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("{e}"))?
+        .to_string())
+}
+
+/// Splits a Dovecot-style `{SCHEME}` prefix off a hash. The scheme is returned in upper case.
+fn split_scheme(hash: &str) -> Option<(String, &str)> {
+    let rest = hash.strip_prefix('{')?;
+    let (scheme, hash) = rest.split_once('}')?;
+    Some((scheme.to_ascii_uppercase(), hash))
+}
+
+/// Splits an encoding suffix like `.HEX` off a Dovecot scheme.
+fn split_encoding(scheme: String) -> (String, Encoding) {
+    match scheme.rsplit_once('.') {
+        Some((scheme, "HEX")) => (scheme.to_string(), Encoding::Hex),
+        Some((scheme, "B64" | "BASE64")) => (scheme.to_string(), Encoding::Base64),
+        _ => (scheme, Encoding::Default),
+    }
+}
+
+/// Returns the password of a `{PLAIN}` hash, or `None` for any other scheme.
+pub fn plaintext(hash: &str) -> Result<Option<String>> {
+    let Some((scheme, hash)) = split_scheme(hash) else {
+        return Ok(None);
+    };
+    match split_encoding(scheme) {
+        (scheme, encoding) if ["PLAIN", "CLEAR", "CLEARTEXT"].contains(&scheme.as_str()) => {
+            let password = encoding.decode(hash, Encoding::Default)?;
+            Ok(Some(
+                String::from_utf8(password).context("invalid UTF-8 in plain password")?,
+            ))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Compares two byte strings in constant time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// The encoding of a Dovecot hash, which can be selected with a `.HEX` or `.B64` suffix of the scheme
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Default,
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    fn decode(self, encoded: &str, default: Encoding) -> Result<Vec<u8>> {
+        match if self == Encoding::Default { default } else { self } {
+            Encoding::Default => Ok(encoded.as_bytes().to_vec()),
+            Encoding::Hex => hex::decode(encoded).context("invalid hex encoding"),
+            Encoding::Base64 => STANDARD.decode(encoded).context("invalid base64 encoding"),
+        }
+    }
+}
+
+/// Verifies a salted or plain digest as used by the `{SHA}`, `{SSHA}`, ... schemes of Dovecot,
+/// which is `digest || salt`, encoded in base64 unless specified otherwise.
+fn verify_digest<D: Digest>(password: &str, encoded: &str, encoding: Encoding, salted: bool) -> Result<bool> {
+    let decoded = encoding.decode(encoded, Encoding::Base64)?;
+    let size = <D as Digest>::output_size();
+    ensure!(
+        if salted {
+            decoded.len() > size
+        } else {
+            decoded.len() == size
+        },
+        "invalid digest length"
+    );
+    let (expected, salt) = decoded.split_at(size);
+    let digest = D::new().chain_update(password.as_bytes()).chain_update(salt).finalize();
+    Ok(constant_time_eq(&digest, expected))
+}
+
+/// Verifies a crypt(3) hash, making sure that it uses the expected method if a scheme was given.
+fn verify_crypt(password: &str, hash: &str, prefixes: &[&str]) -> Result<bool> {
+    ensure!(
+        prefixes.is_empty() || prefixes.iter().any(|x| hash.starts_with(x)),
+        "hash does not match its scheme"
+    );
+    Ok(pwhash::unix::verify(password, hash))
+}
+
+/// Verifies a password against a stored hash. Supported are argon2 PHC strings, bcrypt, SHA512-crypt,
+/// SHA256-crypt and MD5-crypt, as well as Dovecot hashes with a `{SCHEME}` prefix.
+pub fn verify(password: &str, hash: &str) -> Result<()> {
+    let matches = match split_scheme(hash) {
+        None if hash.starts_with("$argon2") => {
+            let hash = PasswordHash::new(hash).map_err(|e| anyhow!("invalid argon2 hash: {e}"))?;
+            ensure!(hash.hash.is_some(), "invalid argon2 hash: missing output");
+            match Argon2::default().verify_password(password.as_bytes(), &hash) {
+                Ok(()) => true,
+                Err(argon2::password_hash::Error::Password) => false,
+                Err(e) => bail!("invalid argon2 hash: {e}"),
+            }
+        }
+        None if ["$2a$", "$2b$", "$2y$", "$5$", "$6$", "$1$"]
+            .iter()
+            .any(|x| hash.starts_with(x)) =>
+        {
+            verify_crypt(password, hash, &[])?
+        }
+        None => bail!("unsupported password hash"),
+        Some((scheme, hash)) => {
+            let (scheme, encoding) = split_encoding(scheme);
+            match scheme.as_str() {
+                "ARGON2I" | "ARGON2ID" => return verify(password, hash),
+                "BLF-CRYPT" => verify_crypt(password, hash, &["$2a$", "$2b$", "$2y$"])?,
+                "SHA512-CRYPT" => verify_crypt(password, hash, &["$6$"])?,
+                "SHA256-CRYPT" => verify_crypt(password, hash, &["$5$"])?,
+                "MD5-CRYPT" | "MD5" => verify_crypt(password, hash, &["$1$"])?,
+                "CRYPT" => verify_crypt(password, hash, &[])?,
+                "SHA" | "SHA1" => verify_digest::<Sha1>(password, hash, encoding, false)?,
+                "SSHA" => verify_digest::<Sha1>(password, hash, encoding, true)?,
+                "SHA256" => verify_digest::<Sha256>(password, hash, encoding, false)?,
+                "SSHA256" => verify_digest::<Sha256>(password, hash, encoding, true)?,
+                "SHA512" => verify_digest::<Sha512>(password, hash, encoding, false)?,
+                "SSHA512" => verify_digest::<Sha512>(password, hash, encoding, true)?,
+                "PLAIN" | "CLEAR" | "CLEARTEXT" => {
+                    constant_time_eq(password.as_bytes(), &encoding.decode(hash, Encoding::Default)?)
+                }
+                scheme => bail!("unsupported password scheme {{{scheme}}}"),
+            }
+        }
+    };
+
+    ensure!(matches, "wrong password");
+    Ok(())
+}
+
+/// Whether a hash should be replaced by one with the current scheme and parameters,
+/// which is the case for everything but argon2id hashes with the configured parameters.
+pub fn needs_rehash(hash: &str) -> bool {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return true;
+    };
+    let Ok(params) = Params::try_from(&hash) else {
+        return true;
+    };
+    let current = argon2();
+    let current = current.params();
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || hash.version != Some(Version::V0x13.into())
+        || params.m_cost() != current.m_cost()
+        || params.t_cost() != current.t_cost()
+        || params.p_cost() != current.p_cost()
+}
+
+#[cfg(test)]
+#[allow(clippy::literal_string_with_formatting_args)] // {SCHEME} prefixes are not format arguments
+mod tests {
+    use super::*;
+
+    // Argon2 vectors from the reference implementation, with reduced memory to keep the tests fast
+    const ARGON2ID: &str = "$argon2id$v=19$m=256,t=2,p=1$c29tZXNhbHQ$nf65EOgLrQMR/uIPnA4rEsF5h7TKyQwu9U1bMCHGi/4";
+    const ARGON2I: &str = "$argon2i$v=19$m=256,t=2,p=1$c29tZXNhbHQ$iekCn0Y3spW+sCcFanM2xBT63UP2sghkUoHLIUpWRS8";
+
+    /// Hashes of "password", as created by Dovecot, crypt(3) or OpenLDAP
+    const VECTORS: &[&str] = &[
+        ARGON2ID,
+        ARGON2I,
+        "{ARGON2ID}$argon2id$v=19$m=256,t=2,p=1$c29tZXNhbHQ$nf65EOgLrQMR/uIPnA4rEsF5h7TKyQwu9U1bMCHGi/4",
+        "{ARGON2I}$argon2i$v=19$m=256,t=2,p=1$c29tZXNhbHQ$iekCn0Y3spW+sCcFanM2xBT63UP2sghkUoHLIUpWRS8",
+        "$2y$05$bvIG6Nmid91Mu9RcmmWZfO5HJIMCT8riNW0hEp8f6/FuA2/mHZFpe",
+        "{BLF-CRYPT}$2y$05$bvIG6Nmid91Mu9RcmmWZfO5HJIMCT8riNW0hEp8f6/FuA2/mHZFpe",
+        "$6$saltsalt$qFmFH.bQmmtXzyBY0s9v7Oicd2z4XSIecDzlB5KiA2/jctKu9YterLp8wwnSq.qc.eoxqOmSuNp2xS0ktL3nh/",
+        "{SHA512-CRYPT}$6$saltsalt$qFmFH.bQmmtXzyBY0s9v7Oicd2z4XSIecDzlB5KiA2/jctKu9YterLp8wwnSq.qc.eoxqOmSuNp2xS0ktL3nh/",
+        "$5$saltsalt$gOjOtoMpVhru2uyjeJSEc/JaLQWOXMNmlOnj6T4AtC.",
+        "{SHA256-CRYPT}$5$saltsalt$gOjOtoMpVhru2uyjeJSEc/JaLQWOXMNmlOnj6T4AtC.",
+        "$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/",
+        "{MD5-CRYPT}$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/",
+        "{MD5}$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/",
+        "{CRYPT}$6$saltsalt$qFmFH.bQmmtXzyBY0s9v7Oicd2z4XSIecDzlB5KiA2/jctKu9YterLp8wwnSq.qc.eoxqOmSuNp2xS0ktL3nh/",
+        "{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=",
+        "{SHA1}W6ph5Mm5Pz8GgiULbPgzG37mj9g=",
+        "{SHA.HEX}5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8",
+        "{SSHA}yrht1iYXEIkejLVu42JWkadd80RzYWx0c2FsdA==",
+        "{ssha}yrht1iYXEIkejLVu42JWkadd80RzYWx0c2FsdA==",
+        "{SSHA.HEX}cab86dd6261710891e8cb56ee3625691a75df34473616c7473616c74",
+        "{SHA256}XohImNooBHFR0OVvjcYpJ3NgPQ1qq73WKhHvch0VQtg=",
+        "{SHA256.HEX}5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8",
+        "{SSHA256}DIzeh0gCRMTRu9dAH3C3rr7fWkRT0Bp2ZdtRqvTX3XJzYWx0c2FsdA==",
+        "{SSHA256.HEX}0c8cde87480244c4d1bbd7401f70b7aebedf5a4453d01a7665db51aaf4d7dd7273616c7473616c74",
+        "{SHA512}sQnzu7wkTrgkQZF+0G1hi5AI3Qmzvv0bXgc5THBqi7mAsdd4Xll27ASbRt9fEyavWi6m0QP9B8lThf+rDKy8hg==",
+        "{SHA512.HEX}b109f3bbbc244eb82441917ed06d618b9008dd09b3befd1b5e07394c706a8bb980b1d7785e5976ec049b46df5f1326af5a2ea6d103fd07c95385ffab0cacbc86",
+        "{SSHA512}9ZxHVj4YomwqqFiYKcIjExMLx2ZblYfXRGc4KMqbgvHq2+HOgwiTIi+eO/Uam/8D0beDAkGpvx14+UFlfBskLnNhbHRzYWx0",
+        "{SSHA512.HEX}f59c47563e18a26c2aa8589829c22313130bc7665b9587d744673828ca9b82f1eadbe1ce830893222f9e3bf51a9bff03d1b7830241a9bf1d78f941657c1b242e73616c7473616c74",
+        "{PLAIN}password",
+        "{CLEAR}password",
+        "{CLEARTEXT}password",
+        "{PLAIN.B64}cGFzc3dvcmQ=",
+        "{PLAIN.HEX}70617373776f7264",
+    ];
+
+    #[test]
+    fn verifies_known_hashes() {
+        for hash in VECTORS {
+            assert!(verify("password", hash).is_ok(), "{hash}");
+        }
+        // A second bcrypt vector, from the test suite of crypt_blowfish
+        assert!(verify("U*U", "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW").is_ok());
+    }
+
+    #[test]
+    fn rejects_wrong_passwords() {
+        for hash in VECTORS {
+            for password in ["wrong", "Password", "password ", ""] {
+                let error = verify(password, hash).expect_err(hash).to_string();
+                assert_eq!(error, "wrong password", "{hash}");
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        for hash in [
+            "",
+            "password",
+            "$argon2id$v=19$m=many,t=2,p=1$c29tZXNhbHQ$nf65EOgLrQMR/uIPnA4rEsF5h7TKyQwu9U1bMCHGi/4",
+            "$argon2id$nonsense",
+            "$argon2id$v=19$m=256,t=2,p=1$c29tZXNhbHQ",
+            "$3$saltsalt$qjXMvbEw8oaL.CzflDtaK/",
+            "{SHA",
+            "{UNKNOWN}password",
+            "{SHA}not base64!",
+            "{SHA.HEX}5baa61e4c9b93f3f0682250b6cf8331b7ee68fdz",
+            // Digests of the wrong length, or salted digests without a salt
+            "{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9gA",
+            "{SHA256}W6ph5Mm5Pz8GgiULbPgzG37mj9g=",
+            "{SSHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=",
+            "{SSHA256.HEX}5e884898",
+            "{PLAIN.HEX}password",
+            // Crypt hashes of a different method than the scheme says
+            "{SHA512-CRYPT}$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/",
+            "{BLF-CRYPT}$6$saltsalt$qFmFH.bQmmtXzyBY0s9v7Oicd2z4XSIecDzlB5KiA2/jctKu9YterLp8wwnSq.qc.eoxqOmSuNp2xS0ktL3nh/",
+            "{MD5-CRYPT}$5$saltsalt$gOjOtoMpVhru2uyjeJSEc/JaLQWOXMNmlOnj6T4AtC.",
+        ] {
+            let error = verify("password", hash).expect_err(hash).to_string();
+            assert_ne!(error, "wrong password", "{hash}");
+        }
+    }
+
+    #[test]
+    fn reads_plaintext() {
+        assert_eq!(plaintext("{PLAIN}password").unwrap().as_deref(), Some("password"));
+        assert_eq!(
+            plaintext("{plain.b64}cGFzc3dvcmQ=").unwrap().as_deref(),
+            Some("password")
+        );
+        assert_eq!(plaintext("{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=").unwrap(), None);
+        assert_eq!(plaintext(ARGON2ID).unwrap(), None);
+        assert!(plaintext("{PLAIN.HEX}ff").is_err());
+    }
+
+    #[test]
+    fn rehashes_everything_but_current_argon2id() {
+        // The default parameters are used, since these tests never call init()
+        let current = hash("password").unwrap();
+        assert!(verify("password", &current).is_ok());
+        assert!(!needs_rehash(&current));
+
+        let params = Params::default();
+        let (m, t, p) = (params.m_cost(), params.t_cost(), params.p_cost());
+        let salt = "c29tZXNhbHQ$nf65EOgLrQMR/uIPnA4rEsF5h7TKyQwu9U1bMCHGi/4";
+        assert!(!needs_rehash(&format!("$argon2id$v=19$m={m},t={t},p={p}${salt}")));
+        assert!(needs_rehash(&format!("$argon2id$v=19$m={},t={t},p={p}${salt}", m * 2)));
+        assert!(needs_rehash(&format!("$argon2id$v=19$m={m},t={},p={p}${salt}", t + 1)));
+        assert!(needs_rehash(&format!("$argon2id$v=19$m={m},t={t},p={}${salt}", p + 1)));
+        assert!(needs_rehash(&format!("$argon2id$v=16$m={m},t={t},p={p}${salt}")));
+        assert!(needs_rehash(&format!("$argon2i$v=19$m={m},t={t},p={p}${salt}")));
+        assert!(needs_rehash(&format!(
+            "{{ARGON2ID}}$argon2id$v=19$m={m},t={t},p={p}${salt}"
+        )));
+        for hash in VECTORS.iter().filter(|x| !x.starts_with("$argon2")) {
+            assert!(needs_rehash(hash), "{hash}");
+        }
+    }
+}
@@ -1,12 +1,18 @@
 use std::collections::HashSet;
 
 use self::state::State;
-use anyhow::{bail, Context, Result};
+use crate::aliases::{validate_max_recv, validate_schedule, ExpiryAction};
+use crate::api_tokens::ssr::hash_token;
+use crate::roles::invalid_permission_patterns;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
 use owo_colors::OwoColorize;
 use sqlx::{QueryBuilder, SqlitePool};
 
 mod state {
-    use serde::Deserialize;
+    use crate::aliases::ExpiryAction;
+    use chrono::{DateTime, Utc};
+    use serde::{de::Error, Deserialize, Deserializer};
     use std::collections::HashMap;
 
     #[derive(Debug, Deserialize)]
@@ -14,8 +20,26 @@ mod state {
         pub password_hash: String,
         #[serde(default = "default_false")]
         pub admin: bool,
+        #[serde(default)]
+        pub role: Option<String>,
         #[serde(default = "default_true")]
         pub active: bool,
+        #[serde(default)]
+        pub max_aliases: Option<u32>,
+        #[serde(default)]
+        pub max_aliases_per_day: Option<u32>,
+        #[serde(default)]
+        pub api_requests_per_minute: Option<u32>,
+        #[serde(default)]
+        pub oidc_subject: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Role {
+        #[serde(default)]
+        pub description: String,
+        #[serde(default)]
+        pub permissions: Vec<String>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -27,6 +51,8 @@ mod state {
         #[serde(default = "default_true")]
         pub active: bool,
         pub owner: String,
+        #[serde(default)]
+        pub members: Vec<String>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -47,10 +73,20 @@ mod state {
         #[serde(default = "default_true")]
         pub active: bool,
         pub owner: String,
+        #[serde(default, deserialize_with = "time")]
+        pub active_from: Option<DateTime<Utc>>,
+        #[serde(default, deserialize_with = "time")]
+        pub expires_at: Option<DateTime<Utc>>,
+        #[serde(default)]
+        pub on_expiry: ExpiryAction,
+        #[serde(default)]
+        pub max_recv: Option<i64>,
     }
 
     #[derive(Debug, Deserialize)]
     pub struct State {
+        #[serde(default)]
+        pub roles: HashMap<String, Role>,
         #[serde(default)]
         pub users: HashMap<String, User>,
         #[serde(default)]
@@ -67,6 +103,23 @@ mod state {
     fn default_true() -> bool {
         true
     }
+
+    /// Accepts both TOML date-times and strings in RFC 3339 format, which must include an offset.
+    fn time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        let value = match toml::Value::deserialize(deserializer)? {
+            toml::Value::Datetime(x) => x.to_string(),
+            toml::Value::String(x) => x,
+            x => {
+                return Err(D::Error::custom(format!(
+                    "expected a date-time, found {}",
+                    x.type_str()
+                )))
+            }
+        };
+        DateTime::parse_from_rfc3339(&value)
+            .map(|x| Some(x.to_utc()))
+            .map_err(|e| D::Error::custom(format!("invalid date-time '{value}': {e}")))
+    }
 }
 
 fn value_or_file(value: String) -> Result<String> {
@@ -98,6 +151,55 @@ pub async fn delete_orphans(
     Ok(())
 }
 
+pub async fn provision_roles(pool: &SqlitePool, state: &State) -> Result<()> {
+    let known_roles = select_provisioned(pool, "roles", "name").await?;
+    let orphaned_roles = &known_roles - &state.roles.keys().cloned().collect::<HashSet<_>>();
+
+    log::info!(
+        "Provisioning {} roles ({}, {})",
+        state.roles.len().yellow(),
+        format!("-{}", orphaned_roles.len()).red(),
+        format!("+{}", state.roles.len() + orphaned_roles.len() - known_roles.len()).green(),
+    );
+    delete_orphans(pool, "roles", "name", &orphaned_roles).await?;
+
+    for (name, role) in &state.roles {
+        let permissions = role.permissions.join(", ");
+        let errors = invalid_permission_patterns(&permissions);
+        if !errors.is_empty() {
+            bail!("Failed to provision role '{name}': {}", errors.join(", "));
+        }
+
+        let mut query = QueryBuilder::new("INSERT INTO roles (name, description, permissions, provisioned)");
+        query.push(" VALUES (");
+        query.push_bind(name);
+        query.push(", ");
+        query.push_bind(&role.description);
+        query.push(", ");
+        query.push_bind(&permissions);
+        query.push(", TRUE)");
+
+        query.push(" ON CONFLICT (name) DO UPDATE SET");
+        query.push(" description = ");
+        query.push_bind(&role.description);
+        query.push(", permissions = ");
+        query.push_bind(&permissions);
+        query.push(", provisioned = TRUE");
+
+        query.build().execute(pool).await?;
+    }
+
+    Ok(())
+}
+
+/// Checks provisioned passwords against the password policy, which is only possible for plain passwords.
+fn ensure_password_policy(password_hash: &str, username: &str) -> Result<()> {
+    if let Some(password) = crate::passwords::plaintext(password_hash)? {
+        crate::password_policy::ssr::check(&password, username).map_err(|e| anyhow!(e))?;
+    }
+    Ok(())
+}
+
 pub async fn provision_users(pool: &SqlitePool, state: &State) -> Result<()> {
     let known_users = select_provisioned(pool, "users", "username").await?;
     let orphaned_users = &known_users - &state.users.keys().cloned().collect::<HashSet<_>>();
@@ -111,8 +213,22 @@ pub async fn provision_users(pool: &SqlitePool, state: &State) -> Result<()> {
     delete_orphans(pool, "users", "username", &orphaned_users).await?;
 
     for (name, user) in &state.users {
+        if let Some(role) = &user.role {
+            let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT * FROM roles WHERE name = ?)")
+                .bind(role)
+                .fetch_one(pool)
+                .await?;
+            if !exists {
+                bail!("Failed to provision user '{name}': Role '{role}' does not exist");
+            }
+        }
+
         let password_hash = value_or_file(user.password_hash.clone())?;
-        let mut query = QueryBuilder::new("INSERT INTO users (username, password_hash, admin, active, provisioned)");
+        ensure_password_policy(&password_hash, name).context(format!("Failed to provision user '{name}'"))?;
+        let mut query = QueryBuilder::new(
+            "INSERT INTO users (username, password_hash, admin, role, active, \
+            max_aliases, max_aliases_per_day, api_requests_per_minute, oidc_subject, provisioned)",
+        );
         query.push(" VALUES (");
         query.push_bind(name);
         query.push(", ");
@@ -120,7 +236,17 @@ pub async fn provision_users(pool: &SqlitePool, state: &State) -> Result<()> {
         query.push(", ");
         query.push_bind(user.admin);
         query.push(", ");
+        query.push_bind(&user.role);
+        query.push(", ");
         query.push_bind(user.active);
+        query.push(", ");
+        query.push_bind(user.max_aliases);
+        query.push(", ");
+        query.push_bind(user.max_aliases_per_day);
+        query.push(", ");
+        query.push_bind(user.api_requests_per_minute);
+        query.push(", ");
+        query.push_bind(&user.oidc_subject);
         query.push(", TRUE)");
 
         query.push(" ON CONFLICT (username) DO UPDATE SET");
@@ -128,8 +254,20 @@ pub async fn provision_users(pool: &SqlitePool, state: &State) -> Result<()> {
         query.push_bind(&password_hash);
         query.push(", admin = ");
         query.push_bind(user.admin);
+        query.push(", role = ");
+        query.push_bind(&user.role);
         query.push(", active = ");
         query.push_bind(user.active);
+        query.push(", max_aliases = ");
+        query.push_bind(user.max_aliases);
+        query.push(", max_aliases_per_day = ");
+        query.push_bind(user.max_aliases_per_day);
+        query.push(", api_requests_per_minute = ");
+        query.push_bind(user.api_requests_per_minute);
+        // Identities linked on login are kept unless the provisioning links a different one
+        query.push(", oidc_subject = COALESCE(");
+        query.push_bind(&user.oidc_subject);
+        query.push(", oidc_subject)");
         query.push(", provisioned = TRUE");
 
         query.build().execute(pool).await?;
@@ -161,6 +299,9 @@ pub async fn provision_domains(pool: &SqlitePool, state: &State) -> Result<()> {
                 domain.owner
             );
         }
+        if let Some(member) = domain.members.iter().find(|x| !state.users.contains_key(*x)) {
+            bail!("Failed to provision domain '{name}': Member '{member}' must be a provisioned user");
+        }
 
         let catch_all = domain.catch_all.as_deref().unwrap_or("");
         let mut query =
@@ -190,6 +331,19 @@ pub async fn provision_domains(pool: &SqlitePool, state: &State) -> Result<()> {
         query.push(", provisioned = TRUE");
 
         query.build().execute(pool).await?;
+
+        // The members of provisioned domains are replaced as a whole
+        sqlx::query("DELETE FROM domain_members WHERE domain = ?")
+            .bind(name)
+            .execute(pool)
+            .await?;
+        for member in domain.members.iter().filter(|x| **x != domain.owner) {
+            sqlx::query("INSERT OR IGNORE INTO domain_members (domain, username) VALUES (?, ?)")
+                .bind(name)
+                .bind(member)
+                .execute(pool)
+                .await?;
+        }
     }
 
     Ok(())
@@ -227,13 +381,13 @@ pub async fn provision_mailboxes(pool: &SqlitePool, state: &State) -> Result<()>
         }
 
         let password_hash = value_or_file(mailbox.password_hash.clone())?;
+        ensure_password_policy(&password_hash, name).context(format!("Failed to provision mailbox '{name}'"))?;
         let api_token = mailbox.api_token.clone().map(value_or_file).transpose()?;
         if api_token.as_ref().is_some_and(|x| x.len() < 16) {
             bail!("Failed to provision mailbox '{name}': API tokens must be at least 16 characters long");
         }
-        let mut query = QueryBuilder::new(
-            "INSERT INTO mailboxes (address, domain, password_hash, api_token, active, owner, provisioned)",
-        );
+        let mut query =
+            QueryBuilder::new("INSERT INTO mailboxes (address, domain, password_hash, active, owner, provisioned)");
         query.push(" VALUES (");
         query.push_bind(name);
         query.push(", ");
@@ -241,8 +395,6 @@ pub async fn provision_mailboxes(pool: &SqlitePool, state: &State) -> Result<()>
         query.push(", ");
         query.push_bind(&password_hash);
         query.push(", ");
-        query.push_bind(&api_token);
-        query.push(", ");
         query.push_bind(mailbox.active);
         query.push(", ");
         query.push_bind(&mailbox.owner);
@@ -251,8 +403,6 @@ pub async fn provision_mailboxes(pool: &SqlitePool, state: &State) -> Result<()>
         query.push(" ON CONFLICT (address) DO UPDATE SET");
         query.push(" password_hash = ");
         query.push_bind(&password_hash);
-        query.push(", api_token = ");
-        query.push_bind(&api_token);
         query.push(", active = ");
         query.push_bind(mailbox.active);
         query.push(", owner = ");
@@ -260,6 +410,26 @@ pub async fn provision_mailboxes(pool: &SqlitePool, state: &State) -> Result<()>
         query.push(", provisioned = TRUE");
 
         query.build().execute(pool).await?;
+
+        // The provisioned token replaces any previously provisioned one
+        let token_hash = api_token.as_deref().map(hash_token);
+        sqlx::query("DELETE FROM api_tokens WHERE owner = ? AND provisioned = TRUE AND token_hash IS NOT ?")
+            .bind(name)
+            .bind(&token_hash)
+            .execute(pool)
+            .await?;
+        if let Some(token_hash) = &token_hash {
+            sqlx::query(
+                "INSERT INTO api_tokens (owner, name, token_hash, scope, provisioned) \
+                VALUES (?, 'Provisioned token', ?, 'full', TRUE) \
+                ON CONFLICT (token_hash) DO UPDATE SET owner = excluded.owner, name = excluded.name, \
+                scope = excluded.scope, expires_at = NULL, provisioned = TRUE",
+            )
+            .bind(name)
+            .bind(token_hash)
+            .execute(pool)
+            .await?;
+        }
     }
 
     Ok(())
@@ -296,9 +466,37 @@ pub async fn provision_aliases(pool: &SqlitePool, state: &State) -> Result<()> {
             );
         }
 
+        validate_schedule(alias.active_from, alias.expires_at)
+            .map_err(|e| anyhow!("Failed to provision alias '{name}': {e}"))?;
+        validate_max_recv(alias.max_recv).map_err(|e| anyhow!("Failed to provision alias '{name}': {e}"))?;
+
+        // Apply schedules that have already passed right away, like the background task would
+        let now = Utc::now();
+        let mut active = alias.active;
+        let mut active_from = alias.active_from;
+        let mut expires_at = alias.expires_at;
+        match active_from {
+            Some(x) if x <= now => active_from = None,
+            Some(_) => active = false,
+            None => (),
+        }
+        if expires_at.is_some_and(|x| x <= now) {
+            if alias.on_expiry == ExpiryAction::Delete {
+                sqlx::query("DELETE FROM aliases WHERE address = ?")
+                    .bind(name)
+                    .execute(pool)
+                    .await?;
+                continue;
+            }
+            active = false;
+            active_from = None;
+            expires_at = None;
+        }
+
         let comment = alias.comment.as_deref().unwrap_or("");
-        let mut query =
-            QueryBuilder::new("INSERT INTO aliases (address, domain, target, comment, active, owner, provisioned)");
+        let mut query = QueryBuilder::new(
+            "INSERT INTO aliases (address, domain, target, comment, active, owner, active_from, expires_at, on_expiry, max_recv, provisioned)",
+        );
 
         query.push(" VALUES (");
         query.push_bind(name);
@@ -309,9 +507,17 @@ pub async fn provision_aliases(pool: &SqlitePool, state: &State) -> Result<()> {
         query.push(", ");
         query.push_bind(comment);
         query.push(", ");
-        query.push_bind(alias.active);
+        query.push_bind(active);
         query.push(", ");
         query.push_bind(&alias.owner);
+        query.push(", datetime(");
+        query.push_bind(active_from);
+        query.push("), datetime(");
+        query.push_bind(expires_at);
+        query.push("), ");
+        query.push_bind(alias.on_expiry);
+        query.push(", ");
+        query.push_bind(alias.max_recv);
         query.push(", TRUE)");
 
         query.push(" ON CONFLICT (address) DO UPDATE SET");
@@ -320,9 +526,18 @@ pub async fn provision_aliases(pool: &SqlitePool, state: &State) -> Result<()> {
         query.push(", comment = ");
         query.push_bind(comment);
         query.push(", active = ");
-        query.push_bind(alias.active);
+        query.push_bind(active);
         query.push(", owner = ");
         query.push_bind(&alias.owner);
+        query.push(", active_from = datetime(");
+        query.push_bind(active_from);
+        query.push("), expires_at = datetime(");
+        query.push_bind(expires_at);
+        query.push("), on_expiry = ");
+        query.push_bind(alias.on_expiry);
+        // Exhausted aliases are deactivated again by a trigger
+        query.push(", max_recv = ");
+        query.push_bind(alias.max_recv);
         query.push(", provisioned = TRUE");
 
         query.build().execute(pool).await?;
@@ -341,6 +556,7 @@ pub async fn provision(pool: &SqlitePool) -> Result<()> {
         .context(format!("Failed to read provision file: {}", provision_file))?;
     let state: State = toml::from_str(&file_content).context("Failed to parse provision state")?;
 
+    provision_roles(pool, &state).await?;
     provision_users(pool, &state).await?;
     provision_domains(pool, &state).await?;
     provision_mailboxes(pool, &state).await?;
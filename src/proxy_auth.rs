@@ -0,0 +1,112 @@
+use crate::auth::{ssr::AuthSession, User};
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http::HeaderName;
+use ipnet::IpNet;
+use sqlx::SqlitePool;
+use std::{net::SocketAddr, sync::OnceLock};
+
+static CONFIG: OnceLock<Option<ProxyAuthConfig>> = OnceLock::new();
+
+struct ProxyAuthConfig {
+    header: HeaderName,
+    trusted_proxies: Vec<IpNet>,
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+/// Parses a comma separated list of networks in CIDR notation. Plain addresses are single host networks.
+pub(crate) fn parse_networks(name: &str, value: &str) -> Result<Vec<IpNet>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            x.parse::<IpNet>()
+                .or_else(|_| x.parse::<std::net::IpAddr>().map(IpNet::from))
+                .context(format!("Invalid network in {name}: '{x}'"))
+        })
+        .collect()
+}
+
+/// Reads the reverse proxy authentication configuration from the environment. Must be called once on startup.
+pub fn init() -> Result<()> {
+    let config = match env("IDMAIL_PROXY_AUTH_TRUSTED_PROXIES") {
+        Some(trusted_proxies) => {
+            let trusted_proxies = parse_networks("IDMAIL_PROXY_AUTH_TRUSTED_PROXIES", &trusted_proxies)?;
+            if trusted_proxies.is_empty() {
+                bail!("IDMAIL_PROXY_AUTH_TRUSTED_PROXIES must contain at least one network");
+            }
+            let header = env("IDMAIL_PROXY_AUTH_HEADER").unwrap_or_else(|| "X-Remote-User".to_string());
+            let header = HeaderName::try_from(&header)
+                .context(format!("Invalid value for IDMAIL_PROXY_AUTH_HEADER: '{header}'"))?;
+            Some(ProxyAuthConfig {
+                header,
+                trusted_proxies,
+            })
+        }
+        None => None,
+    };
+
+    match &config {
+        Some(config) => log::info!(
+            "Reverse proxy authentication via header '{}' from {:?}",
+            config.header,
+            config.trusted_proxies
+        ),
+        None => log::info!("Reverse proxy authentication is disabled"),
+    }
+
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+/// Authenticates requests of trusted reverse proxies as the user named in the configured header,
+/// replacing the user of the session for that request. Requests without the header, or from any other
+/// address, keep using the session, so that the local login is still available for direct access.
+pub async fn middleware(State(pool): State<SqlitePool>, mut request: Request, next: Next) -> Response {
+    let Some(config) = CONFIG.get().and_then(Option::as_ref) else {
+        return next.run(request).await;
+    };
+
+    let trusted = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .is_some_and(|ConnectInfo(addr)| config.trusted_proxies.iter().any(|x| x.contains(&addr.ip())));
+    let username = request
+        .headers()
+        .get(&config.header)
+        .and_then(|x| x.to_str().ok())
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(str::to_string);
+
+    if let (true, Some(username)) = (trusted, username) {
+        // The proxy decides who is logged in, so an unknown user must not fall back to the session
+        let user = match User::get(&username, &pool).await {
+            Some(user) if user.active => Some(user),
+            Some(_) => {
+                log::warn!("denying reverse proxy authentication because user '{username}' is inactive");
+                None
+            }
+            None => {
+                log::warn!("denying reverse proxy authentication of unknown user '{username}'");
+                None
+            }
+        };
+        if let Some(auth) = request.extensions_mut().get_mut::<AuthSession>() {
+            auth.current_user = user;
+        }
+    }
+
+    next.run(request).await
+}
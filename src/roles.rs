@@ -0,0 +1,298 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::utils::{THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::{ev::MouseEvent, *};
+use leptos_struct_table::*;
+use serde::{Deserialize, Serialize};
+
+/// The rights a role can grant on the accounts of other users. Admins have all of them,
+/// and are the only ones who can manage users, roles and webhooks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    UsersRead,
+    SessionsRevoke,
+    LockoutsLift,
+    DomainsRead,
+    DomainsWrite,
+    DomainsDelete,
+    MailboxesRead,
+    MailboxesWrite,
+    MailboxesDelete,
+    MailboxesResetPassword,
+    AliasesRead,
+    AliasesWrite,
+    AliasesToggle,
+    AliasesDelete,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 14] = [
+        Permission::UsersRead,
+        Permission::SessionsRevoke,
+        Permission::LockoutsLift,
+        Permission::DomainsRead,
+        Permission::DomainsWrite,
+        Permission::DomainsDelete,
+        Permission::MailboxesRead,
+        Permission::MailboxesWrite,
+        Permission::MailboxesDelete,
+        Permission::MailboxesResetPassword,
+        Permission::AliasesRead,
+        Permission::AliasesWrite,
+        Permission::AliasesToggle,
+        Permission::AliasesDelete,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Permission::UsersRead => "users.read",
+            Permission::SessionsRevoke => "sessions.revoke",
+            Permission::LockoutsLift => "lockouts.lift",
+            Permission::DomainsRead => "domains.read",
+            Permission::DomainsWrite => "domains.write",
+            Permission::DomainsDelete => "domains.delete",
+            Permission::MailboxesRead => "mailboxes.read",
+            Permission::MailboxesWrite => "mailboxes.write",
+            Permission::MailboxesDelete => "mailboxes.delete",
+            Permission::MailboxesResetPassword => "mailboxes.reset_password",
+            Permission::AliasesRead => "aliases.read",
+            Permission::AliasesWrite => "aliases.write",
+            Permission::AliasesToggle => "aliases.toggle",
+            Permission::AliasesDelete => "aliases.delete",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Permission> {
+        Permission::ALL.into_iter().find(|x| x.name() == name)
+    }
+
+    /// Whether the permission is selected by the given comma separated list of
+    /// permission names and patterns like `aliases.*`. Unlike webhook event filters,
+    /// an empty list selects nothing.
+    pub fn matches(self, filter: &str) -> bool {
+        let name = self.name();
+        filter
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .any(|pattern| match pattern.strip_suffix(".*") {
+                _ if pattern == "*" => true,
+                Some(entity) => name.strip_prefix(entity).is_some_and(|x| x.starts_with('.')),
+                None => pattern == name,
+            })
+    }
+
+    /// Returns all permissions selected by a comma separated list of names and patterns.
+    pub fn parse_list(filter: &str) -> Vec<Permission> {
+        Permission::ALL.into_iter().filter(|x| x.matches(filter)).collect()
+    }
+}
+
+/// Returns an error for each entry of a permission list that can never match.
+pub fn invalid_permission_patterns(filter: &str) -> Vec<String> {
+    filter
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .filter(|pattern| !Permission::ALL.iter().any(|x| x.matches(pattern)))
+        .map(|pattern| format!("Unknown permission '{pattern}'"))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct Role {
+    #[table(class = "w-1")]
+    pub name: String,
+    pub description: String,
+    pub permissions: String,
+    #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoleQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{Permission, Role};
+    use crate::{api::ApiError, auth::User};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::ops::Range;
+
+    /// Returns the permissions granted by the given role. Unknown roles grant nothing.
+    pub async fn permissions(pool: &SqlitePool, role: &str) -> Result<Vec<Permission>, ApiError> {
+        let permissions = sqlx::query_scalar::<_, String>("SELECT permissions FROM roles WHERE name = ?")
+            .bind(role)
+            .fetch_optional(pool)
+            .await?;
+        Ok(permissions.as_deref().map(Permission::parse_list).unwrap_or_default())
+    }
+
+    /// Fails if the given role does not exist.
+    pub async fn ensure_exists(pool: &SqlitePool, role: &str) -> Result<(), ApiError> {
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT * FROM roles WHERE name = ?)")
+            .bind(role)
+            .fetch_one(pool)
+            .await?;
+        if !exists {
+            return Err(ApiError::BadRequest(format!("Role '{role}' does not exist")));
+        }
+        Ok(())
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<Role>, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+
+        let mut query = QueryBuilder::new("SELECT name, description, permissions, created_at FROM roles");
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<Role>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM roles")
+            .fetch_one(pool)
+            .await?;
+        Ok(count as usize)
+    }
+
+    /// Returns the names of all roles, to choose from when assigning one.
+    pub async fn names(pool: &SqlitePool, user: &User) -> Result<Vec<String>, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+        Ok(sqlx::query_scalar::<_, String>("SELECT name FROM roles ORDER BY name")
+            .fetch_all(pool)
+            .await?)
+    }
+}
+
+#[server]
+pub async fn list_roles(query: RoleQuery) -> Result<Vec<Role>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let RoleQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, Role::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn role_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn role_names() -> Result<Vec<String>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::names(&pool, &user).await?)
+}
+
+#[derive(Default)]
+pub struct RoleTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<Role> for RoleTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<Role>, Range<usize>), String> {
+        list_roles(RoleQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        role_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+/// Lists the available roles and their permissions. Roles are defined in the database or by provisioning.
+#[component]
+pub fn Roles() -> impl IntoView {
+    let mut rows = RoleTableDataProvider::default();
+    let default_sorting = VecDeque::from([(0, ColumnSort::Ascending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload_controller = ReloadController::default();
+    let (count, set_count) = create_signal(0);
+
+    #[allow(unused_variables, non_snake_case)]
+    let role_row_renderer = move |class: Signal<String>,
+                                  row: Role,
+                                  index: usize,
+                                  selected: Signal<bool>,
+                                  on_select: EventHandler<MouseEvent>,
+                                  on_change: EventHandler<ChangeEvent<Role>>| {
+        view! {
+            <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                {row.render_row(index, on_change)}
+            </tr>
+        }
+    };
+
+    view! {
+        <div class="h-full flex-1 flex-col mt-12">
+            <div class="flex items-center justify-between space-y-2 mb-4">
+                <h3 class="text-2xl font-bold">Roles</h3>
+            </div>
+            <div class="space-y-4">
+                <div class="flex flex-wrap items-center justify-between">
+                    <div class="flex flex-1"></div>
+                    <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                        {count} " roles"
+                    </div>
+                </div>
+
+                <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                    <div class="overflow-auto grow min-h-0">
+                        <table class="table-auto text-left w-full">
+                            <TableContent
+                                rows
+                                sorting=sorting
+                                sorting_mode=SortingMode::SingleColumn
+                                row_renderer=role_row_renderer
+                                reload_controller=reload_controller
+                                loading_row_display_limit=0
+                                on_row_count=set_count
+                            />
+                        </table>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,861 @@
+#![allow(clippy::unused_unit)] // The TableRow derive generates unit expressions
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::auth::User;
+use crate::roles::Permission;
+use crate::utils::{DeleteModal, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::{ev::MouseEvent, logging::error, *};
+use leptos_icons::Icon;
+use leptos_struct_table::*;
+use serde::{Deserialize, Serialize};
+
+/// A session of the current user
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct Session {
+    #[table(skip)]
+    pub id: i64,
+    pub device: String,
+    #[table(class = "w-1", title = "IP", none_value = "-")]
+    pub ip: Option<String>,
+    #[table(class = "w-1", title = "Last seen", renderer = "TimediffRenderer")]
+    pub last_seen_at: DateTime<Utc>,
+    #[table(class = "w-1", title = "Logged in", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+    /// Whether this is the session the list was requested with
+    #[table(skip)]
+    pub current: bool,
+}
+
+/// A session of any user or mailbox, as seen by admins
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct UserSession {
+    #[table(skip)]
+    pub id: i64,
+    #[table(title = "User")]
+    pub username: String,
+    pub device: String,
+    #[table(class = "w-1", title = "IP", none_value = "-")]
+    pub ip: Option<String>,
+    #[table(class = "w-1", title = "Last seen", renderer = "TimediffRenderer")]
+    pub last_seen_at: DateTime<Utc>,
+    #[table(class = "w-1", title = "Logged in", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{Session, UserSession};
+    use crate::{
+        api::ApiError,
+        auth::{ssr::AuthSession, User},
+        roles::Permission,
+    };
+    use anyhow::{bail, Context, Result};
+    use axum::{
+        extract::{Request, State},
+        middleware::Next,
+        response::Response,
+    };
+    use axum_session::SameSite;
+    use sha2::{Digest, Sha256};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::{net::IpAddr, ops::Range, sync::OnceLock};
+
+    /// The last seen time of a session is only updated after this many seconds, to avoid a write on every request
+    const LAST_SEEN_PRECISION: u32 = 60;
+
+    static CONFIG: OnceLock<SessionConfig> = OnceLock::new();
+
+    /// What a session is bound to, besides its cookie
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Binding {
+        None,
+        /// The user agent that logged in
+        UserAgent,
+        /// The user agent and the address that logged in
+        IpAndUserAgent,
+    }
+
+    #[derive(Debug)]
+    struct SessionConfig {
+        /// Seconds of inactivity after which a session without "remember me" ends
+        idle_timeout: u32,
+        /// Seconds after which every session ends
+        max_lifetime: u32,
+        cookie_name: String,
+        cookie_domain: Option<String>,
+        cookie_secure: bool,
+        cookie_same_site: SameSite,
+        binding: Binding,
+    }
+
+    impl Default for SessionConfig {
+        fn default() -> Self {
+            Self {
+                idle_timeout: 6 * 60 * 60,
+                max_lifetime: 30 * 24 * 60 * 60,
+                cookie_name: "session".to_string(),
+                cookie_domain: None,
+                cookie_secure: false,
+                cookie_same_site: SameSite::Lax,
+                binding: Binding::None,
+            }
+        }
+    }
+
+    fn env(name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Reads a positive number of seconds from the given environment variable.
+    fn seconds_from_env(name: &str, default: u32) -> Result<u32> {
+        let Ok(value) = std::env::var(name) else {
+            return Ok(default);
+        };
+        value
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|x| *x > 0)
+            .context(format!("Invalid value for {name}: '{value}'"))
+    }
+
+    /// Reads the session timeouts from the environment. Must be called once on startup.
+    pub fn init() -> Result<()> {
+        let defaults = SessionConfig::default();
+        let config = SessionConfig {
+            idle_timeout: seconds_from_env("IDMAIL_SESSION_IDLE_TIMEOUT", defaults.idle_timeout)?,
+            max_lifetime: seconds_from_env("IDMAIL_SESSION_MAX_LIFETIME", defaults.max_lifetime)?,
+            cookie_name: env("IDMAIL_COOKIE_NAME").unwrap_or(defaults.cookie_name),
+            cookie_domain: env("IDMAIL_COOKIE_DOMAIN"),
+            cookie_secure: match env("IDMAIL_COOKIE_SECURE") {
+                Some(x) => x
+                    .parse::<bool>()
+                    .context(format!("Invalid value for IDMAIL_COOKIE_SECURE: '{x}'"))?,
+                None => defaults.cookie_secure,
+            },
+            cookie_same_site: match env("IDMAIL_COOKIE_SAME_SITE")
+                .map(|x| x.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("strict") => SameSite::Strict,
+                Some("lax") => SameSite::Lax,
+                Some("none") => SameSite::None,
+                Some(x) => bail!("Invalid value for IDMAIL_COOKIE_SAME_SITE: '{x}'"),
+                None => defaults.cookie_same_site,
+            },
+            binding: match env("IDMAIL_SESSION_BINDING").as_deref() {
+                Some("none") => Binding::None,
+                Some("user-agent") => Binding::UserAgent,
+                Some("ip-and-user-agent") => Binding::IpAndUserAgent,
+                Some(x) => bail!("Invalid value for IDMAIL_SESSION_BINDING: '{x}'"),
+                None => defaults.binding,
+            },
+        };
+        if !config
+            .cookie_name
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || "-_.".contains(x))
+        {
+            bail!("Invalid value for IDMAIL_COOKIE_NAME: '{}'", config.cookie_name);
+        }
+        anyhow::ensure!(
+            config.cookie_secure || config.cookie_same_site != SameSite::None,
+            "IDMAIL_COOKIE_SAME_SITE=none requires IDMAIL_COOKIE_SECURE=true"
+        );
+        anyhow::ensure!(
+            config.idle_timeout <= config.max_lifetime,
+            "IDMAIL_SESSION_IDLE_TIMEOUT must not be longer than IDMAIL_SESSION_MAX_LIFETIME"
+        );
+
+        log::info!("Using session timeouts: {config:?}");
+        let _ = CONFIG.set(config);
+        Ok(())
+    }
+
+    fn config() -> &'static SessionConfig {
+        CONFIG.get_or_init(SessionConfig::default)
+    }
+
+    /// Applies the configured cookie attributes to the configuration of the session layer.
+    pub fn with_cookie_config(session_config: axum_session::SessionConfig) -> axum_session::SessionConfig {
+        let config = config();
+        let session_config = session_config
+            .with_session_name(config.cookie_name.clone())
+            .with_secure(config.cookie_secure)
+            .with_cookie_same_site(config.cookie_same_site)
+            .with_http_only(true);
+        match &config.cookie_domain {
+            Some(domain) => session_config.with_cookie_domain(domain.clone()),
+            None => session_config,
+        }
+    }
+
+    /// The name of the session cookie.
+    pub fn cookie_name() -> &'static str {
+        &config().cookie_name
+    }
+
+    /// How long a session without "remember me" lasts without activity.
+    pub fn idle_timeout() -> chrono::Duration {
+        chrono::Duration::seconds(config().idle_timeout.into())
+    }
+
+    /// How long any session lasts at most.
+    pub fn max_lifetime() -> chrono::Duration {
+        chrono::Duration::seconds(config().max_lifetime.into())
+    }
+
+    /// Where a login comes from
+    #[derive(Debug, Default)]
+    pub struct Client {
+        pub ip: Option<IpAddr>,
+        pub user_agent: Option<String>,
+    }
+
+    impl Client {
+        /// Returns the client of the current server function request.
+        pub fn current() -> Self {
+            leptos::use_context::<http::request::Parts>()
+                .map(|parts| Self::from_parts(&parts))
+                .unwrap_or_default()
+        }
+
+        /// Returns the client of a request.
+        pub fn from_parts(parts: &http::request::Parts) -> Self {
+            Self::new(&parts.headers, &parts.extensions)
+        }
+
+        /// Returns the client of a request that is passed through a middleware.
+        pub fn from_request(request: &Request) -> Self {
+            Self::new(request.headers(), request.extensions())
+        }
+
+        fn new(headers: &http::HeaderMap, extensions: &http::Extensions) -> Self {
+            Self {
+                ip: crate::client_ip::client_ip(headers, extensions),
+                user_agent: headers
+                    .get(http::header::USER_AGENT)
+                    .and_then(|x| x.to_str().ok().map(str::to_string)),
+            }
+        }
+    }
+
+    /// Describes the browser and operating system of a user agent, like "Firefox on Linux".
+    fn describe_device(user_agent: Option<&str>) -> String {
+        let Some(user_agent) = user_agent else {
+            return "Unknown device".to_string();
+        };
+
+        let browser = [
+            ("Edg/", "Edge"),
+            ("OPR/", "Opera"),
+            ("Firefox/", "Firefox"),
+            ("FxiOS/", "Firefox"),
+            ("CriOS/", "Chrome"),
+            ("Chrome/", "Chrome"),
+            ("Safari/", "Safari"),
+        ]
+        .into_iter()
+        .find(|(pattern, _)| user_agent.contains(pattern))
+        .map(|(_, name)| name);
+        let os = [
+            ("Android", "Android"),
+            ("iPhone", "iOS"),
+            ("iPad", "iPadOS"),
+            ("Windows", "Windows"),
+            ("Macintosh", "macOS"),
+            ("CrOS", "ChromeOS"),
+            ("Linux", "Linux"),
+        ]
+        .into_iter()
+        .find(|(pattern, _)| user_agent.contains(pattern))
+        .map(|(_, name)| name);
+
+        match (browser, os) {
+            (Some(browser), Some(os)) => format!("{browser} on {os}"),
+            (Some(name), None) | (None, Some(name)) => name.to_string(),
+            // Probably not a browser, so show the product name like "curl"
+            (None, None) => user_agent
+                .split(['/', ' '])
+                .next()
+                .filter(|x| !x.is_empty())
+                .unwrap_or("Unknown device")
+                .chars()
+                .take(64)
+                .collect(),
+        }
+    }
+
+    /// Hashes the user agent of a client, which is enough to recognize it again.
+    fn fingerprint(client: &Client) -> String {
+        hex::encode(Sha256::digest(client.user_agent.as_deref().unwrap_or_default()))
+    }
+
+    /// The condition that a session has not expired yet
+    fn push_valid(query: &mut QueryBuilder<'_, sqlx::Sqlite>) {
+        let config = config();
+        query.push("created_at > datetime('now', ");
+        query.push_bind(format!("-{} seconds", config.max_lifetime));
+        query.push(") AND (remember OR last_seen_at > datetime('now', ");
+        query.push_bind(format!("-{} seconds", config.idle_timeout));
+        query.push("))");
+    }
+
+    /// Records a new session for the given user and returns its token.
+    pub async fn create(pool: &SqlitePool, username: &str, remember: bool, client: Client) -> Result<String, ApiError> {
+        let mut query = QueryBuilder::new("DELETE FROM user_sessions WHERE NOT (");
+        push_valid(&mut query);
+        query.push(")");
+        query.build().execute(pool).await?;
+
+        let mut buf = [0u8; 32];
+        getrandom::getrandom(&mut buf).map_err(|e| ApiError::ServerError(e.to_string()))?;
+        let token = hex::encode(buf);
+
+        sqlx::query(
+            "INSERT INTO user_sessions (token, username, remember, device, ip, fingerprint) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&token)
+        .bind(username)
+        .bind(remember)
+        .bind(describe_device(client.user_agent.as_deref()))
+        .bind(client.ip.map(|x| x.to_string()))
+        .bind(fingerprint(&client))
+        .execute(pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Returns the user of the session with the given token, if it is still valid, and marks it as used.
+    pub async fn validate(pool: &SqlitePool, token: &str) -> Result<Option<String>, ApiError> {
+        let mut query = QueryBuilder::new("SELECT username FROM user_sessions WHERE token = ");
+        query.push_bind(token);
+        query.push(" AND ");
+        push_valid(&mut query);
+        let Some(username) = query.build_query_scalar::<String>().fetch_optional(pool).await? else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "UPDATE user_sessions SET last_seen_at = CURRENT_TIMESTAMP \
+            WHERE token = ? AND last_seen_at < datetime('now', ?)",
+        )
+        .bind(token)
+        .bind(format!("-{LAST_SEEN_PRECISION} seconds"))
+        .execute(pool)
+        .await?;
+
+        Ok(Some(username))
+    }
+
+    /// Ignores the session of requests from another client than the one that logged in, if sessions are bound
+    /// to their client. The session itself stays valid, so that its rightful owner is not logged out.
+    pub async fn binding_middleware(State(pool): State<SqlitePool>, mut request: Request, next: Next) -> Response {
+        let binding = config().binding;
+        let token = match request.extensions().get::<AuthSession>() {
+            Some(auth) if binding != Binding::None && auth.current_user.is_some() => auth.id.clone(),
+            _ => return next.run(request).await,
+        };
+
+        let client = Client::from_request(&request);
+        let bound = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+            "SELECT fingerprint, ip FROM user_sessions WHERE token = ?",
+        )
+        .bind(&token)
+        .fetch_optional(&pool)
+        .await;
+        let matches = match bound {
+            Ok(Some((bound_fingerprint, bound_ip))) => {
+                bound_fingerprint == Some(fingerprint(&client))
+                    && (binding == Binding::UserAgent || bound_ip == client.ip.map(|x| x.to_string()))
+            }
+            Ok(None) => false,
+            Err(e) => {
+                log::error!("failed to check the binding of a session: {e}");
+                false
+            }
+        };
+
+        if !matches {
+            if let Some(auth) = request.extensions_mut().get_mut::<AuthSession>() {
+                if let Some(user) = auth.current_user.take() {
+                    log::warn!(
+                        "ignoring session of user '{}' used by another client from {:?}",
+                        user.username,
+                        client.ip
+                    );
+                }
+            }
+        }
+
+        next.run(request).await
+    }
+
+    /// Ends the session with the given token.
+    pub async fn end(pool: &SqlitePool, token: &str) -> Result<(), ApiError> {
+        sqlx::query("DELETE FROM user_sessions WHERE token = ?")
+            .bind(token)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Ends all sessions of the given user or mailbox, except for the session with the given token.
+    pub async fn end_all(pool: &SqlitePool, username: &str, except_token: Option<&str>) -> Result<(), ApiError> {
+        let ended = sqlx::query("DELETE FROM user_sessions WHERE username = ? AND token IS NOT ?")
+            .bind(username)
+            .bind(except_token)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        if ended > 0 {
+            log::info!("ended {ended} sessions of user '{username}'");
+        }
+        Ok(())
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        current_token: &str,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<Session>, ApiError> {
+        let mut query = QueryBuilder::new("SELECT id, device, ip, last_seen_at, created_at, token = ");
+        query.push_bind(current_token);
+        query.push(" AS current FROM user_sessions WHERE username = ");
+        query.push_bind(&user.username);
+        query.push(" AND ");
+        push_valid(&mut query);
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<Session>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM user_sessions WHERE username = ");
+        query.push_bind(&user.username);
+        query.push(" AND ");
+        push_valid(&mut query);
+        let count = query.build_query_scalar::<i64>().fetch_one(pool).await?;
+        Ok(count as usize)
+    }
+
+    pub async fn list_all(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<UserSession>, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+
+        let mut query =
+            QueryBuilder::new("SELECT id, username, device, ip, last_seen_at, created_at FROM user_sessions WHERE ");
+        push_valid(&mut query);
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<UserSession>().fetch_all(pool).await?)
+    }
+
+    pub async fn count_all(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM user_sessions WHERE ");
+        push_valid(&mut query);
+        let count = query.build_query_scalar::<i64>().fetch_one(pool).await?;
+        Ok(count as usize)
+    }
+
+    /// Ends a session of the user, or of anyone if the user may revoke sessions.
+    pub async fn revoke(pool: &SqlitePool, user: &User, id: i64) -> Result<(), ApiError> {
+        let mut query = QueryBuilder::new("DELETE FROM user_sessions WHERE id = ");
+        query.push_bind(id);
+        if !user.can(Permission::SessionsRevoke) {
+            query.push(" AND username = ");
+            query.push_bind(&user.username);
+        }
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("Session {id} does not exist")));
+        }
+
+        log::info!("session {id} was revoked by '{}'", user.username);
+        Ok(())
+    }
+}
+
+#[server]
+pub async fn list_sessions(query: SessionQuery) -> Result<Vec<Session>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let auth = crate::database::ssr::auth()?;
+    let SessionQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, &auth.id, Session::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn session_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn list_all_sessions(query: SessionQuery) -> Result<Vec<UserSession>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let SessionQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list_all(&pool, &user, UserSession::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn all_session_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count_all(&pool, &user).await?)
+}
+
+#[server]
+pub async fn revoke_session(id: i64) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::revoke(&pool, &user, id).await?)
+}
+
+/// Ends all sessions of the current user, including this one.
+#[server]
+pub async fn logout_everywhere() -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let auth = crate::database::ssr::auth()?;
+    let pool = crate::database::ssr::pool()?;
+
+    ssr::end_all(&pool, &user.username, None).await?;
+    auth.logout_user();
+    leptos_axum::redirect("/login");
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct SessionTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<Session> for SessionTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<Session>, Range<usize>), String> {
+        list_sessions(SessionQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        session_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+#[derive(Default)]
+pub struct UserSessionTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<UserSession> for UserSessionTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<UserSession>, Range<usize>), String> {
+        list_all_sessions(SessionQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        all_session_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+/// The sessions of the current user
+#[component]
+pub fn Sessions() -> impl IntoView {
+    let mut rows = SessionTableDataProvider::default();
+    let default_sorting = VecDeque::from([(2, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload_controller = ReloadController::default();
+    let (count, set_count) = create_signal(0);
+
+    let revoke_modal_session = create_rw_signal(None);
+    let revoke_modal_session_id = create_rw_signal(None);
+    let logout_everywhere_modal = create_rw_signal(None);
+
+    #[allow(unused_variables, non_snake_case)]
+    let session_row_renderer = move |class: Signal<String>,
+                                     row: Session,
+                                     index: usize,
+                                     selected: Signal<bool>,
+                                     on_select: EventHandler<MouseEvent>,
+                                     on_change: EventHandler<ChangeEvent<Session>>| {
+        let current = row.current;
+        let revoke_name = format!("session on {}", row.device);
+        let revoke_id = row.id;
+        view! {
+            <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                {row.render_row(index, on_change)}
+                <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                    <Show
+                        when=move || !current
+                        fallback=|| {
+                            view! {
+                                <span class="text-sm font-medium text-gray-500 dark:text-gray-400 px-4">
+                                    "This device"
+                                </span>
+                            }
+                        }
+                    >
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            on:click={
+                                let revoke_name = revoke_name.clone();
+                                move |_| {
+                                    revoke_modal_session_id.set(Some(revoke_id));
+                                    revoke_modal_session.set(Some(revoke_name.clone()));
+                                }
+                            }
+                        >
+                            <Icon icon=icondata::FiLogOut class="w-5 h-5"/>
+                        </button>
+                    </Show>
+                </td>
+            </tr>
+        }
+    };
+
+    view! {
+        <div class="flex items-center justify-between space-y-2 mt-8 mb-4">
+            <h3 class="text-2xl font-bold">Sessions</h3>
+        </div>
+        <div class="space-y-4">
+            <p class="text-sm text-gray-500 dark:text-gray-400">
+                "These devices are logged in to your account. Changing your password logs out all other devices."
+            </p>
+            <div class="flex flex-wrap items-center justify-between">
+                <button
+                    type="button"
+                    class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-red-600 dark:bg-red-700 hover:bg-red-500 dark:hover:bg-red-600 focus:ring-red-300 dark:focus:ring-red-900"
+                    on:click=move |_| logout_everywhere_modal.set(Some("all sessions".to_string()))
+                >
+                    <Icon icon=icondata::FiLogOut class="w-6 h-6 me-2"/>
+                    "Log out everywhere"
+                </button>
+                <div class="flex flex-1"></div>
+                <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                    {count} " sessions"
+                </div>
+            </div>
+
+            <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                <div class="overflow-auto grow min-h-0">
+                    <table class="table-auto text-left w-full">
+                        <TableContent
+                            rows
+                            sorting=sorting
+                            sorting_mode=SortingMode::SingleColumn
+                            row_renderer=session_row_renderer
+                            reload_controller=reload_controller
+                            loading_row_display_limit=0
+                            on_row_count=set_count
+                        />
+                    </table>
+                </div>
+            </div>
+        </div>
+
+        <DeleteModal
+            data=revoke_modal_session
+            text="Are you sure you want to log out this device?".into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Some(id) = revoke_modal_session_id.get_untracked() {
+                        if let Err(e) = revoke_session(id).await {
+                            error!("Failed to revoke session: {}", e);
+                        } else {
+                            reload_controller.reload();
+                        }
+                    }
+                    revoke_modal_session.set(None);
+                    revoke_modal_session_id.set(None);
+                });
+            }
+        />
+
+        <DeleteModal
+            data=logout_everywhere_modal
+            text="Are you sure you want to log out all devices, including this one?".into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Err(e) = logout_everywhere().await {
+                        error!("Failed to log out everywhere: {}", e);
+                    }
+                    logout_everywhere_modal.set(None);
+                });
+            }
+        />
+    }
+}
+
+/// The sessions of all users and mailboxes
+#[component]
+pub fn AllSessions(user: User) -> impl IntoView {
+    let can_revoke = user.can(Permission::SessionsRevoke);
+    let mut rows = UserSessionTableDataProvider::default();
+    let default_sorting = VecDeque::from([(3, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let reload_controller = ReloadController::default();
+    let (count, set_count) = create_signal(0);
+
+    let revoke_modal_session = create_rw_signal(None);
+    let revoke_modal_session_id = create_rw_signal(None);
+
+    #[allow(unused_variables, non_snake_case)]
+    let session_row_renderer = move |class: Signal<String>,
+                                     row: UserSession,
+                                     index: usize,
+                                     selected: Signal<bool>,
+                                     on_select: EventHandler<MouseEvent>,
+                                     on_change: EventHandler<ChangeEvent<UserSession>>| {
+        let revoke_name = format!("session of {} on {}", row.username, row.device);
+        let revoke_id = row.id;
+        view! {
+            <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                {row.render_row(index, on_change)}
+                <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                    <button
+                        class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                        on:click=move |_| {
+                            revoke_modal_session_id.set(Some(revoke_id));
+                            revoke_modal_session.set(Some(revoke_name.clone()));
+                        }
+
+                        disabled=move || !can_revoke
+                    >
+                        <Icon icon=icondata::FiLogOut class="w-5 h-5"/>
+                    </button>
+                </td>
+            </tr>
+        }
+    };
+
+    view! {
+        <div class="h-full flex-1 flex-col mt-12">
+            <div class="flex items-center justify-between space-y-2 mb-4">
+                <h3 class="text-2xl font-bold">Sessions</h3>
+            </div>
+            <div class="space-y-4">
+                <div class="flex flex-wrap items-center justify-between">
+                    <button
+                        type="button"
+                        class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-gray-800 dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-white dark:bg-black border-[1.5px] border-gray-200 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900"
+                        on:click=move |_| reload_controller.reload()
+                    >
+                        <Icon icon=icondata::FiRefreshCw class="w-5 h-5 me-2"/>
+                        Refresh
+                    </button>
+                    <div class="flex flex-1"></div>
+                    <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                        {count} " sessions"
+                    </div>
+                </div>
+
+                <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                    <div class="overflow-auto grow min-h-0">
+                        <table class="table-auto text-left w-full">
+                            <TableContent
+                                rows
+                                sorting=sorting
+                                sorting_mode=SortingMode::SingleColumn
+                                row_renderer=session_row_renderer
+                                reload_controller=reload_controller
+                                loading_row_display_limit=0
+                                on_row_count=set_count
+                            />
+                        </table>
+                    </div>
+                </div>
+            </div>
+        </div>
+
+        <DeleteModal
+            data=revoke_modal_session
+            text="Are you sure you want to end this session?".into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Some(id) = revoke_modal_session_id.get_untracked() {
+                        if let Err(e) = revoke_session(id).await {
+                            error!("Failed to revoke session: {}", e);
+                        } else {
+                            reload_controller.reload();
+                        }
+                    }
+                    revoke_modal_session.set(None);
+                    revoke_modal_session_id.set(None);
+                });
+            }
+        />
+    }
+}
@@ -1,7 +1,9 @@
+use crate::limits::RateLimiter;
 use axum::extract::FromRef;
 use leptos::LeptosOptions;
 use leptos_router::RouteListing;
 use sqlx::SqlitePool;
+use std::sync::Arc;
 
 /// This takes advantage of Axum's SubStates feature by deriving FromRef. This is the only way to have more than one
 /// item in Axum's State. Leptos requires you to have leptosOptions in your State struct for the leptos route handlers
@@ -10,4 +12,8 @@ pub struct AppState {
     pub leptos_options: LeptosOptions,
     pub pool: SqlitePool,
     pub routes: Vec<RouteListing>,
+    /// Random key generated on startup to sign short-lived values handed out to API clients
+    pub signing_key: [u8; 32],
+    /// Request counters of API tokens and client addresses
+    pub rate_limiter: Arc<RateLimiter>,
 }
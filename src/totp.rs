@@ -0,0 +1,644 @@
+use crate::utils::{EditModal, Modal};
+
+use leptos::html::Dialog;
+use leptos::*;
+use leptos_icons::Icon;
+use serde::{Deserialize, Serialize};
+
+/// The two-factor authentication state of the current account
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpStatus {
+    pub enabled: bool,
+    /// The number of unused recovery codes
+    pub recovery_codes_left: usize,
+    /// Whether the account must enable two-factor authentication to use its admin rights
+    pub required: bool,
+}
+
+/// The details of a new TOTP secret that still needs to be confirmed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpEnrolment {
+    /// The base32 encoded secret, for manual entry
+    pub secret: String,
+    /// The `otpauth://` URL of the secret
+    pub url: String,
+    /// The URL as a QR code in SVG format
+    pub qr_code: String,
+}
+
+/// Whether the code has the format of a TOTP code, otherwise it may be a recovery code.
+pub fn is_totp_code(code: &str) -> bool {
+    code.len() == 6 && code.chars().all(|x| x.is_ascii_digit())
+}
+
+/// Removes whitespace and separators that users may enter along with a code.
+pub fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|x| !x.is_whitespace() && *x != '-')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{is_totp_code, normalize_code, TotpEnrolment, TotpStatus};
+    use crate::{api::ApiError, api_tokens::ssr::hash_token, auth::User, passwords};
+    use anyhow::{Context, Result};
+    use qrcode::{render::svg, QrCode};
+    use rand::{rngs::OsRng, seq::SliceRandom};
+    use sqlx::SqlitePool;
+    use std::{
+        sync::OnceLock,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+    use totp_rs::{Algorithm, Secret, TOTP};
+
+    static REQUIRED_FOR_ADMINS: OnceLock<bool> = OnceLock::new();
+
+    const ISSUER: &str = "idmail";
+    /// The validity of a single code in seconds
+    const STEP: u64 = 30;
+    const RECOVERY_CODE_COUNT: usize = 10;
+    /// Recovery codes use lowercase letters and digits that can't be confused with each other
+    const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+    /// Reads whether admins must use two-factor authentication from the environment.
+    /// Must be called once on startup.
+    pub fn init() -> Result<()> {
+        let required = match std::env::var("IDMAIL_REQUIRE_ADMIN_TOTP") {
+            Ok(value) => value
+                .trim()
+                .parse::<bool>()
+                .context(format!("Invalid value for IDMAIL_REQUIRE_ADMIN_TOTP: '{value}'"))?,
+            Err(_) => false,
+        };
+
+        log::info!("Two-factor authentication required for admins: {required}");
+        let _ = REQUIRED_FOR_ADMINS.set(required);
+        Ok(())
+    }
+
+    /// Whether admins can only use their admin rights after enabling two-factor authentication
+    pub fn required_for_admins() -> bool {
+        *REQUIRED_FOR_ADMINS.get().unwrap_or(&false)
+    }
+
+    fn totp(secret: &str, username: &str) -> Result<TOTP, ApiError> {
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| ApiError::ServerError(format!("invalid TOTP secret: {e:?}")))?;
+        // Codes are only checked for a single time step, the allowed clock skew is handled by `check_code`
+        Ok(TOTP::new_unchecked(
+            Algorithm::SHA1,
+            6,
+            0,
+            STEP,
+            secret,
+            Some(ISSUER.to_string()),
+            username.to_string(),
+        ))
+    }
+
+    /// Whether the user or mailbox has enabled two-factor authentication
+    pub async fn is_enabled(pool: &SqlitePool, username: &str) -> Result<bool, sqlx::Error> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM totp WHERE username = ? AND enabled = TRUE")
+            .bind(username)
+            .fetch_one(pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    pub async fn status(pool: &SqlitePool, user: &User) -> Result<TotpStatus, ApiError> {
+        let recovery_codes_left = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM totp_recovery_codes WHERE username = ? AND used_at IS NULL",
+        )
+        .bind(&user.username)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(TotpStatus {
+            enabled: is_enabled(pool, &user.username).await?,
+            recovery_codes_left: recovery_codes_left as usize,
+            required: (user.admin || !user.permissions.is_empty() || user.admin_requires_totp) && required_for_admins(),
+        })
+    }
+
+    /// Creates a new secret for the user, which is only used after it has been confirmed.
+    pub async fn start_enrolment(pool: &SqlitePool, user: &User) -> Result<TotpEnrolment, ApiError> {
+        if is_enabled(pool, &user.username).await? {
+            return Err(ApiError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let mut secret = [0u8; 20];
+        getrandom::getrandom(&mut secret).map_err(|e| ApiError::ServerError(e.to_string()))?;
+        let secret = Secret::Raw(secret.to_vec()).to_encoded().to_string();
+        let totp = totp(&secret, &user.username)?;
+
+        sqlx::query(
+            "INSERT INTO totp (username, secret) VALUES (?, ?) \
+            ON CONFLICT (username) DO UPDATE SET secret = excluded.secret, last_used_step = 0",
+        )
+        .bind(&user.username)
+        .bind(&secret)
+        .execute(pool)
+        .await?;
+
+        let url = totp.get_url();
+        let qr_code = QrCode::new(url.as_bytes())
+            .map_err(|e| ApiError::ServerError(e.to_string()))?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+
+        Ok(TotpEnrolment { secret, url, qr_code })
+    }
+
+    /// Enables two-factor authentication if the code matches the new secret and returns the recovery codes.
+    pub async fn confirm_enrolment(pool: &SqlitePool, user: &User, code: &str) -> Result<Vec<String>, ApiError> {
+        if !check_code(pool, &user.username, code, false).await? {
+            return Err(ApiError::BadRequest("Invalid code".to_string()));
+        }
+
+        sqlx::query("UPDATE totp SET enabled = TRUE WHERE username = ?")
+            .bind(&user.username)
+            .execute(pool)
+            .await?;
+        log::info!("enabled two-factor authentication for '{}'", user.username);
+
+        replace_recovery_codes(pool, &user.username).await
+    }
+
+    /// Replaces all recovery codes of the user if the code is valid.
+    pub async fn regenerate_recovery_codes(
+        pool: &SqlitePool,
+        user: &User,
+        code: &str,
+    ) -> Result<Vec<String>, ApiError> {
+        if !check_code(pool, &user.username, code, true).await? {
+            return Err(ApiError::BadRequest("Invalid code".to_string()));
+        }
+
+        replace_recovery_codes(pool, &user.username).await
+    }
+
+    pub async fn disable(pool: &SqlitePool, user: &User) -> Result<(), ApiError> {
+        sqlx::query("DELETE FROM totp WHERE username = ?")
+            .bind(&user.username)
+            .execute(pool)
+            .await?;
+        sqlx::query("DELETE FROM totp_recovery_codes WHERE username = ?")
+            .bind(&user.username)
+            .execute(pool)
+            .await?;
+        log::info!("disabled two-factor authentication for '{}'", user.username);
+
+        Ok(())
+    }
+
+    /// Checks the second factor of a login, which is either a TOTP code or an unused recovery code.
+    pub async fn verify(pool: &SqlitePool, username: &str, code: &str) -> Result<bool, ApiError> {
+        let code = normalize_code(code);
+        if is_totp_code(&code) {
+            return check_code(pool, username, &code, true).await;
+        }
+
+        let unused = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, code_hash FROM totp_recovery_codes WHERE username = ? AND used_at IS NULL",
+        )
+        .bind(username)
+        .fetch_all(pool)
+        .await?;
+        // Codes generated before they were hashed with argon2 are stored as plain SHA-256 hashes
+        let legacy_hash = hash_token(&code);
+        let Some((id, _)) = unused
+            .into_iter()
+            .find(|(_, code_hash)| match code_hash.starts_with("$argon2") {
+                true => passwords::verify(&code, code_hash).is_ok(),
+                false => *code_hash == legacy_hash,
+            })
+        else {
+            return Ok(false);
+        };
+
+        let used =
+            sqlx::query("UPDATE totp_recovery_codes SET used_at = CURRENT_TIMESTAMP WHERE id = ? AND used_at IS NULL")
+                .bind(id)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                > 0;
+        if used {
+            log::warn!("recovery code used by '{username}'");
+        }
+
+        Ok(used)
+    }
+
+    /// Checks a TOTP code against the (enabled or pending) secret of the user.
+    /// Codes of the previous and next time step are accepted as well, but each code only once.
+    async fn check_code(pool: &SqlitePool, username: &str, code: &str, enabled: bool) -> Result<bool, ApiError> {
+        let code = normalize_code(code);
+        let Some((secret, last_used_step)) = sqlx::query_as::<_, (String, i64)>(
+            "SELECT secret, last_used_step FROM totp WHERE username = ? AND enabled = ?",
+        )
+        .bind(username)
+        .bind(enabled)
+        .fetch_optional(pool)
+        .await?
+        else {
+            return Ok(false);
+        };
+
+        let totp = totp(&secret, username)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| ApiError::ServerError(e.to_string()))?
+            .as_secs();
+        let current_step = now / STEP;
+
+        for step in [current_step - 1, current_step, current_step + 1] {
+            if step as i64 > last_used_step && totp.check(&code, step * STEP) {
+                // The condition on the previous step prevents concurrent requests from using the same code
+                let updated =
+                    sqlx::query("UPDATE totp SET last_used_step = ? WHERE username = ? AND last_used_step < ?")
+                        .bind(step as i64)
+                        .bind(username)
+                        .bind(step as i64)
+                        .execute(pool)
+                        .await?
+                        .rows_affected();
+                return Ok(updated > 0);
+            }
+        }
+
+        Ok(false)
+    }
+
+    async fn replace_recovery_codes(pool: &SqlitePool, username: &str) -> Result<Vec<String>, ApiError> {
+        let codes = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let chars = (0..10)
+                    .map(|_| {
+                        *RECOVERY_CODE_ALPHABET
+                            .choose(&mut OsRng)
+                            .expect("alphabet is not empty") as char
+                    })
+                    .collect::<String>();
+                format!("{}-{}", &chars[..5], &chars[5..])
+            })
+            .collect::<Vec<_>>();
+
+        // Recovery codes are short enough to be guessed from a leaked hash, so they are hashed like passwords
+        let code_hashes = codes
+            .iter()
+            .map(|code| passwords::hash(&normalize_code(code)))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| ApiError::ServerError(e.to_string()))?;
+
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM totp_recovery_codes WHERE username = ?")
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        for code_hash in code_hashes {
+            sqlx::query("INSERT INTO totp_recovery_codes (username, code_hash) VALUES (?, ?)")
+                .bind(username)
+                .bind(code_hash)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(codes)
+    }
+}
+
+#[server]
+pub async fn get_totp_status() -> Result<TotpStatus, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::status(&pool, &user).await?)
+}
+
+#[server]
+pub async fn start_totp_enrolment() -> Result<TotpEnrolment, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::start_enrolment(&pool, &user).await?)
+}
+
+#[server]
+pub async fn confirm_totp_enrolment(code: String) -> Result<Vec<String>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    let codes = ssr::confirm_enrolment(&pool, &user, &code).await?;
+
+    Ok(codes)
+}
+
+#[server]
+pub async fn regenerate_recovery_codes(code: String) -> Result<Vec<String>, ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::regenerate_recovery_codes(&pool, &user, &code).await?)
+}
+
+#[server]
+pub async fn disable_totp(password: String) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_any().await?;
+
+    // Reauthenticate
+    let _ = crate::auth::authenticate_user(user.username.clone(), password).await?;
+
+    let pool = crate::database::ssr::pool()?;
+    ssr::disable(&pool, &user).await?;
+
+    Ok(())
+}
+
+#[component]
+pub fn TwoFactor() -> impl IntoView {
+    let reload = create_trigger();
+    let status = create_resource(move || reload.track(), move |_| get_totp_status());
+
+    let enrolment_modal = create_rw_signal(None);
+    let regenerate_modal = create_rw_signal(None);
+    let disable_modal = create_rw_signal(None);
+    let (input_code, set_input_code) = create_signal("".to_string());
+    let (input_password, set_input_password) = create_signal("".to_string());
+
+    let recovery_codes_modal = create_node_ref::<Dialog>();
+    let recovery_codes_modal_open = create_rw_signal(false);
+    let recovery_codes = create_rw_signal(Vec::<String>::new());
+    create_effect(move |_| {
+        // Clear recovery codes when dialog closes in any way
+        if !recovery_codes_modal_open() {
+            recovery_codes.set(Vec::new());
+        }
+    });
+    let show_recovery_codes = move |codes: Vec<String>| {
+        recovery_codes.set(codes);
+        recovery_codes_modal_open.set(true);
+    };
+
+    let (enrolment_error, set_enrolment_error) = create_signal(None);
+    let start_enrolment = move || {
+        spawn_local(async move {
+            match start_totp_enrolment().await {
+                Ok(enrolment) => {
+                    set_enrolment_error(None);
+                    set_input_code("".to_string());
+                    enrolment_modal.set(Some(Some(enrolment)));
+                }
+                Err(e) => set_enrolment_error(Some(e.to_string())),
+            }
+        });
+    };
+
+    let on_confirm_enrolment = move |(_data, on_error): (Option<TotpEnrolment>, Callback<String>)| {
+        spawn_local(async move {
+            match confirm_totp_enrolment(input_code.get_untracked()).await {
+                Err(e) => on_error(e.to_string()),
+                Ok(codes) => {
+                    reload.notify();
+                    enrolment_modal.set(None);
+                    show_recovery_codes(codes);
+                }
+            }
+        });
+    };
+
+    let on_regenerate = move |(_data, on_error): (Option<()>, Callback<String>)| {
+        spawn_local(async move {
+            match regenerate_recovery_codes(input_code.get_untracked()).await {
+                Err(e) => on_error(e.to_string()),
+                Ok(codes) => {
+                    reload.notify();
+                    regenerate_modal.set(None);
+                    show_recovery_codes(codes);
+                }
+            }
+        });
+    };
+
+    let on_disable = move |(_data, on_error): (Option<()>, Callback<String>)| {
+        spawn_local(async move {
+            if let Err(e) = disable_totp(input_password.get_untracked()).await {
+                on_error(e.to_string())
+            } else {
+                reload.notify();
+                disable_modal.set(None);
+            }
+        });
+    };
+
+    let has_invalid_code = move || !is_totp_code(&normalize_code(&input_code()));
+    let code_errors = create_memo(move |_| {
+        let mut errors = Vec::new();
+        if has_invalid_code() {
+            errors.push("Enter the 6-digit code from your authenticator app".to_string());
+        }
+        errors
+    });
+    let no_errors = Signal::derive(Vec::new);
+
+    let button_class = "inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 transition-all rounded-lg focus:ring-4 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 focus:ring-blue-300 dark:focus:ring-blue-900";
+    let secondary_button_class = "inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-gray-900 dark:text-gray-200 py-2.5 px-4 transition-all rounded-lg focus:ring-4 bg-white dark:bg-black border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 dark:focus:ring-zinc-800";
+    let input_class = "flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50";
+    let code_input = move || {
+        view! {
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="code"
+                >
+                    "Code"
+                </label>
+                <input
+                    class=input_class
+                    class=("!ring-4", has_invalid_code)
+                    class=("!ring-red-500", has_invalid_code)
+                    type="text"
+                    inputmode="numeric"
+                    autocomplete="one-time-code"
+                    placeholder="123456"
+                    maxlength="7"
+                    on:input=move |ev| set_input_code(event_target_value(&ev))
+                    prop:value=input_code
+                />
+            </div>
+        }
+    };
+
+    view! {
+        <div class="flex items-center justify-between space-y-2 mt-8 mb-4">
+            <h3 class="text-2xl font-bold">Two-Factor Authentication</h3>
+        </div>
+        <Transition fallback=move || {
+            view! { <span class="text-gray-300 dark:text-gray-600">"Loading..."</span> }
+        }>
+            {move || {
+                status
+                    .get()
+                    .map(|status| match status {
+                        Err(e) => view! { <p class="text-red-600">{e.to_string()}</p> }.into_view(),
+                        Ok(status) if status.enabled => {
+                            view! {
+                                <div class="grid gap-4 grid-cols-1 sm:max-w-sm">
+                                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                                        "Logins require a code from your authenticator app. You have "
+                                        {status.recovery_codes_left}
+                                        " unused recovery codes left."
+                                    </p>
+                                    <button
+                                        type="button"
+                                        class=secondary_button_class
+                                        on:click=move |_| {
+                                            set_input_code("".to_string());
+                                            regenerate_modal.set(Some(Some(())));
+                                        }
+                                    >
+                                        "Regenerate recovery codes"
+                                    </button>
+                                    <button
+                                        type="button"
+                                        class=secondary_button_class
+                                        on:click=move |_| {
+                                            set_input_password("".to_string());
+                                            disable_modal.set(Some(Some(())));
+                                        }
+                                    >
+                                        "Disable two-factor authentication"
+                                    </button>
+                                </div>
+                            }
+                                .into_view()
+                        }
+                        Ok(status) => {
+                            view! {
+                                <div class="grid gap-4 grid-cols-1 sm:max-w-sm">
+                                    <Show when=move || status.required>
+                                        <div class="rounded-lg p-4 flex bg-yellow-100 dark:bg-yellow-900 text-yellow-800 dark:text-yellow-200">
+                                            "Admins and users with a role must enable two-factor authentication to manage this server."
+                                        </div>
+                                    </Show>
+                                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                                        "Protect your account with a code from an authenticator app in addition to your password."
+                                    </p>
+                                    <button type="button" class=button_class on:click=move |_| start_enrolment()>
+                                        "Enable two-factor authentication"
+                                    </button>
+                                    {move || enrolment_error().map(|e| view! { <p class="text-red-600">{e}</p> })}
+                                </div>
+                            }
+                                .into_view()
+                        }
+                    })
+            }}
+
+        </Transition>
+
+        <EditModal
+            data=enrolment_modal
+            what="Two-Factor Authentication".to_string()
+            get_title=move |_| { "two-factor authentication" }
+            on_confirm=on_confirm_enrolment
+            errors=code_errors
+        >
+            <p class="text-sm text-gray-500 dark:text-gray-400 max-w-lg">
+                "Scan the QR code with your authenticator app or enter the secret manually, then enter the code it shows to confirm."
+            </p>
+            {move || {
+                enrolment_modal
+                    .get()
+                    .flatten()
+                    .map(|enrolment| {
+                        view! {
+                            <div class="mx-auto p-2 bg-white rounded-lg" inner_html=enrolment.qr_code></div>
+                            <input
+                                type="text"
+                                class="bg-gray-50 dark:bg-black border border-gray-300 text-gray-500 dark:text-gray-400 text-sm rounded-lg block w-full p-2.5 font-mono"
+                                value=enrolment.secret
+                                readonly
+                            />
+                        }
+                    })
+            }}
+
+            {code_input}
+        </EditModal>
+
+        <EditModal
+            data=regenerate_modal
+            what="Recovery Codes".to_string()
+            get_title=move |_| { "recovery codes" }
+            on_confirm=on_regenerate
+            errors=code_errors
+        >
+            <p class="text-sm text-gray-500 dark:text-gray-400 max-w-lg">
+                "Your existing recovery codes will stop working. Enter a code from your authenticator app to continue."
+            </p>
+            {code_input}
+        </EditModal>
+
+        <EditModal
+            data=disable_modal
+            what="Two-Factor Authentication".to_string()
+            get_title=move |_| { "two-factor authentication" }
+            on_confirm=on_disable
+            errors=no_errors
+        >
+            <p class="text-sm text-gray-500 dark:text-gray-400 max-w-lg">
+                "Enter your password to disable two-factor authentication. Your recovery codes will be deleted."
+            </p>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="password"
+                >
+                    "Current Password"
+                </label>
+                <input
+                    class=input_class
+                    type="password"
+                    required="required"
+                    maxlength="1024"
+                    on:input=move |ev| set_input_password(event_target_value(&ev))
+                    prop:value=input_password
+                />
+            </div>
+        </EditModal>
+
+        <Modal open=recovery_codes_modal_open dialog_el=recovery_codes_modal>
+            <div class="relative p-4 transform overflow-hidden rounded-lg bg-white dark:bg-black text-left transition-all sm:w-full sm:max-w-xl">
+                <h3 class="text-2xl tracking-tight mt-2 mb-2 font-semibold text-gray-900 dark:text-gray-200">
+                    "Recovery Codes"
+                </h3>
+                <div class="pb-3 space-y-3">
+                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                        "Each of these codes can be used once to log in without your authenticator app. Make sure to save them now, as they will not be displayed again."
+                    </p>
+                    <div class="grid grid-cols-2 gap-2 rounded-lg bg-gray-50 dark:bg-zinc-900 p-4 font-mono text-center">
+                        <For each=recovery_codes key=|x| x.clone() let:code>
+                            <span>{code}</span>
+                        </For>
+                    </div>
+                </div>
+                <div class="flex flex-col gap-3 sm:flex-row-reverse">
+                    <button
+                        type="button"
+                        class="inline-flex w-full min-w-20 justify-center rounded-lg transition-all bg-white dark:bg-black px-3 py-2 font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
+                        on:click=move |_ev| {
+                            recovery_codes_modal_open.set(false);
+                        }
+                    >
+                        <Icon icon=icondata::BiCheckRegular class="w-5 h-5 me-1.5"/>
+                        "I saved them"
+                    </button>
+                </div>
+            </div>
+        </Modal>
+    }
+}
@@ -1,15 +1,25 @@
+#![allow(clippy::unused_unit)] // The TableRow derive generates unit expressions
 use std::collections::VecDeque;
 use std::ops::Range;
 
-use crate::utils::{DeleteModal, EditModal, Modal};
+use crate::api_tokens::ApiTokens;
+use crate::app_passwords::AppPasswords;
+use crate::auth;
+use crate::passkeys::{get_passkeys_enabled, Passkeys};
+use crate::password_policy::{use_password_policy, PasswordStrength};
+use crate::password_resets::PasswordResetModal;
+use crate::roles::role_names;
+use crate::sessions::Sessions;
+use crate::totp::TwoFactor;
+use crate::utils::{DeleteModal, EditModal, Select};
 use crate::utils::{SliderRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
 
 use chrono::{DateTime, Utc};
-use leptos::html::Dialog;
+use leptos::leptos_dom::is_browser;
 use leptos::{ev::MouseEvent, logging::error, *};
 use leptos_icons::Icon;
 use leptos_struct_table::*;
-use leptos_use::{use_debounce_fn_with_arg, use_timeout_fn};
+use leptos_use::use_debounce_fn_with_arg;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "ssr")]
 use sqlx::QueryBuilder;
@@ -23,14 +33,28 @@ pub struct User {
     pub password_hash: String,
     #[table(class = "w-1", renderer = "SliderRenderer")]
     pub admin: bool,
+    #[table(class = "w-1")]
+    pub role: Option<String>,
     #[table(class = "w-1", renderer = "SliderRenderer")]
     pub active: bool,
     #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
     pub created_at: DateTime<Utc>,
+    #[table(skip)]
+    #[cfg_attr(feature = "ssr", sqlx(flatten))]
+    pub limits: UserLimits,
 }
 
-pub(crate) fn is_valid_pw(password: &str) -> bool {
-    (12..=1024).contains(&password.len())
+/// Overrides of the global limits for a user and their mailboxes.
+/// `None` uses the global default, 0 disables the limit.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow, utoipa::ToSchema))]
+pub struct UserLimits {
+    /// Maximum number of aliases
+    pub max_aliases: Option<u32>,
+    /// Maximum number of aliases created within 24 hours
+    pub max_aliases_per_day: Option<u32>,
+    /// Maximum number of API requests per minute and token
+    pub api_requests_per_minute: Option<u32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -41,57 +65,191 @@ pub struct UserQuery {
     search: String,
 }
 
-#[server]
-pub async fn list_users(query: UserQuery) -> Result<Vec<User>, ServerFnError> {
-    let _user = crate::auth::auth_admin().await?;
-    let UserQuery { sort, range, search } = query;
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{mk_password_hash, User, UserLimits};
+    use crate::{
+        api::ApiError,
+        auth,
+        roles::Permission,
+        webhooks::{ssr::notify, WebhookEvent},
+    };
+    use serde_json::{json, Value};
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::ops::Range;
+
+    /// The values of a user that can be set when creating or updating it
+    pub struct UserEdit {
+        pub username: String,
+        /// The new password, or empty to keep the current one
+        pub password: String,
+        pub admin: bool,
+        /// The role of the user, or `None` for no role
+        pub role: Option<String>,
+        pub active: bool,
+        pub limits: UserLimits,
+    }
 
-    let mut query = QueryBuilder::new("SELECT * FROM users");
-    if !search.is_empty() {
-        query.push(" WHERE username LIKE concat('%', ");
-        query.push_bind(&search);
-        query.push(", '%')");
+    /// The data sent to webhooks for user events, which must not include the password hash.
+    pub fn webhook_data(user: &User) -> Value {
+        json!({
+            "username": user.username,
+            "admin": user.admin,
+            "role": user.role,
+            "active": user.active,
+            "created_at": user.created_at,
+            "limits": user.limits,
+        })
     }
 
-    if let Some(order) = User::sorting_to_sql(&sort) {
-        query.push(" ");
-        query.push(order);
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &auth::User,
+        search: &str,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<User>, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
+
+        let mut query = QueryBuilder::new("SELECT * FROM users");
+        if !search.is_empty() {
+            query.push(" WHERE username LIKE concat('%', ");
+            query.push_bind(search);
+            query.push(", '%')");
+        }
+
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        let mut users = query.build_query_as::<User>().fetch_all(pool).await?;
+        // Password hashes are only shown to admins, not to users with a role
+        if !user.admin {
+            users.iter_mut().for_each(|x| x.password_hash.clear());
+        }
+        Ok(users)
     }
 
-    query.push(" LIMIT ");
-    query.push_bind(range.len() as i64);
-    query.push(" OFFSET ");
-    query.push_bind(range.start as i64);
+    pub async fn get(pool: &SqlitePool, user: &auth::User, username: &str) -> Result<User, ApiError> {
+        user.ensure_permission(Permission::UsersRead)?;
 
-    let pool = crate::database::ssr::pool()?;
-    Ok(query.build_query_as::<User>().fetch_all(&pool).await?)
-}
+        sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("User '{username}' does not exist")))
+    }
 
-#[server]
-pub async fn regenerate_api_key() -> Result<String, ServerFnError> {
-    let user = crate::auth::auth_any().await?;
-    if user.mailbox_owner.is_none() {
-        return Err(ServerFnError::new("Must be a mailbox user."));
+    pub async fn delete(pool: &SqlitePool, user: &auth::User, username: &str) -> Result<(), ApiError> {
+        user.ensure_admin()?;
+
+        let mut query = QueryBuilder::new("DELETE FROM users WHERE username = ");
+        query.push_bind(username);
+
+        if query.build().execute(pool).await?.rows_affected() == 0 {
+            return Err(ApiError::NotFound(format!("User '{username}' does not exist")));
+        }
+
+        notify(pool, user, WebhookEvent::UserDeleted, json!({ "username": username })).await;
+        Ok(())
     }
 
-    let mut buf = [0u8; 24];
-    getrandom::getrandom(&mut buf)?;
-    let api_token = hex::encode(buf);
+    pub async fn create_or_update(
+        pool: &SqlitePool,
+        user: &auth::User,
+        old_username: Option<String>,
+        edit: UserEdit,
+    ) -> Result<User, ApiError> {
+        user.ensure_admin()?;
+
+        let UserEdit {
+            username,
+            password,
+            admin,
+            role,
+            active,
+            limits,
+        } = edit;
+
+        if let Some(role) = &role {
+            crate::roles::ssr::ensure_exists(pool, role).await?;
+        }
 
-    let mut query = QueryBuilder::new("UPDATE mailboxes SET api_token = ");
-    query.push_bind(&api_token);
-    query.push(" WHERE address = ");
-    query.push_bind(&user.username);
+        if let Some(old_username) = old_username {
+            // Make sure the user exists
+            get(pool, user, &old_username).await?;
+
+            let mut query = QueryBuilder::new("UPDATE users SET admin = ");
+            query.push_bind(admin);
+            query.push(", role = ");
+            query.push_bind(role);
+            if !password.is_empty() {
+                let password_hash = mk_password_hash(&password, &old_username)?;
+                query.push(", password_hash = ");
+                query.push_bind(password_hash);
+            }
+            query.push(", active = ");
+            query.push_bind(active);
+            query.push(", max_aliases = ");
+            query.push_bind(limits.max_aliases);
+            query.push(", max_aliases_per_day = ");
+            query.push_bind(limits.max_aliases_per_day);
+            query.push(", api_requests_per_minute = ");
+            query.push_bind(limits.api_requests_per_minute);
+            query.push(" WHERE username = ");
+            query.push_bind(&old_username);
+
+            query.build().execute(pool).await?;
+            if !password.is_empty() || !active {
+                crate::sessions::ssr::end_all(pool, &old_username, None).await?;
+            }
+            // Users cannot be renamed
+            let updated = get(pool, user, &old_username).await?;
+            notify(pool, user, WebhookEvent::UserUpdated, webhook_data(&updated)).await;
+            Ok(updated)
+        } else {
+            let password_hash = mk_password_hash(&password, &username)?;
+            sqlx::query(
+                "INSERT INTO users (username, password_hash, admin, role, active, \
+                max_aliases, max_aliases_per_day, api_requests_per_minute) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&username)
+            .bind(password_hash)
+            .bind(admin)
+            .bind(role)
+            .bind(active)
+            .bind(limits.max_aliases)
+            .bind(limits.max_aliases_per_day)
+            .bind(limits.api_requests_per_minute)
+            .execute(pool)
+            .await?;
+
+            let created = get(pool, user, &username).await?;
+            notify(pool, user, WebhookEvent::UserCreated, webhook_data(&created)).await;
+            Ok(created)
+        }
+    }
+}
 
-    let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
+#[server]
+pub async fn list_users(query: UserQuery) -> Result<Vec<User>, ServerFnError> {
+    let user = crate::auth::auth_user().await?;
+    let UserQuery { sort, range, search } = query;
 
-    Ok(api_token)
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, &search, User::sorting_to_sql(&sort), range).await?)
 }
 
 #[server]
 pub async fn admin_count() -> Result<usize, ServerFnError> {
-    let _user = crate::auth::auth_admin().await?;
+    let user = crate::auth::auth_user().await?;
+    user.ensure_permission(crate::roles::Permission::UsersRead)?;
     let mut query = QueryBuilder::new("SELECT COUNT(*) FROM users WHERE admin = TRUE");
 
     let pool = crate::database::ssr::pool()?;
@@ -102,7 +260,8 @@ pub async fn admin_count() -> Result<usize, ServerFnError> {
 
 #[server]
 pub async fn user_count() -> Result<usize, ServerFnError> {
-    let _user = crate::auth::auth_admin().await?;
+    let user = crate::auth::auth_user().await?;
+    user.ensure_permission(crate::roles::Permission::UsersRead)?;
     let mut query = QueryBuilder::new("SELECT COUNT(*) FROM users");
 
     let pool = crate::database::ssr::pool()?;
@@ -113,41 +272,18 @@ pub async fn user_count() -> Result<usize, ServerFnError> {
 
 #[server]
 pub async fn delete_user(username: String) -> Result<(), ServerFnError> {
-    let _user = crate::auth::auth_admin().await?;
-
-    // Force user reload on next request
-    let auth = crate::database::ssr::auth()?;
-    auth.cache_clear_user(username.clone());
-
-    let mut query = QueryBuilder::new("DELETE FROM users WHERE username = ");
-    query.push_bind(username);
-
+    let user = crate::auth::auth_admin().await?;
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
-    Ok(())
+    Ok(ssr::delete(&pool, &user, &username).await?)
 }
 
 #[cfg(feature = "ssr")]
-pub fn mk_password_hash(password: &str) -> Result<String, ServerFnError> {
-    if !is_valid_pw(password) {
-        return Err(ServerFnError::new("Password is invalid."));
-    }
-
-    use argon2::{
-        password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
-        Argon2,
-    };
+/// Hashes a new password of the given user or mailbox after checking it against the password policy.
+pub fn mk_password_hash(password: &str, username: &str) -> Result<String, ServerFnError> {
+    crate::password_policy::ssr::check(password, username).map_err(ServerFnError::new)?;
 
-    let salt = SaltString::generate(&mut OsRng);
-    // Argon2 with default params (Argon2id v19)
-    let argon2 = Argon2::default();
     // Hash password to PHC string ($argon2id$v=19$...)
-    let password_hash = argon2
-        .hash_password(password.as_bytes(), &salt)
-        .map_err(|e| ServerFnError::new(e.to_string()))?
-        .to_string();
-
-    Ok(password_hash)
+    crate::passwords::hash(password).map_err(|e| ServerFnError::new(e.to_string()))
 }
 
 #[server]
@@ -156,11 +292,7 @@ pub async fn change_password(current_password: String, new_password: String) ->
 
     // Reauthenticate
     let _ = crate::auth::authenticate_user(user.username.clone(), current_password.clone()).await?;
-    let password_hash = mk_password_hash(&new_password)?;
-
-    // Force user reload on next request
-    let auth = crate::database::ssr::auth()?;
-    auth.cache_clear_user(user.username.clone());
+    let password_hash = mk_password_hash(&new_password, &user.username)?;
 
     let mut query = QueryBuilder::new("UPDATE users SET password_hash = ");
     query.push_bind(password_hash);
@@ -170,6 +302,17 @@ pub async fn change_password(current_password: String, new_password: String) ->
     let pool = crate::database::ssr::pool()?;
     query.build().execute(&pool).await.map(|_| ())?;
 
+    // Log out all other devices
+    let auth = crate::database::ssr::auth()?;
+    crate::sessions::ssr::end_all(&pool, &user.username, Some(&auth.id)).await?;
+
+    let updated = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+        .bind(&user.username)
+        .fetch_one(&pool)
+        .await?;
+    let data = ssr::webhook_data(&updated);
+    crate::webhooks::ssr::notify(&pool, &user, crate::webhooks::WebhookEvent::UserUpdated, data).await;
+
     Ok(())
 }
 
@@ -179,56 +322,44 @@ pub async fn create_or_update_user(
     username: String,
     password: String,
     admin: bool,
+    role: Option<String>,
     active: bool,
+    limits: UserLimits,
 ) -> Result<(), ServerFnError> {
-    let _user = crate::auth::auth_admin().await?;
+    let user = crate::auth::auth_admin().await?;
     let pool = crate::database::ssr::pool()?;
 
-    if let Some(old_username) = old_username {
-        // Force user reload on next request
-        let auth = crate::database::ssr::auth()?;
-        auth.cache_clear_user(username.clone());
-
-        let mut query = QueryBuilder::new("UPDATE users SET admin = ");
-        query.push_bind(admin);
-        if !password.is_empty() {
-            let password_hash = mk_password_hash(&password)?;
-            query.push(", password_hash = ");
-            query.push_bind(password_hash);
-        }
-        query.push(", active = ");
-        query.push_bind(active);
-        query.push(" WHERE username = ");
-        query.push_bind(old_username);
-
-        query.build().execute(&pool).await.map(|_| ())?;
-    } else {
-        let password_hash = mk_password_hash(&password)?;
-        sqlx::query("INSERT INTO users (username, password_hash, admin, active) VALUES (?, ?, ?, ?)")
-            .bind(username)
-            .bind(password_hash)
-            .bind(admin)
-            .bind(active)
-            .execute(&pool)
-            .await
-            .map(|_| ())?;
-    }
-
+    let edit = ssr::UserEdit {
+        username,
+        password,
+        admin,
+        role: role.filter(|x| !x.is_empty()),
+        active,
+        limits,
+    };
+    ssr::create_or_update(&pool, &user, old_username, edit).await?;
     Ok(())
 }
 
 #[server]
 pub async fn update_user_admin_or_active(username: String, admin: bool, active: bool) -> Result<(), ServerFnError> {
-    let _user = crate::auth::auth_admin().await?;
+    let user = crate::auth::auth_admin().await?;
     let mut query = QueryBuilder::new("UPDATE users SET admin = ");
     query.push_bind(admin);
     query.push(", active = ");
     query.push_bind(active);
     query.push(" WHERE username = ");
-    query.push_bind(username);
+    query.push_bind(&username);
 
     let pool = crate::database::ssr::pool()?;
-    query.build().execute(&pool).await.map(|_| ())?;
+    if query.build().execute(&pool).await?.rows_affected() > 0 {
+        if !active {
+            crate::sessions::ssr::end_all(&pool, &username, None).await?;
+        }
+        let updated = ssr::get(&pool, &user, &username).await?;
+        let data = ssr::webhook_data(&updated);
+        crate::webhooks::ssr::notify(&pool, &user, crate::webhooks::WebhookEvent::UserUpdated, data).await;
+    }
     Ok(())
 }
 
@@ -266,8 +397,10 @@ impl TableDataProvider<User> for UserTableDataProvider {
     }
 }
 
+/// All users, which can be changed by admins and seen by those with the permission to see all users
 #[component]
-pub fn Users() -> impl IntoView {
+pub fn Users(user: auth::User) -> impl IntoView {
+    let is_admin = user.admin;
     let mut rows = UserTableDataProvider::default();
     let default_sorting = VecDeque::from([(3, ColumnSort::Descending)]);
     rows.set_sorting(&default_sorting);
@@ -279,12 +412,51 @@ pub fn Users() -> impl IntoView {
 
     let delete_modal_user = create_rw_signal(None);
     let edit_modal_user = create_rw_signal(None);
+    let reset_modal_user = create_rw_signal(None);
 
     let (edit_modal_input_username, set_edit_modal_input_username) = create_signal("".to_string());
     let (edit_modal_input_password, set_edit_modal_input_password) = create_signal("".to_string());
     let (edit_modal_input_password_repeat, set_edit_modal_input_password_repeat) = create_signal("".to_string());
     let (edit_modal_input_admin, set_edit_modal_input_admin) = create_signal(false);
+    let (edit_modal_input_role, set_edit_modal_input_role) = create_signal("".to_string());
+    let (roles, set_roles) = create_signal(vec![]);
+    if is_browser() && is_admin {
+        spawn_local(async move {
+            match role_names().await {
+                Err(e) => error!("Failed to load roles: {}", e),
+                // An empty choice assigns no role
+                Ok(names) => set_roles(std::iter::once(String::new()).chain(names).collect()),
+            }
+        });
+    }
     let (edit_modal_input_active, set_edit_modal_input_active) = create_signal(true);
+    let (edit_modal_input_max_aliases, set_edit_modal_input_max_aliases) = create_signal("".to_string());
+    let (edit_modal_input_max_aliases_per_day, set_edit_modal_input_max_aliases_per_day) =
+        create_signal("".to_string());
+    let (edit_modal_input_api_requests_per_minute, set_edit_modal_input_api_requests_per_minute) =
+        create_signal("".to_string());
+    let set_limit_inputs = move |limits: UserLimits| {
+        let to_input = |x: Option<u32>| x.map(|x| x.to_string()).unwrap_or_default();
+        set_edit_modal_input_max_aliases(to_input(limits.max_aliases));
+        set_edit_modal_input_max_aliases_per_day(to_input(limits.max_aliases_per_day));
+        set_edit_modal_input_api_requests_per_minute(to_input(limits.api_requests_per_minute));
+    };
+    // An empty limit uses the global default
+    let parse_limit = |value: String| -> Result<Option<u32>, ()> {
+        let value = value.trim();
+        if value.is_empty() {
+            Ok(None)
+        } else {
+            value.parse().map(Some).map_err(|_| ())
+        }
+    };
+    let limits = move || -> Result<UserLimits, ()> {
+        Ok(UserLimits {
+            max_aliases: parse_limit(edit_modal_input_max_aliases.get())?,
+            max_aliases_per_day: parse_limit(edit_modal_input_max_aliases_per_day.get())?,
+            api_requests_per_minute: parse_limit(edit_modal_input_api_requests_per_minute.get())?,
+        })
+    };
     let edit_modal_open_with = Callback::new(move |edit_user: Option<User>| {
         edit_modal_user.set(Some(edit_user.clone()));
         set_edit_modal_input_password("".to_string());
@@ -293,11 +465,15 @@ pub fn Users() -> impl IntoView {
         if let Some(edit_user) = edit_user {
             set_edit_modal_input_username(edit_user.username.clone());
             set_edit_modal_input_admin(edit_user.admin);
+            set_edit_modal_input_role(edit_user.role.unwrap_or_default());
             set_edit_modal_input_active(edit_user.active);
+            set_limit_inputs(edit_user.limits);
         } else {
             set_edit_modal_input_username("".to_string());
             set_edit_modal_input_admin(false);
+            set_edit_modal_input_role("".to_string());
             set_edit_modal_input_active(true);
+            set_limit_inputs(UserLimits::default());
         }
     });
 
@@ -308,7 +484,9 @@ pub fn Users() -> impl IntoView {
                 edit_modal_input_username.get_untracked(),
                 edit_modal_input_password.get_untracked(),
                 edit_modal_input_admin.get_untracked(),
+                Some(edit_modal_input_role.get_untracked()),
                 edit_modal_input_active.get_untracked(),
+                untrack(limits).unwrap_or_default(),
             )
             .await
             {
@@ -346,6 +524,7 @@ pub fn Users() -> impl IntoView {
                                   on_select: EventHandler<MouseEvent>,
                                   on_change: EventHandler<ChangeEvent<User>>| {
         let delete_username = row.username.clone();
+        let reset_username = row.username.clone();
         let edit_user = row.clone();
         view! {
             <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
@@ -355,14 +534,25 @@ pub fn Users() -> impl IntoView {
                         <button
                             class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-blue-600 dark:hover:bg-blue-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-l-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
                             on:click=move |_| edit_modal_open_with(Some(edit_user.clone()))
+                            disabled=move || !is_admin
                         >
                             <Icon icon=icondata::FiEdit class="w-5 h-5"/>
                         </button>
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-blue-600 dark:hover:bg-blue-500 transition-all border-l-0 border-[1.5px] border-gray-200 dark:border-zinc-800 font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            title="Create password reset link"
+                            on:click=move |_| reset_modal_user.set(Some(reset_username.clone()))
+                            disabled=move || !is_admin
+                        >
+                            <Icon icon=icondata::FiLink class="w-5 h-5"/>
+                        </button>
                         <button
                             class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-l-0 border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-r-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
                             on:click=move |_| {
                                 delete_modal_user.set(Some(delete_username.clone()));
                             }
+
+                            disabled=move || !is_admin
                         >
 
                             <Icon icon=icondata::FiTrash2 class="w-5 h-5"/>
@@ -374,21 +564,25 @@ pub fn Users() -> impl IntoView {
     };
 
     let has_password_mismatch = move || edit_modal_input_password() != edit_modal_input_password_repeat();
-    let has_invalid_password = create_memo(move |_| {
+    let password_policy = use_password_policy();
+    let password_problems = create_memo(move |_| {
         // Either we edit an existing user (in which case an empty password means no change)
-        // or the password is of correct length.
+        // or the password complies with the password policy.
         let is_new = matches!(edit_modal_user.get(), Some(None));
-        let is_valid_pw = is_valid_pw(&edit_modal_input_password());
-        let valid = is_valid_pw || (!is_new && edit_modal_input_password().is_empty());
-        !valid
+        if !is_new && edit_modal_input_password().is_empty() {
+            return Vec::new();
+        }
+        password_policy().problems(&edit_modal_input_password(), &edit_modal_input_username())
     });
+    let has_invalid_password = move || !password_problems().is_empty();
     let errors = create_memo(move |_| {
         let mut errors = Vec::new();
         if has_password_mismatch() {
             errors.push("Passwords don't match".to_string());
         }
-        if has_invalid_password() {
-            errors.push("Password must be between 12 and 512 characters".to_string());
+        errors.extend(password_problems());
+        if limits().is_err() {
+            errors.push("Limits must be empty or a non-negative number".to_string());
         }
         errors
     });
@@ -414,6 +608,7 @@ pub fn Users() -> impl IntoView {
                         type="button"
                         class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-blue-600 dark:bg-blue-700 hover:bg-blue-500 dark:hover:bg-blue-600 focus:ring-blue-300 dark:focus:ring-blue-900"
                         on:click=move |_| edit_modal_open_with(None)
+                        disabled=move || !is_admin
                     >
                         <Icon icon=icondata::FiPlus class="w-6 h-6 me-2"/>
                         New
@@ -443,6 +638,8 @@ pub fn Users() -> impl IntoView {
             </div>
         </div>
 
+        <PasswordResetModal username=reset_modal_user/>
+
         <DeleteModal
             data=delete_modal_user
             text="Are you sure you want to delete this user? This action cannot be undone.".into_view()
@@ -506,6 +703,7 @@ pub fn Users() -> impl IntoView {
                     on:input=move |ev| set_edit_modal_input_password(event_target_value(&ev))
                     prop:value=edit_modal_input_password
                 />
+                <PasswordStrength password=edit_modal_input_password/>
             </div>
             <div class="flex flex-col gap-2">
                 <label
@@ -525,6 +723,68 @@ pub fn Users() -> impl IntoView {
                     prop:value=edit_modal_input_password_repeat
                 />
             </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="max_aliases"
+                >
+                    Max. aliases
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="number"
+                    min="0"
+                    placeholder="Default"
+                    on:input=move |ev| set_edit_modal_input_max_aliases(event_target_value(&ev))
+                    prop:value=edit_modal_input_max_aliases
+                />
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="max_aliases_per_day"
+                >
+                    Max. new aliases per day
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="number"
+                    min="0"
+                    placeholder="Default"
+                    on:input=move |ev| set_edit_modal_input_max_aliases_per_day(event_target_value(&ev))
+                    prop:value=edit_modal_input_max_aliases_per_day
+                />
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="api_requests_per_minute"
+                >
+                    Max. API requests per minute and token
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="number"
+                    min="0"
+                    placeholder="Default"
+                    on:input=move |ev| set_edit_modal_input_api_requests_per_minute(event_target_value(&ev))
+                    prop:value=edit_modal_input_api_requests_per_minute
+                />
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="role"
+                >
+                    Role
+                </label>
+                <Select
+                    class="w-full h-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all focus:ring-4 focus:ring-blue-300 dark:focus:ring-blue-900"
+                    choices=roles
+                    value=edit_modal_input_role
+                    set_value=set_edit_modal_input_role
+                />
+            </div>
             <div class="flex flex-row gap-2 mt-2 items-center">
                 <input
                     id="users_admin"
@@ -560,7 +820,9 @@ pub fn Users() -> impl IntoView {
 }
 
 #[component]
-pub fn AccountSettings(user: crate::auth::User) -> impl IntoView {
+pub fn AccountSettings(user: auth::User) -> impl IntoView {
+    // Users manage the app passwords of their mailboxes next to them
+    let is_mailbox = user.mailbox_owner.is_some();
     let edit_modal_password = create_rw_signal(None);
 
     let (edit_modal_input_current_password, set_edit_modal_input_current_password) = create_signal("".to_string());
@@ -589,32 +851,20 @@ pub fn AccountSettings(user: crate::auth::User) -> impl IntoView {
     };
 
     let has_password_mismatch = move || edit_modal_input_password() != edit_modal_input_password_repeat();
-    let has_invalid_password = create_memo(move |_| !is_valid_pw(&edit_modal_input_password()));
+    let password_policy = use_password_policy();
+    let password_problems =
+        create_memo(move |_| password_policy().problems(&edit_modal_input_password(), &user.username));
+    let passkeys_enabled = create_resource(|| (), |_| get_passkeys_enabled());
+    let has_invalid_password = move || !password_problems().is_empty();
     let errors = create_memo(move |_| {
         let mut errors = Vec::new();
         if has_password_mismatch() {
             errors.push("Passwords don't match".to_string());
         }
-        if has_invalid_password() {
-            errors.push("Password must be between 12 and 512 characters".to_string());
-        }
+        errors.extend(password_problems());
         errors
     });
 
-    let api_token_modal = create_node_ref::<Dialog>();
-    let api_token_modal_open = create_rw_signal(false);
-    let api_token_modal_token = create_rw_signal("".to_string());
-    let api_token_modal_copied_timer = use_timeout_fn(|_: ()| (), 3000.0);
-    let (api_token_modal_server_error, api_token_modal_set_server_error) = create_signal(None);
-    create_effect(move |_| {
-        // Clear API token when dialog closes in any way
-        if !api_token_modal_open() {
-            api_token_modal_token.set("".to_string());
-            (api_token_modal_copied_timer.stop)();
-            api_token_modal_set_server_error(None);
-        }
-    });
-
     view! {
         <div class="h-full flex-1 flex-col mt-12">
             <div class="flex items-center justify-between space-y-2 mb-4">
@@ -628,30 +878,18 @@ pub fn AccountSettings(user: crate::auth::User) -> impl IntoView {
                 >
                     "Change password"
                 </button>
-                <button
-                    type="button"
-                    class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 transition-all rounded-lg focus:ring-4 bg-blue-600 dark:bg-blue-600 hover:bg-blue-500 dark:hover:bg-blue-500 focus:ring-blue-300 dark:focus:ring-blue-900 disabled:pointer-events-none disabled:cursor-not-allowed disabled:opacity-50"
-                    on:click=move |_| {
-                        spawn_local(async move {
-                            match regenerate_api_key().await {
-                                Err(e) => api_token_modal_set_server_error(Some(e.to_string())),
-                                Ok(api_token) => api_token_modal_token.set(api_token),
-                            }
-                            api_token_modal_open.set(true)
-                        });
-                    }
-
-                    disabled=user.mailbox_owner.is_none()
-                >
-
-                    {if user.mailbox_owner.is_none() {
-                        "Regenerate API Token (login as a mailbox first)"
-                    } else {
-                        "Regenerate API Token"
-                    }}
-
-                </button>
             </div>
+            <TwoFactor/>
+            <Transition fallback=|| ()>
+                <Show when=move || passkeys_enabled.get().and_then(Result::ok).unwrap_or(false)>
+                    <Passkeys/>
+                </Show>
+            </Transition>
+            <Sessions/>
+            <ApiTokens/>
+            <Show when=move || is_mailbox>
+                <AppPasswords/>
+            </Show>
         </div>
 
         <EditModal
@@ -694,6 +932,7 @@ pub fn AccountSettings(user: crate::auth::User) -> impl IntoView {
                     on:input=move |ev| set_edit_modal_input_password(event_target_value(&ev))
                     prop:value=edit_modal_input_password
                 />
+                <PasswordStrength password=edit_modal_input_password/>
             </div>
             <div class="flex flex-col gap-2">
                 <label
@@ -714,82 +953,5 @@ pub fn AccountSettings(user: crate::auth::User) -> impl IntoView {
                 />
             </div>
         </EditModal>
-
-        <Modal open=api_token_modal_open dialog_el=api_token_modal>
-            <div class="relative p-4 transform overflow-hidden rounded-lg bg-white dark:bg-black text-left transition-all sm:w-full sm:max-w-xl">
-                <h3 class="text-2xl tracking-tight mt-2 mb-2 font-semibold text-gray-900 dark:text-gray-200">
-                    "API Token"
-                </h3>
-                <div class="pb-3 space-y-3">
-                    <p class="text-sm text-gray-500 dark:text-gray-400">
-                        "Your new API Token is displayed below. Make sure to save it now, as it will not be displayed again."
-                    </p>
-                    <div class="w-full relative">
-                        <input
-                            type="text"
-                            class="col-span-6 bg-gray-50 dark:bg-gray-900 dark:bg-black border border-gray-300 text-gray-500 dark:text-gray-400 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full px-2.5 py-4"
-                            value=move || api_token_modal_token
-                            disabled
-                            readonly
-                        />
-                        <button
-                            class="absolute end-2.5 top-1/2 -translate-y-1/2 text-gray-900 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg py-2 px-2.5 inline-flex items-center justify-center bg-white dark:bg-black border-gray-200 border"
-                            on:click=move |_ev| {
-                                (api_token_modal_copied_timer.start)(());
-                                let clipboard = window().navigator().clipboard();
-                                let _ = clipboard.write_text(&api_token_modal_token.get());
-                            }
-                        >
-
-                            <span
-                                class="inline-flex items-center"
-                                class=("hidden", api_token_modal_copied_timer.is_pending)
-                            >
-                                <Icon icon=icondata::RiFileCopy2DocumentFill class="w-3 h-3 me-1.5"/>
-                                <span class="text-xs font-semibold">Copy</span>
-                            </span>
-                            <span
-                                class="hidden items-center"
-                                class=("!inline-flex", api_token_modal_copied_timer.is_pending)
-                            >
-                                <Icon
-                                    icon=icondata::BiCheckRegular
-                                    class="w-3 h-3 me-1.5 text-blue-700 dark:text-blue-300"
-                                />
-                                <span class="text-xs font-semibold text-blue-700 dark:text-blue-300">Copied</span>
-                            </span>
-                        </button>
-                    </div>
-                    <Show when=move || api_token_modal_server_error().is_some()>
-                        <div class="rounded-lg p-4 flex bg-red-100 dark:bg-red-900 mt-2">
-                            <div>
-                                <Icon icon=icondata::BiXCircleSolid class="w-5 h-5 text-red-400 dark:text-red-200"/>
-                            </div>
-                            <div class="ml-3 text-red-700 dark:text-red-200">
-                                {move || {
-                                    match api_token_modal_server_error() {
-                                        None => view! {}.into_view(),
-                                        Some(error) => view! { <p>{error}</p> }.into_view(),
-                                    }
-                                }}
-
-                            </div>
-                        </div>
-                    </Show>
-                </div>
-                <div class="flex flex-col gap-3 sm:flex-row-reverse">
-                    <button
-                        type="button"
-                        class="inline-flex w-full min-w-20 justify-center rounded-lg transition-all bg-white dark:bg-black px-3 py-2 font-semibold text-gray-900 dark:text-gray-200 focus:ring-4 dark:focus:ring-zinc-800 border-[1.5px] border-gray-300 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900 sm:w-auto"
-                        on:click=move |_ev| {
-                            api_token_modal_open.set(false);
-                        }
-                    >
-
-                        Dismiss
-                    </button>
-                </div>
-            </div>
-        </Modal>
     }
 }
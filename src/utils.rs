@@ -40,6 +40,25 @@ where
     view! { <td class=class>{time_tooltip}</td> }
 }
 
+#[component]
+#[allow(unused_variables, non_snake_case)]
+pub fn OptionalTimediffRenderer<F>(
+    class: String,
+    #[prop(into)] value: MaybeSignal<Option<DateTime<Utc>>>,
+    on_change: F,
+    index: usize,
+) -> impl IntoView
+where
+    F: Fn(Option<DateTime<Utc>>) + 'static,
+{
+    view! {
+        {move || match value() {
+            Some(value) => view! { <TimediffRenderer class=class.clone() value on_change=|_| {} index/> }.into_view(),
+            None => view! { <td class=class.clone()>"Never"</td> }.into_view(),
+        }}
+    }
+}
+
 #[component]
 #[allow(unused_variables, non_snake_case)]
 pub fn SliderRenderer<F>(
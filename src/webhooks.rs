@@ -0,0 +1,1008 @@
+#![allow(clippy::unused_unit)] // The TableRow derive generates unit expressions
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+
+use crate::utils::{DeleteModal, EditModal};
+use crate::utils::{SliderRenderer, THeadCellRenderer, TailwindClassesPreset, TimediffRenderer};
+
+use chrono::{DateTime, Utc};
+use leptos::{ev::MouseEvent, logging::error, *};
+use leptos_icons::Icon;
+use leptos_struct_table::*;
+use serde::{Deserialize, Serialize};
+
+/// The changes webhooks can be notified about
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookEvent {
+    AliasCreated,
+    AliasUpdated,
+    AliasToggled,
+    AliasDeleted,
+    MailboxCreated,
+    MailboxUpdated,
+    MailboxDeleted,
+    DomainCreated,
+    DomainUpdated,
+    DomainDeleted,
+    UserCreated,
+    UserUpdated,
+    UserDeleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 13] = [
+        WebhookEvent::AliasCreated,
+        WebhookEvent::AliasUpdated,
+        WebhookEvent::AliasToggled,
+        WebhookEvent::AliasDeleted,
+        WebhookEvent::MailboxCreated,
+        WebhookEvent::MailboxUpdated,
+        WebhookEvent::MailboxDeleted,
+        WebhookEvent::DomainCreated,
+        WebhookEvent::DomainUpdated,
+        WebhookEvent::DomainDeleted,
+        WebhookEvent::UserCreated,
+        WebhookEvent::UserUpdated,
+        WebhookEvent::UserDeleted,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WebhookEvent::AliasCreated => "alias.created",
+            WebhookEvent::AliasUpdated => "alias.updated",
+            WebhookEvent::AliasToggled => "alias.toggled",
+            WebhookEvent::AliasDeleted => "alias.deleted",
+            WebhookEvent::MailboxCreated => "mailbox.created",
+            WebhookEvent::MailboxUpdated => "mailbox.updated",
+            WebhookEvent::MailboxDeleted => "mailbox.deleted",
+            WebhookEvent::DomainCreated => "domain.created",
+            WebhookEvent::DomainUpdated => "domain.updated",
+            WebhookEvent::DomainDeleted => "domain.deleted",
+            WebhookEvent::UserCreated => "user.created",
+            WebhookEvent::UserUpdated => "user.updated",
+            WebhookEvent::UserDeleted => "user.deleted",
+        }
+    }
+
+    /// Whether the event is selected by the given comma separated list of
+    /// event names and patterns like `alias.*`. An empty list selects all events.
+    pub fn matches(self, filter: &str) -> bool {
+        let name = self.name();
+        let mut patterns = filter.split(',').map(str::trim).filter(|x| !x.is_empty()).peekable();
+        patterns.peek().is_none()
+            || patterns.any(|pattern| match pattern.strip_suffix(".*") {
+                _ if pattern == "*" => true,
+                Some(entity) => name.strip_prefix(entity).is_some_and(|x| x.starts_with('.')),
+                None => pattern == name,
+            })
+    }
+}
+
+/// Returns an error for each entry of an event filter that can never match.
+pub fn invalid_event_patterns(filter: &str) -> Vec<String> {
+    filter
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .filter(|pattern| !WebhookEvent::ALL.iter().any(|event| event.matches(pattern)))
+        .map(|pattern| format!("Unknown event '{pattern}'"))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct Webhook {
+    #[table(skip)]
+    pub id: i64,
+    #[table(title = "URL")]
+    pub url: String,
+    #[table(class = "w-1")]
+    pub events: String,
+    #[table(skip)]
+    pub secret: String,
+    #[table(class = "w-1", renderer = "SliderRenderer")]
+    pub active: bool,
+    #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// The state of a webhook delivery
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "TEXT", rename_all = "lowercase"))]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Waiting for the first or another attempt
+    Pending,
+    Delivered,
+    /// All attempts failed
+    Failed,
+}
+
+impl fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryStatus::Pending => write!(f, "pending"),
+            DeliveryStatus::Delivered => write!(f, "delivered"),
+            DeliveryStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl CellValue for DeliveryStatus {
+    type RenderOptions = ();
+
+    fn render_value(self, _options: &Self::RenderOptions) -> impl IntoView {
+        self.to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TableRow)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[table(sortable, classes_provider = TailwindClassesPreset, thead_cell_renderer = THeadCellRenderer)]
+pub struct WebhookDelivery {
+    #[table(skip)]
+    pub id: i64,
+    #[table(class = "w-1")]
+    pub event: String,
+    #[table(title = "URL")]
+    pub url: String,
+    #[table(class = "w-1")]
+    pub status: DeliveryStatus,
+    #[table(class = "w-1")]
+    pub attempts: i64,
+    #[table(class = "w-1", title = "Response", none_value = "-")]
+    pub last_status_code: Option<i64>,
+    #[table(title = "Error", none_value = "-")]
+    pub last_error: Option<String>,
+    #[table(class = "w-1", title = "Created", renderer = "TimediffRenderer")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookQuery {
+    #[serde(default)]
+    sort: VecDeque<(usize, ColumnSort)>,
+    range: Range<usize>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{invalid_event_patterns, DeliveryStatus, Webhook, WebhookDelivery, WebhookEvent};
+    use crate::{api::ApiError, auth::User};
+    use hmac::{Hmac, Mac};
+    use serde::Serialize;
+    use sha2::Sha256;
+    use sqlx::{QueryBuilder, SqlitePool};
+    use std::{ops::Range, time::Duration};
+
+    /// How often the queue is checked for deliveries that are due
+    const POLL_INTERVAL: Duration = Duration::from_secs(5);
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+    /// A delivery is marked as failed after this many attempts
+    const MAX_ATTEMPTS: i64 = 8;
+    /// The delay before the first retry, which doubles with each further attempt
+    const RETRY_DELAY_SECONDS: i64 = 30;
+    /// Finished deliveries are removed from the log after this many days
+    const LOG_RETENTION_DAYS: i64 = 30;
+
+    /// The values of a webhook that can be set when creating or updating it
+    pub struct WebhookEdit {
+        pub url: String,
+        pub events: String,
+        /// The new secret, or empty to keep the current one (or generate one for new webhooks)
+        pub secret: String,
+        pub active: bool,
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<Webhook>, ApiError> {
+        user.ensure_admin()?;
+
+        let mut query = QueryBuilder::new("SELECT * FROM webhooks");
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<Webhook>().fetch_all(pool).await?)
+    }
+
+    pub async fn count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        user.ensure_admin()?;
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM webhooks")
+            .fetch_one(pool)
+            .await?;
+        Ok(count as usize)
+    }
+
+    pub async fn get(pool: &SqlitePool, user: &User, id: i64) -> Result<Webhook, ApiError> {
+        user.ensure_admin()?;
+
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Webhook {id} does not exist")))
+    }
+
+    pub async fn create_or_update(
+        pool: &SqlitePool,
+        user: &User,
+        id: Option<i64>,
+        edit: WebhookEdit,
+    ) -> Result<Webhook, ApiError> {
+        user.ensure_admin()?;
+
+        let WebhookEdit {
+            url,
+            events,
+            secret,
+            active,
+        } = edit;
+
+        let url = url.trim();
+        if !reqwest::Url::parse(url).is_ok_and(|x| matches!(x.scheme(), "http" | "https")) {
+            return Err(ApiError::BadRequest("url must be a valid http(s) url".to_string()));
+        }
+        if let Some(error) = invalid_event_patterns(&events).into_iter().next() {
+            return Err(ApiError::BadRequest(error));
+        }
+        let events = events
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        if let Some(id) = id {
+            // Make sure the webhook exists
+            get(pool, user, id).await?;
+
+            let mut query = QueryBuilder::new("UPDATE webhooks SET url = ");
+            query.push_bind(url);
+            query.push(", events = ");
+            query.push_bind(&events);
+            if !secret.is_empty() {
+                query.push(", secret = ");
+                query.push_bind(&secret);
+            }
+            query.push(", active = ");
+            query.push_bind(active);
+            query.push(" WHERE id = ");
+            query.push_bind(id);
+            query.build().execute(pool).await?;
+
+            get(pool, user, id).await
+        } else {
+            let secret = if secret.is_empty() {
+                let mut buf = [0u8; 32];
+                getrandom::getrandom(&mut buf).map_err(|e| ApiError::ServerError(e.to_string()))?;
+                hex::encode(buf)
+            } else {
+                secret
+            };
+
+            let id = sqlx::query_scalar::<_, i64>(
+                "INSERT INTO webhooks (url, events, secret, active) VALUES (?, ?, ?, ?) RETURNING id",
+            )
+            .bind(url)
+            .bind(&events)
+            .bind(&secret)
+            .bind(active)
+            .fetch_one(pool)
+            .await?;
+
+            get(pool, user, id).await
+        }
+    }
+
+    pub async fn delete(pool: &SqlitePool, user: &User, id: i64) -> Result<(), ApiError> {
+        user.ensure_admin()?;
+
+        if sqlx::query("DELETE FROM webhooks WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected()
+            == 0
+        {
+            return Err(ApiError::NotFound(format!("Webhook {id} does not exist")));
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_deliveries(
+        pool: &SqlitePool,
+        user: &User,
+        order: Option<String>,
+        range: Range<usize>,
+    ) -> Result<Vec<WebhookDelivery>, ApiError> {
+        user.ensure_admin()?;
+
+        let mut query = QueryBuilder::new(
+            "SELECT * FROM (SELECT d.id, d.event, w.url, d.status, d.attempts, d.last_status_code, \
+            d.last_error, d.created_at FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id)",
+        );
+        if let Some(order) = order {
+            query.push(" ");
+            query.push(order);
+        }
+
+        query.push(" LIMIT ");
+        query.push_bind(range.len() as i64);
+        query.push(" OFFSET ");
+        query.push_bind(range.start as i64);
+
+        Ok(query.build_query_as::<WebhookDelivery>().fetch_all(pool).await?)
+    }
+
+    pub async fn delivery_count(pool: &SqlitePool, user: &User) -> Result<usize, ApiError> {
+        user.ensure_admin()?;
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM webhook_deliveries")
+            .fetch_one(pool)
+            .await?;
+        Ok(count as usize)
+    }
+
+    /// Queues a failed delivery for another round of attempts.
+    pub async fn retry_delivery(pool: &SqlitePool, user: &User, id: i64) -> Result<(), ApiError> {
+        user.ensure_admin()?;
+
+        if sqlx::query(
+            "UPDATE webhook_deliveries SET status = 'pending', attempts = 0, \
+            next_attempt_at = CURRENT_TIMESTAMP WHERE id = ? AND status = 'failed'",
+        )
+        .bind(id)
+        .execute(pool)
+        .await?
+        .rows_affected()
+            == 0
+        {
+            return Err(ApiError::NotFound(format!("Failed delivery {id} does not exist")));
+        }
+
+        Ok(())
+    }
+
+    /// Queues a notification about the given event for all interested webhooks. Failures are
+    /// only logged, since the change that caused the event has already been made.
+    pub async fn notify(pool: &SqlitePool, actor: &User, event: WebhookEvent, data: impl Serialize) {
+        enqueue(pool, Some(&actor.username), event, data).await;
+    }
+
+    /// Like [`notify`], but for changes made by idmail itself, which have no actor.
+    pub async fn notify_system(pool: &SqlitePool, event: WebhookEvent, data: impl Serialize) {
+        enqueue(pool, None, event, data).await;
+    }
+
+    async fn enqueue(pool: &SqlitePool, actor: Option<&str>, event: WebhookEvent, data: impl Serialize) {
+        let payload = serde_json::json!({
+            "event": event.name(),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "actor": actor,
+            "data": data,
+        })
+        .to_string();
+
+        let result = async {
+            let webhooks = sqlx::query_as::<_, (i64, String)>("SELECT id, events FROM webhooks WHERE active = TRUE")
+                .fetch_all(pool)
+                .await?;
+            for (id, _events) in webhooks.iter().filter(|(_id, events)| event.matches(events)) {
+                sqlx::query("INSERT INTO webhook_deliveries (webhook_id, event, payload) VALUES (?, ?, ?)")
+                    .bind(id)
+                    .bind(event.name())
+                    .bind(&payload)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok::<_, sqlx::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            log::error!("failed to queue webhook event {}: {e}", event.name());
+        }
+    }
+
+    /// Computes the signature sent in the `X-Idmail-Signature` header. It covers the timestamp of the attempt,
+    /// so that receivers can reject old deliveries that are replayed.
+    fn signature(secret: &str, timestamp: i64, payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+        mac.update(format!("{timestamp}.{payload}").as_bytes());
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[derive(sqlx::FromRow)]
+    struct DueDelivery {
+        id: i64,
+        url: String,
+        secret: String,
+        event: String,
+        payload: String,
+        attempts: i64,
+    }
+
+    async fn deliver(client: &reqwest::Client, delivery: &DueDelivery) -> Result<reqwest::StatusCode, String> {
+        let timestamp = chrono::Utc::now().timestamp();
+        client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header("X-Idmail-Event", &delivery.event)
+            .header("X-Idmail-Delivery", delivery.id.to_string())
+            .header("X-Idmail-Timestamp", timestamp.to_string())
+            .header(
+                "X-Idmail-Signature",
+                signature(&delivery.secret, timestamp, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await
+            .map(|response| response.status())
+            .map_err(|e| {
+                // reqwest hides the actual cause (e.g. connection refused) in the error sources
+                let mut message = e.to_string();
+                let mut source = std::error::Error::source(&e);
+                while let Some(cause) = source {
+                    let cause_message = cause.to_string();
+                    if !message.ends_with(&cause_message) {
+                        message.push_str(&format!(": {cause_message}"));
+                    }
+                    source = cause.source();
+                }
+                message
+            })
+    }
+
+    async fn deliver_due(pool: &SqlitePool, client: &reqwest::Client) -> Result<(), sqlx::Error> {
+        let due = sqlx::query_as::<_, DueDelivery>(
+            "SELECT d.id, w.url, w.secret, d.event, d.payload, d.attempts \
+            FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id \
+            WHERE d.status = 'pending' AND d.next_attempt_at <= CURRENT_TIMESTAMP AND w.active = TRUE \
+            ORDER BY d.id LIMIT 100",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        for delivery in due {
+            let attempts = delivery.attempts + 1;
+            let (status_code, error) = match deliver(client, &delivery).await {
+                Ok(status) if status.is_success() => (Some(status.as_u16()), None),
+                Ok(status) => (
+                    Some(status.as_u16()),
+                    Some(format!("Unexpected response status {status}")),
+                ),
+                Err(e) => (None, Some(e)),
+            };
+
+            let status = match error {
+                None => DeliveryStatus::Delivered,
+                Some(_) if attempts >= MAX_ATTEMPTS => DeliveryStatus::Failed,
+                Some(_) => DeliveryStatus::Pending,
+            };
+            if let Some(error) = &error {
+                log::warn!(
+                    "webhook delivery {} to {} failed (attempt {attempts}): {error}",
+                    delivery.id,
+                    delivery.url
+                );
+            }
+
+            let retry_delay = RETRY_DELAY_SECONDS << (attempts - 1).min(16);
+            sqlx::query(
+                "UPDATE webhook_deliveries SET status = ?, attempts = ?, last_status_code = ?, last_error = ?, \
+                next_attempt_at = datetime('now', ?) WHERE id = ?",
+            )
+            .bind(status)
+            .bind(attempts)
+            .bind(status_code)
+            .bind(error)
+            .bind(format!("+{retry_delay} seconds"))
+            .bind(delivery.id)
+            .execute(pool)
+            .await?;
+        }
+
+        sqlx::query("DELETE FROM webhook_deliveries WHERE status != 'pending' AND created_at < datetime('now', ?)")
+            .bind(format!("-{LOG_RETENTION_DAYS} days"))
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Starts the background task that sends queued deliveries.
+    pub fn spawn_delivery_worker(pool: SqlitePool) -> anyhow::Result<()> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("idmail/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            // A redirect would send the signed delivery to a URL that was never configured
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = deliver_due(&pool, &client).await {
+                    log::error!("failed to process webhook deliveries: {e}");
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[server]
+pub async fn list_webhooks(query: WebhookQuery) -> Result<Vec<Webhook>, ServerFnError> {
+    let user = crate::auth::auth_admin().await?;
+    let WebhookQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list(&pool, &user, Webhook::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn webhook_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_admin().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn create_or_update_webhook(
+    id: Option<i64>,
+    url: String,
+    events: String,
+    secret: String,
+    active: bool,
+) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_admin().await?;
+    let pool = crate::database::ssr::pool()?;
+
+    let edit = ssr::WebhookEdit {
+        url,
+        events,
+        secret,
+        active,
+    };
+    ssr::create_or_update(&pool, &user, id, edit).await?;
+    Ok(())
+}
+
+#[server]
+pub async fn update_webhook_active(id: i64, active: bool) -> Result<(), ServerFnError> {
+    let _user = crate::auth::auth_admin().await?;
+    let pool = crate::database::ssr::pool()?;
+    sqlx::query("UPDATE webhooks SET active = ? WHERE id = ?")
+        .bind(active)
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn delete_webhook(id: i64) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_admin().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::delete(&pool, &user, id).await?)
+}
+
+#[server]
+pub async fn list_webhook_deliveries(query: WebhookQuery) -> Result<Vec<WebhookDelivery>, ServerFnError> {
+    let user = crate::auth::auth_admin().await?;
+    let WebhookQuery { sort, range } = query;
+
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::list_deliveries(&pool, &user, WebhookDelivery::sorting_to_sql(&sort), range).await?)
+}
+
+#[server]
+pub async fn webhook_delivery_count() -> Result<usize, ServerFnError> {
+    let user = crate::auth::auth_admin().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::delivery_count(&pool, &user).await?)
+}
+
+#[server]
+pub async fn retry_webhook_delivery(id: i64) -> Result<(), ServerFnError> {
+    let user = crate::auth::auth_admin().await?;
+    let pool = crate::database::ssr::pool()?;
+    Ok(ssr::retry_delivery(&pool, &user, id).await?)
+}
+
+#[derive(Default)]
+pub struct WebhookTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<Webhook> for WebhookTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<Webhook>, Range<usize>), String> {
+        list_webhooks(WebhookQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        webhook_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+#[derive(Default)]
+pub struct WebhookDeliveryTableDataProvider {
+    sort: VecDeque<(usize, ColumnSort)>,
+}
+
+impl TableDataProvider<WebhookDelivery> for WebhookDeliveryTableDataProvider {
+    async fn get_rows(&self, range: Range<usize>) -> Result<(Vec<WebhookDelivery>, Range<usize>), String> {
+        list_webhook_deliveries(WebhookQuery {
+            sort: self.sort.clone(),
+            range: range.clone(),
+        })
+        .await
+        .map_err(|e| format!("{e:?}"))
+        .map(|rows| {
+            let len = rows.len();
+            (rows, range.start..range.start + len)
+        })
+    }
+
+    async fn row_count(&self) -> Option<usize> {
+        webhook_delivery_count().await.ok()
+    }
+
+    fn set_sorting(&mut self, sorting: &VecDeque<(usize, ColumnSort)>) {
+        self.sort = sorting.clone();
+    }
+}
+
+#[component]
+pub fn Webhooks() -> impl IntoView {
+    let mut rows = WebhookTableDataProvider::default();
+    let default_sorting = VecDeque::from([(3, ColumnSort::Descending)]);
+    rows.set_sorting(&default_sorting);
+    let sorting = create_rw_signal(default_sorting);
+
+    let mut delivery_rows = WebhookDeliveryTableDataProvider::default();
+    let default_delivery_sorting = VecDeque::from([(6, ColumnSort::Descending)]);
+    delivery_rows.set_sorting(&default_delivery_sorting);
+    let delivery_sorting = create_rw_signal(default_delivery_sorting);
+
+    let reload_controller = ReloadController::default();
+    let delivery_reload_controller = ReloadController::default();
+    let (count, set_count) = create_signal(0);
+    let (delivery_count, set_delivery_count) = create_signal(0);
+
+    let delete_modal_webhook = create_rw_signal(None);
+    let delete_modal_webhook_id = create_rw_signal(None);
+    let edit_modal_webhook = create_rw_signal(None);
+
+    let (edit_modal_input_url, set_edit_modal_input_url) = create_signal("".to_string());
+    let (edit_modal_input_events, set_edit_modal_input_events) = create_signal("".to_string());
+    let (edit_modal_input_secret, set_edit_modal_input_secret) = create_signal("".to_string());
+    let (edit_modal_input_active, set_edit_modal_input_active) = create_signal(true);
+    let edit_modal_open_with = Callback::new(move |edit_webhook: Option<Webhook>| {
+        edit_modal_webhook.set(Some(edit_webhook.clone()));
+
+        if let Some(edit_webhook) = edit_webhook {
+            set_edit_modal_input_url(edit_webhook.url.clone());
+            set_edit_modal_input_events(edit_webhook.events.clone());
+            set_edit_modal_input_secret(edit_webhook.secret.clone());
+            set_edit_modal_input_active(edit_webhook.active);
+        } else {
+            set_edit_modal_input_url("".to_string());
+            set_edit_modal_input_events("".to_string());
+            set_edit_modal_input_secret("".to_string());
+            set_edit_modal_input_active(true);
+        }
+    });
+
+    let on_edit = move |(data, on_error): (Option<Webhook>, Callback<String>)| {
+        spawn_local(async move {
+            if let Err(e) = create_or_update_webhook(
+                data.map(|x| x.id),
+                edit_modal_input_url.get_untracked(),
+                edit_modal_input_events.get_untracked(),
+                edit_modal_input_secret.get_untracked(),
+                edit_modal_input_active.get_untracked(),
+            )
+            .await
+            {
+                on_error(e.to_string())
+            } else {
+                reload_controller.reload();
+                edit_modal_webhook.set(None);
+            }
+        });
+    };
+
+    let on_row_change = move |ev: ChangeEvent<Webhook>| {
+        spawn_local(async move {
+            if let Err(e) = update_webhook_active(ev.changed_row.id, ev.changed_row.active).await {
+                error!("Failed to update active status of {}: {}", ev.changed_row.url, e);
+            }
+            reload_controller.reload();
+        });
+    };
+
+    #[allow(unused_variables, non_snake_case)]
+    let webhook_row_renderer = move |class: Signal<String>,
+                                     row: Webhook,
+                                     index: usize,
+                                     selected: Signal<bool>,
+                                     on_select: EventHandler<MouseEvent>,
+                                     on_change: EventHandler<ChangeEvent<Webhook>>| {
+        let delete_url = row.url.clone();
+        let delete_id = row.id;
+        let edit_webhook = row.clone();
+        view! {
+            <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                {row.render_row(index, on_change)}
+                <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                    <div class="inline-flex items-center rounded-md">
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-blue-600 dark:hover:bg-blue-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-l-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            on:click=move |_| edit_modal_open_with(Some(edit_webhook.clone()))
+                        >
+                            <Icon icon=icondata::FiEdit class="w-5 h-5"/>
+                        </button>
+                        <button
+                            class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-red-600 dark:hover:bg-red-500 transition-all border-l-0 border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-r-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                            on:click=move |_| {
+                                delete_modal_webhook_id.set(Some(delete_id));
+                                delete_modal_webhook.set(Some(delete_url.clone()));
+                            }
+                        >
+
+                            <Icon icon=icondata::FiTrash2 class="w-5 h-5"/>
+                        </button>
+                    </div>
+                </td>
+            </tr>
+        }
+    };
+
+    #[allow(unused_variables, non_snake_case)]
+    let delivery_row_renderer =
+        move |class: Signal<String>,
+              row: WebhookDelivery,
+              index: usize,
+              selected: Signal<bool>,
+              on_select: EventHandler<MouseEvent>,
+              on_change: EventHandler<ChangeEvent<WebhookDelivery>>| {
+            let retry_id = row.id;
+            let failed = row.status == DeliveryStatus::Failed;
+            view! {
+                <tr class=class on:click=move |mouse_event| on_select.run(mouse_event)>
+                    {row.render_row(index, on_change)}
+                    <td class="w-1 px-4 py-2 whitespace-nowrap text-ellipsis">
+                        <Show when=move || failed>
+                            <button
+                                class="text-gray-800 dark:text-zinc-100 hover:text-white dark:hover:text-black bg-white dark:bg-black hover:bg-blue-600 dark:hover:bg-blue-500 transition-all border-[1.5px] border-gray-200 dark:border-zinc-800 rounded-lg font-medium px-4 py-2 inline-flex space-x-1 items-center"
+                                on:click=move |_| {
+                                    spawn_local(async move {
+                                        if let Err(e) = retry_webhook_delivery(retry_id).await {
+                                            error!("Failed to retry webhook delivery: {}", e);
+                                        }
+                                        delivery_reload_controller.reload();
+                                    });
+                                }
+                            >
+                                <Icon icon=icondata::FiRefreshCw class="w-5 h-5"/>
+                            </button>
+                        </Show>
+                    </td>
+                </tr>
+            }
+        };
+
+    let has_invalid_url = move || {
+        let url = edit_modal_input_url();
+        !(url.starts_with("http://") || url.starts_with("https://"))
+    };
+    let errors = create_memo(move |_| {
+        let mut errors = invalid_event_patterns(&edit_modal_input_events());
+        if has_invalid_url() {
+            errors.push("URL must start with http:// or https://".to_string());
+        }
+        errors
+    });
+
+    view! {
+        <div class="h-full flex-1 flex-col mt-12">
+            <div class="flex items-center justify-between space-y-2 mb-4">
+                <h2 class="text-4xl font-bold">Webhooks</h2>
+            </div>
+            <div class="space-y-4">
+                <div class="flex flex-wrap items-center justify-between">
+                    <button
+                        type="button"
+                        class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-white dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-blue-600 dark:bg-blue-700 hover:bg-blue-500 dark:hover:bg-blue-600 focus:ring-blue-300 dark:focus:ring-blue-900"
+                        on:click=move |_| edit_modal_open_with(None)
+                    >
+                        <Icon icon=icondata::FiPlus class="w-6 h-6 me-2"/>
+                        New
+                    </button>
+                    <div class="flex flex-1"></div>
+                    <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                        {count} " webhooks"
+                    </div>
+                </div>
+
+                <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                    <div class="overflow-auto grow min-h-0">
+                        <table class="table-auto text-left w-full">
+                            <TableContent
+                                rows
+                                sorting=sorting
+                                sorting_mode=SortingMode::SingleColumn
+                                row_renderer=webhook_row_renderer
+                                reload_controller=reload_controller
+                                loading_row_display_limit=0
+                                on_row_count=set_count
+                                on_change=on_row_change
+                            />
+                        </table>
+                    </div>
+                </div>
+            </div>
+
+            <div class="flex items-center justify-between space-y-2 mt-8 mb-4">
+                <h3 class="text-2xl font-bold">Deliveries</h3>
+            </div>
+            <div class="space-y-4">
+                <div class="flex flex-wrap items-center justify-between">
+                    <button
+                        type="button"
+                        class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-gray-800 dark:text-zinc-100 py-2.5 px-4 me-2 mb-2 transition-all rounded-lg focus:ring-4 bg-white dark:bg-black border-[1.5px] border-gray-200 dark:border-zinc-800 hover:bg-gray-100 dark:hover:bg-zinc-900"
+                        on:click=move |_| delivery_reload_controller.reload()
+                    >
+                        <Icon icon=icondata::FiRefreshCw class="w-5 h-5 me-2"/>
+                        Refresh
+                    </button>
+                    <div class="flex flex-1"></div>
+                    <div class="inline-flex flex-none items-center justify-center whitespace-nowrap font-medium text-base text-right px-4">
+                        {delivery_count} " deliveries"
+                    </div>
+                </div>
+
+                <div class="rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 text-base flex flex-col overflow-hidden">
+                    <div class="overflow-auto grow min-h-0">
+                        <table class="table-auto text-left w-full">
+                            <TableContent
+                                rows=delivery_rows
+                                sorting=delivery_sorting
+                                sorting_mode=SortingMode::SingleColumn
+                                row_renderer=delivery_row_renderer
+                                reload_controller=delivery_reload_controller
+                                loading_row_display_limit=0
+                                on_row_count=set_delivery_count
+                            />
+                        </table>
+                    </div>
+                </div>
+            </div>
+        </div>
+
+        <DeleteModal
+            data=delete_modal_webhook
+            text="Are you sure you want to delete this webhook? Its delivery log will be deleted as well.".into_view()
+            on_confirm=move |_| {
+                spawn_local(async move {
+                    if let Some(id) = delete_modal_webhook_id.get_untracked() {
+                        if let Err(e) = delete_webhook(id).await {
+                            error!("Failed to delete webhook: {}", e);
+                        } else {
+                            reload_controller.reload();
+                            delivery_reload_controller.reload();
+                        }
+                    }
+                    delete_modal_webhook.set(None);
+                    delete_modal_webhook_id.set(None);
+                });
+            }
+        />
+
+        <EditModal
+            data=edit_modal_webhook
+            what="Webhook".to_string()
+            get_title=move |x| { &x.url }
+            on_confirm=on_edit
+            errors
+        >
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="url"
+                >
+                    URL
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    class=("!ring-4", has_invalid_url)
+                    class=("!ring-red-500", has_invalid_url)
+                    type="url"
+                    placeholder="https://example.com/hooks/idmail"
+                    on:input=move |ev| set_edit_modal_input_url(event_target_value(&ev))
+                    prop:value=edit_modal_input_url
+                />
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="events"
+                >
+                    Events
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="text"
+                    placeholder="All events"
+                    on:input=move |ev| set_edit_modal_input_events(event_target_value(&ev))
+                    prop:value=edit_modal_input_events
+                />
+                <p class="text-sm text-gray-500 dark:text-gray-400">
+                    "Comma separated events like alias.created or alias.*, leave empty to receive all events."
+                </p>
+            </div>
+            <div class="flex flex-col gap-2">
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="secret"
+                >
+                    Secret
+                </label>
+                <input
+                    class="flex flex-none w-full rounded-lg border-[1.5px] border-gray-200 dark:border-zinc-800 bg-transparent dark:bg-transparent text-sm p-2.5 transition-all placeholder:text-gray-500 dark:placeholder:text-zinc-500 focus-visible:outline-none focus-visible:ring-4 focus-visible:ring-ring disabled:cursor-not-allowed disabled:opacity-50"
+                    type="text"
+                    placeholder="Generated if left empty"
+                    on:input=move |ev| set_edit_modal_input_secret(event_target_value(&ev))
+                    prop:value=edit_modal_input_secret
+                />
+                <p class="text-sm text-gray-500 dark:text-gray-400">
+                    "Used to sign each request with HMAC-SHA256 in the X-Idmail-Signature header."
+                </p>
+            </div>
+            <div class="flex flex-row gap-2 mt-2 items-center">
+                <input
+                    id="webhooks_active"
+                    class="w-4 h-4 bg-transparent dark:bg-transparent text-blue-600 border-[1.5px] border-gray-200 dark:border-zinc-800 rounded checked:bg-blue-600 dark:checked:bg-blue-600 dark:bg-blue-600 focus:ring-ring focus:ring-4 transition-all"
+                    type="checkbox"
+                    on:change=move |ev| set_edit_modal_input_active(event_target_checked(&ev))
+                    prop:checked=edit_modal_input_active
+                />
+                <label
+                    class="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                    for="webhooks_active"
+                >
+                    Active
+                </label>
+            </div>
+        </EditModal>
+    }
+}